@@ -1,19 +1,93 @@
 mod activity_tracker;
+mod stats;
 mod view;
 
+use std::path::PathBuf;
+
 use anyhow::Result;
+use clap::{Parser, Subcommand};
 
+use common::constants::{ALLIUM_IMPORT_DIR, ALLIUM_SD_ROOT};
+use common::database::Database;
+use common::export::{self, Export, ExportFormat};
 use common::platform::{DefaultPlatform, Platform};
 use simple_logger::SimpleLogger;
 
 use crate::activity_tracker::ActivityTracker;
 
+#[derive(Parser, Debug)]
+#[command(author, version, about, long_about = None)]
+struct Cli {
+    #[command(subcommand)]
+    command: Option<Commands>,
+}
+
+#[derive(Subcommand, Debug)]
+enum Commands {
+    /// Exports play statistics, guide reading positions and library metadata, to be imported on
+    /// another device
+    Export {
+        /// File to write, or directory for CSV. Defaults to the export directory
+        path: Option<PathBuf>,
+        /// Write a directory of CSV files instead of a JSON file
+        #[arg(long)]
+        csv: bool,
+    },
+    /// Merges an export into the play statistics. Games are matched by ROM hash or path, and
+    /// their play time is added up. Defaults to every export in the import directory
+    Import {
+        /// JSON file, or directory of CSV files
+        path: Option<PathBuf>,
+    },
+}
+
 #[tokio::main]
 async fn main() -> Result<()> {
     SimpleLogger::new().init().unwrap();
 
+    match Cli::parse().command {
+        Some(Commands::Export { path, csv }) => return export(path, csv),
+        Some(Commands::Import { path }) => return import(path),
+        None => {}
+    }
+
     let platform = DefaultPlatform::new()?;
     let mut app = ActivityTracker::new(platform)?;
     app.run_event_loop().await?;
     Ok(())
 }
+
+/// Writes an export, and prints where it was written to.
+fn export(path: Option<PathBuf>, csv: bool) -> Result<()> {
+    let format = if csv {
+        ExportFormat::Csv
+    } else {
+        ExportFormat::Json
+    };
+    let path = path.unwrap_or_else(|| format.default_path());
+    let database = Database::new()?;
+    Export::from_database(&database, &ALLIUM_SD_ROOT)?.write(&path, format)?;
+    println!("Exported to {}", path.display());
+    Ok(())
+}
+
+/// Merges an export into the database, and prints what was imported.
+fn import(path: Option<PathBuf>) -> Result<()> {
+    let database = Database::new()?;
+    match path {
+        Some(path) => {
+            let report = Export::read(&path)?.merge_into(&database, &ALLIUM_SD_ROOT)?;
+            println!("{}", report);
+        }
+        None => {
+            let (count, report) =
+                export::import_dir(&database, &ALLIUM_SD_ROOT, &ALLIUM_IMPORT_DIR)?;
+            if count == 0 {
+                println!("Nothing to import in {}", ALLIUM_IMPORT_DIR.display());
+            } else {
+                println!("{}", report);
+            }
+        }
+    }
+    Ok(())
+}
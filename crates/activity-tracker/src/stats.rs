@@ -0,0 +1,226 @@
+use std::collections::{BTreeSet, HashMap};
+use std::path::{Component, Path};
+
+use chrono::{DateTime, Datelike, Duration, Local, NaiveDate, Utc};
+use common::database::{Game, Session};
+
+/// Consecutive days with at least one play session.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Streaks {
+    /// Streak that includes today, or yesterday if nothing was played today yet.
+    pub current: u32,
+    pub longest: u32,
+}
+
+/// The local date that a session is counted towards. Sessions that run past midnight are counted
+/// towards the day they started.
+pub fn session_date(start: DateTime<Utc>) -> NaiveDate {
+    start.with_timezone(&Local).date_naive()
+}
+
+/// Play time on each of the last `days` days, ending with `today`.
+pub fn daily(sessions: &[Session], today: NaiveDate, days: u32) -> Vec<(NaiveDate, Duration)> {
+    let mut totals = HashMap::new();
+    for session in sessions {
+        *totals
+            .entry(session_date(session.start))
+            .or_insert_with(Duration::zero) += session.duration;
+    }
+
+    (0..days as i64)
+        .rev()
+        .map(|i| {
+            let date = today - Duration::days(i);
+            (
+                date,
+                totals.get(&date).copied().unwrap_or_else(Duration::zero),
+            )
+        })
+        .collect()
+}
+
+/// Play time in each of the last `weeks` weeks, ending with the week of `today`. Weeks start on
+/// Monday, and are identified by their first day.
+pub fn weekly(sessions: &[Session], today: NaiveDate, weeks: u32) -> Vec<(NaiveDate, Duration)> {
+    let week_start =
+        |date: NaiveDate| date - Duration::days(date.weekday().num_days_from_monday() as i64);
+
+    let mut totals = HashMap::new();
+    for session in sessions {
+        *totals
+            .entry(week_start(session_date(session.start)))
+            .or_insert_with(Duration::zero) += session.duration;
+    }
+
+    let this_week = week_start(today);
+    (0..weeks as i64)
+        .rev()
+        .map(|i| {
+            let date = this_week - Duration::weeks(i);
+            (
+                date,
+                totals.get(&date).copied().unwrap_or_else(Duration::zero),
+            )
+        })
+        .collect()
+}
+
+/// Total play time of each console, most played first. The console of a game is the top-level
+/// folder it is in under `root`.
+pub fn by_console(games: &[Game], root: &Path) -> Vec<(String, Duration)> {
+    let mut totals: HashMap<String, Duration> = HashMap::new();
+    for game in games {
+        *totals
+            .entry(console_name(&game.path, root))
+            .or_insert_with(Duration::zero) += game.play_time;
+    }
+
+    let mut totals = totals
+        .into_iter()
+        .filter(|(_, play_time)| *play_time > Duration::zero())
+        .collect::<Vec<_>>();
+    totals.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    totals
+}
+
+fn console_name(path: &Path, root: &Path) -> String {
+    let folder = match path
+        .strip_prefix(root)
+        .ok()
+        .and_then(|p| p.components().next())
+    {
+        Some(Component::Normal(name)) if path.parent() != Some(root) => Some(name),
+        _ => path.parent().and_then(Path::file_name),
+    };
+    folder
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default()
+}
+
+/// Current and longest streaks of days played.
+pub fn streaks(sessions: &[Session], today: NaiveDate) -> Streaks {
+    let dates = sessions
+        .iter()
+        .map(|session| session_date(session.start))
+        .collect::<BTreeSet<_>>();
+
+    let mut streaks = Streaks::default();
+    let mut length = 0;
+    let mut previous: Option<NaiveDate> = None;
+    for date in &dates {
+        length = match previous {
+            Some(previous) if *date - previous == Duration::days(1) => length + 1,
+            _ => 1,
+        };
+        streaks.longest = streaks.longest.max(length);
+        previous = Some(*date);
+    }
+
+    if previous.is_some_and(|last| last == today || last == today - Duration::days(1)) {
+        streaks.current = length;
+    }
+
+    streaks
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use chrono::TimeZone;
+
+    use super::*;
+
+    fn session(date: NaiveDate, minutes: i64) -> Session {
+        let start = Local
+            .from_local_datetime(&date.and_hms_opt(12, 0, 0).unwrap())
+            .unwrap()
+            .with_timezone(&Utc);
+        Session {
+            path: PathBuf::from("Roms/GBA/Game.gba"),
+            core: None,
+            start,
+            end: start + Duration::minutes(minutes),
+            duration: Duration::minutes(minutes),
+        }
+    }
+
+    #[test]
+    fn test_stats() {
+        // Wednesday
+        let today = NaiveDate::from_ymd_opt(2024, 5, 15).unwrap();
+        let day = |days_ago| today - Duration::days(days_ago);
+        let sessions = vec![
+            session(day(20), 10),
+            session(day(19), 10),
+            session(day(18), 10),
+            session(day(17), 10),
+            session(day(2), 15),
+            session(day(1), 30),
+            session(day(1), 30),
+        ];
+
+        let daily = daily(&sessions, today, 3);
+        assert_eq!(
+            daily,
+            vec![
+                (day(2), Duration::minutes(15)),
+                (day(1), Duration::minutes(60)),
+                (today, Duration::zero()),
+            ]
+        );
+
+        let weekly = weekly(&sessions, today, 4);
+        assert_eq!(
+            weekly,
+            vec![
+                (day(23), Duration::minutes(40)),
+                (day(16), Duration::zero()),
+                (day(9), Duration::zero()),
+                (day(2), Duration::minutes(75)),
+            ]
+        );
+
+        assert_eq!(
+            streaks(&sessions, today),
+            Streaks {
+                current: 2,
+                longest: 4
+            }
+        );
+        assert_eq!(streaks(&sessions, day(-2)).current, 0);
+    }
+
+    #[test]
+    fn test_by_console() {
+        let root = Path::new("Roms");
+        let game = |path: &str, minutes| Game {
+            name: String::new(),
+            path: PathBuf::from(path),
+            image: None,
+            play_count: 1,
+            play_time: Duration::minutes(minutes),
+            last_played: 1,
+            core: None,
+            rating: None,
+            release_date: None,
+            developer: None,
+            publisher: None,
+            genres: Vec::new(),
+        };
+        let games = vec![
+            game("Roms/GBA/A.gba", 10),
+            game("Roms/GBA/RPG/B.gba", 20),
+            game("Roms/SNES/C.sfc", 20),
+            game("Roms/Unplayed/D.sfc", 0),
+        ];
+
+        assert_eq!(
+            by_console(&games, root),
+            vec![
+                ("GBA".to_string(), Duration::minutes(30)),
+                ("SNES".to_string(), Duration::minutes(20)),
+            ]
+        );
+    }
+}
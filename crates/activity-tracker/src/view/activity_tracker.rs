@@ -2,28 +2,49 @@ use std::collections::{HashMap, VecDeque};
 
 use anyhow::Result;
 use async_trait::async_trait;
+use chrono::{DateTime, Duration, Local};
 use common::command::Command;
-use common::constants::{RECENT_GAMES_LIMIT, SELECTION_MARGIN};
+use common::constants::{
+    ALLIUM_GAMES_DIR, ALLIUM_IMPORT_DIR, ALLIUM_SD_ROOT, RECENT_GAMES_LIMIT, SELECTION_MARGIN,
+};
 use common::database::{Database, Game};
 use common::display::Display;
+use common::export::{self, Export, ExportFormat};
 use common::geom::{Alignment, Point, Rect};
 use common::locale::Locale;
 use common::platform::{DefaultPlatform, Key, KeyEvent, Platform};
 use common::resources::Resources;
-use common::stylesheet::Stylesheet;
+use common::stylesheet::{Stylesheet, StylesheetColor};
 use common::view::{ButtonHint, ButtonIcon, Label, Row, SettingsList, View};
 use embedded_graphics::prelude::OriginDimensions;
+use log::error;
 use serde::{Deserialize, Serialize};
 use tokio::sync::mpsc::Sender;
 
+use crate::stats;
+use crate::view::bar_chart::{Bar, BarChart, Orientation};
+use crate::view::session_log::SessionLog;
+
+/// Number of days shown in the daily chart.
+const DAILY_CHART_DAYS: u32 = 30;
+/// Number of weeks shown in the weekly chart.
+const WEEKLY_CHART_WEEKS: u32 = 12;
+
 #[derive(Debug)]
 pub struct ActivityTracker {
     rect: Rect,
     res: Resources,
+    page: Page,
+    tabs: Row<Label<String>>,
     entries: Vec<Game>,
     sort: Sort,
     list: SettingsList,
+    summary: Label<String>,
+    chart: BarChart,
+    actions: SettingsList,
+    session_log: Option<SessionLog>,
     button_hints: Row<ButtonHint<String>>,
+    dirty: bool,
 }
 
 impl ActivityTracker {
@@ -32,37 +53,72 @@ impl ActivityTracker {
 
         let styles = res.get::<Stylesheet>();
 
+        let tabs = Row::new(
+            Point::new(x + 12, y),
+            {
+                let locale = res.get::<Locale>();
+                Page::ALL
+                    .iter()
+                    .map(|page| {
+                        let mut tab =
+                            Label::new(Point::zero(), page.title(&locale), Alignment::Left, None);
+                        tab.color(StylesheetColor::Tab);
+                        tab.font_size(styles.tab_font_size);
+                        tab
+                    })
+                    .collect()
+            },
+            Alignment::Left,
+            12,
+        );
+
+        let content_y = y + styles.tab_font_size() as i32 + 8;
+        let content_h = (y + h as i32 - content_y) as u32 - 8 - ButtonIcon::diameter(&styles);
+
         let list = SettingsList::new(
-            Rect::new(x + 12, y, w - 24, h - 8 - ButtonIcon::diameter(&styles)),
+            Rect::new(x + 12, content_y, w - 24, content_h),
             Vec::new(),
             Vec::new(),
             res.get::<Stylesheet>().ui_font.size + SELECTION_MARGIN,
         );
 
+        let summary = Label::new(
+            Point::new(x + 24, content_y),
+            String::new(),
+            Alignment::Left,
+            Some(w - 48),
+        );
+
+        let chart_y = content_y + styles.ui_font.size as i32 + 16;
+        let chart = BarChart::new(
+            Rect::new(
+                x + 24,
+                chart_y,
+                w - 48,
+                (content_y + content_h as i32 - chart_y) as u32,
+            ),
+            Vec::new(),
+            Orientation::Vertical,
+        );
+
+        let actions = SettingsList::new(
+            Rect::new(
+                x + 12,
+                chart_y,
+                w - 24,
+                (content_y + content_h as i32 - chart_y) as u32,
+            ),
+            Vec::new(),
+            Vec::new(),
+            styles.ui_font.size + SELECTION_MARGIN,
+        );
+
         let button_hints = Row::new(
             Point::new(
                 x + w as i32 - 12,
                 y + h as i32 - ButtonIcon::diameter(&styles) as i32 - 8,
             ),
-            {
-                let locale = res.get::<Locale>();
-                vec![
-                    ButtonHint::new(
-                        res.clone(),
-                        Point::zero(),
-                        Key::B,
-                        locale.t("button-back"),
-                        Alignment::Right,
-                    ),
-                    ButtonHint::new(
-                        res.clone(),
-                        Point::zero(),
-                        Key::Y,
-                        Sort::MostPlayed.button_hint(&locale),
-                        Alignment::Right,
-                    ),
-                ]
-            },
+            Vec::new(),
             Alignment::Right,
             12,
         );
@@ -72,17 +128,46 @@ impl ActivityTracker {
         let mut this = Self {
             rect,
             res,
+            page: Page::Games,
+            tabs,
             entries: Vec::new(),
             sort: Sort::MostPlayed,
             list,
+            summary,
+            chart,
+            actions,
+            session_log: None,
             button_hints,
+            dirty: true,
         };
 
-        this.load_entries()?;
+        this.set_page(Page::Games)?;
 
         Ok(this)
     }
 
+    fn set_page(&mut self, page: Page) -> Result<()> {
+        if let Some(tab) = self.tabs.get_mut(self.page as usize) {
+            tab.color(StylesheetColor::Tab);
+        }
+        self.page = page;
+        if let Some(tab) = self.tabs.get_mut(self.page as usize) {
+            tab.color(StylesheetColor::TabSelected);
+        }
+
+        match page {
+            Page::Games => self.load_entries()?,
+            Page::Days => self.load_days()?,
+            Page::Weeks => self.load_weeks()?,
+            Page::Consoles => self.load_consoles()?,
+            Page::Data => self.load_actions(),
+        }
+        self.update_button_hints();
+        self.set_should_draw();
+
+        Ok(())
+    }
+
     fn load_entries(&mut self) -> Result<()> {
         self.entries = match self.sort {
             Sort::LastPlayed => self
@@ -100,19 +185,7 @@ impl ActivityTracker {
             self.entries.iter().map(|e| e.name.to_string()).collect(),
             self.entries
                 .iter()
-                .map(|e| {
-                    let mut map = HashMap::new();
-                    map.insert(
-                        "hours_decimal".to_string(),
-                        format!("{:.1}", (e.play_time.num_minutes() as f32 / 60.0)).into(),
-                    );
-                    map.insert("hours".to_string(), e.play_time.num_hours().into());
-                    map.insert(
-                        "minutes".to_string(),
-                        (e.play_time.num_minutes() % 60).into(),
-                    );
-                    locale.ta("activity-tracker-play-time", &map)
-                })
+                .map(|e| format_play_time(&locale, e.play_time))
                 .map(|s| {
                     Box::new(Label::new(
                         Point::zero(),
@@ -126,6 +199,263 @@ impl ActivityTracker {
 
         Ok(())
     }
+
+    fn load_days(&mut self) -> Result<()> {
+        let sessions = self
+            .res
+            .get::<Database>()
+            .select_sessions(DateTime::UNIX_EPOCH)?;
+        let today = Local::now().date_naive();
+
+        let days = stats::daily(&sessions, today, DAILY_CHART_DAYS);
+        let streaks = stats::streaks(&sessions, today);
+        let total = days
+            .iter()
+            .fold(Duration::zero(), |total, (_, play_time)| total + *play_time);
+
+        let locale = self.res.get::<Locale>();
+        let summary = if sessions.is_empty() {
+            locale.t("activity-tracker-no-sessions")
+        } else {
+            let mut map = HashMap::new();
+            map.insert(
+                "play_time".to_string(),
+                format_play_time(&locale, total).into(),
+            );
+            map.insert("current".to_string(), streaks.current.into());
+            map.insert("longest".to_string(), streaks.longest.into());
+            locale.ta("activity-tracker-last-30-days", &map)
+        };
+        self.summary.set_text(summary);
+
+        let bars = days
+            .into_iter()
+            .map(|(date, play_time)| Bar {
+                label: date.format("%-d").to_string(),
+                value: format_play_time(&locale, play_time),
+                play_time,
+            })
+            .collect();
+        self.chart.set_bars(bars, Orientation::Vertical);
+
+        Ok(())
+    }
+
+    fn load_weeks(&mut self) -> Result<()> {
+        let today = Local::now().date_naive();
+        let since = today - Duration::weeks(WEEKLY_CHART_WEEKS as i64);
+        let sessions = self.res.get::<Database>().select_sessions(
+            since
+                .and_hms_opt(0, 0, 0)
+                .map(|since| since.and_utc())
+                .unwrap_or_default(),
+        )?;
+
+        let weeks = stats::weekly(&sessions, today, WEEKLY_CHART_WEEKS);
+        let total = weeks
+            .iter()
+            .fold(Duration::zero(), |total, (_, play_time)| total + *play_time);
+
+        let locale = self.res.get::<Locale>();
+        let summary = if sessions.is_empty() {
+            locale.t("activity-tracker-no-sessions")
+        } else {
+            let mut map = HashMap::new();
+            map.insert(
+                "play_time".to_string(),
+                format_play_time(&locale, total / WEEKLY_CHART_WEEKS as i32).into(),
+            );
+            locale.ta("activity-tracker-weekly-average", &map)
+        };
+        self.summary.set_text(summary);
+
+        let bars = weeks
+            .into_iter()
+            .map(|(date, play_time)| Bar {
+                label: date.format("%-d/%-m").to_string(),
+                value: format_play_time(&locale, play_time),
+                play_time,
+            })
+            .collect();
+        self.chart.set_bars(bars, Orientation::Vertical);
+
+        Ok(())
+    }
+
+    fn load_consoles(&mut self) -> Result<()> {
+        let games = self.res.get::<Database>().select_most_played(i64::MAX)?;
+        let consoles = stats::by_console(&games, &ALLIUM_GAMES_DIR);
+        let total = consoles
+            .iter()
+            .fold(Duration::zero(), |total, (_, play_time)| total + *play_time);
+
+        let locale = self.res.get::<Locale>();
+        let mut map = HashMap::new();
+        map.insert(
+            "play_time".to_string(),
+            format_play_time(&locale, total).into(),
+        );
+        self.summary
+            .set_text(locale.ta("activity-tracker-total", &map));
+
+        let bars = consoles
+            .into_iter()
+            .map(|(console, play_time)| Bar {
+                label: console,
+                value: format_play_time(&locale, play_time),
+                play_time,
+            })
+            .collect();
+        self.chart.set_bars(bars, Orientation::Horizontal);
+
+        Ok(())
+    }
+
+    fn load_actions(&mut self) {
+        let locale = self.res.get::<Locale>();
+        self.summary.set_text(
+            locale.ta(
+                "activity-tracker-data-hint",
+                &[(
+                    "path".to_string(),
+                    ALLIUM_IMPORT_DIR.display().to_string().into(),
+                )]
+                .into_iter()
+                .collect(),
+            ),
+        );
+        self.actions.set_items(
+            DataAction::ALL
+                .iter()
+                .map(|action| action.label(&locale))
+                .collect(),
+            DataAction::ALL
+                .iter()
+                .map(|_| {
+                    Box::new(Label::new(
+                        Point::zero(),
+                        String::new(),
+                        Alignment::Right,
+                        None,
+                    )) as Box<dyn View>
+                })
+                .collect(),
+        );
+    }
+
+    /// Runs an export or import, and shows the result in the summary.
+    fn run_action(&mut self, action: DataAction) {
+        let result = {
+            let database = self.res.get::<Database>();
+            match action {
+                DataAction::ExportJson | DataAction::ExportCsv => {
+                    let format = action.format();
+                    let path = format.default_path();
+                    Export::from_database(&database, &ALLIUM_SD_ROOT)
+                        .and_then(|export| export.write(&path, format))
+                        .map(|()| {
+                            self.res.get::<Locale>().ta(
+                                "activity-tracker-exported",
+                                &[("path".to_string(), path.display().to_string().into())]
+                                    .into_iter()
+                                    .collect(),
+                            )
+                        })
+                }
+                DataAction::Import => {
+                    export::import_dir(&database, &ALLIUM_SD_ROOT, &ALLIUM_IMPORT_DIR).map(
+                        |(count, report)| {
+                            let locale = self.res.get::<Locale>();
+                            if count == 0 {
+                                locale.t("activity-tracker-nothing-to-import")
+                            } else {
+                                let mut map = HashMap::new();
+                                map.insert("merged".to_string(), report.merged.into());
+                                map.insert("added".to_string(), report.added.into());
+                                map.insert("skipped".to_string(), report.skipped.into());
+                                locale.ta("activity-tracker-imported", &map)
+                            }
+                        },
+                    )
+                }
+            }
+        };
+
+        let text = match result {
+            Ok(text) => text,
+            Err(e) => {
+                error!("failed to {:?}: {}", action, e);
+                self.res.get::<Locale>().t("activity-tracker-data-failed")
+            }
+        };
+        self.summary.set_text(text);
+    }
+
+    fn update_button_hints(&mut self) {
+        let locale = self.res.get::<Locale>();
+
+        while self.button_hints.pop().is_some() {}
+        self.button_hints.push(ButtonHint::new(
+            self.res.clone(),
+            Point::zero(),
+            Key::B,
+            locale.t("button-back"),
+            Alignment::Right,
+        ));
+        if self.page == Page::Games && self.session_log.is_none() {
+            self.button_hints.push(ButtonHint::new(
+                self.res.clone(),
+                Point::zero(),
+                Key::Y,
+                self.sort.button_hint(&locale),
+                Alignment::Right,
+            ));
+            self.button_hints.push(ButtonHint::new(
+                self.res.clone(),
+                Point::zero(),
+                Key::A,
+                locale.t("activity-tracker-sessions"),
+                Alignment::Right,
+            ));
+        }
+        if self.page == Page::Data {
+            self.button_hints.push(ButtonHint::new(
+                self.res.clone(),
+                Point::zero(),
+                Key::A,
+                locale.t("button-select"),
+                Alignment::Right,
+            ));
+        }
+    }
+
+    fn open_session_log(&mut self) -> Result<()> {
+        let Some(game) = self.entries.get(self.list.selected()) else {
+            return Ok(());
+        };
+        let Rect { x, w, .. } = self.rect;
+        let list_rect = self.list.bounding_box(&self.res.get::<Stylesheet>());
+        self.session_log = Some(SessionLog::new(
+            Rect::new(x, list_rect.y, w, list_rect.h),
+            self.res.clone(),
+            game,
+        )?);
+        self.update_button_hints();
+        self.set_should_draw();
+        Ok(())
+    }
+}
+
+/// Formats play time, e.g. "1.5 hours".
+pub fn format_play_time(locale: &Locale, play_time: Duration) -> String {
+    let mut map = HashMap::new();
+    map.insert(
+        "hours_decimal".to_string(),
+        format!("{:.1}", (play_time.num_minutes() as f32 / 60.0)).into(),
+    );
+    map.insert("hours".to_string(), play_time.num_hours().into());
+    map.insert("minutes".to_string(), (play_time.num_minutes() % 60).into());
+    locale.ta("activity-tracker-play-time", &map)
 }
 
 #[async_trait(?Send)]
@@ -137,7 +467,30 @@ impl View for ActivityTracker {
     ) -> Result<bool> {
         let mut drawn = false;
 
-        drawn |= self.list.should_draw() && self.list.draw(display, styles)?;
+        if self.dirty {
+            display.load(self.rect)?;
+            self.dirty = false;
+            drawn = true;
+        }
+
+        drawn |= self.tabs.should_draw() && self.tabs.draw(display, styles)?;
+
+        match (self.page, self.session_log.as_mut()) {
+            (Page::Games, Some(session_log)) => {
+                drawn |= session_log.should_draw() && session_log.draw(display, styles)?;
+            }
+            (Page::Games, None) => {
+                drawn |= self.list.should_draw() && self.list.draw(display, styles)?;
+            }
+            (Page::Data, _) => {
+                drawn |= self.summary.should_draw() && self.summary.draw(display, styles)?;
+                drawn |= self.actions.should_draw() && self.actions.draw(display, styles)?;
+            }
+            _ => {
+                drawn |= self.summary.should_draw() && self.summary.draw(display, styles)?;
+                drawn |= self.chart.should_draw() && self.chart.draw(display, styles)?;
+            }
+        }
 
         if self.button_hints.should_draw() {
             display.load(Rect::new(
@@ -156,11 +509,27 @@ impl View for ActivityTracker {
     }
 
     fn should_draw(&self) -> bool {
-        self.list.should_draw() || self.button_hints.should_draw()
+        self.dirty
+            || self.tabs.should_draw()
+            || match (self.page, self.session_log.as_ref()) {
+                (Page::Games, Some(session_log)) => session_log.should_draw(),
+                (Page::Games, None) => self.list.should_draw(),
+                (Page::Data, _) => self.summary.should_draw() || self.actions.should_draw(),
+                _ => self.summary.should_draw() || self.chart.should_draw(),
+            }
+            || self.button_hints.should_draw()
     }
 
     fn set_should_draw(&mut self) {
+        self.dirty = true;
+        self.tabs.set_should_draw();
         self.list.set_should_draw();
+        self.summary.set_should_draw();
+        self.chart.set_should_draw();
+        self.actions.set_should_draw();
+        if let Some(session_log) = self.session_log.as_mut() {
+            session_log.set_should_draw();
+        }
         self.button_hints.set_should_draw();
     }
 
@@ -170,8 +539,28 @@ impl View for ActivityTracker {
         commands: Sender<Command>,
         bubble: &mut VecDeque<Command>,
     ) -> Result<bool> {
+        if let Some(session_log) = self.session_log.as_mut() {
+            return match event {
+                KeyEvent::Pressed(Key::B) => {
+                    self.session_log = None;
+                    self.update_button_hints();
+                    self.set_should_draw();
+                    Ok(true)
+                }
+                _ => session_log.handle_key_event(event, commands, bubble).await,
+            };
+        }
+
         match event {
-            KeyEvent::Pressed(Key::Y) => {
+            KeyEvent::Pressed(Key::L) | KeyEvent::Autorepeat(Key::L) => {
+                self.set_page(self.page.prev())?;
+                Ok(true)
+            }
+            KeyEvent::Pressed(Key::R) | KeyEvent::Autorepeat(Key::R) => {
+                self.set_page(self.page.next())?;
+                Ok(true)
+            }
+            KeyEvent::Pressed(Key::Y) if self.page == Page::Games => {
                 self.sort = self.sort.next();
                 self.button_hints
                     .get_mut(1)
@@ -180,20 +569,64 @@ impl View for ActivityTracker {
                 self.load_entries()?;
                 Ok(true)
             }
+            KeyEvent::Pressed(Key::A) if self.page == Page::Games => {
+                self.open_session_log()?;
+                Ok(true)
+            }
+            KeyEvent::Pressed(Key::A) if self.page == Page::Data => {
+                if let Some(action) = DataAction::ALL.get(self.actions.selected()) {
+                    self.run_action(*action);
+                }
+                Ok(true)
+            }
             KeyEvent::Pressed(Key::B) => {
                 commands.send(Command::Exit).await?;
                 Ok(true)
             }
-            _ => self.list.handle_key_event(event, commands, bubble).await,
+            _ if self.page == Page::Games => {
+                self.list.handle_key_event(event, commands, bubble).await
+            }
+            _ if self.page == Page::Data => {
+                self.actions.handle_key_event(event, commands, bubble).await
+            }
+            _ => Ok(false),
         }
     }
 
     fn children(&self) -> Vec<&dyn View> {
-        vec![&self.list, &self.button_hints]
+        let mut children: Vec<&dyn View> = vec![&self.tabs];
+        match (self.page, self.session_log.as_ref()) {
+            (Page::Games, Some(session_log)) => children.push(session_log),
+            (Page::Games, None) => children.push(&self.list),
+            (Page::Data, _) => {
+                children.push(&self.summary);
+                children.push(&self.actions);
+            }
+            _ => {
+                children.push(&self.summary);
+                children.push(&self.chart);
+            }
+        }
+        children.push(&self.button_hints);
+        children
     }
 
     fn children_mut(&mut self) -> Vec<&mut dyn View> {
-        vec![&mut self.list, &mut self.button_hints]
+        let mut children: Vec<&mut dyn View> = vec![&mut self.tabs];
+        match (self.page, self.session_log.as_mut()) {
+            (Page::Games, Some(session_log)) => children.push(session_log),
+            (Page::Games, None) => children.push(&mut self.list),
+            (Page::Data, _) => {
+                children.push(&mut self.summary);
+                children.push(&mut self.actions);
+            }
+            _ => {
+                children.push(&mut self.summary);
+                children.push(&mut self.chart);
+            }
+        }
+        children.push(&mut self.button_hints);
+        children
     }
 
     fn bounding_box(&mut self, _styles: &Stylesheet) -> Rect {
@@ -204,6 +637,81 @@ impl View for ActivityTracker {
         unimplemented!()
     }
 }
+
+/// Pages of the activity tracker, switched between with L and R.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Page {
+    /// Play time of each game, and their session logs.
+    Games,
+    /// Play time per day over the last 30 days, and streaks.
+    Days,
+    /// Play time per week.
+    Weeks,
+    /// Play time per console.
+    Consoles,
+    /// Export and import of play history.
+    Data,
+}
+
+impl Page {
+    const ALL: [Page; 5] = [
+        Page::Games,
+        Page::Days,
+        Page::Weeks,
+        Page::Consoles,
+        Page::Data,
+    ];
+
+    fn title(&self, locale: &Locale) -> String {
+        match self {
+            Page::Games => locale.t("activity-tracker-games"),
+            Page::Days => locale.t("activity-tracker-days"),
+            Page::Weeks => locale.t("activity-tracker-weeks"),
+            Page::Consoles => locale.t("activity-tracker-consoles"),
+            Page::Data => locale.t("activity-tracker-data"),
+        }
+    }
+
+    fn next(self) -> Self {
+        Self::ALL[(self as usize + 1) % Self::ALL.len()]
+    }
+
+    fn prev(self) -> Self {
+        Self::ALL[(self as usize + Self::ALL.len() - 1) % Self::ALL.len()]
+    }
+}
+
+/// Actions on the data page.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum DataAction {
+    ExportJson,
+    ExportCsv,
+    Import,
+}
+
+impl DataAction {
+    const ALL: [DataAction; 3] = [
+        DataAction::ExportJson,
+        DataAction::ExportCsv,
+        DataAction::Import,
+    ];
+
+    fn label(&self, locale: &Locale) -> String {
+        match self {
+            DataAction::ExportJson => locale.t("activity-tracker-export-json"),
+            DataAction::ExportCsv => locale.t("activity-tracker-export-csv"),
+            DataAction::Import => locale.t("activity-tracker-import"),
+        }
+    }
+
+    fn format(&self) -> ExportFormat {
+        match self {
+            DataAction::ExportCsv => ExportFormat::Csv,
+            _ => ExportFormat::Json,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Copy, Serialize, Deserialize)]
 enum Sort {
     LastPlayed,
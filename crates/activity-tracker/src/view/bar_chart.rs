@@ -0,0 +1,239 @@
+use std::collections::VecDeque;
+
+use anyhow::Result;
+use async_trait::async_trait;
+use chrono::Duration;
+use common::command::Command;
+use common::display::Display;
+use common::geom::{Alignment, Point, Rect};
+use common::platform::{DefaultPlatform, KeyEvent, Platform};
+use common::stylesheet::{Stylesheet, StylesheetColor};
+use common::view::{Label, View};
+use embedded_graphics::prelude::Size;
+use embedded_graphics::primitives::{Primitive, PrimitiveStyle, Rectangle};
+use embedded_graphics::Drawable;
+use tokio::sync::mpsc::Sender;
+
+/// A bar of a `BarChart`.
+#[derive(Debug, Clone)]
+pub struct Bar {
+    /// Name of the bar, e.g. a date or a console.
+    pub label: String,
+    /// Formatted play time, shown next to the bar.
+    pub value: String,
+    pub play_time: Duration,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Orientation {
+    /// Bars grow upwards, with their labels below. Suited to play time over time.
+    Vertical,
+    /// Bars grow to the right, one per line with their label and value. Suited to rankings.
+    Horizontal,
+}
+
+/// A bar chart of play time.
+#[derive(Debug)]
+pub struct BarChart {
+    rect: Rect,
+    bars: Vec<Bar>,
+    orientation: Orientation,
+    dirty: bool,
+}
+
+impl BarChart {
+    pub fn new(rect: Rect, bars: Vec<Bar>, orientation: Orientation) -> Self {
+        Self {
+            rect,
+            bars,
+            orientation,
+            dirty: true,
+        }
+    }
+
+    pub fn set_bars(&mut self, bars: Vec<Bar>, orientation: Orientation) {
+        self.bars = bars;
+        self.orientation = orientation;
+        self.dirty = true;
+    }
+
+    fn max_seconds(&self) -> i64 {
+        self.bars
+            .iter()
+            .map(|bar| bar.play_time.num_seconds())
+            .max()
+            .unwrap_or(0)
+            .max(1)
+    }
+
+    fn draw_vertical(
+        &mut self,
+        display: &mut <DefaultPlatform as Platform>::Display,
+        styles: &Stylesheet,
+    ) -> Result<()> {
+        let Rect { x, y, w, h } = self.rect;
+        if self.bars.is_empty() {
+            return Ok(());
+        }
+
+        let font_size = styles.status_bar_font_size() as u32;
+        let max = self.max_seconds();
+        let bar_w = w / self.bars.len() as u32;
+        let chart_h = h.saturating_sub(font_size * 2 + 16) as i64;
+        let bottom = y + font_size as i32 + 8 + chart_h as i32;
+
+        // Scale, shown as the value of the longest bar
+        if let Some(longest) = self
+            .bars
+            .iter()
+            .max_by_key(|bar| bar.play_time.num_seconds())
+        {
+            let mut label = Label::new(
+                Point::new(x, y),
+                longest.value.clone(),
+                Alignment::Left,
+                Some(w),
+            );
+            label
+                .font_size(styles.status_bar_font_size)
+                .color(StylesheetColor::Disabled);
+            label.draw(display, styles)?;
+        }
+
+        // Label every few bars so that labels don't overlap
+        let label_w = font_size * 3;
+        let label_every = (label_w / bar_w.max(1) + 1) as usize;
+
+        for (i, bar) in self.bars.iter().enumerate() {
+            let bar_x = x + (i as u32 * bar_w) as i32;
+            let bar_h = (chart_h * bar.play_time.num_seconds() / max) as u32;
+            if bar_h > 0 {
+                Rectangle::new(
+                    Point::new(bar_x + 1, bottom - bar_h as i32).into(),
+                    Size::new(bar_w.saturating_sub(2).max(1), bar_h),
+                )
+                .into_styled(PrimitiveStyle::with_fill(styles.highlight_color))
+                .draw(display)?;
+            }
+
+            if (self.bars.len() - 1 - i).is_multiple_of(label_every) {
+                let mut label = Label::new(
+                    Point::new(bar_x + bar_w as i32 / 2, bottom + 8),
+                    bar.label.clone(),
+                    Alignment::Center,
+                    None,
+                );
+                label.font_size(styles.status_bar_font_size);
+                label.draw(display, styles)?;
+            }
+        }
+
+        Rectangle::new(Point::new(x, bottom).into(), Size::new(w, 1))
+            .into_styled(PrimitiveStyle::with_fill(styles.disabled_color))
+            .draw(display)?;
+
+        Ok(())
+    }
+
+    fn draw_horizontal(
+        &mut self,
+        display: &mut <DefaultPlatform as Platform>::Display,
+        styles: &Stylesheet,
+    ) -> Result<()> {
+        let Rect { x, y, w, h } = self.rect;
+
+        let line_h = styles.ui_font.size + 8;
+        let label_w = w / 3;
+        let value_w = w / 5;
+        let chart_w = (w - label_w - value_w - 24) as i64;
+        let max = self.max_seconds();
+
+        let visible = (h / line_h) as usize;
+        for (i, bar) in self.bars.iter().take(visible).enumerate() {
+            let line_y = y + (i as u32 * line_h) as i32;
+
+            let mut label = Label::new(
+                Point::new(x, line_y),
+                bar.label.clone(),
+                Alignment::Left,
+                Some(label_w),
+            );
+            label.draw(display, styles)?;
+
+            let bar_w = (chart_w * bar.play_time.num_seconds() / max) as u32;
+            if bar_w > 0 {
+                Rectangle::new(
+                    Point::new(x + label_w as i32 + 12, line_y + 4).into(),
+                    Size::new(bar_w, styles.ui_font.size - 8),
+                )
+                .into_styled(PrimitiveStyle::with_fill(styles.highlight_color))
+                .draw(display)?;
+            }
+
+            let mut value = Label::new(
+                Point::new(x + w as i32, line_y),
+                bar.value.clone(),
+                Alignment::Right,
+                Some(value_w),
+            );
+            value.draw(display, styles)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[async_trait(?Send)]
+impl View for BarChart {
+    fn draw(
+        &mut self,
+        display: &mut <DefaultPlatform as Platform>::Display,
+        styles: &Stylesheet,
+    ) -> Result<bool> {
+        if !self.dirty {
+            return Ok(false);
+        }
+
+        display.load(self.rect)?;
+        match self.orientation {
+            Orientation::Vertical => self.draw_vertical(display, styles)?,
+            Orientation::Horizontal => self.draw_horizontal(display, styles)?,
+        }
+        self.dirty = false;
+
+        Ok(true)
+    }
+
+    fn should_draw(&self) -> bool {
+        self.dirty
+    }
+
+    fn set_should_draw(&mut self) {
+        self.dirty = true;
+    }
+
+    async fn handle_key_event(
+        &mut self,
+        _event: KeyEvent,
+        _commands: Sender<Command>,
+        _bubble: &mut VecDeque<Command>,
+    ) -> Result<bool> {
+        Ok(false)
+    }
+
+    fn children(&self) -> Vec<&dyn View> {
+        vec![]
+    }
+
+    fn children_mut(&mut self) -> Vec<&mut dyn View> {
+        vec![]
+    }
+
+    fn bounding_box(&mut self, _styles: &Stylesheet) -> Rect {
+        self.rect
+    }
+
+    fn set_position(&mut self, _point: Point) {
+        unimplemented!()
+    }
+}
@@ -1,5 +1,7 @@
 mod activity_tracker;
 mod app;
+mod bar_chart;
+mod session_log;
 
 pub use activity_tracker::ActivityTracker;
 pub use app::App;
@@ -0,0 +1,139 @@
+use std::collections::VecDeque;
+
+use anyhow::Result;
+use async_trait::async_trait;
+use chrono::Local;
+use common::command::Command;
+use common::constants::SELECTION_MARGIN;
+use common::database::{Database, Game};
+use common::geom::{Alignment, Point, Rect};
+use common::locale::Locale;
+use common::platform::{DefaultPlatform, KeyEvent, Platform};
+use common::resources::Resources;
+use common::stylesheet::{Stylesheet, StylesheetColor};
+use common::view::{Label, SettingsList, View};
+use tokio::sync::mpsc::Sender;
+
+use crate::view::activity_tracker::format_play_time;
+
+/// Maximum number of sessions listed in the session log.
+const SESSION_LOG_LIMIT: i64 = 100;
+
+/// Lists the play sessions of a game, most recent first.
+#[derive(Debug)]
+pub struct SessionLog {
+    rect: Rect,
+    title: Label<String>,
+    list: SettingsList,
+}
+
+impl SessionLog {
+    pub fn new(rect: Rect, res: Resources, game: &Game) -> Result<Self> {
+        let Rect { x, y, w, h } = rect;
+
+        let styles = res.get::<Stylesheet>();
+        let locale = res.get::<Locale>();
+
+        let sessions = res
+            .get::<Database>()
+            .select_game_sessions(&game.path, SESSION_LOG_LIMIT)?;
+
+        let mut title = Label::new(
+            Point::new(x + 12, y),
+            game.name.clone(),
+            Alignment::Left,
+            Some(w - 24),
+        );
+        title.color(StylesheetColor::Highlight);
+
+        let (left, right) = if sessions.is_empty() {
+            (
+                vec![locale.t("activity-tracker-no-sessions")],
+                vec![Box::new(Label::new(
+                    Point::zero(),
+                    String::new(),
+                    Alignment::Right,
+                    None,
+                )) as Box<dyn View>],
+            )
+        } else {
+            sessions
+                .iter()
+                .map(|session| {
+                    let start = session.start.with_timezone(&Local);
+                    let left = match session.core.as_ref() {
+                        Some(core) => format!("{} · {}", start.format("%Y-%m-%d %H:%M"), core),
+                        None => start.format("%Y-%m-%d %H:%M").to_string(),
+                    };
+                    let right = Box::new(Label::new(
+                        Point::zero(),
+                        format_play_time(&locale, session.duration),
+                        Alignment::Right,
+                        Some(w / 3),
+                    )) as Box<dyn View>;
+                    (left, right)
+                })
+                .unzip()
+        };
+
+        let list_y = y + styles.ui_font.size as i32 + 8;
+        let list = SettingsList::new(
+            Rect::new(x + 12, list_y, w - 24, h - (list_y - y) as u32),
+            left,
+            right,
+            styles.ui_font.size + SELECTION_MARGIN,
+        );
+
+        Ok(Self { rect, title, list })
+    }
+}
+
+#[async_trait(?Send)]
+impl View for SessionLog {
+    fn draw(
+        &mut self,
+        display: &mut <DefaultPlatform as Platform>::Display,
+        styles: &Stylesheet,
+    ) -> Result<bool> {
+        let mut drawn = false;
+
+        drawn |= self.title.should_draw() && self.title.draw(display, styles)?;
+        drawn |= self.list.should_draw() && self.list.draw(display, styles)?;
+
+        Ok(drawn)
+    }
+
+    fn should_draw(&self) -> bool {
+        self.title.should_draw() || self.list.should_draw()
+    }
+
+    fn set_should_draw(&mut self) {
+        self.title.set_should_draw();
+        self.list.set_should_draw();
+    }
+
+    async fn handle_key_event(
+        &mut self,
+        event: KeyEvent,
+        commands: Sender<Command>,
+        bubble: &mut VecDeque<Command>,
+    ) -> Result<bool> {
+        self.list.handle_key_event(event, commands, bubble).await
+    }
+
+    fn children(&self) -> Vec<&dyn View> {
+        vec![&self.title, &self.list]
+    }
+
+    fn children_mut(&mut self) -> Vec<&mut dyn View> {
+        vec![&mut self.title, &mut self.list]
+    }
+
+    fn bounding_box(&mut self, _styles: &Stylesheet) -> Rect {
+        self.rect
+    }
+
+    fn set_position(&mut self, _point: Point) {
+        unimplemented!()
+    }
+}
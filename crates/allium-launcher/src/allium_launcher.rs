@@ -1,14 +1,16 @@
 use std::collections::VecDeque;
 use std::path::Path;
 use std::process;
-use std::time::Instant;
+use std::time::{Duration, Instant};
 
 use anyhow::Result;
 use common::command::Command;
-use common::constants::{ALLIUM_GAMES_DIR, ALLIUM_SD_ROOT};
+use common::constants::ALLIUM_SD_ROOT;
 use common::display::color::Color;
 use common::geom;
+use common::launcher_request::LauncherRequest;
 use common::locale::{Locale, LocaleSettings};
+use common::parental::ParentalSettings;
 use common::resources::Resources;
 use common::view::View;
 use embedded_graphics::image::ImageRaw;
@@ -20,11 +22,14 @@ use common::database::Database;
 use common::display::Display;
 use common::platform::{DefaultPlatform, Key, KeyEvent, Platform};
 use common::stylesheet::Stylesheet;
+use tokio::sync::mpsc::{Receiver, Sender};
 use type_map::TypeMap;
 
+use crate::collections;
 use crate::consoles::ConsoleMapper;
-use crate::entry::directory::Directory;
+use crate::entry::app;
 use crate::entry::game::Game;
+use crate::indexer::Indexer;
 use crate::view::{App, Toast};
 
 #[derive(Debug)]
@@ -34,6 +39,11 @@ pub struct AlliumLauncher<P: Platform> {
     res: Resources,
     view: App<P::Battery>,
     toast: Option<Toast>,
+    commands: Sender<Command>,
+    receiver: Option<Receiver<Command>>,
+    indexer: Option<Indexer>,
+    /// Whether the toast shows the progress of the indexer.
+    index_toast: bool,
 }
 
 impl AlliumLauncher<DefaultPlatform> {
@@ -43,16 +53,28 @@ impl AlliumLauncher<DefaultPlatform> {
 
         let mut console_mapper = ConsoleMapper::new();
         console_mapper.load_config()?;
+        console_mapper.set_restrictions(ParentalSettings::load()?);
 
         let mut res = TypeMap::new();
-        res.insert(Database::new()?);
+        let database = Database::new()?;
+        if let Err(e) = collections::import_all(&database) {
+            error!("failed to import collections: {}", e);
+        }
+        if let Err(e) = console_mapper.hide_restricted_games(&database) {
+            error!("failed to hide restricted games: {}", e);
+        }
+
+        res.insert(database);
         res.insert(console_mapper);
         res.insert(Stylesheet::load()?);
         res.insert(Locale::new(&LocaleSettings::load()?.lang));
         res.insert(Into::<geom::Size>::into(display.size()));
         let res = Resources::new(res);
 
-        let view = App::load_or_new(display.bounding_box().into(), res.clone(), battery)?;
+        let mut view = App::load_or_new(display.bounding_box().into(), res.clone(), battery)?;
+        view.lock();
+
+        let (commands, receiver) = tokio::sync::mpsc::channel(100);
 
         Ok(AlliumLauncher {
             platform,
@@ -60,6 +82,10 @@ impl AlliumLauncher<DefaultPlatform> {
             res,
             view,
             toast: None,
+            commands,
+            receiver: Some(receiver),
+            indexer: None,
+            index_toast: false,
         })
     }
 
@@ -82,8 +108,20 @@ impl AlliumLauncher<DefaultPlatform> {
         #[cfg(unix)]
         let mut sigterm =
             tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate())?;
+        #[cfg(unix)]
+        let mut sigusr1 =
+            tokio::signal::unix::signal(tokio::signal::unix::SignalKind::user_defined1())?;
+
+        let mut rx = self.receiver.take().unwrap();
+        let tx = self.commands.clone();
+
+        // Requests made while a game was running
+        self.handle_request().await?;
 
-        let (tx, mut rx) = tokio::sync::mpsc::channel(100);
+        // Pick up games that were added, removed or renamed since the last index
+        if self.res.get::<Database>().has_indexed()? {
+            self.start_indexing(false, false);
+        }
 
         let mut keys: EnumMap<Key, bool> = EnumMap::default();
 
@@ -118,6 +156,9 @@ impl AlliumLauncher<DefaultPlatform> {
                 _ = sigterm.recv() => {
                     self.handle_command(Command::Exit).await?;
                 }
+                _ = sigusr1.recv() => {
+                    self.handle_request().await?;
+                }
                 cmd = rx.recv() => {
                     if let Some(cmd) = cmd {
                         self.handle_command(cmd).await?;
@@ -136,7 +177,10 @@ impl AlliumLauncher<DefaultPlatform> {
                     }
 
                     // Ignore menu key presses
-                    if !keys[Key::Menu] && !matches!(event, KeyEvent::Released(Key::Menu)) {
+                    if !keys[Key::Menu]
+                        && !matches!(event, KeyEvent::Released(Key::Menu))
+                        && !self.handle_index_toast_key(event).await?
+                    {
                         self.view.handle_key_event(event, tx.clone(), &mut bubble).await?;
                     }
                 }
@@ -154,10 +198,134 @@ impl AlliumLauncher<DefaultPlatform> {
         }
     }
 
+    /// Handles a pending request from alliumd, if there is one.
+    async fn handle_request(&mut self) -> Result<()> {
+        let request = match LauncherRequest::take() {
+            Ok(Some(request)) => request,
+            Ok(None) => return Ok(()),
+            Err(e) => {
+                error!("failed to read launcher request: {}", e);
+                return Ok(());
+            }
+        };
+        info!("handling launcher request: {:?}", request);
+
+        match request {
+            LauncherRequest::Launch { path } => {
+                let command = {
+                    let database = self.res.get::<Database>();
+                    let mut game = match database.select_game(&path)? {
+                        Some(game) => Game::from_db(game),
+                        None => Game::new(path),
+                    };
+                    self.res.get::<ConsoleMapper>().launch_game(
+                        &database,
+                        &self.res.get(),
+                        &mut game,
+                        false,
+                        None,
+                    )?
+                };
+                if let Some(command) = command {
+                    self.handle_command(command).await?;
+                }
+            }
+            LauncherRequest::LaunchApp { path } => {
+                // Apps need the PIN in restricted mode, like the Apps tab
+                if self.view.is_locked() {
+                    warn!("apps are locked, not launching {}", path.display());
+                    let text = self.res.get::<Locale>().t("parental-apps-locked");
+                    self.handle_command(Command::Toast(text, Some(Duration::from_secs(2))))
+                        .await?;
+                    return Ok(());
+                }
+                let command = app::App::new(path)?.command();
+                self.handle_command(command).await?;
+            }
+            LauncherRequest::Rescan => self.start_indexing(false, false),
+        }
+        Ok(())
+    }
+
+    /// Saves the state of the view and loads it again, e.g. after settings that change how it is
+    /// drawn. Apps and Settings stay unlocked if the PIN was already entered.
+    fn reload_view(&mut self) -> Result<()> {
+        self.view.save()?;
+        let locked = self.view.is_locked();
+        self.view = App::load_or_new(
+            self.display.bounding_box().into(),
+            self.res.clone(),
+            self.platform.battery()?,
+        )?;
+        if locked {
+            self.view.lock();
+        }
+        Ok(())
+    }
+
+    fn redraw(&mut self) -> Result<()> {
+        trace!("redrawing");
+        self.display.load(self.display.bounding_box().into())?;
+        self.view.set_should_draw();
+        Ok(())
+    }
+
+    /// Starts indexing games in the background, unless it is already running. If `show_toast` is
+    /// true, progress is shown in a toast that can be hidden or used to cancel indexing.
+    fn start_indexing(&mut self, full: bool, show_toast: bool) {
+        self.index_toast |= show_toast;
+        if self
+            .indexer
+            .as_ref()
+            .is_some_and(|indexer| !indexer.is_finished())
+        {
+            info!("already indexing");
+            return;
+        }
+        info!("indexing games, full: {}", full);
+        self.indexer = Some(Indexer::spawn(full, self.commands.clone()));
+        if show_toast {
+            self.toast = Some(Toast::new(
+                self.res.get::<Locale>().t("populating-database"),
+                None,
+            ));
+        }
+    }
+
+    /// Handles a key press while the indexing toast is shown: A hides the toast, and B cancels
+    /// indexing. Returns true if the key was handled.
+    async fn handle_index_toast_key(&mut self, event: KeyEvent) -> Result<bool> {
+        if !self.index_toast {
+            return Ok(false);
+        }
+        match event {
+            KeyEvent::Pressed(Key::A) => {
+                self.index_toast = false;
+                self.toast = None;
+            }
+            KeyEvent::Pressed(Key::B) => {
+                if let Some(indexer) = self.indexer.as_ref() {
+                    indexer.cancel();
+                }
+                self.index_toast = false;
+                self.toast = Some(Toast::new(
+                    self.res.get::<Locale>().t("indexing-cancelled"),
+                    Some(Duration::from_secs(2)),
+                ));
+            }
+            _ => return Ok(false),
+        }
+        self.redraw()?;
+        Ok(true)
+    }
+
     async fn handle_command(&mut self, command: Command) -> Result<()> {
         match command {
             Command::Exit => {
                 info!("goodbye from allium launcher");
+                if let Some(indexer) = self.indexer.as_ref() {
+                    indexer.cancel();
+                }
                 self.view.save()?;
                 self.display.clear(Color::new(0, 0, 0))?;
                 self.display.flush()?;
@@ -211,12 +379,7 @@ impl AlliumLauncher<DefaultPlatform> {
                 }
 
                 self.res.insert(*styles);
-                self.view.save()?;
-                self.view = App::load_or_new(
-                    self.display.bounding_box().into(),
-                    self.res.clone(),
-                    self.platform.battery()?,
-                )?;
+                self.reload_view()?;
             }
             Command::SaveDisplaySettings(mut settings) => {
                 trace!("saving display settings");
@@ -227,18 +390,18 @@ impl AlliumLauncher<DefaultPlatform> {
                 trace!("saving locale settings");
                 settings.save()?;
                 self.res.insert(Locale::new(&settings.lang));
-                self.view.save()?;
-                self.view = App::load_or_new(
-                    self.display.bounding_box().into(),
-                    self.res.clone(),
-                    self.platform.battery()?,
-                )?;
+                self.reload_view()?;
             }
-            Command::Redraw => {
-                trace!("redrawing");
-                self.display.load(self.display.bounding_box().into())?;
-                self.view.set_should_draw();
+            Command::SaveParentalSettings(settings) => {
+                trace!("saving parental settings");
+                settings.save()?;
+                let mut console_mapper = self.res.get::<ConsoleMapper>().clone();
+                console_mapper.set_restrictions(*settings);
+                console_mapper.hide_restricted_games(&self.res.get())?;
+                self.res.insert(console_mapper);
+                self.reload_view()?;
             }
+            Command::Redraw => self.redraw()?,
             Command::StartSearch => {
                 trace!("starting search");
                 self.view.start_search();
@@ -251,71 +414,47 @@ impl AlliumLauncher<DefaultPlatform> {
                 trace!("showing toast: {:?}", text);
                 self.toast = Some(Toast::new(text, duration));
             }
-            Command::PopulateDb => {
-                #[cfg(feature = "miyoo")]
-                {
-                    std::process::Command::new("show")
-                        .arg("--clear")
-                        .spawn()?
-                        .wait()?;
-                    std::process::Command::new("say")
-                        .arg(self.res.get::<Locale>().t("populating-database"))
-                        .spawn()?
-                        .wait()?;
+            Command::PopulateDb => self.start_indexing(true, true),
+            Command::IndexProgress { indexed, total } => {
+                let locale = self.res.get::<Locale>();
+                let args = [
+                    ("indexed".to_string(), indexed.into()),
+                    ("total".to_string(), total.into()),
+                ]
+                .into_iter()
+                .collect();
+                self.view
+                    .set_indexing_status(locale.ta("indexing-status", &args));
+                if self.index_toast {
+                    let text = format!(
+                        "{}\n{}",
+                        locale.ta("indexing-progress", &args),
+                        locale.t("indexing-hint")
+                    );
+                    drop(locale);
+                    self.toast = Some(Toast::new(text, None));
+                    self.redraw()?;
                 }
-
-                let mut queue = VecDeque::with_capacity(10);
-                queue.push_back(Directory::new(ALLIUM_GAMES_DIR.clone()));
-
-                let database = self.res.get::<Database>();
-                let console_mapper = self.res.get::<ConsoleMapper>();
-
-                database.delete_all_directories()?;
-                database.delete_all_unplayed_games()?;
-
-                let mut games = database.select_all_games()?;
-                for game in games.iter_mut() {
-                    if let Some(old) = Game::resync(&mut game.path)? {
-                        if let Err(e) = database.update_game_path(&old, &game.path) {
-                            warn!("failed to update game path: {}", e);
-                        }
-                    } else if !game.path.exists() {
-                        database.delete_game(&game.path)?;
-                    }
+            }
+            Command::IndexFinished { changed } => {
+                info!("finished indexing, changed: {}", changed);
+                self.indexer = None;
+                self.view.set_indexing_status(String::new());
+                if self.index_toast {
+                    self.index_toast = false;
+                    self.toast = None;
+                    self.redraw()?;
                 }
-
-                while let Some(dir) = queue.pop_front() {
-                    #[cfg(feature = "miyoo")]
+                if changed {
+                    if let Err(e) = self
+                        .res
+                        .get::<ConsoleMapper>()
+                        .hide_restricted_games(&self.res.get())
                     {
-                        std::process::Command::new("show")
-                            .arg("--clear")
-                            .spawn()?
-                            .wait()?;
-                        let mut map = std::collections::HashMap::new();
-                        map.insert(
-                            "directory".to_string(),
-                            dir.path
-                                .file_name()
-                                .unwrap_or_default()
-                                .to_string_lossy()
-                                .into(),
-                        );
-                        std::process::Command::new("say")
-                            .arg(self.res.get::<Locale>().ta("populating-games", &map))
-                            .spawn()?
-                            .wait()?;
+                        error!("failed to hide restricted games: {}", e);
                     }
-                    dir.populate_db(&mut queue, &database, &console_mapper, &self.res.get())?;
+                    self.reload_view()?;
                 }
-
-                database.set_has_indexed(true)?;
-
-                self.view.save()?;
-                self.view = App::load_or_new(
-                    self.display.bounding_box().into(),
-                    self.res.clone(),
-                    self.platform.battery()?,
-                )?;
             }
             command => {
                 warn!("unhandled command: {:?}", command);
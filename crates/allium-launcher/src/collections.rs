@@ -0,0 +1,249 @@
+use std::collections::HashSet;
+use std::ffi::OsStr;
+use std::fs::{self, File};
+use std::path::{Component, Path, PathBuf};
+
+use anyhow::Result;
+use common::constants::ALLIUM_COLLECTIONS_DIR;
+use common::database::Database;
+use log::{debug, error, warn};
+use serde::{Deserialize, Serialize};
+
+/// JSON representation of a collection.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct CollectionFile {
+    name: String,
+    games: Vec<PathBuf>,
+}
+
+/// Imports every .m3u and .json file in the collections directory, replacing the contents of
+/// the matching collections. Collections without a file are deleted, as the files are the source
+/// of truth.
+pub fn import_all(database: &Database) -> Result<()> {
+    if !ALLIUM_COLLECTIONS_DIR.is_dir() {
+        return Ok(());
+    }
+
+    let mut names = HashSet::new();
+    for entry in fs::read_dir(ALLIUM_COLLECTIONS_DIR.as_path())? {
+        let path = entry?.path();
+        let collection = match path.extension().and_then(OsStr::to_str) {
+            Some("m3u") => parse_m3u(&path),
+            Some("json") => parse_json(&path),
+            _ => continue,
+        };
+        let (name, games) = match collection {
+            Ok(collection) => collection,
+            Err(e) => {
+                error!("Failed to import collection {}: {}", path.display(), e);
+                continue;
+            }
+        };
+
+        debug!("importing collection {} ({} games)", name, games.len());
+        let id = database.create_collection(&name)?;
+        database.set_collection_games(id, &games)?;
+        names.insert(name);
+    }
+
+    for collection in database.select_collections()? {
+        if !names.contains(&collection.name) {
+            debug!("deleting collection {} without a file", collection.name);
+            database.delete_collection(collection.id)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Writes a collection to the collections directory. The existing file format is kept if the
+/// collection was imported from JSON, otherwise it is written as an M3U playlist.
+pub fn export(database: &Database, name: &str) -> Result<()> {
+    let Some(collection) = database.select_collection(name)? else {
+        warn!("collection {} does not exist, not exporting", name);
+        return Ok(());
+    };
+    let games = database.select_collection_paths(collection.id)?;
+
+    fs::create_dir_all(ALLIUM_COLLECTIONS_DIR.as_path())?;
+    let json = ALLIUM_COLLECTIONS_DIR.join(format!("{}.json", file_name(name)));
+    if json.exists() {
+        let file = File::create(json)?;
+        serde_json::to_writer_pretty(
+            file,
+            &CollectionFile {
+                name: collection.name,
+                games: games
+                    .iter()
+                    .map(|path| relative_path(path, &ALLIUM_COLLECTIONS_DIR))
+                    .collect(),
+            },
+        )?;
+    } else {
+        fs::write(
+            ALLIUM_COLLECTIONS_DIR.join(format!("{}.m3u", file_name(name))),
+            to_m3u(&collection.name, &games, &ALLIUM_COLLECTIONS_DIR),
+        )?;
+    }
+
+    Ok(())
+}
+
+/// Writes an M3U playlist for a collection in `dir`. The name is kept in a `#PLAYLIST:` line, as
+/// the file name can't contain every character.
+fn to_m3u(name: &str, games: &[PathBuf], dir: &Path) -> String {
+    let mut m3u = format!("#EXTM3U\n#PLAYLIST:{name}\n");
+    for path in games {
+        m3u.push_str(&relative_path(path, dir).to_string_lossy());
+        m3u.push('\n');
+    }
+    m3u
+}
+
+/// Parses an M3U playlist. The collection is named by its `#PLAYLIST:` line, or otherwise after
+/// the file, and paths are relative to the playlist.
+fn parse_m3u(path: &Path) -> Result<(String, Vec<PathBuf>)> {
+    let mut name = path
+        .file_stem()
+        .and_then(OsStr::to_str)
+        .unwrap_or_default()
+        .to_string();
+    let dir = path.parent().unwrap_or(Path::new(""));
+    let mut games = Vec::new();
+    for line in fs::read_to_string(path)?.lines().map(str::trim) {
+        if let Some(playlist) = line.strip_prefix("#PLAYLIST:") {
+            if !playlist.trim().is_empty() {
+                name = playlist.trim().to_string();
+            }
+        } else if !line.is_empty() && !line.starts_with('#') {
+            games.push(resolve_path(dir, Path::new(line)));
+        }
+    }
+    Ok((name, games))
+}
+
+fn parse_json(path: &Path) -> Result<(String, Vec<PathBuf>)> {
+    let file: CollectionFile = serde_json::from_reader(File::open(path)?)?;
+    let dir = path.parent().unwrap_or(Path::new(""));
+    let games = file
+        .games
+        .iter()
+        .map(|game| resolve_path(dir, game))
+        .collect();
+    Ok((file.name, games))
+}
+
+/// Resolves a path from a collection file, canonicalizing it if possible so that it matches the
+/// paths stored in the database.
+fn resolve_path(dir: &Path, path: &Path) -> PathBuf {
+    let path = dir.join(path);
+    path.canonicalize().unwrap_or(path)
+}
+
+/// Returns `path` relative to `base` if they share a common prefix, otherwise `path` itself.
+fn relative_path(path: &Path, base: &Path) -> PathBuf {
+    let base = base.canonicalize().unwrap_or_else(|_| base.to_path_buf());
+    let mut path_components = path.components().peekable();
+    let mut base_components = base.components().peekable();
+
+    if path_components.peek() != base_components.peek() {
+        return path.to_path_buf();
+    }
+
+    while let (Some(a), Some(b)) = (path_components.peek(), base_components.peek()) {
+        if a != b {
+            break;
+        }
+        path_components.next();
+        base_components.next();
+    }
+
+    let mut relative = PathBuf::new();
+    for _ in base_components {
+        relative.push(Component::ParentDir);
+    }
+    relative.extend(path_components);
+    relative
+}
+
+/// Replaces characters that are not allowed in FAT32 file names.
+fn file_name(name: &str) -> String {
+    name.chars()
+        .map(|c| match c {
+            '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' => '_',
+            c => c,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_relative_path() {
+        assert_eq!(
+            relative_path(
+                Path::new("/mnt/SDCARD/Roms/GBA/Game.gba"),
+                Path::new("/mnt/SDCARD/.allium/collections")
+            ),
+            PathBuf::from("../../Roms/GBA/Game.gba")
+        );
+        assert_eq!(
+            relative_path(
+                Path::new("/mnt/SDCARD/.allium/collections/Game.gba"),
+                Path::new("/mnt/SDCARD/.allium/collections")
+            ),
+            PathBuf::from("Game.gba")
+        );
+        assert_eq!(
+            relative_path(Path::new("Roms/Game.gba"), Path::new("/mnt/SDCARD")),
+            PathBuf::from("Roms/Game.gba")
+        );
+    }
+
+    #[test]
+    fn test_parse_m3u() -> Result<()> {
+        let dir = std::env::temp_dir().join("allium-test-parse-m3u");
+        fs::create_dir_all(&dir)?;
+        let path = dir.join("RPGs to finish.m3u");
+        fs::write(
+            &path,
+            "#EXTM3U\n../Roms/GBA/Golden Sun.gba\n\n# comment\n/abs/Chrono Trigger.sfc\n",
+        )?;
+
+        let (name, games) = parse_m3u(&path)?;
+        assert_eq!(name, "RPGs to finish");
+        assert_eq!(
+            games,
+            vec![
+                dir.join("../Roms/GBA/Golden Sun.gba"),
+                PathBuf::from("/abs/Chrono Trigger.sfc"),
+            ]
+        );
+
+        fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_m3u_name() -> Result<()> {
+        let dir = std::env::temp_dir().join("allium-test-m3u-name");
+        fs::create_dir_all(&dir)?;
+        let name = "Mario: Best";
+        let games = vec![dir.join("Super Mario World.sfc")];
+        let path = dir.join(format!("{}.m3u", file_name(name)));
+        fs::write(&path, to_m3u(name, &games, &dir))?;
+
+        assert_eq!(path.file_stem(), Some(OsStr::new("Mario_ Best")));
+        assert_eq!(parse_m3u(&path)?, (name.to_string(), games));
+
+        fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_file_name() {
+        assert_eq!(file_name("Party: 4 players?"), "Party_ 4 players_");
+    }
+}
@@ -1,15 +1,24 @@
 use std::fmt;
 use std::path::PathBuf;
+use std::time::Duration;
 use std::{collections::HashMap, path::Path};
 
-use anyhow::{anyhow, bail, Context, Result};
+use anyhow::{bail, Context, Result};
+use chrono::{Local, Utc};
 use common::command::Command;
 use common::database::Database;
 use common::game_info::GameInfo;
+use common::launch_profile::{LaunchProfile, LaunchProfileScope};
+use common::locale::Locale;
+use common::parental::ParentalSettings;
+use common::power::{self, PowerSettings, ScheduleStatus};
+use common::save_state::AUTO_SLOT;
 use serde::Deserialize;
 
-use common::constants::{ALLIUM_CONFIG_CONSOLES, ALLIUM_CONFIG_CORES, ALLIUM_RETROARCH};
-use log::{debug, error, trace};
+use common::constants::{
+    ALLIUM_CONFIG_CONSOLES, ALLIUM_CONFIG_CORES, ALLIUM_RETROARCH, ALLIUM_RETROARCH_CHEEVOS_CONFIG,
+};
+use log::{debug, error, trace, warn};
 
 use crate::entry::game::Game;
 
@@ -68,6 +77,32 @@ impl fmt::Display for Core {
     }
 }
 
+/// What made a path map to a console.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum ConsoleMatch {
+    /// The file name is listed in the console's `file_name`.
+    FileName(String),
+    /// One of the file's extensions is listed in the console's `extensions`.
+    Extension(String),
+    /// A directory's name matches one of the console's `patterns`.
+    Pattern { directory: String, pattern: String },
+}
+
+impl fmt::Display for ConsoleMatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConsoleMatch::FileName(name) => write!(f, "file name \"{}\"", name),
+            ConsoleMatch::Extension(ext) => write!(f, "extension \".{}\"", ext),
+            ConsoleMatch::Pattern { directory, pattern } if directory == pattern => {
+                write!(f, "folder \"{}\"", directory)
+            }
+            ConsoleMatch::Pattern { directory, pattern } => {
+                write!(f, "folder \"{}\" contains \"({})\"", directory, pattern)
+            }
+        }
+    }
+}
+
 #[derive(Debug, Deserialize)]
 struct CoresConfig {
     cores: HashMap<CoreName, Core>,
@@ -77,6 +112,8 @@ struct CoresConfig {
 pub struct ConsoleMapper {
     cores: HashMap<CoreName, Core>,
     consoles: Vec<Console>,
+    /// Parental controls that hide consoles, directories and games.
+    restrictions: ParentalSettings,
 }
 
 impl Default for ConsoleMapper {
@@ -90,34 +127,86 @@ impl ConsoleMapper {
         ConsoleMapper {
             cores: HashMap::new(),
             consoles: Vec::new(),
+            restrictions: ParentalSettings::default(),
         }
     }
 
     pub fn load_config(&mut self) -> Result<()> {
-        let consoles = std::fs::read_to_string(ALLIUM_CONFIG_CONSOLES.as_path()).map_err(|e| {
-            anyhow!(
-                "Failed to load consoles config: {:?}, {}",
-                &*ALLIUM_CONFIG_CONSOLES,
-                e
-            )
-        })?;
-        let consoles: ConsoleConfig =
-            toml::from_str(&consoles).context("Failed to parse consoles.toml.")?;
-        self.consoles = consoles.consoles;
-
-        let cores = std::fs::read_to_string(ALLIUM_CONFIG_CORES.as_path()).map_err(|e| {
-            anyhow!(
-                "Failed to load cores config: {:?}, {}",
-                &*ALLIUM_CONFIG_CORES,
-                e
-            )
-        })?;
-        let cores: CoresConfig = toml::from_str(&cores).context("Failed to parse cores.toml.")?;
-        self.cores = cores.cores;
-
+        self.consoles = Self::load_consoles(&ALLIUM_CONFIG_CONSOLES)?;
+        self.cores = Self::load_cores(&ALLIUM_CONFIG_CORES)?;
         Ok(())
     }
 
+    /// Parses the consoles from a `consoles.toml`.
+    pub fn load_consoles(path: &Path) -> Result<Vec<Console>> {
+        let consoles = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to load consoles config: {}", path.display()))?;
+        let consoles: ConsoleConfig = toml::from_str(&consoles)
+            .with_context(|| format!("Failed to parse {}", path.display()))?;
+        Ok(consoles.consoles)
+    }
+
+    /// Parses the cores from a `cores.toml`.
+    pub fn load_cores(path: &Path) -> Result<HashMap<CoreName, Core>> {
+        let cores = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to load cores config: {}", path.display()))?;
+        let cores: CoresConfig = toml::from_str(&cores)
+            .with_context(|| format!("Failed to parse {}", path.display()))?;
+        Ok(cores.cores)
+    }
+
+    /// Creates a mapper from already loaded consoles and cores.
+    pub fn with_config(consoles: Vec<Console>, cores: HashMap<CoreName, Core>) -> Self {
+        Self {
+            cores,
+            consoles,
+            restrictions: ParentalSettings::default(),
+        }
+    }
+
+    pub fn consoles(&self) -> &[Console] {
+        &self.consoles
+    }
+
+    pub fn restrictions(&self) -> &ParentalSettings {
+        &self.restrictions
+    }
+
+    /// Sets the parental controls that decide which consoles, directories and games are hidden.
+    pub fn set_restrictions(&mut self, restrictions: ParentalSettings) {
+        self.restrictions = restrictions;
+    }
+
+    /// Whether a directory or game is hidden by parental controls, either by its path or because
+    /// its console is hidden.
+    pub fn is_hidden(&self, path: &Path) -> bool {
+        self.restrictions.is_path_hidden(path)
+            || self
+                .get_console(path)
+                .is_some_and(|console| self.restrictions.is_console_hidden(&console.name))
+    }
+
+    /// Hides the games in the database that are hidden by parental controls from its queries, so
+    /// that they are left out of recents, favorites and search results.
+    pub fn hide_restricted_games(&self, database: &Database) -> Result<()> {
+        let hidden: Vec<PathBuf> = if self.restrictions.enabled {
+            database
+                .select_all_games()?
+                .into_iter()
+                .map(|game| game.path)
+                .filter(|path| self.is_hidden(path))
+                .collect()
+        } else {
+            Vec::new()
+        };
+        debug!("hiding {} restricted games", hidden.len());
+        database.set_hidden_games(&hidden)
+    }
+
+    pub fn cores(&self) -> &HashMap<CoreName, Core> {
+        &self.cores
+    }
+
     /// Returns a console that matches the directory name exactly, or none.
     pub fn get_console_by_dir(&self, path: &Path) -> Option<&Console> {
         if let Some(name) = path.file_name().and_then(std::ffi::OsStr::to_str) {
@@ -135,6 +224,12 @@ impl ConsoleMapper {
 
     /// Returns a console that this path maps to, or none.
     pub fn get_console(&self, path: &Path) -> Option<&Console> {
+        self.resolve_console(path).map(|(console, _)| console)
+    }
+
+    /// Returns a console that this path maps to and what matched, or none. File names are matched
+    /// first, then extensions, then patterns of the path's directories from the innermost out.
+    pub fn resolve_console(&self, path: &Path) -> Option<(&Console, ConsoleMatch)> {
         let path_lowercase = path.as_os_str().to_ascii_lowercase();
 
         if let Some(name) = path.file_name().and_then(std::ffi::OsStr::to_str) {
@@ -142,8 +237,8 @@ impl ConsoleMapper {
                 .consoles
                 .iter()
                 .find(|core| core.file_name.iter().any(|s| name == s));
-            if console.is_some() {
-                return console;
+            if let Some(console) = console {
+                return Some((console, ConsoleMatch::FileName(name.to_string())));
             }
         }
 
@@ -153,8 +248,8 @@ impl ConsoleMapper {
                     .consoles
                     .iter()
                     .find(|core| core.extensions.iter().any(|s| s == ext));
-                if console.is_some() {
-                    return console;
+                if let Some(console) = console {
+                    return Some((console, ConsoleMatch::Extension(ext.to_string())));
                 }
             }
         }
@@ -163,13 +258,22 @@ impl ConsoleMapper {
         while let Some(path) = parent {
             trace!("path: {:?}", path);
             if let Some(filename) = path.file_name().and_then(std::ffi::OsStr::to_str) {
-                let console = self.consoles.iter().find(|core| {
-                    core.patterns.iter().any(|pattern| {
-                        filename == pattern || filename.contains(&format!("({})", pattern))
-                    })
+                let found = self.consoles.iter().find_map(|core| {
+                    core.patterns
+                        .iter()
+                        .find(|pattern| {
+                            filename == *pattern || filename.contains(&format!("({})", pattern))
+                        })
+                        .map(|pattern| (core, pattern))
                 });
-                if console.is_some() {
-                    return console;
+                if let Some((console, pattern)) = found {
+                    return Some((
+                        console,
+                        ConsoleMatch::Pattern {
+                            directory: filename.to_string(),
+                            pattern: pattern.clone(),
+                        },
+                    ));
                 }
             }
             parent = path.parent();
@@ -178,12 +282,48 @@ impl ConsoleMapper {
         None
     }
 
+    /// Launches a game. If `state_slot` is given, RetroArch loads that state instead of the auto
+    /// state. Returns a toast instead if the sleep schedule doesn't allow playing now.
     pub fn launch_game(
         &self,
         database: &Database,
+        locale: &Locale,
         game: &mut Game,
         disable_savestate_auto_load: bool,
+        state_slot: Option<i8>,
     ) -> Result<Option<Command>> {
+        if self.is_hidden(&game.path) {
+            warn!(
+                "refusing to launch restricted game: {}",
+                game.path.display()
+            );
+            return Ok(Some(Command::Toast(
+                locale.t("parental-blocked"),
+                Some(Duration::from_secs(3)),
+            )));
+        }
+
+        // alliumd would quit the game right away
+        let schedule = PowerSettings::load()?.schedule;
+        if schedule.enabled {
+            let sessions = database.select_sessions(Utc::now() - chrono::Duration::days(1))?;
+            let played_today = power::played_today(&sessions, Local::now());
+            let key = match schedule.status(&Local::now(), played_today) {
+                ScheduleStatus::Allowed(_) => None,
+                ScheduleStatus::Bedtime => Some("schedule-blocked-bedtime"),
+                ScheduleStatus::BudgetSpent => Some("schedule-blocked-budget-spent"),
+            };
+            if let Some(key) = key {
+                return Ok(Some(Command::Toast(
+                    locale.t(key),
+                    Some(Duration::from_secs(3)),
+                )));
+            }
+        }
+
+        let state_slot = state_slot.filter(|&slot| slot != AUTO_SLOT);
+        let disable_savestate_auto_load = disable_savestate_auto_load || state_slot.is_some();
+
         if !game.path.exists() {
             if let Some(old) = Game::resync(&mut game.path)? {
                 database.update_game_path(&old, &game.path)?;
@@ -200,33 +340,47 @@ impl ConsoleMapper {
                 game.path.to_string_lossy()
             );
         };
-        let Some(core_name) = game.core.as_ref().or_else(|| console.cores.first()) else {
+
+        let mut profile = self.launch_profile(database, console, game)?;
+        // RetroArch only uses the last `--appendconfig`, so achievements are appended with the
+        // profile's configs, which can still override them
+        if ALLIUM_RETROARCH_CHEEVOS_CONFIG.exists() {
+            profile
+                .append_configs
+                .insert(0, ALLIUM_RETROARCH_CHEEVOS_CONFIG.clone());
+        }
+        let Some(core_name) = profile.core.as_ref() else {
             return Ok(None);
         };
         let Some(core) = self.cores.get(core_name) else {
             error!("Core \"{}\" does not exist.", core_name);
             return Ok(None);
         };
-        let game_info = match &core.core {
-            CoreType::RetroArch(libretro_core) => GameInfo::new(
-                game.name.clone(),
-                game.path.clone(),
-                core_name.clone(),
-                image,
-                if disable_savestate_auto_load {
-                    ALLIUM_RETROARCH
-                        .parent()
-                        .unwrap()
-                        .join("launch_without_savestate_auto_load.sh")
-                        .display()
-                        .to_string()
-                } else {
-                    ALLIUM_RETROARCH.display().to_string()
-                },
-                vec![libretro_core.to_string(), game.path.display().to_string()],
-                true,
-                core.swap,
-            ),
+        let needs_swap = profile.swap.unwrap_or(core.swap);
+        let mut game_info = match &core.core {
+            CoreType::RetroArch(libretro_core) => {
+                let mut args = vec![libretro_core.to_string(), game.path.display().to_string()];
+                args.extend(profile.retroarch_command_args());
+                GameInfo::new(
+                    game.name.clone(),
+                    game.path.clone(),
+                    core_name.clone(),
+                    image,
+                    if disable_savestate_auto_load {
+                        ALLIUM_RETROARCH
+                            .parent()
+                            .unwrap()
+                            .join("launch_without_savestate_auto_load.sh")
+                            .display()
+                            .to_string()
+                    } else {
+                        ALLIUM_RETROARCH.display().to_string()
+                    },
+                    args,
+                    true,
+                    needs_swap,
+                )
+            }
             CoreType::Path(path) => GameInfo::new(
                 game.name.clone(),
                 game.path.clone(),
@@ -235,12 +389,50 @@ impl ConsoleMapper {
                 path.to_string_lossy().to_string(),
                 vec![game.path.display().to_string()],
                 false,
-                core.swap,
+                needs_swap,
             ),
         };
+        game_info.cpu = profile.cpu.clone();
         debug!("Saving game info: {:?}", game_info);
         game_info.save()?;
-        Ok(Some(Command::Exec(game_info.command())))
+        if let Err(e) = game_info.cpu.apply() {
+            warn!("Failed to apply CPU settings: {}", e);
+        }
+
+        // The entry slot is not saved in the game info, so that resuming after a restart loads the
+        // auto state instead
+        let mut command = game_info.command();
+        if let (CoreType::RetroArch(_), Some(slot)) = (&core.core, state_slot) {
+            command.arg("--entryslot").arg(slot.to_string());
+        }
+        Ok(Some(Command::Exec(command)))
+    }
+
+    /// Resolves the launch profile of a game by layering, from lowest to highest priority:
+    /// 1. the console's default core from `consoles.toml`
+    /// 2. the console's launch profile
+    /// 3. the game's launch profile, including the core picked from the context menu
+    ///
+    /// The core's defaults from `cores.toml` apply to anything left unset.
+    pub fn launch_profile(
+        &self,
+        database: &Database,
+        console: &Console,
+        game: &Game,
+    ) -> Result<LaunchProfile> {
+        let defaults = LaunchProfile {
+            core: console.cores.first().cloned(),
+            ..Default::default()
+        };
+        let console_profile =
+            database.get_launch_profile(LaunchProfileScope::Console, &console.name)?;
+        let mut game_profile = database
+            .get_launch_profile(LaunchProfileScope::Game, &game.path.display().to_string())?;
+        if game_profile.core.is_none() {
+            game_profile.core = game.core.clone();
+        }
+
+        Ok(defaults.merge(&console_profile).merge(&game_profile))
     }
 
     pub fn get_core_name(&self, core: &str) -> String {
@@ -284,6 +476,72 @@ mod tests {
         assert!(mapper.get_console(Path::new("Roms/rom.gba")).is_none());
     }
 
+    #[test]
+    fn test_restrictions() -> Result<()> {
+        let mut mapper = ConsoleMapper::new();
+        mapper.consoles = vec![
+            Console {
+                name: "Game Boy".to_string(),
+                patterns: vec!["GB".to_string()],
+                extensions: vec!["gb".to_string()],
+                cores: vec![],
+                file_name: vec![],
+            },
+            Console {
+                name: "Super Nintendo".to_string(),
+                patterns: vec!["SFC".to_string()],
+                extensions: vec!["sfc".to_string()],
+                cores: vec![],
+                file_name: vec![],
+            },
+        ];
+        mapper.set_restrictions(ParentalSettings {
+            enabled: true,
+            hidden_consoles: vec!["Game Boy".to_string()],
+            hidden_paths: vec![PathBuf::from("Roms/SFC/Horror")],
+        });
+
+        assert!(mapper.is_hidden(Path::new("Roms/GB")));
+        assert!(mapper.is_hidden(Path::new("Roms/GB/rom.zip")));
+        assert!(mapper.is_hidden(Path::new("Roms/Misc/rom.gb")));
+        assert!(mapper.is_hidden(Path::new("Roms/SFC/Horror/rom.sfc")));
+        assert!(!mapper.is_hidden(Path::new("Roms/SFC/rom.sfc")));
+        assert!(!mapper.is_hidden(Path::new("Roms/Misc")));
+
+        let database = Database::in_memory()?;
+        let game = |path: &str| common::database::NewGame {
+            name: path.to_string(),
+            path: PathBuf::from(path),
+            image: None,
+            core: None,
+            rating: None,
+            release_date: None,
+            developer: None,
+            publisher: None,
+            genres: vec![],
+        };
+        database.update_games(&[
+            game("Roms/GB/rom.gb"),
+            game("Roms/SFC/rom.sfc"),
+            game("Roms/SFC/Horror/rom.sfc"),
+        ])?;
+        mapper.hide_restricted_games(&database)?;
+        assert_eq!(
+            database
+                .search("rom", 10)?
+                .into_iter()
+                .map(|game| game.path)
+                .collect::<Vec<_>>(),
+            vec![PathBuf::from("Roms/SFC/rom.sfc")]
+        );
+
+        mapper.set_restrictions(ParentalSettings::default());
+        mapper.hide_restricted_games(&database)?;
+        assert_eq!(database.search("rom", 10)?.len(), 3);
+
+        Ok(())
+    }
+
     #[test]
     fn test_config() {
         env::set_var("ALLIUM_BASE_DIR", "../../static/.allium");
@@ -0,0 +1,464 @@
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::Result;
+use common::database::Database;
+use common::launch_profile::LaunchProfileScope;
+use log::warn;
+
+use crate::consoles::{ConsoleMapper, CoreType};
+use crate::entry::Entry;
+
+/// A problem with `consoles.toml` or `cores.toml`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Issue {
+    /// A config file could not be read or parsed.
+    InvalidConfig { path: PathBuf, error: String },
+    /// A console lists a core that is not defined in `cores.toml`.
+    UnknownCore { console: String, core: String },
+    /// A RetroArch core's `.so` file does not exist.
+    MissingRetroArchCore { core: String, path: PathBuf },
+    /// A core's launch script does not exist.
+    MissingLaunchScript { core: String, path: PathBuf },
+    /// Several consoles share a pattern. Only the first one is ever matched.
+    OverlappingPattern {
+        pattern: String,
+        consoles: Vec<String>,
+    },
+    /// Several consoles share an extension. Only the first one is ever matched.
+    OverlappingExtension {
+        extension: String,
+        consoles: Vec<String>,
+    },
+    /// Several consoles share a file name. Only the first one is ever matched.
+    OverlappingFileName {
+        file_name: String,
+        consoles: Vec<String>,
+    },
+}
+
+impl fmt::Display for Issue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Issue::InvalidConfig { path, error } => write!(f, "{}: {}", path.display(), error),
+            Issue::UnknownCore { console, core } => {
+                write!(f, "console \"{}\" uses unknown core \"{}\"", console, core)
+            }
+            Issue::MissingRetroArchCore { core, path } => {
+                write!(f, "core \"{}\" is missing {}", core, path.display())
+            }
+            Issue::MissingLaunchScript { core, path } => write!(
+                f,
+                "core \"{}\" is missing launch script {}",
+                core,
+                path.display()
+            ),
+            Issue::OverlappingPattern { pattern, consoles } => write!(
+                f,
+                "pattern \"{}\" is used by {}, only \"{}\" will match",
+                pattern,
+                consoles.join(", "),
+                consoles[0]
+            ),
+            Issue::OverlappingExtension {
+                extension,
+                consoles,
+            } => write!(
+                f,
+                "extension \".{}\" is used by {}, only \"{}\" will match",
+                extension,
+                consoles.join(", "),
+                consoles[0]
+            ),
+            Issue::OverlappingFileName {
+                file_name,
+                consoles,
+            } => write!(
+                f,
+                "file name \"{}\" is used by {}, only \"{}\" will match",
+                file_name,
+                consoles.join(", "),
+                consoles[0]
+            ),
+        }
+    }
+}
+
+/// Where a group of games in a ROM folder resolve to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Resolution {
+    /// Console the games map to, if any.
+    pub console: Option<String>,
+    /// Display name of the core the games launch with by default, if any.
+    pub core: Option<String>,
+    /// Why the games map to the console.
+    pub reason: String,
+    /// Number of games that resolve this way.
+    pub games: usize,
+}
+
+impl fmt::Display for Resolution {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (&self.console, &self.core) {
+            (Some(console), Some(core)) => write!(f, "{} ({})", console, core)?,
+            (Some(console), None) => write!(f, "{} (no core)", console)?,
+            (None, _) => write!(f, "no console")?,
+        }
+        write!(f, ": {} games, {}", self.games, self.reason)
+    }
+}
+
+/// How the games in a top-level ROM folder resolve.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FolderReport {
+    pub path: PathBuf,
+    /// Resolutions, from most to least games.
+    pub resolutions: Vec<Resolution>,
+}
+
+impl FolderReport {
+    pub fn name(&self) -> String {
+        self.path
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_default()
+    }
+}
+
+/// Validates the console mapper config and reports how each ROM folder resolves.
+#[derive(Debug, Clone, Default)]
+pub struct Diagnostics {
+    pub issues: Vec<Issue>,
+    pub folders: Vec<FolderReport>,
+}
+
+impl Diagnostics {
+    /// Runs the diagnostics. `retroarch_cores` is the directory containing the `*_libretro.so`
+    /// files. Console launch profiles are taken into account if a database is given.
+    pub fn run(
+        consoles_path: &Path,
+        cores_path: &Path,
+        retroarch_cores: &Path,
+        games_dir: &Path,
+        database: Option<&Database>,
+    ) -> Self {
+        let mut issues = Vec::new();
+
+        let consoles = ConsoleMapper::load_consoles(consoles_path).unwrap_or_else(|e| {
+            issues.push(Issue::InvalidConfig {
+                path: consoles_path.to_path_buf(),
+                error: format!("{:#}", e),
+            });
+            Vec::new()
+        });
+        let cores = ConsoleMapper::load_cores(cores_path).unwrap_or_else(|e| {
+            issues.push(Issue::InvalidConfig {
+                path: cores_path.to_path_buf(),
+                error: format!("{:#}", e),
+            });
+            HashMap::new()
+        });
+        let mapper = ConsoleMapper::with_config(consoles, cores);
+
+        issues.extend(validate(&mapper, retroarch_cores));
+
+        let folders = match report_folders(&mapper, games_dir, database) {
+            Ok(folders) => folders,
+            Err(e) => {
+                warn!("failed to list {}: {}", games_dir.display(), e);
+                Vec::new()
+            }
+        };
+
+        Self { issues, folders }
+    }
+}
+
+/// Checks the cores referenced by consoles and overlapping matchers.
+fn validate(mapper: &ConsoleMapper, retroarch_cores: &Path) -> Vec<Issue> {
+    let mut issues = Vec::new();
+
+    let consoles = mapper.consoles();
+    let cores = mapper.cores();
+
+    for console in consoles {
+        for core in &console.cores {
+            if !cores.contains_key(core) {
+                issues.push(Issue::UnknownCore {
+                    console: console.name.clone(),
+                    core: core.clone(),
+                });
+            }
+        }
+    }
+
+    // Only check cores that are used, sorted so that the report is stable
+    let mut used = consoles
+        .iter()
+        .flat_map(|console| &console.cores)
+        .filter_map(|name| cores.get_key_value(name))
+        .collect::<Vec<_>>();
+    used.sort_by_key(|(name, _)| *name);
+    used.dedup_by_key(|(name, _)| *name);
+    for (name, core) in used {
+        match &core.core {
+            CoreType::RetroArch(libretro_core) => {
+                let path = retroarch_cores.join(format!("{}_libretro.so", libretro_core));
+                if !path.exists() {
+                    issues.push(Issue::MissingRetroArchCore {
+                        core: name.clone(),
+                        path,
+                    });
+                }
+            }
+            CoreType::Path(path) => {
+                if !path.exists() {
+                    issues.push(Issue::MissingLaunchScript {
+                        core: name.clone(),
+                        path: path.clone(),
+                    });
+                }
+            }
+        }
+    }
+
+    for (pattern, consoles) in overlaps(consoles.iter().map(|c| (&c.name, &c.patterns))) {
+        issues.push(Issue::OverlappingPattern { pattern, consoles });
+    }
+    for (extension, consoles) in overlaps(consoles.iter().map(|c| (&c.name, &c.extensions))) {
+        issues.push(Issue::OverlappingExtension {
+            extension,
+            consoles,
+        });
+    }
+    for (file_name, consoles) in overlaps(consoles.iter().map(|c| (&c.name, &c.file_name))) {
+        issues.push(Issue::OverlappingFileName {
+            file_name,
+            consoles,
+        });
+    }
+
+    issues
+}
+
+/// Returns the values that are listed by more than one console, with the consoles in config order.
+fn overlaps<'a>(
+    consoles: impl Iterator<Item = (&'a String, &'a Vec<String>)>,
+) -> BTreeMap<String, Vec<String>> {
+    let mut users: BTreeMap<String, Vec<String>> = BTreeMap::new();
+    for (console, values) in consoles {
+        for value in values {
+            let users = users.entry(value.clone()).or_default();
+            if !users.contains(console) {
+                users.push(console.clone());
+            }
+        }
+    }
+    users.retain(|_, consoles| consoles.len() > 1);
+    users
+}
+
+/// Resolves every game in each top-level folder of `games_dir`, grouped by console and reason.
+fn report_folders(
+    mapper: &ConsoleMapper,
+    games_dir: &Path,
+    database: Option<&Database>,
+) -> Result<Vec<FolderReport>> {
+    let mut folders = fs::read_dir(games_dir)?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| {
+            matches!(
+                Entry::new(path.clone(), mapper),
+                Ok(Some(Entry::Directory(_)))
+            )
+        })
+        .collect::<Vec<_>>();
+    folders.sort();
+
+    let mut reports = Vec::with_capacity(folders.len());
+    for folder in folders {
+        let mut groups: HashMap<(Option<String>, String), usize> = HashMap::new();
+
+        let mut queue = VecDeque::from([folder.clone()]);
+        while let Some(dir) = queue.pop_front() {
+            for entry in fs::read_dir(&dir)? {
+                let path = entry?.path();
+                match Entry::new(path.clone(), mapper)? {
+                    Some(Entry::Directory(_)) => queue.push_back(path),
+                    Some(Entry::Game(_)) => {
+                        let key = match mapper.resolve_console(&path) {
+                            Some((console, reason)) => {
+                                (Some(console.name.clone()), reason.to_string())
+                            }
+                            None => (
+                                None,
+                                "no matching file name, extension or folder pattern".to_string(),
+                            ),
+                        };
+                        *groups.entry(key).or_default() += 1;
+                    }
+                    Some(Entry::App(_)) | None => {}
+                }
+            }
+        }
+
+        let mut resolutions = groups
+            .into_iter()
+            .map(|((console, reason), games)| {
+                let core = console
+                    .as_ref()
+                    .and_then(|name| mapper.consoles().iter().find(|c| &c.name == name))
+                    .and_then(|console| {
+                        database
+                            .and_then(|db| {
+                                db.get_launch_profile(LaunchProfileScope::Console, &console.name)
+                                    .ok()
+                            })
+                            .and_then(|profile| profile.core)
+                            .or_else(|| console.cores.first().cloned())
+                    })
+                    .map(|core| mapper.get_core_name(&core));
+                Resolution {
+                    console,
+                    core,
+                    reason,
+                    games,
+                }
+            })
+            .collect::<Vec<_>>();
+        resolutions.sort_by(|a, b| b.games.cmp(&a.games).then(a.reason.cmp(&b.reason)));
+
+        reports.push(FolderReport {
+            path: folder,
+            resolutions,
+        });
+    }
+
+    Ok(reports)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_diagnostics() {
+        let dir = std::env::temp_dir().join("allium-test-diagnostics");
+        let _ = fs::remove_dir_all(&dir);
+        let cores_dir = dir.join("cores");
+        let games_dir = dir.join("Roms");
+        fs::create_dir_all(&cores_dir).unwrap();
+        fs::create_dir_all(games_dir.join("GBA")).unwrap();
+        fs::create_dir_all(games_dir.join("Misc")).unwrap();
+
+        fs::write(
+            dir.join("consoles.toml"),
+            r#"
+[[consoles]]
+name = "Game Boy Advance"
+cores = ["gpsp", "missing"]
+patterns = ["GBA"]
+extensions = ["gba"]
+
+[[consoles]]
+name = "Other"
+cores = ["script"]
+patterns = ["GBA"]
+extensions = ["gba", "bin"]
+"#,
+        )
+        .unwrap();
+        fs::write(
+            dir.join("cores.toml"),
+            r#"
+[cores.gpsp]
+retroarch = "gpsp"
+name = "gpSP"
+
+[cores.script]
+path = "/nonexistent/launch.sh"
+name = "Script"
+"#,
+        )
+        .unwrap();
+        fs::write(cores_dir.join("gpsp_libretro.so"), "").unwrap();
+        fs::write(games_dir.join("GBA/Game One.zip"), "").unwrap();
+        fs::write(games_dir.join("GBA/Game Two.gba"), "").unwrap();
+        fs::write(games_dir.join("GBA/Game Three.zip"), "").unwrap();
+        fs::write(games_dir.join("Misc/Game.bin"), "").unwrap();
+        fs::write(games_dir.join("Misc/Game.xyz"), "").unwrap();
+
+        let diagnostics = Diagnostics::run(
+            &dir.join("consoles.toml"),
+            &dir.join("cores.toml"),
+            &cores_dir,
+            &games_dir,
+            None,
+        );
+
+        assert_eq!(
+            diagnostics.issues,
+            vec![
+                Issue::UnknownCore {
+                    console: "Game Boy Advance".to_string(),
+                    core: "missing".to_string(),
+                },
+                Issue::MissingLaunchScript {
+                    core: "script".to_string(),
+                    path: PathBuf::from("/nonexistent/launch.sh"),
+                },
+                Issue::OverlappingPattern {
+                    pattern: "GBA".to_string(),
+                    consoles: vec!["Game Boy Advance".to_string(), "Other".to_string()],
+                },
+                Issue::OverlappingExtension {
+                    extension: "gba".to_string(),
+                    consoles: vec!["Game Boy Advance".to_string(), "Other".to_string()],
+                },
+            ]
+        );
+
+        assert_eq!(diagnostics.folders.len(), 2);
+        let gba = &diagnostics.folders[0];
+        assert_eq!(gba.name(), "GBA");
+        assert_eq!(
+            gba.resolutions,
+            vec![
+                Resolution {
+                    console: Some("Game Boy Advance".to_string()),
+                    core: Some("gpSP".to_string()),
+                    reason: "folder \"GBA\"".to_string(),
+                    games: 2,
+                },
+                Resolution {
+                    console: Some("Game Boy Advance".to_string()),
+                    core: Some("gpSP".to_string()),
+                    reason: "extension \".gba\"".to_string(),
+                    games: 1,
+                },
+            ]
+        );
+        let misc = &diagnostics.folders[1];
+        assert_eq!(misc.resolutions.len(), 2);
+        assert_eq!(misc.resolutions[0].console.as_deref(), Some("Other"));
+        assert_eq!(misc.resolutions[0].core.as_deref(), Some("Script"));
+        assert_eq!(misc.resolutions[1].console, None);
+
+        fs::write(dir.join("cores.toml"), "[cores.gpsp\n").unwrap();
+        let diagnostics = Diagnostics::run(
+            &dir.join("consoles.toml"),
+            &dir.join("cores.toml"),
+            &cores_dir,
+            &games_dir,
+            None,
+        );
+        assert!(matches!(
+            &diagnostics.issues[0],
+            Issue::InvalidConfig { path, .. } if path == &dir.join("cores.toml")
+        ));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -1,24 +1,26 @@
 use std::{
-    collections::{HashSet, VecDeque},
+    collections::HashSet,
     ffi::OsStr,
-    fs::{self, File},
-    io::Read,
+    fs,
     path::{Path, PathBuf},
+    time::UNIX_EPOCH,
 };
 
 use anyhow::{anyhow, Result};
 use common::{
-    constants::ALLIUM_GAMES_DIR,
-    database::{Database, NewGame},
+    constants::{ALLIUM_BASE_DIR, ALLIUM_COLLECTIONS_DIR, ALLIUM_GAMES_DIR},
+    database::{Database, GamelistFingerprint, GroupBy, NewGame},
+    gamelist::GameList,
     locale::Locale,
 };
 use itertools::Itertools;
-use log::{error, trace};
+use log::{error, trace, warn};
 use serde::{Deserialize, Serialize};
 
 use crate::{
     consoles::ConsoleMapper,
-    entry::{game::Game, gamelist::GameList, lazy_image::LazyImage, short_name, Entry},
+    entry::{game::Game, lazy_image::LazyImage, multi_disc::DiscSets, short_name, Entry},
+    identify::Identifier,
 };
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
@@ -29,11 +31,27 @@ pub struct Directory {
     /// image is loaded lazily.
     /// None means image hasn't been looked for, Some(None) means no image was found, Some(Some(path)) means an image was found.
     pub image: LazyImage,
+    /// Name of the collection if this is a virtual directory listing a collection's games.
+    #[serde(default)]
+    pub collection: Option<String>,
+    /// Set if this is a virtual directory listing games by metadata.
+    #[serde(default)]
+    pub browse: Option<Browse>,
+}
+
+/// A virtual directory that lists games by metadata instead of by folder.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub enum Browse {
+    /// Lists every group, e.g. all genres.
+    Groups(GroupBy),
+    /// Lists the games in a group across every console, e.g. all RPGs.
+    Group(GroupBy, String),
 }
 
 impl Ord for Directory {
     fn cmp(&self, other: &Self) -> std::cmp::Ordering {
-        self.name.cmp(&other.name)
+        // Virtual directories are listed before regular directories
+        (!self.is_virtual(), &self.name).cmp(&(!other.is_virtual(), &other.name))
     }
 }
 
@@ -50,6 +68,8 @@ impl Default for Directory {
             full_name: "Games".to_string(),
             path: ALLIUM_GAMES_DIR.to_owned(),
             image: LazyImage::Unknown(ALLIUM_GAMES_DIR.to_owned()),
+            collection: None,
+            browse: None,
         }
     }
 }
@@ -68,6 +88,8 @@ impl Directory {
             full_name,
             path,
             image,
+            collection: None,
+            browse: None,
         }
     }
 
@@ -83,30 +105,53 @@ impl Directory {
             full_name,
             path,
             image,
+            collection: None,
+            browse: None,
+        }
+    }
+
+    /// A virtual directory listing the games of a collection.
+    pub fn collection(name: String) -> Directory {
+        Directory {
+            full_name: name.clone(),
+            path: ALLIUM_COLLECTIONS_DIR.join(&name),
+            image: LazyImage::NotFound,
+            collection: Some(name.clone()),
+            browse: None,
+            name,
         }
     }
 
+    /// A virtual directory listing games by metadata.
+    pub fn browse(browse: Browse, name: String) -> Directory {
+        let path = match &browse {
+            Browse::Groups(group_by) => ALLIUM_BASE_DIR.join("browse").join(group_key(*group_by)),
+            Browse::Group(group_by, group) => ALLIUM_BASE_DIR
+                .join("browse")
+                .join(group_key(*group_by))
+                .join(group),
+        };
+        Directory {
+            full_name: name.clone(),
+            path,
+            image: LazyImage::NotFound,
+            collection: None,
+            browse: Some(browse),
+            name,
+        }
+    }
+
+    /// Whether this directory is a collection or metadata listing rather than a folder.
+    pub fn is_virtual(&self) -> bool {
+        self.collection.is_some() || self.browse.is_some()
+    }
+
     pub fn image(&mut self) -> Option<&Path> {
         self.image.image()
     }
 
     fn parse_game_list(&self, game_list: &Path) -> Result<Vec<Entry>> {
-        let mut file = File::open(game_list)?;
-        let mut s = String::with_capacity(1024);
-        file.read_to_string(&mut s)?;
-        let gamelist: GameList = match quick_xml::de::from_str(&s) {
-            Ok(gamelist) => gamelist,
-            Err(quick_xml::DeError::InvalidXml(quick_xml::Error::EscapeError(
-                quick_xml::escape::EscapeError::UnterminatedEntity(..),
-            ))) => {
-                // Some scrapers produce ill-formed XML where ampersands (&) are not escaped,
-                // so we try to failover to replacing them to &amp;
-                // (https://github.com/RReverser/serde-xml-rs/issues/106)
-                s = s.replace('&', "&amp;");
-                quick_xml::de::from_str(&s)?
-            }
-            Err(e) => return Err(e.into()),
-        };
+        let gamelist = GameList::from_xml(&fs::read_to_string(game_list)?)?;
 
         let games = gamelist.games.into_iter().filter_map(|game| {
             let path = self.path.join(&game.path).canonicalize().ok()?;
@@ -165,12 +210,34 @@ impl Directory {
         Ok(folders.chain(games).collect())
     }
 
+    /// Lists the entries in this directory, leaving out those hidden by parental controls.
     pub fn entries(
         &self,
         database: &Database,
         console_mapper: &ConsoleMapper,
-        #[allow(unused)] locale: &Locale,
+        locale: &Locale,
     ) -> Result<Vec<Entry>> {
+        let mut entries = self.list_entries(database, console_mapper, Some(locale))?;
+        // Hidden games are still indexed, so that they are in the database once they are shown
+        entries.retain(|entry| !console_mapper.is_hidden(entry.path()));
+        Ok(entries)
+    }
+
+    /// Lists the entries in this directory. If `locale` is given, a message is shown while a
+    /// gamelist is being parsed, which is left out when indexing in the background.
+    fn list_entries(
+        &self,
+        database: &Database,
+        console_mapper: &ConsoleMapper,
+        #[allow(unused)] locale: Option<&Locale>,
+    ) -> Result<Vec<Entry>> {
+        if let Some(collection) = self.collection.as_deref() {
+            return self.collection_entries(database, collection);
+        }
+        if let Some(browse) = &self.browse {
+            return self.browse_entries(database, browse);
+        }
+
         let mut entries: Vec<Entry> = Vec::with_capacity(64);
 
         let fingerprint = database.get_gamelist_fingerprint(&self.path)?;
@@ -180,35 +247,23 @@ impl Directory {
                 return Ok(false);
             }
 
-            if let Some(fingerprint) = fingerprint {
-                let Ok(metadata) = fs::metadata(path) else {
-                    trace!("Failed to get gamelist metadata, don't parse.");
-                    return Ok(false);
-                };
-                let file_size = metadata.len();
-                if file_size == fingerprint {
-                    trace!("Same gamelist size, not parsing.");
-                    return Ok(false);
-                }
-                database.set_gamelist_fingerprint(&self.path, file_size)?;
-                trace!("Different gamelist size, parse gamelist.");
-                Ok(true)
-            } else {
-                trace!("No gamelist fingerprint, parse gamelist.");
-                let Ok(metadata) = fs::metadata(path) else {
-                    trace!("Failed to get gamelist metadata, don't parse.");
-                    return Ok(false);
-                };
-                let file_size = metadata.len();
-                database.set_gamelist_fingerprint(&self.path, file_size)?;
-                Ok(true)
+            let Some(current) = gamelist_fingerprint(path) else {
+                trace!("Failed to get gamelist metadata, don't parse.");
+                return Ok(false);
+            };
+            if fingerprint == Some(current) {
+                trace!("Same gamelist size and modification time, not parsing.");
+                return Ok(false);
             }
+            database.set_gamelist_fingerprint(&self.path, current)?;
+            trace!("Different or no gamelist fingerprint, parse gamelist.");
+            Ok(true)
         };
 
         let gamelist = self.path.join("gamelist.xml");
         if should_parse_gamelist(&gamelist)? {
             #[cfg(feature = "miyoo")]
-            {
+            if let Some(locale) = locale {
                 std::process::Command::new("show")
                     .arg("--darken")
                     .spawn()?
@@ -259,7 +314,7 @@ impl Directory {
             let gamelist = self.path.join("miyoogamelist.xml");
             if should_parse_gamelist(&gamelist)? {
                 #[cfg(feature = "miyoo")]
-                {
+                if let Some(locale) = locale {
                     std::process::Command::new("show")
                         .arg("--darken")
                         .spawn()?
@@ -311,16 +366,35 @@ impl Directory {
 
         entries.extend(
             database
-                .select_games_in_directory(&self.path)?
+                .select_visible_games_in_directory(&self.path)?
                 .into_iter()
                 .map(Game::from_db)
                 .map(Entry::Game),
         );
 
+        // Discs of multi-disc games are listed once, as the playlist that launches them
+        let disc_sets = match DiscSets::group(&self.path, database, console_mapper) {
+            Ok(disc_sets) => disc_sets,
+            Err(e) => {
+                error!("Failed to group discs in {:?}: {}", self.path, e);
+                DiscSets::default()
+            }
+        };
+        for entry in entries.iter_mut() {
+            if let Entry::Game(game) = entry {
+                if let Some(playlist) = disc_sets.playlist(&game.path) {
+                    game.path = playlist.to_path_buf();
+                    game.extension = "m3u".to_string();
+                }
+            }
+        }
+        entries.retain(|e| !matches!(e, Entry::Game(game) if disc_sets.is_hidden(&game.path)));
+
         entries.extend(
             std::fs::read_dir(&self.path)
                 .map_err(|e| anyhow!("Failed to open directory: {:?}, {}", &self.path, e))?
                 .filter_map(std::result::Result::ok)
+                .filter(|entry| !disc_sets.is_hidden(&entry.path()))
                 .filter_map(|entry| match Entry::new(entry.path(), console_mapper) {
                     Ok(Some(entry)) => Some(entry),
                     _ => None,
@@ -329,6 +403,15 @@ impl Directory {
                 .dedup_by(|a, b| a.name() == b.name()),
         );
 
+        if self.path == *ALLIUM_GAMES_DIR {
+            entries.extend(
+                database
+                    .select_collections()?
+                    .into_iter()
+                    .map(|c| Entry::Directory(Directory::collection(c.name))),
+            );
+        }
+
         let mut uniques = HashSet::new();
         entries.retain(|e| uniques.insert(e.path().to_path_buf()));
 
@@ -343,25 +426,62 @@ impl Directory {
         Ok(entries)
     }
 
-    /// Populate the database with the games in this directory, pushing any subdirectories onto the
-    /// queue.
+    /// Lists the games in a collection. Games that have not been indexed yet are listed as long as
+    /// they exist.
+    fn collection_entries(&self, database: &Database, collection: &str) -> Result<Vec<Entry>> {
+        let Some(collection) = database.select_collection(collection)? else {
+            return Ok(Vec::new());
+        };
+
+        let paths = database.select_collection_paths(collection.id)?;
+        let games =
+            database.select_games(&paths.iter().map(PathBuf::as_path).collect::<Vec<_>>())?;
+
+        Ok(paths
+            .into_iter()
+            .zip(games)
+            .filter_map(|(path, game)| match game {
+                Some(game) => Some(Entry::Game(Game::from_db(game))),
+                None if path.exists() => Some(Entry::Game(Game::new(path))),
+                None => None,
+            })
+            .collect())
+    }
+
+    /// Lists the groups or games of a metadata listing.
+    fn browse_entries(&self, database: &Database, browse: &Browse) -> Result<Vec<Entry>> {
+        Ok(match browse {
+            Browse::Groups(group_by) => database
+                .select_groups(*group_by)?
+                .into_iter()
+                .map(|group| {
+                    let name = match group_by {
+                        GroupBy::Decade => format!("{}s", group),
+                        _ => group.clone(),
+                    };
+                    Entry::Directory(Directory::browse(Browse::Group(*group_by, group), name))
+                })
+                .collect(),
+            Browse::Group(group_by, group) => database
+                .select_games_in_group(*group_by, group)?
+                .into_iter()
+                .filter(|game| game.path.exists())
+                .map(|game| Entry::Game(Game::from_db(game)))
+                .collect(),
+        })
+    }
+
+    /// Populate the database with the games in this directory. Games are hashed and identified
+    /// using `identifier`.
     pub fn populate_db(
         &self,
-        queue: &mut VecDeque<Directory>,
         database: &Database,
         console_mapper: &ConsoleMapper,
-        locale: &Locale,
+        identifier: &Identifier,
     ) -> Result<()> {
-        let entries = self.entries(database, console_mapper, locale)?;
+        let entries = self.list_entries(database, console_mapper, None)?;
 
-        for entry in &entries {
-            match entry {
-                Entry::Directory(dir) => queue.push_back(dir.clone()),
-                Entry::Game(_) | Entry::App(_) => {}
-            }
-        }
-
-        let games: Vec<_> = entries
+        let mut games: Vec<_> = entries
             .into_iter()
             .filter_map(|entry| match entry {
                 Entry::Game(game) => Some(NewGame {
@@ -378,7 +498,35 @@ impl Directory {
                 _ => None,
             })
             .collect();
+
+        let mut hashes = Vec::with_capacity(games.len());
+        for game in games.iter_mut() {
+            let hash = match identifier.identify(&game.path) {
+                Ok(Some(hash)) => hash,
+                Ok(None) => continue,
+                Err(e) => {
+                    warn!("failed to hash {}: {}", game.path.display(), e);
+                    continue;
+                }
+            };
+
+            // Replace names that came from badly named files, but not those from a gamelist
+            if let Some(title) = &hash.title {
+                let file_name = game
+                    .path
+                    .file_stem()
+                    .and_then(OsStr::to_str)
+                    .unwrap_or_default();
+                if game.name == short_name(file_name) {
+                    game.name.clone_from(title);
+                }
+            }
+
+            hashes.push(hash);
+        }
+
         database.update_games(&games)?;
+        database.update_game_hashes(&hashes)?;
 
         Ok(())
     }
@@ -389,3 +537,26 @@ impl From<&Path> for Directory {
         Directory::new(path.into())
     }
 }
+
+fn group_key(group_by: GroupBy) -> &'static str {
+    match group_by {
+        GroupBy::Genre => "genre",
+        GroupBy::Developer => "developer",
+        GroupBy::Publisher => "publisher",
+        GroupBy::Decade => "decade",
+    }
+}
+
+/// Size and modification time of a gamelist, to tell whether it needs to be parsed again.
+pub fn gamelist_fingerprint(path: &Path) -> Option<GamelistFingerprint> {
+    let metadata = fs::metadata(path).ok()?;
+    Some(GamelistFingerprint {
+        size: metadata.len(),
+        mtime: metadata
+            .modified()
+            .ok()?
+            .duration_since(UNIX_EPOCH)
+            .ok()?
+            .as_nanos() as i64,
+    })
+}
@@ -1,8 +1,8 @@
 pub mod app;
 pub mod directory;
 pub mod game;
-mod gamelist;
 pub mod lazy_image;
+pub mod multi_disc;
 
 use std::ffi::OsStr;
 use std::fmt::Debug;
@@ -0,0 +1,339 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::ffi::{OsStr, OsString};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::Result;
+use common::database::{Database, NewGame};
+use common::save_state::SaveState;
+use lazy_static::lazy_static;
+use log::{info, warn};
+use regex::Regex;
+
+use crate::consoles::ConsoleMapper;
+use crate::entry::short_name;
+
+/// Extensions of disc images that can be listed in a playlist, from most to least preferred. When
+/// a disc is available in several formats, e.g. a .cue with its .bin, the preferred one is used.
+const DISC_EXTENSIONS: [&str; 12] = [
+    "cue", "chd", "pbp", "ccd", "cdi", "gdi", "mds", "nrg", "iso", "cso", "img", "bin",
+];
+
+lazy_static! {
+    /// Matches the disc number in a file stem, e.g. "Game (USA) (Disc 1)" or "Game [CD2 of 3]".
+    static ref DISC_RE: Regex =
+        Regex::new(r"(?i)^(.*?)\s*[\(\[](?:disc|disk|cd)\s*(\d+)(?:\s*of\s*\d+)?[\)\]](.*)$")
+            .unwrap();
+}
+
+/// Multi-disc games found in a directory, and the playlists they are launched with.
+#[derive(Debug, Default)]
+pub struct DiscSets {
+    /// File names of discs that are listed in a playlist, and should not be listed themselves.
+    hidden: HashSet<OsString>,
+    /// Playlist of each set, by file name of the set's first disc.
+    playlists: HashMap<OsString, PathBuf>,
+}
+
+impl DiscSets {
+    /// Finds the multi-disc games in a directory and groups them. Existing playlists are reused,
+    /// otherwise one is written next to the discs. The play history and save states of the discs
+    /// are moved to the playlist, so that they are attributed to the game as a whole.
+    pub fn group(dir: &Path, database: &Database, console_mapper: &ConsoleMapper) -> Result<Self> {
+        let mut sets = Self::default();
+
+        let files = fs::read_dir(dir)?
+            .filter_map(std::result::Result::ok)
+            .map(|entry| entry.path())
+            .filter(|path| path.is_file())
+            .collect::<Vec<_>>();
+
+        // Discs already listed in a playlist, and tracks of cue sheets
+        let mut listed = HashSet::new();
+        for path in &files {
+            match extension(path).as_deref() {
+                Some("m3u") => {
+                    let referenced = referenced_files(path, "")?;
+                    sets.hidden.extend(referenced.iter().cloned());
+                    listed.extend(referenced);
+                }
+                Some("cue") => listed.extend(referenced_files(path, "FILE ")?),
+                _ => {}
+            }
+        }
+
+        for (base, discs) in find_sets(&files, &listed) {
+            let playlist = dir.join(format!("{}.m3u", base));
+            if !playlist.exists() {
+                info!("writing playlist {}", playlist.display());
+                let contents = discs
+                    .iter()
+                    .filter_map(|disc| disc.file_name().and_then(OsStr::to_str))
+                    .map(|name| format!("{}\n", name))
+                    .collect::<String>();
+                fs::write(&playlist, contents)?;
+                if let Err(e) = move_save_states(&discs, &playlist, database, console_mapper) {
+                    warn!(
+                        "failed to move save states to {}: {}",
+                        playlist.display(),
+                        e
+                    );
+                }
+            }
+
+            merge_history(&discs, &playlist, database)?;
+
+            sets.playlists
+                .insert(discs[0].file_name().unwrap().to_owned(), playlist);
+            sets.hidden.extend(
+                discs
+                    .iter()
+                    .filter_map(|disc| disc.file_name())
+                    .map(OsStr::to_owned),
+            );
+        }
+
+        Ok(sets)
+    }
+
+    /// Whether the file is a disc that is listed in a playlist.
+    pub fn is_hidden(&self, path: &Path) -> bool {
+        path.file_name()
+            .is_some_and(|name| self.hidden.contains(name))
+    }
+
+    /// The playlist to launch instead, if the file is the first disc of a multi-disc game.
+    pub fn playlist(&self, path: &Path) -> Option<&Path> {
+        path.file_name()
+            .and_then(|name| self.playlists.get(name))
+            .map(PathBuf::as_path)
+    }
+}
+
+fn extension(path: &Path) -> Option<String> {
+    path.extension()
+        .and_then(OsStr::to_str)
+        .map(str::to_lowercase)
+}
+
+/// File names referenced by a playlist or cue sheet. Lines starting with `prefix` are read, and
+/// the (optionally quoted) path that follows is taken.
+fn referenced_files(path: &Path, prefix: &str) -> Result<Vec<OsString>> {
+    let contents = fs::read_to_string(path)?;
+    Ok(contents
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .filter_map(|line| line.strip_prefix(prefix))
+        .filter_map(|line| {
+            let line = line.trim();
+            let file = match line.strip_prefix('"') {
+                Some(quoted) => quoted.split('"').next()?,
+                None if prefix.is_empty() => line,
+                None => line.split_whitespace().next()?,
+            };
+            Path::new(file).file_name().map(OsStr::to_owned)
+        })
+        .collect())
+}
+
+/// Groups files into multi-disc sets by their name without the disc number. Sets are sorted by
+/// disc number, and only have the preferred extension. Sets with a single disc are ignored.
+fn find_sets(files: &[PathBuf], listed: &HashSet<OsString>) -> BTreeMap<String, Vec<PathBuf>> {
+    // base -> extension -> disc number -> path
+    let mut candidates: BTreeMap<String, HashMap<String, BTreeMap<u32, PathBuf>>> = BTreeMap::new();
+    for path in files {
+        let (Some(name), Some(stem), Some(ext)) = (
+            path.file_name(),
+            path.file_stem().and_then(OsStr::to_str),
+            extension(path),
+        ) else {
+            continue;
+        };
+        if listed.contains(name) || !DISC_EXTENSIONS.contains(&ext.as_str()) {
+            continue;
+        }
+        let Some(captures) = DISC_RE.captures(stem) else {
+            continue;
+        };
+        let Ok(disc) = captures[2].parse::<u32>() else {
+            continue;
+        };
+        let base = format!("{}{}", &captures[1], &captures[3]);
+        candidates
+            .entry(base)
+            .or_default()
+            .entry(ext)
+            .or_default()
+            .insert(disc, path.clone());
+    }
+
+    candidates
+        .into_iter()
+        .filter_map(|(base, mut by_extension)| {
+            let ext = DISC_EXTENSIONS
+                .iter()
+                .find(|ext| by_extension.contains_key(**ext))?;
+            let discs = by_extension.remove(*ext)?;
+            (discs.len() > 1).then(|| (base, discs.into_values().collect()))
+        })
+        .collect()
+}
+
+/// Moves the play history of the discs to the playlist.
+fn merge_history(discs: &[PathBuf], playlist: &Path, database: &Database) -> Result<()> {
+    let paths = discs.iter().map(PathBuf::as_path).collect::<Vec<_>>();
+    let games = database.select_games(&paths)?;
+    let Some(first) = games.iter().flatten().next() else {
+        return Ok(());
+    };
+
+    if database.select_game(playlist)?.is_none() {
+        let stem = playlist
+            .file_stem()
+            .and_then(OsStr::to_str)
+            .unwrap_or_default();
+        database.update_games(&[NewGame {
+            name: short_name(stem),
+            path: playlist.to_path_buf(),
+            image: first.image.clone(),
+            core: first.core.clone(),
+            rating: first.rating,
+            release_date: first.release_date,
+            developer: first.developer.clone(),
+            publisher: first.publisher.clone(),
+            genres: first.genres.clone(),
+        }])?;
+    }
+    database.merge_games(&paths, playlist)?;
+
+    Ok(())
+}
+
+/// Moves the save states of the discs to the playlist. For each slot, the most recent state of any
+/// disc is kept.
+fn move_save_states(
+    discs: &[PathBuf],
+    playlist: &Path,
+    database: &Database,
+    console_mapper: &ConsoleMapper,
+) -> Result<()> {
+    let core = match database.get_core(&discs[0])? {
+        Some(core) => core,
+        None => match console_mapper
+            .get_console(playlist)
+            .and_then(|console| console.cores.first())
+        {
+            Some(core) => core.clone(),
+            None => return Ok(()),
+        },
+    };
+
+    let mut states = Vec::new();
+    for disc in discs {
+        states.extend(SaveState::list(disc, &core)?);
+    }
+    states.sort_by_key(|state| std::cmp::Reverse(state.modified));
+
+    let mut slots = SaveState::list(playlist, &core)?
+        .into_iter()
+        .map(|state| state.slot)
+        .collect::<HashSet<_>>();
+    for state in states {
+        if slots.insert(state.slot) {
+            state.move_to(playlist)?;
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_group_discs() -> Result<()> {
+        let dir = std::env::temp_dir().join("allium-test-multi-disc");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir)?;
+        for file in [
+            "Game (USA) (Disc 1).cue",
+            "Game (USA) (Disc 1) (Track 1).bin",
+            "Game (USA) (Disc 2).cue",
+            "Game (USA) (Disc 2) (Track 1).bin",
+            "Other (Disc 1).chd",
+            "Other (Disc 2).chd",
+            "Listed (Disc 1).iso",
+            "Listed (Disc 2).iso",
+            "Single (Disc 1).iso",
+        ] {
+            fs::write(dir.join(file), "")?;
+        }
+        fs::write(
+            dir.join("Game (USA) (Disc 1).cue"),
+            "FILE \"Game (USA) (Disc 1) (Track 1).bin\" BINARY\n",
+        )?;
+        fs::write(
+            dir.join("Game (USA) (Disc 2).cue"),
+            "FILE \"Game (USA) (Disc 2) (Track 1).bin\" BINARY\n",
+        )?;
+        fs::write(
+            dir.join("My Playlist.m3u"),
+            "Listed (Disc 1).iso\nListed (Disc 2).iso\n",
+        )?;
+
+        let database = Database::in_memory()?;
+        let disc = NewGame {
+            name: "Game".to_string(),
+            path: dir.join("Game (USA) (Disc 2).cue"),
+            image: None,
+            core: None,
+            rating: Some(8),
+            release_date: None,
+            developer: None,
+            publisher: None,
+            genres: Vec::new(),
+        };
+        database.update_games(std::slice::from_ref(&disc))?;
+        database.increment_play_count(&disc)?;
+
+        let sets = DiscSets::group(&dir, &database, &ConsoleMapper::new())?;
+
+        let playlist = dir.join("Game (USA).m3u");
+        assert_eq!(
+            fs::read_to_string(&playlist)?,
+            "Game (USA) (Disc 1).cue\nGame (USA) (Disc 2).cue\n"
+        );
+        assert_eq!(
+            fs::read_to_string(dir.join("Other.m3u"))?,
+            "Other (Disc 1).chd\nOther (Disc 2).chd\n"
+        );
+        assert!(!dir.join("Listed.m3u").exists());
+        assert!(!dir.join("Single.m3u").exists());
+
+        assert!(sets.is_hidden(&dir.join("Game (USA) (Disc 2).cue")));
+        assert!(sets.is_hidden(&dir.join("Listed (Disc 1).iso")));
+        assert!(!sets.is_hidden(&dir.join("Single (Disc 1).iso")));
+        assert!(!sets.is_hidden(&playlist));
+        assert_eq!(
+            sets.playlist(&dir.join("Game (USA) (Disc 1).cue")),
+            Some(playlist.as_path())
+        );
+        assert_eq!(sets.playlist(&dir.join("Game (USA) (Disc 2).cue")), None);
+
+        let game = database.select_game(&playlist)?.unwrap();
+        assert_eq!(game.name, "Game");
+        assert_eq!(game.play_count, 1);
+        assert_eq!(game.rating, Some(8));
+        assert_eq!(database.select_game(&disc.path)?, None);
+
+        // Playlists are reused
+        fs::write(&playlist, "Game (USA) (Disc 1).cue\n")?;
+        DiscSets::group(&dir, &database, &ConsoleMapper::new())?;
+        assert_eq!(fs::read_to_string(&playlist)?, "Game (USA) (Disc 1).cue\n");
+
+        fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+}
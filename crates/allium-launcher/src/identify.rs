@@ -0,0 +1,61 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use anyhow::Result;
+use common::constants::ALLIUM_DATS_DIR;
+use common::dat::Dat;
+use common::database::{Database, GameHash};
+use common::hash;
+use log::debug;
+
+/// Identifies ROMs by hash while indexing, and matches them against the No-Intro/Redump DATs in
+/// the dats directory.
+#[derive(Debug)]
+pub struct Identifier {
+    /// Hashes from the previous index, reused if the file size has not changed.
+    known: HashMap<PathBuf, GameHash>,
+    dat: Dat,
+}
+
+impl Identifier {
+    /// Must be created before unplayed games are deleted from the database, so that their hashes
+    /// can be reused.
+    pub fn new(database: &Database) -> Result<Self> {
+        let known = database
+            .select_game_hashes()?
+            .into_iter()
+            .map(|hash| (hash.path.clone(), hash))
+            .collect();
+        let dat = Dat::load_dir(&ALLIUM_DATS_DIR)?;
+
+        Ok(Self { known, dat })
+    }
+
+    /// Hashes a ROM and looks it up in the DATs. Returns None if the ROM cannot be hashed, e.g.
+    /// because it is too large.
+    pub fn identify(&self, path: &Path) -> Result<Option<GameHash>> {
+        if path.is_dir() {
+            return Ok(None);
+        }
+
+        let hash = match self.known.get(path) {
+            Some(known) if hash::rom_size(path)? == Some(known.hash.size) => known.hash.clone(),
+            _ => {
+                debug!("hashing {}", path.display());
+                match hash::hash_rom(path)? {
+                    Some(hash) => hash,
+                    None => return Ok(None),
+                }
+            }
+        };
+
+        let game = self.dat.find(&hash);
+        Ok(Some(GameHash {
+            path: path.to_path_buf(),
+            title: game.map(|game| game.title.clone()),
+            region: game.and_then(|game| game.region.clone()),
+            revision: game.and_then(|game| game.revision.clone()),
+            hash,
+        }))
+    }
+}
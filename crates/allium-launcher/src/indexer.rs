@@ -0,0 +1,431 @@
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread::JoinHandle;
+use std::time::UNIX_EPOCH;
+
+use anyhow::Result;
+use common::command::Command;
+use common::constants::ALLIUM_GAMES_DIR;
+use common::database::Database;
+use log::{error, info, warn};
+use tokio::sync::mpsc::Sender;
+
+use crate::collections;
+use crate::consoles::ConsoleMapper;
+use crate::entry::directory::{self, Directory};
+use crate::entry::Entry;
+use crate::identify::Identifier;
+use crate::repair::MoveDetector;
+
+/// Indexes the games directory on a separate thread, so that the launcher can be used in the
+/// meantime. Progress is sent as `Command::IndexProgress`, followed by `Command::IndexFinished`.
+#[derive(Debug)]
+pub struct Indexer {
+    cancelled: Arc<AtomicBool>,
+    handle: JoinHandle<()>,
+}
+
+impl Indexer {
+    /// Starts indexing. If `full` is true, every directory is indexed, otherwise only the
+    /// directories that changed since they were last indexed.
+    pub fn spawn(full: bool, commands: Sender<Command>) -> Self {
+        let cancelled = Arc::new(AtomicBool::new(false));
+        let handle = std::thread::spawn({
+            let cancelled = Arc::clone(&cancelled);
+            move || {
+                let changed = match run(full, &cancelled, &commands) {
+                    Ok(changed) => changed,
+                    Err(e) => {
+                        error!("failed to index games: {:#}", e);
+                        false
+                    }
+                };
+                let _ = commands.blocking_send(Command::IndexFinished { changed });
+            }
+        });
+
+        Self { cancelled, handle }
+    }
+
+    /// Stops indexing after the current directory. Directories that were not indexed yet are
+    /// indexed the next time.
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.handle.is_finished()
+    }
+}
+
+fn run(full: bool, cancelled: &AtomicBool, commands: &Sender<Command>) -> Result<bool> {
+    // The launcher's database and console mapper can't be shared across threads
+    let database = Database::new()?;
+    let mut console_mapper = ConsoleMapper::new();
+    console_mapper.load_config()?;
+    let identifier = Identifier::new(&database)?;
+
+    let changes = index(
+        &ALLIUM_GAMES_DIR,
+        full,
+        &database,
+        &console_mapper,
+        &identifier,
+        cancelled,
+        |indexed, total| {
+            let _ = commands.blocking_send(Command::IndexProgress { indexed, total });
+        },
+    )?;
+    info!("indexed games: {:?}", changes);
+
+    if !cancelled.load(Ordering::Relaxed) {
+        database.set_has_indexed(true)?;
+    }
+    if let Err(e) = collections::import_all(&database) {
+        error!("failed to import collections: {}", e);
+    }
+
+    Ok(full || changes.has_changes())
+}
+
+/// What changed in the games directory since it was last indexed.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Changes {
+    /// Directories that were indexed.
+    pub directories: usize,
+    /// Files that were not indexed before.
+    pub added: usize,
+    /// Games whose file no longer exists.
+    pub removed: usize,
+    /// Games whose file was renamed or moved. Their play history is kept.
+    pub renamed: usize,
+}
+
+impl Changes {
+    pub fn has_changes(&self) -> bool {
+        self.added > 0 || self.removed > 0 || self.renamed > 0
+    }
+}
+
+/// Indexes the directories under `root`. Unless `full` is true, directories are skipped if their
+/// modification time and gamelist are the same as when they were last indexed.
+///
+/// Games that no longer exist are matched with new files with the same contents, and are renamed
+/// instead of being removed. `progress` is called with the number
+/// of directories indexed so far and the number of directories to index.
+///
+/// Cancelling stops before the next directory is indexed. Removed and renamed games are applied
+/// before any directory is indexed, and are kept if indexing is cancelled afterwards.
+pub fn index(
+    root: &Path,
+    full: bool,
+    database: &Database,
+    console_mapper: &ConsoleMapper,
+    identifier: &Identifier,
+    cancelled: &AtomicBool,
+    mut progress: impl FnMut(usize, usize),
+) -> Result<Changes> {
+    let mut changes = Changes::default();
+
+    let directories = walk(root, console_mapper)?;
+    let previous = database
+        .select_indexed_directories()?
+        .into_iter()
+        .collect::<HashSet<_>>();
+
+    let mut changed = Vec::new();
+    for (dir, mtime) in &directories {
+        if full
+            || database.get_directory_mtime(dir)? != Some(*mtime)
+            || gamelist_changed(dir, database)?
+        {
+            changed.push(dir.as_path());
+        }
+    }
+    let walked = directories
+        .iter()
+        .map(|(dir, _)| dir.as_path())
+        .collect::<HashSet<_>>();
+    let removed_directories = previous
+        .iter()
+        .filter(|dir| !walked.contains(dir.as_path()))
+        .collect::<Vec<_>>();
+
+    // Directories whose games may have been removed
+    let scanned = changed
+        .iter()
+        .copied()
+        .chain(removed_directories.iter().map(|dir| dir.as_path()))
+        .collect::<Vec<_>>();
+
+    // Last known sizes of files, to match removed games with added files
+    let mut known_sizes = HashMap::new();
+    let mut removed_games = HashSet::new();
+    if full {
+        for game in database.select_all_games()? {
+            if !game.path.exists() {
+                removed_games.insert(game.path);
+            }
+        }
+    }
+    for dir in &scanned {
+        known_sizes.extend(database.select_indexed_files(dir)?);
+        for game in database.select_games_in_directory(dir)? {
+            if !game.path.exists() {
+                removed_games.insert(game.path);
+            }
+        }
+    }
+
+    let mut current_files = HashMap::new();
+    let mut added = Vec::new();
+    for dir in &changed {
+        let files = list_files(dir)?;
+        let games = database
+            .select_games_in_directory(dir)?
+            .into_iter()
+            .map(|game| game.path)
+            .collect::<HashSet<_>>();
+        for (path, size) in &files {
+            if !known_sizes.contains_key(path) && !games.contains(path) {
+                added.push((path.clone(), *size));
+            }
+        }
+        current_files.insert(*dir, files);
+    }
+
+    if cancelled.load(Ordering::Relaxed) {
+        info!("indexing cancelled");
+        return Ok(changes);
+    }
+
+    let mut removed_games = removed_games.into_iter().collect::<Vec<_>>();
+    removed_games.sort();
+
+    let mut detector = MoveDetector::new(added);
+    for old in removed_games {
+        let size = known_sizes.get(&old).copied();
+        let hash = database.select_game_hash(&old)?.map(|hash| hash.hash);
+        match detector.find(&old, size, hash.as_ref()) {
+            Some(new) => {
+                info!("renaming game {} -> {}", old.display(), new.display());
+                database.update_game_path(&old, &new)?;
+                changes.renamed += 1;
+            }
+            None => {
+                info!("removing game {}", old.display());
+                database.delete_game(&old)?;
+                changes.removed += 1;
+            }
+        }
+    }
+    changes.added = detector.into_remaining().len();
+
+    if full {
+        // Forces gamelists to be parsed again
+        database.delete_all_directories()?;
+    }
+
+    let total = changed.len();
+    for (i, dir) in changed.into_iter().enumerate() {
+        if cancelled.load(Ordering::Relaxed) {
+            info!("indexing cancelled");
+            return Ok(changes);
+        }
+        progress(i, total);
+
+        if let Err(e) =
+            Directory::new(dir.to_path_buf()).populate_db(database, console_mapper, identifier)
+        {
+            warn!("failed to index {}: {}", dir.display(), e);
+            continue;
+        }
+        database.set_indexed_files(dir, &current_files[dir])?;
+        // Indexing may add playlists for multi-disc games, which changes the modification time
+        database.set_directory_mtime(dir, mtime(dir)?)?;
+        changes.directories += 1;
+    }
+
+    for dir in removed_directories {
+        database.delete_directory(dir)?;
+    }
+
+    progress(total, total);
+
+    Ok(changes)
+}
+
+/// Lists `root` and the directories under it that games can be in, with their modification times.
+fn walk(root: &Path, console_mapper: &ConsoleMapper) -> Result<Vec<(PathBuf, i64)>> {
+    let mut directories = Vec::new();
+    let mut stack = vec![root.to_path_buf()];
+    while let Some(dir) = stack.pop() {
+        for entry in fs::read_dir(&dir)?.filter_map(std::result::Result::ok) {
+            if let Ok(Some(Entry::Directory(child))) = Entry::new(entry.path(), console_mapper) {
+                stack.push(child.path);
+            }
+        }
+        let mtime = mtime(&dir)?;
+        directories.push((dir, mtime));
+    }
+    directories.sort();
+    Ok(directories)
+}
+
+/// Modification time in nanoseconds, which changes when files are added, removed or renamed.
+fn mtime(path: &Path) -> Result<i64> {
+    Ok(fs::metadata(path)?
+        .modified()?
+        .duration_since(UNIX_EPOCH)?
+        .as_nanos() as i64)
+}
+
+/// Whether a gamelist was changed in place, which does not change the directory's modification
+/// time.
+fn gamelist_changed(dir: &Path, database: &Database) -> Result<bool> {
+    let fingerprint = database.get_gamelist_fingerprint(dir)?;
+    for name in ["gamelist.xml", "miyoogamelist.xml"] {
+        if let Some(current) = directory::gamelist_fingerprint(&dir.join(name)) {
+            return Ok(fingerprint != Some(current));
+        }
+    }
+    Ok(false)
+}
+
+/// Lists the files in a directory and their sizes, excluding hidden files.
+fn list_files(dir: &Path) -> Result<Vec<(PathBuf, u64)>> {
+    Ok(fs::read_dir(dir)?
+        .filter_map(std::result::Result::ok)
+        .filter(|entry| {
+            !entry
+                .file_name()
+                .to_str()
+                .is_none_or(|name| name.starts_with('.') || name.starts_with('_'))
+        })
+        .filter_map(|entry| {
+            let metadata = entry.metadata().ok()?;
+            metadata.is_file().then(|| (entry.path(), metadata.len()))
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_incremental_index() -> Result<()> {
+        let root = std::env::temp_dir().join("allium-test-indexer");
+        let _ = fs::remove_dir_all(&root);
+        let gba = root.join("GBA");
+        fs::create_dir_all(&gba)?;
+        fs::write(gba.join("Game A.gba"), "aaaa")?;
+        fs::write(gba.join("Game B.gba"), "bb")?;
+
+        let database = Database::in_memory()?;
+        let console_mapper = ConsoleMapper::new();
+        let identifier = Identifier::new(&database)?;
+        let cancelled = AtomicBool::new(false);
+        let index = |full| {
+            index(
+                &root,
+                full,
+                &database,
+                &console_mapper,
+                &identifier,
+                &cancelled,
+                |_, _| {},
+            )
+        };
+
+        let changes = index(false)?;
+        assert_eq!(changes.directories, 2);
+        assert_eq!(changes.added, 2);
+        let game_a = database.select_game(&gba.join("Game A.gba"))?.unwrap();
+        database.increment_play_count(&common::database::NewGame {
+            name: game_a.name,
+            path: game_a.path,
+            image: None,
+            core: None,
+            rating: None,
+            release_date: None,
+            developer: None,
+            publisher: None,
+            genres: Vec::new(),
+        })?;
+
+        // Nothing changed
+        assert_eq!(index(false)?, Changes::default());
+
+        // Games hidden by parental controls are renamed and removed too
+        database.set_hidden_games(&[gba.join("Game A.gba"), gba.join("Game B.gba")])?;
+        fs::rename(gba.join("Game A.gba"), gba.join("Game A (USA).gba"))?;
+        fs::remove_file(gba.join("Game B.gba"))?;
+        fs::write(gba.join("Game C.gba"), "ccc")?;
+        let changes = index(false)?;
+        assert_eq!(
+            changes,
+            Changes {
+                directories: 1,
+                added: 1,
+                removed: 1,
+                renamed: 1,
+            }
+        );
+        let game = database
+            .select_game(&gba.join("Game A (USA).gba"))?
+            .unwrap();
+        assert_eq!(game.play_count, 1);
+        assert!(database.select_game(&gba.join("Game B.gba"))?.is_none());
+        assert!(database.select_game(&gba.join("Game C.gba"))?.is_some());
+
+        // Moved to another directory
+        let gbc = root.join("GBC");
+        fs::create_dir_all(&gbc)?;
+        fs::rename(gba.join("Game A (USA).gba"), gbc.join("Game A (USA).gba"))?;
+        fs::remove_dir_all(&gba)?;
+        let changes = index(false)?;
+        assert_eq!(changes.renamed, 1);
+        assert_eq!(changes.removed, 1);
+        let game = database
+            .select_game(&gbc.join("Game A (USA).gba"))?
+            .unwrap();
+        assert_eq!(game.play_count, 1);
+        assert!(!database.select_indexed_directories()?.contains(&gba));
+
+        // Gamelist edited in place without changing its size
+        let gamelist = gbc.join("gamelist.xml");
+        let write_gamelist = |name: &str, mtime: u64| -> Result<()> {
+            fs::write(
+                &gamelist,
+                format!(
+                    "<gameList><game><path>./Game A (USA).gba</path><name>{}</name></game></gameList>",
+                    name
+                ),
+            )?;
+            fs::File::options()
+                .write(true)
+                .open(&gamelist)?
+                .set_modified(UNIX_EPOCH + std::time::Duration::from_secs(mtime))?;
+            Ok(())
+        };
+        write_gamelist("Alpha", 1000)?;
+        assert_eq!(index(false)?.directories, 1);
+        let game = database
+            .select_game(&gbc.join("Game A (USA).gba"))?
+            .unwrap();
+        assert_eq!(game.name, "Alpha");
+        write_gamelist("Omega", 2000)?;
+        assert_eq!(index(false)?.directories, 1);
+        let game = database
+            .select_game(&gbc.join("Game A (USA).gba"))?
+            .unwrap();
+        assert_eq!(game.name, "Omega");
+
+        fs::remove_dir_all(&root)?;
+        Ok(())
+    }
+}
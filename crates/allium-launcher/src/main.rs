@@ -3,22 +3,107 @@
 #![feature(trait_upcasting)]
 
 mod allium_launcher;
+mod collections;
 mod consoles;
+mod diagnostics;
 mod entry;
+mod identify;
+mod indexer;
+mod repair;
 mod view;
 
 use anyhow::Result;
+use clap::{Parser, Subcommand};
 
 use allium_launcher::AlliumLauncher;
+use common::constants::{
+    ALLIUM_CONFIG_CONSOLES, ALLIUM_CONFIG_CORES, ALLIUM_GAMES_DIR, ALLIUM_RETROARCH_CORES_DIR,
+};
+use common::database::Database;
 use common::platform::{DefaultPlatform, Platform};
 use simple_logger::SimpleLogger;
 
+use crate::diagnostics::Diagnostics;
+
+#[derive(Parser, Debug)]
+#[command(author, version, about, long_about = None)]
+struct Cli {
+    #[command(subcommand)]
+    command: Option<Commands>,
+}
+
+#[derive(Subcommand, Debug)]
+enum Commands {
+    /// Validates consoles.toml and cores.toml, and shows which console and core the games in each
+    /// ROM folder resolve to and why
+    Diagnose,
+    /// Re-links games and guide reading positions whose files were moved or renamed, and prints
+    /// what was changed
+    Repair,
+}
+
 #[tokio::main]
 async fn main() -> Result<()> {
     SimpleLogger::new().init().unwrap();
 
+    match Cli::parse().command {
+        Some(Commands::Diagnose) => return diagnose(),
+        Some(Commands::Repair) => return repair(),
+        None => {}
+    }
+
     let platform = DefaultPlatform::new()?;
     let mut app = AlliumLauncher::new(platform)?;
     app.run_event_loop().await?;
     Ok(())
 }
+
+/// Prints the console mapper diagnostics. Exits with an error if there are any issues.
+fn diagnose() -> Result<()> {
+    let database = Database::new().ok();
+    let diagnostics = Diagnostics::run(
+        &ALLIUM_CONFIG_CONSOLES,
+        &ALLIUM_CONFIG_CORES,
+        &ALLIUM_RETROARCH_CORES_DIR,
+        &ALLIUM_GAMES_DIR,
+        database.as_ref(),
+    );
+
+    if diagnostics.issues.is_empty() {
+        println!("No issues found");
+    } else {
+        println!("Issues:");
+        for issue in &diagnostics.issues {
+            println!("  {}", issue);
+        }
+    }
+
+    println!();
+    println!("ROM folders:");
+    for folder in &diagnostics.folders {
+        println!("  {}", folder.name());
+        if folder.resolutions.is_empty() {
+            println!("    no games");
+        }
+        for resolution in &folder.resolutions {
+            println!("    {}", resolution);
+        }
+    }
+
+    if !diagnostics.issues.is_empty() {
+        std::process::exit(1);
+    }
+    Ok(())
+}
+
+/// Re-links moved games and guides, and prints what was changed.
+fn repair() -> Result<()> {
+    let database = Database::new()?;
+    let report = repair::repair(&ALLIUM_GAMES_DIR, &database)?;
+    if report.is_empty() {
+        println!("Nothing to repair");
+    } else {
+        print!("{}", report);
+    }
+    Ok(())
+}
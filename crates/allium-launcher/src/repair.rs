@@ -0,0 +1,327 @@
+use std::collections::{HashMap, HashSet};
+use std::ffi::OsStr;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::Result;
+use common::database::Database;
+use common::hash::{self, RomHash};
+use log::{info, warn};
+
+/// Finds where games were moved or renamed to, among files that appeared since they were indexed.
+///
+/// A file matches if it has the same extension and size as the game, and the same content hash if
+/// the game was hashed. Games that were not hashed only match files with the same name.
+#[derive(Debug)]
+pub struct MoveDetector {
+    /// Files that games may have moved to, and their sizes.
+    candidates: Vec<(PathBuf, u64)>,
+    /// Hashes of candidates, computed when they are first needed.
+    hashes: HashMap<PathBuf, Option<RomHash>>,
+}
+
+impl MoveDetector {
+    pub fn new(candidates: Vec<(PathBuf, u64)>) -> Self {
+        Self {
+            candidates,
+            hashes: HashMap::new(),
+        }
+    }
+
+    /// Finds the file that a game was moved to, and removes it from the candidates. `size` is the
+    /// size of the file when it was last indexed, and `hash` the hash of its contents.
+    pub fn find(
+        &mut self,
+        old: &Path,
+        size: Option<u64>,
+        hash: Option<&RomHash>,
+    ) -> Option<PathBuf> {
+        let extension = |path: &Path| path.extension().map(OsStr::to_ascii_lowercase);
+
+        let mut candidates = self
+            .candidates
+            .iter()
+            .enumerate()
+            .filter(|(_, (new, new_size))| {
+                extension(new) == extension(old) && size.is_none_or(|size| size == *new_size)
+            })
+            .map(|(i, (new, _))| (i, new.clone()))
+            .collect::<Vec<_>>();
+        // Prefer files with the same name, e.g. when a game was moved to another folder
+        candidates.sort_by_key(|(_, new)| new.file_name() != old.file_name());
+
+        let i = candidates.into_iter().find_map(|(i, new)| match hash {
+            Some(hash) => (self.hash(&new) == Some(hash)).then_some(i),
+            None => (new.file_name() == old.file_name()).then_some(i),
+        })?;
+
+        Some(self.candidates.remove(i).0)
+    }
+
+    /// Candidates that no game was moved to.
+    pub fn into_remaining(self) -> Vec<(PathBuf, u64)> {
+        self.candidates
+    }
+
+    fn hash(&mut self, path: &Path) -> Option<&RomHash> {
+        self.hashes
+            .entry(path.to_path_buf())
+            .or_insert_with(|| match hash::hash_rom(path) {
+                Ok(hash) => hash,
+                Err(e) => {
+                    warn!("failed to hash {}: {}", path.display(), e);
+                    None
+                }
+            })
+            .as_ref()
+    }
+}
+
+/// What `repair` changed.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct RepairReport {
+    /// Games that were re-linked to the file they were moved to, as (old, new) paths.
+    pub games: Vec<(PathBuf, PathBuf)>,
+    /// Guides whose reading position was re-linked to the file they were moved to.
+    pub guides: Vec<(PathBuf, PathBuf)>,
+    /// Games whose file could not be found. They are left as they are.
+    pub missing_games: Vec<PathBuf>,
+    /// Guides whose file could not be found. They are left as they are.
+    pub missing_guides: Vec<PathBuf>,
+}
+
+impl RepairReport {
+    pub fn is_empty(&self) -> bool {
+        self.games.is_empty()
+            && self.guides.is_empty()
+            && self.missing_games.is_empty()
+            && self.missing_guides.is_empty()
+    }
+}
+
+impl fmt::Display for RepairReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (old, new) in &self.games {
+            writeln!(f, "game: {} -> {}", old.display(), new.display())?;
+        }
+        for (old, new) in &self.guides {
+            writeln!(f, "guide: {} -> {}", old.display(), new.display())?;
+        }
+        for path in &self.missing_games {
+            writeln!(f, "missing game: {}", path.display())?;
+        }
+        for path in &self.missing_guides {
+            writeln!(f, "missing guide: {}", path.display())?;
+        }
+        Ok(())
+    }
+}
+
+/// Re-links games and guides whose files no longer exist to the files they were moved to under
+/// `root`, keeping their play history, core overrides and reading positions.
+pub fn repair(root: &Path, database: &Database) -> Result<RepairReport> {
+    let mut report = RepairReport::default();
+
+    let games = database.select_all_games()?;
+    let orphaned_games = games
+        .iter()
+        .filter(|game| !game.path.exists())
+        .map(|game| game.path.clone())
+        .collect::<Vec<_>>();
+    let orphaned_guides = database
+        .select_guides()?
+        .into_iter()
+        .filter(|path| !path.exists())
+        .collect::<Vec<_>>();
+    if orphaned_games.is_empty() && orphaned_guides.is_empty() {
+        return Ok(report);
+    }
+
+    let known = games
+        .into_iter()
+        .map(|game| game.path)
+        .collect::<HashSet<_>>();
+    let mut roms = Vec::new();
+    let mut guides = Vec::new();
+    list_files(root, false, &mut roms, &mut guides)?;
+    roms.retain(|(path, _)| !known.contains(path));
+
+    let mut sizes = HashMap::new();
+    for dir in orphaned_games.iter().filter_map(|path| path.parent()) {
+        if !sizes.contains_key(dir) {
+            let files = database.select_indexed_files(dir)?;
+            sizes.insert(
+                dir.to_path_buf(),
+                files.into_iter().collect::<HashMap<_, _>>(),
+            );
+        }
+    }
+
+    let mut detector = MoveDetector::new(roms);
+    for old in orphaned_games {
+        let size = old
+            .parent()
+            .and_then(|dir| sizes.get(dir))
+            .and_then(|files| files.get(&old))
+            .copied();
+        let hash = database.select_game_hash(&old)?.map(|hash| hash.hash);
+        match detector.find(&old, size, hash.as_ref()) {
+            Some(new) => {
+                info!("re-linking game {} -> {}", old.display(), new.display());
+                database.update_game_path(&old, &new)?;
+                report.games.push((old, new));
+            }
+            None => report.missing_games.push(old),
+        }
+    }
+
+    for old in orphaned_guides {
+        match find_guide(&old, &mut guides) {
+            Some(new) => {
+                info!("re-linking guide {} -> {}", old.display(), new.display());
+                database.update_guide_path(&old, &new)?;
+                report.guides.push((old, new));
+            }
+            None => report.missing_guides.push(old),
+        }
+    }
+
+    Ok(report)
+}
+
+/// Lists the files under `dir` with their sizes. Files in Guides directories are listed separately.
+fn list_files(
+    dir: &Path,
+    in_guides: bool,
+    roms: &mut Vec<(PathBuf, u64)>,
+    guides: &mut Vec<PathBuf>,
+) -> Result<()> {
+    for entry in fs::read_dir(dir)?.filter_map(std::result::Result::ok) {
+        let Some(name) = entry.file_name().to_str().map(str::to_string) else {
+            continue;
+        };
+        if name.starts_with('.') || name.starts_with('_') {
+            continue;
+        }
+        let Ok(metadata) = entry.metadata() else {
+            continue;
+        };
+        if metadata.is_dir() {
+            if name != "Imgs" {
+                list_files(&entry.path(), in_guides || name == "Guides", roms, guides)?;
+            }
+        } else if in_guides {
+            guides.push(entry.path());
+        } else {
+            roms.push((entry.path(), metadata.len()));
+        }
+    }
+    Ok(())
+}
+
+/// Finds the guide file that a guide was moved to. Guides have the same name as their game, so a
+/// single file with the same name is required, or one in a folder with the same name.
+fn find_guide(old: &Path, guides: &mut Vec<PathBuf>) -> Option<PathBuf> {
+    let parent_name = |path: &Path| path.parent().and_then(Path::file_name).map(OsStr::to_owned);
+
+    let candidates = guides
+        .iter()
+        .enumerate()
+        .filter(|(_, new)| new.file_name() == old.file_name())
+        .map(|(i, _)| i)
+        .collect::<Vec<_>>();
+    let i = match candidates.as_slice() {
+        [i] => *i,
+        _ => *candidates
+            .iter()
+            .find(|i| parent_name(&guides[**i]) == parent_name(old))?,
+    };
+
+    Some(guides.remove(i))
+}
+
+#[cfg(test)]
+mod tests {
+    use common::database::NewGame;
+
+    use super::*;
+
+    #[test]
+    fn test_repair() -> Result<()> {
+        let root = std::env::temp_dir().join("allium-test-repair");
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(root.join("GBA/Guides"))?;
+        fs::create_dir_all(root.join("GBA/RPG/Guides"))?;
+
+        let database = Database::in_memory()?;
+        let new_game = |path: PathBuf| NewGame {
+            name: "Game".to_string(),
+            path,
+            image: None,
+            core: Some("mgba".to_string()),
+            rating: None,
+            release_date: None,
+            developer: None,
+            publisher: None,
+            genres: Vec::new(),
+        };
+
+        // Hashed game, renamed and moved into a subfolder
+        let hashed = root.join("GBA/Hashed.gba");
+        fs::write(&hashed, "hashed")?;
+        database.update_games(&[new_game(hashed.clone())])?;
+        database.update_game_hashes(&[common::database::GameHash {
+            path: hashed.clone(),
+            hash: hash::hash_rom(&hashed)?.unwrap(),
+            title: None,
+            region: None,
+            revision: None,
+        }])?;
+        database.increment_play_count(&new_game(hashed.clone()))?;
+        fs::rename(&hashed, root.join("GBA/RPG/Hashed (USA).gba"))?;
+        // A file of the same size with different contents is not a match
+        fs::write(root.join("GBA/Decoy.gba"), "decoys")?;
+
+        // Game without a hash, moved into a subfolder
+        let unhashed = root.join("GBA/Unhashed.gba");
+        database.update_games(&[new_game(unhashed.clone())])?;
+        fs::write(root.join("GBA/RPG/Unhashed.gba"), "unhashed")?;
+
+        // Game that was deleted
+        let deleted = root.join("GBA/Deleted.gba");
+        database.update_games(&[new_game(deleted.clone())])?;
+
+        // Guide that was moved along with its game
+        let guide = root.join("GBA/Guides/Unhashed.txt");
+        database.update_guide_cursor(&guide, 100)?;
+        fs::write(root.join("GBA/RPG/Guides/Unhashed.txt"), "guide")?;
+
+        let report = repair(&root, &database)?;
+        assert_eq!(
+            report,
+            RepairReport {
+                games: vec![
+                    (hashed, root.join("GBA/RPG/Hashed (USA).gba")),
+                    (unhashed, root.join("GBA/RPG/Unhashed.gba")),
+                ],
+                guides: vec![(guide, root.join("GBA/RPG/Guides/Unhashed.txt"))],
+                missing_games: vec![deleted],
+                missing_guides: vec![],
+            }
+        );
+
+        let game = database
+            .select_game(&root.join("GBA/RPG/Hashed (USA).gba"))?
+            .unwrap();
+        assert_eq!(game.play_count, 1);
+        assert_eq!(game.core, Some("mgba".to_string()));
+        assert_eq!(
+            database.get_guide_cursor(&root.join("GBA/RPG/Guides/Unhashed.txt"))?,
+            100
+        );
+
+        fs::remove_dir_all(&root)?;
+        Ok(())
+    }
+}
@@ -1,49 +1,78 @@
 use std::collections::VecDeque;
 use std::fs::{self, File};
+use std::io::Read;
+use std::time::Duration;
 
 use anyhow::Result;
 use async_trait::async_trait;
 use common::battery::Battery;
-use common::command::Command;
+use common::command::{Command, Value};
 use common::constants::ALLIUM_LAUNCHER_STATE;
 use common::display::Display;
 use common::geom::{Alignment, Point, Rect};
 use common::locale::Locale;
 use common::platform::{DefaultPlatform, Key, KeyEvent, Platform};
+use common::power::PowerSettings;
 use common::resources::Resources;
 use common::stylesheet::{Stylesheet, StylesheetColor};
-use common::view::{BatteryIndicator, Label, Row, View};
+use common::view::{BatteryIndicator, Keyboard, Label, Row, View};
 use log::{trace, warn};
 use serde::{Deserialize, Serialize};
 use tokio::sync::mpsc::Sender;
 
+use crate::consoles::ConsoleMapper;
 use crate::view::apps::AppsState;
+use crate::view::favorites::FavoritesState;
 use crate::view::games::GamesState;
 use crate::view::recents::RecentsState;
 use crate::view::settings::SettingsState;
 use crate::view::Recents;
-use crate::view::{Apps, Games, Settings};
+use crate::view::{Apps, Favorites, Games, Settings};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 struct AppState {
     selected: usize,
     recents: RecentsState,
+    /// Missing in state saved before the Favorites tab was added.
+    #[serde(default)]
+    favorites: Option<FavoritesState>,
     games: GamesState,
     apps: AppsState,
     settings: SettingsState,
 }
 
+impl AppState {
+    /// Reads the saved state, or returns None if it can't be read.
+    fn from_reader(reader: impl Read) -> Option<Self> {
+        let mut state: Self = serde_json::from_reader(reader).ok()?;
+        // Before the Favorites tab was added, the tabs after Recents started at 1
+        if state.favorites.is_none() && state.selected >= 1 {
+            state.selected += 1;
+        }
+        Some(state)
+    }
+}
+
 #[derive(Debug)]
 pub struct App<B>
 where
     B: Battery + 'static,
 {
     rect: Rect,
+    res: Resources,
     battery_indicator: BatteryIndicator<B>,
-    views: (Recents, Games, Apps, Settings),
+    /// Shows the progress of the background indexer, left of the battery indicator.
+    indexing: Label<String>,
+    /// Area drawn by the indexing status, cleared before it is drawn again.
+    indexing_rect: Option<Rect>,
+    views: (Recents, Favorites, Games, Apps, Settings),
     selected: usize,
     tabs: Row<Label<String>>,
     // title: Label<String>,
+    /// Whether Apps and Settings need the parental controls PIN to be opened.
+    locked: bool,
+    /// Keyboard for entering the PIN, and the tab it unlocks.
+    pin: Option<(Keyboard, usize)>,
     dirty: bool,
 }
 
@@ -54,7 +83,7 @@ where
     pub fn new(
         rect: Rect,
         res: Resources,
-        views: (Recents, Games, Apps, Settings),
+        views: (Recents, Favorites, Games, Apps, Settings),
         selected: usize,
         battery: B,
     ) -> Result<Self> {
@@ -69,6 +98,17 @@ where
             styles.show_battery_level,
         );
 
+        let mut indexing = Label::new(
+            Point::new(
+                w as i32 - 12 - (styles.status_bar_font_size() * 5.0) as i32 - 12,
+                y + 8,
+            ),
+            String::new(),
+            Alignment::Right,
+            None,
+        );
+        indexing.font_size(styles.status_bar_font_size);
+
         let mut tabs = Row::new(
             Point::new(x + 12, y + 8),
             {
@@ -79,6 +119,12 @@ where
                         Alignment::Left,
                         None,
                     ),
+                    Label::new(
+                        Point::zero(),
+                        locale.t("tab-favorites"),
+                        Alignment::Left,
+                        None,
+                    ),
                     Label::new(Point::zero(), locale.t("tab-games"), Alignment::Left, None),
                     Label::new(Point::zero(), locale.t("tab-apps"), Alignment::Left, None),
                     Label::new(
@@ -114,11 +160,16 @@ where
 
         Ok(Self {
             rect,
+            res: res.clone(),
             views,
             selected,
             battery_indicator,
+            indexing,
+            indexing_rect: None,
             tabs,
             // title,
+            locked: false,
+            pin: None,
             dirty: true,
         })
     }
@@ -137,9 +188,10 @@ where
 
         if ALLIUM_LAUNCHER_STATE.exists() {
             let file = File::open(ALLIUM_LAUNCHER_STATE.as_path())?;
-            if let Ok(state) = serde_json::from_reader::<_, AppState>(file) {
+            if let Some(state) = AppState::from_reader(file) {
                 let views = (
                     Recents::load_or_new(tab_rect, res.clone(), Some(state.recents))?,
+                    Favorites::load_or_new(tab_rect, res.clone(), state.favorites)?,
                     Games::load_or_new(tab_rect, res.clone(), Some(state.games)).unwrap_or_else(
                         |_| Games::load_or_new(tab_rect, res.clone(), None).unwrap(),
                     ),
@@ -147,7 +199,7 @@ where
                     Settings::new(
                         tab_rect,
                         res.clone(),
-                        if state.selected == 3 {
+                        if state.selected == 4 {
                             // Only load settings if it was the last selected tab
                             state.settings
                         } else {
@@ -163,11 +215,12 @@ where
 
         let views = (
             Recents::load_or_new(tab_rect, res.clone(), None)?,
+            Favorites::load_or_new(tab_rect, res.clone(), None)?,
             Games::load_or_new(tab_rect, res.clone(), None)?,
             Apps::load_or_new(tab_rect, res.clone(), None)?,
             Settings::new(tab_rect, res.clone(), Default::default())?,
         );
-        let selected = 1;
+        let selected = 2;
         Self::new(rect, res, views, selected, battery)
     }
 
@@ -176,9 +229,10 @@ where
         let state = AppState {
             selected: self.selected,
             recents: self.views.0.save(),
-            games: self.views.1.save(),
-            apps: self.views.2.save(),
-            settings: self.views.3.save(),
+            favorites: Some(self.views.1.save()),
+            games: self.views.2.save(),
+            apps: self.views.3.save(),
+            settings: self.views.4.save(),
         };
         serde_json::to_writer(file, &state)?;
         Ok(())
@@ -190,6 +244,7 @@ where
             1 => &self.views.1,
             2 => &self.views.2,
             3 => &self.views.3,
+            4 => &self.views.4,
             _ => unreachable!(),
         }
     }
@@ -200,16 +255,48 @@ where
             1 => &mut self.views.1,
             2 => &mut self.views.2,
             3 => &mut self.views.3,
+            4 => &mut self.views.4,
             _ => unreachable!(),
         }
     }
 
+    /// Locks Apps and Settings behind the sleep schedule PIN if parental controls are on, leaving
+    /// them if either is open.
+    pub fn lock(&mut self) {
+        let schedule = PowerSettings::load().unwrap_or_default().schedule;
+        self.locked = self
+            .res
+            .get::<ConsoleMapper>()
+            .restrictions()
+            .is_locked(&schedule);
+        if self.locked && is_restricted_tab(self.selected) {
+            self.tab_change(2);
+        }
+    }
+
+    pub fn is_locked(&self) -> bool {
+        self.locked
+    }
+
     fn tab_change(&mut self, selected: usize) {
+        if self.locked && is_restricted_tab(selected) {
+            self.pin = Some((
+                Keyboard::new(self.res.clone(), String::new(), true),
+                selected,
+            ));
+            return;
+        }
+
         self.tabs
             .get_mut(self.selected)
             .unwrap()
             .color(StylesheetColor::Tab);
         self.selected = selected;
+        if self.selected == 1 {
+            if let Err(e) = self.views.1.reload() {
+                warn!("failed to reload favorites: {}", e);
+            }
+        }
         self.view_mut().set_should_draw();
         self.set_should_draw();
         self.tabs
@@ -220,15 +307,38 @@ where
     }
 
     fn next(&mut self) {
-        let selected = (self.selected + 1).rem_euclid(4);
+        let selected = (self.selected + 1).rem_euclid(5);
         self.tab_change(selected)
     }
 
     fn prev(&mut self) {
-        let selected = (self.selected as isize - 1).rem_euclid(4);
+        let selected = (self.selected as isize - 1).rem_euclid(5);
         self.tab_change(selected as usize)
     }
 
+    /// Sets the text of the indexing status, or hides it if the text is empty.
+    pub fn set_indexing_status(&mut self, text: String) {
+        self.indexing.set_text(text);
+    }
+
+    /// Checks the PIN entered to open a locked tab, opening it if the PIN is correct.
+    async fn unlock(&mut self, pin: &str, tab: usize, commands: Sender<Command>) -> Result<()> {
+        if PowerSettings::load()
+            .unwrap_or_default()
+            .schedule
+            .check_pin(pin)
+        {
+            self.locked = false;
+            self.tab_change(tab);
+        } else {
+            let text = self.res.get::<Locale>().t("parental-wrong-pin");
+            commands
+                .send(Command::Toast(text, Some(Duration::from_secs(2))))
+                .await?;
+        }
+        Ok(())
+    }
+
     pub fn start_search(&mut self) {
         self.tab_change(0);
         self.views.0.start_search();
@@ -263,6 +373,16 @@ where
         let mut drawn = false;
         drawn |=
             self.battery_indicator.should_draw() && self.battery_indicator.draw(display, styles)?;
+        if self.indexing.should_draw() {
+            if let Some(rect) = self.indexing_rect.take() {
+                display.load(rect)?;
+            }
+            self.indexing.draw(display, styles)?;
+            if !self.indexing.text().is_empty() {
+                self.indexing_rect = Some(self.indexing.bounding_box(styles));
+            }
+            drawn = true;
+        }
         // drawn |= self.title.should_draw() && self.title.draw(display, styles)?;
         drawn |= self.view().should_draw() && self.view_mut().draw(display, styles)?;
 
@@ -270,18 +390,33 @@ where
             drawn = true;
         }
 
+        if let Some((keyboard, _)) = self.pin.as_mut() {
+            if drawn {
+                keyboard.set_should_draw();
+            }
+            drawn |= keyboard.should_draw() && keyboard.draw(display, styles)?;
+        }
+
         Ok(drawn)
     }
 
     fn should_draw(&self) -> bool {
-        self.battery_indicator.should_draw() || self.view().should_draw() || self.tabs.should_draw()
+        self.battery_indicator.should_draw()
+            || self.indexing.should_draw()
+            || self.view().should_draw()
+            || self.tabs.should_draw()
+            || self.pin.as_ref().is_some_and(|(k, _)| k.should_draw())
     }
 
     fn set_should_draw(&mut self) {
         self.dirty = true;
         self.battery_indicator.set_should_draw();
+        self.indexing.set_should_draw();
         self.view_mut().set_should_draw();
         self.tabs.set_should_draw();
+        if let Some((keyboard, _)) = self.pin.as_mut() {
+            keyboard.set_should_draw();
+        }
     }
 
     async fn handle_key_event(
@@ -290,6 +425,35 @@ where
         commands: Sender<Command>,
         bubble: &mut VecDeque<Command>,
     ) -> Result<bool> {
+        if let Some((keyboard, tab)) = self.pin.as_mut() {
+            let tab = *tab;
+            keyboard
+                .handle_key_event(event, commands.clone(), bubble)
+                .await?;
+            let mut pin = None;
+            let mut closed = false;
+            bubble.retain_mut(|c| match c {
+                Command::ValueChanged(_, Value::String(val)) => {
+                    pin = Some(val.clone());
+                    false
+                }
+                Command::CloseView => {
+                    closed = true;
+                    false
+                }
+                _ => true,
+            });
+            if closed {
+                self.pin = None;
+                self.set_should_draw();
+                commands.send(Command::Redraw).await?;
+            }
+            if let Some(pin) = pin {
+                self.unlock(&pin, tab, commands).await?;
+            }
+            return Ok(true);
+        }
+
         if self
             .view_mut()
             .handle_key_event(event, commands, bubble)
@@ -314,7 +478,12 @@ where
     }
 
     fn children(&self) -> Vec<&dyn View> {
-        vec![&self.battery_indicator, self.view(), &self.tabs]
+        vec![
+            &self.battery_indicator,
+            &self.indexing,
+            self.view(),
+            &self.tabs,
+        ]
     }
 
     fn children_mut(&mut self) -> Vec<&mut dyn View> {
@@ -323,9 +492,15 @@ where
             1 => &mut self.views.1,
             2 => &mut self.views.2,
             3 => &mut self.views.3,
+            4 => &mut self.views.4,
             _ => unreachable!(),
         };
-        vec![&mut self.battery_indicator, view, &mut self.tabs]
+        vec![
+            &mut self.battery_indicator,
+            &mut self.indexing,
+            view,
+            &mut self.tabs,
+        ]
     }
 
     fn bounding_box(&mut self, _styles: &Stylesheet) -> Rect {
@@ -337,12 +512,166 @@ where
     }
 }
 
+/// Whether a tab is locked by parental controls: Apps and Settings.
+fn is_restricted_tab(selected: usize) -> bool {
+    matches!(selected, 3 | 4)
+}
+
 // fn title(locale: &Locale, selected: usize) -> String {
 //     match selected {
 //         0 => locale.t("tab-recents"),
-//         1 => locale.t("tab-games"),
-//         2 => locale.t("tab-apps"),
-//         3 => locale.t("tab-settings"),
+//         1 => locale.t("tab-favorites"),
+//         2 => locale.t("tab-games"),
+//         3 => locale.t("tab-apps"),
+//         4 => locale.t("tab-settings"),
 //         _ => unreachable!(),
 //     }
 // }
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use common::database::Database;
+    use common::platform::headless::{test_locale, test_stylesheet, HeadlessPlatform};
+    use common::snapshot_path;
+    use embedded_graphics::prelude::*;
+    use serde_json::Value;
+    use type_map::TypeMap;
+
+    use super::*;
+    use crate::consoles::ConsoleMapper;
+    use crate::entry::directory::Directory;
+    use crate::view::apps::AppsSort;
+    use crate::view::entry_list::EntryList;
+    use crate::view::games::GamesSort;
+    use crate::view::recents::RecentsSort;
+
+    fn create_dir(path: &Path, children: &[&str]) -> Result<()> {
+        if path.exists() {
+            fs::remove_dir_all(path)?;
+        }
+        for child in children {
+            fs::create_dir_all(path.join(child))?;
+        }
+        fs::create_dir_all(path)?;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_app_snapshot() -> Result<()> {
+        let dir = std::env::temp_dir().join("allium-test-app-snapshot");
+        let roms = dir.join("Roms");
+        let apps = dir.join("Apps");
+        create_dir(
+            &roms,
+            &[
+                "Game Boy Advance",
+                "Nintendo Entertainment System",
+                "PlayStation",
+            ],
+        )?;
+        create_dir(&apps, &[])?;
+
+        let mut platform = HeadlessPlatform::new()?;
+        platform.set_battery(80, false);
+        let mut display = platform.display()?;
+
+        let mut res = TypeMap::new();
+        res.insert(Database::in_memory()?);
+        res.insert(ConsoleMapper::new());
+        res.insert(test_stylesheet());
+        res.insert(test_locale());
+        res.insert(Into::<common::geom::Size>::into(display.size()));
+        let res = Resources::new(res);
+
+        let rect: Rect = display.bounding_box().into();
+        let tab_rect = Rect::new(rect.x, rect.y + 46, rect.w, rect.h - 46);
+        let views = (
+            Recents::load_or_new(tab_rect, res.clone(), None)?,
+            Favorites::load_or_new(tab_rect, res.clone(), None)?,
+            Games::new(
+                tab_rect,
+                res.clone(),
+                EntryList::new(
+                    tab_rect,
+                    res.clone(),
+                    GamesSort::Alphabetical(Directory::new(roms)),
+                )?,
+            )?,
+            Apps::new(
+                tab_rect,
+                res.clone(),
+                EntryList::new(
+                    tab_rect,
+                    res.clone(),
+                    AppsSort::Alphabetical(Directory::new(apps)),
+                )?,
+            )?,
+            Settings::new(tab_rect, res.clone(), Default::default())?,
+        );
+        let mut app = App::new(rect, res.clone(), views, 2, platform.battery()?)?;
+
+        display.clear(res.get::<Stylesheet>().background_color)?;
+        display.save()?;
+        app.draw(&mut display, &res.get::<Stylesheet>())?;
+        display.assert_snapshot(&snapshot_path!("app_games"));
+
+        platform.press(Key::Right);
+        platform.press(Key::Right);
+        let (tx, _rx) = tokio::sync::mpsc::channel(100);
+        while platform.has_key_events() {
+            let event = platform.poll().await;
+            app.handle_key_event(event, tx.clone(), &mut VecDeque::new())
+                .await?;
+        }
+        while app.should_draw() {
+            app.draw(&mut display, &res.get::<Stylesheet>())?;
+        }
+        display.assert_snapshot(&snapshot_path!("app_settings"));
+
+        Ok(())
+    }
+    #[test]
+    fn test_state_before_favorites() -> Result<()> {
+        let dir = std::env::temp_dir().join("allium-test-app-state");
+        create_dir(&dir, &[])?;
+        let path = dir.join("launcher-state.json");
+
+        let list = |sort: Value| serde_json::json!({ "sort": sort, "selected": 0, "child": null });
+        let directory = Directory::new(dir.join("Roms"));
+        let mut json = serde_json::json!({
+            "selected": 3,
+            "recents": list(serde_json::to_value(RecentsSort::LastPlayed)?),
+            "games": list(serde_json::to_value(GamesSort::Alphabetical(directory.clone()))?),
+            "apps": list(serde_json::to_value(AppsSort::Alphabetical(directory))?),
+            "settings": { "selected": 7, "child": { "selected": 2 } },
+        });
+
+        // Settings was the last tab
+        fs::write(&path, json.to_string())?;
+        let state = AppState::from_reader(File::open(&path)?).unwrap();
+        assert_eq!(state.selected, 4);
+        assert!(state.favorites.is_none());
+        assert_eq!(serde_json::to_value(&state.settings)?["selected"], 7);
+
+        // Games was the last tab
+        json["selected"] = 1.into();
+        fs::write(&path, json.to_string())?;
+        assert_eq!(
+            AppState::from_reader(File::open(&path)?).unwrap().selected,
+            2
+        );
+
+        // Recents is still the first tab
+        json["selected"] = 0.into();
+        fs::write(&path, json.to_string())?;
+        assert_eq!(
+            AppState::from_reader(File::open(&path)?).unwrap().selected,
+            0
+        );
+
+        fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+}
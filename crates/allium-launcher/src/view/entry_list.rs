@@ -1,25 +1,33 @@
 use std::collections::VecDeque;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
 
 use anyhow::Result;
 use async_trait::async_trait;
-use common::command::Command;
-use common::constants::{IMAGE_WIDTH, SELECTION_MARGIN};
-use common::database::Database;
+use common::command::{Command, Value};
+use common::constants::{ALLIUM_GAMES_DIR, IMAGE_WIDTH, SELECTION_MARGIN};
+use common::database::{Collection, Database};
 use common::display::Display;
 use common::geom::{Alignment, Point, Rect};
 use common::locale::Locale;
 use common::platform::{DefaultPlatform, Key, KeyEvent, Platform};
+use common::power::PowerSettings;
 use common::resources::Resources;
 use common::stylesheet::{Stylesheet, StylesheetColor};
-use common::view::{ButtonHint, ButtonIcon, Image, ImageMode, Row, ScrollList, View};
+use common::view::{ButtonHint, ButtonIcon, Image, ImageMode, Keyboard, Row, ScrollList, View};
 use embedded_graphics::prelude::{Dimensions, OriginDimensions, Size};
 use embedded_graphics::primitives::{CornerRadii, Primitive, PrimitiveStyle, RoundedRectangle};
 use embedded_graphics::Drawable;
+use log::{error, info};
 use serde::{Deserialize, Serialize};
 use tokio::sync::mpsc::Sender;
 
+use crate::collections;
 use crate::consoles::ConsoleMapper;
 use crate::entry::{Entry, Sort};
+use crate::repair;
+use crate::view::launch_options::LaunchOptions;
+use crate::view::save_states::SaveStates;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct EntryListState<S> {
@@ -34,6 +42,13 @@ pub struct CoreSelection {
     cores: Vec<String>,
 }
 
+/// Selected collection in the context menu. An index of `collections.len()` means a new collection.
+#[derive(Debug)]
+pub struct CollectionSelection {
+    collection: usize,
+    collections: Vec<Collection>,
+}
+
 #[derive(Debug)]
 pub struct EntryList<S>
 where
@@ -46,7 +61,16 @@ where
     list: ScrollList,
     image: Image,
     menu: Option<ScrollList>,
+    /// Entries of the open menu, in the order they are listed.
+    menu_entries: Vec<MenuEntry>,
     core: Option<CoreSelection>,
+    collection: Option<CollectionSelection>,
+    keyboard: Option<Keyboard>,
+    /// Directory or game to hide or show once the PIN is entered on the keyboard, instead of
+    /// naming a collection.
+    hide_path: Option<PathBuf>,
+    save_states: Option<SaveStates>,
+    launch_options: Option<LaunchOptions>,
     button_hints: Row<ButtonHint<String>>,
     pub child: Option<Box<EntryList<S>>>,
 }
@@ -124,7 +148,13 @@ where
             list,
             image,
             menu: None,
+            menu_entries: Vec::new(),
             core: None,
+            collection: None,
+            keyboard: None,
+            hide_path: None,
+            save_states: None,
+            launch_options: None,
             button_hints,
             child: None,
         };
@@ -168,9 +198,11 @@ where
                 }
                 Entry::Game(game) => {
                     let command = self.res.get::<ConsoleMapper>().launch_game(
+                        &self.res.get(),
                         &self.res.get(),
                         game,
                         false,
+                        None,
                     )?;
                     if let Some(cmd) = command {
                         commands.send(cmd).await?;
@@ -196,6 +228,14 @@ where
         Ok(())
     }
 
+    /// Reloads the entries from the current sort, e.g. when the underlying data changed.
+    pub fn reload(&mut self) -> Result<()> {
+        if let Some(child) = self.child.as_mut() {
+            child.reload()?;
+        }
+        self.load_entries()
+    }
+
     fn load_entries(&mut self) -> Result<()> {
         self.entries = self
             .sort
@@ -213,16 +253,50 @@ where
         let styles = self.res.get::<Stylesheet>();
         let locale = self.res.get::<Locale>();
 
-        let mut entries = [
+        let entry = self.entries.get(self.list.selected()).unwrap();
+        let is_hidden = self
+            .res
+            .get::<ConsoleMapper>()
+            .restrictions()
+            .hidden_paths
+            .iter()
+            .any(|path| path == entry.path());
+        let mut entries = vec![
             MenuEntry::Launch(None),
             MenuEntry::Reset,
+            MenuEntry::SaveStates,
+            MenuEntry::LaunchOptions,
+            MenuEntry::ToggleFavorite(false),
             MenuEntry::RemoveFromRecents,
             MenuEntry::RepopulateDatabase,
+            MenuEntry::RepairLibrary,
+            MenuEntry::ToggleHidden(is_hidden),
         ];
 
-        let entry = self.entries.get(self.list.selected()).unwrap();
         match entry {
             Entry::Game(game) => {
+                if let MenuEntry::ToggleFavorite(ref mut is_favorite) = entries[4] {
+                    *is_favorite = self.res.get::<Database>().is_favorite(&game.path)?;
+                }
+
+                // Only games can be added to collections
+                let collections = self.res.get::<Database>().select_collections()?;
+                let selected = match collections.first() {
+                    Some(collection) => {
+                        let is_member = self
+                            .res
+                            .get::<Database>()
+                            .is_in_collection(collection.id, &game.path)?;
+                        Some((collection.name.clone(), is_member))
+                    }
+                    None => None,
+                };
+                entries.insert(5, MenuEntry::Collection(selected));
+                self.collection = Some(CollectionSelection {
+                    collection: 0,
+                    collections,
+                });
+
                 let cores = self
                     .res
                     .get::<ConsoleMapper>()
@@ -244,7 +318,9 @@ where
                     self.core = None;
                 }
             }
-            Entry::App(_) | Entry::Directory(_) => {}
+            Entry::App(_) | Entry::Directory(_) => {
+                self.collection = None;
+            }
         }
 
         let height = entries.len() as u32 * (styles.ui_font.size + SELECTION_MARGIN);
@@ -262,9 +338,114 @@ where
         );
         menu.set_background_color(Some(StylesheetColor::BackgroundHighlightBlend));
         self.menu = Some(menu);
+        self.menu_entries = entries;
 
         Ok(())
     }
+
+    /// Re-links games and guides that were moved, and returns a summary of what changed.
+    fn repair_library(&self) -> String {
+        let locale = self.res.get::<Locale>();
+        let report = match repair::repair(&ALLIUM_GAMES_DIR, &self.res.get::<Database>()) {
+            Ok(report) => report,
+            Err(e) => {
+                error!("failed to repair library: {}", e);
+                return locale.t("repair-library-failed");
+            }
+        };
+        info!("repaired library:\n{}", report);
+
+        if report.is_empty() {
+            return locale.t("repair-library-nothing");
+        }
+        let args = [
+            ("games".to_string(), report.games.len().into()),
+            ("guides".to_string(), report.guides.len().into()),
+            ("missing".to_string(), report.missing_games.len().into()),
+        ]
+        .into_iter()
+        .collect();
+        let mut text = locale.ta("repair-library-relinked", &args);
+        if !report.missing_games.is_empty() {
+            text.push('\n');
+            text.push_str(&locale.ta("repair-library-missing", &args));
+        }
+        text
+    }
+
+    /// Menu entry for the currently selected collection.
+    fn collection_menu_entry(&self) -> Result<MenuEntry> {
+        let (Some(selection), Some(Entry::Game(game))) = (
+            self.collection.as_ref(),
+            self.entries.get(self.list.selected()),
+        ) else {
+            return Ok(MenuEntry::Collection(None));
+        };
+
+        Ok(match selection.collections.get(selection.collection) {
+            Some(collection) => {
+                let is_member = self
+                    .res
+                    .get::<Database>()
+                    .is_in_collection(collection.id, &game.path)?;
+                MenuEntry::Collection(Some((collection.name.clone(), is_member)))
+            }
+            None => MenuEntry::Collection(None),
+        })
+    }
+
+    /// Adds the selected game to the collection, or removes it if it is already in it.
+    fn toggle_collection(&mut self, collection: &Collection) -> Result<()> {
+        if let Some(Entry::Game(game)) = self.entries.get(self.list.selected()) {
+            let db = self.res.get::<Database>();
+            if db.is_in_collection(collection.id, &game.path)? {
+                db.remove_from_collection(collection.id, &game.path)?;
+            } else {
+                db.add_to_collection(collection.id, &game.path)?;
+            }
+            collections::export(&db, &collection.name)?;
+        }
+        Ok(())
+    }
+
+    /// Creates a new collection containing the selected game.
+    fn create_collection(&mut self, name: &str) -> Result<()> {
+        let name = name.trim();
+        if name.is_empty() {
+            return Ok(());
+        }
+
+        if let Some(Entry::Game(game)) = self.entries.get(self.list.selected()) {
+            let db = self.res.get::<Database>();
+            let id = db.create_collection(name)?;
+            db.add_to_collection(id, &game.path)?;
+            collections::export(&db, name)?;
+        }
+        Ok(())
+    }
+
+    /// Hides a directory or game in restricted mode, or shows it again.
+    async fn toggle_hidden(&self, path: &Path, commands: Sender<Command>) -> Result<()> {
+        let mut settings = self.res.get::<ConsoleMapper>().restrictions().clone();
+        settings.toggle_hidden_path(path);
+        commands
+            .send(Command::SaveParentalSettings(Box::new(settings)))
+            .await?;
+        Ok(())
+    }
+
+    /// Hides or shows `path` if `pin` unlocks parental controls.
+    async fn unlock_hidden(&self, path: &Path, pin: &str, commands: Sender<Command>) -> Result<()> {
+        if PowerSettings::load()?.schedule.check_pin(pin) {
+            self.toggle_hidden(path, commands).await
+        } else {
+            let text = self.res.get::<Locale>().t("parental-wrong-pin");
+            commands
+                .send(Command::Toast(text, Some(Duration::from_secs(2))))
+                .await?;
+            Ok(())
+        }
+    }
 }
 
 #[async_trait(?Send)]
@@ -281,6 +462,18 @@ where
             return child.draw(display, styles);
         }
 
+        if let Some(keyboard) = self.keyboard.as_mut() {
+            return Ok(keyboard.should_draw() && keyboard.draw(display, styles)?);
+        }
+
+        if let Some(save_states) = self.save_states.as_mut() {
+            return Ok(save_states.should_draw() && save_states.draw(display, styles)?);
+        }
+
+        if let Some(launch_options) = self.launch_options.as_mut() {
+            return Ok(launch_options.should_draw() && launch_options.draw(display, styles)?);
+        }
+
         let mut drawn = false;
 
         if let Some(menu) = &mut self.menu {
@@ -343,6 +536,12 @@ where
     fn should_draw(&self) -> bool {
         if let Some(child) = self.child.as_ref() {
             child.should_draw()
+        } else if let Some(keyboard) = self.keyboard.as_ref() {
+            keyboard.should_draw()
+        } else if let Some(save_states) = self.save_states.as_ref() {
+            save_states.should_draw()
+        } else if let Some(launch_options) = self.launch_options.as_ref() {
+            launch_options.should_draw()
         } else {
             self.menu
                 .as_ref()
@@ -356,6 +555,12 @@ where
     fn set_should_draw(&mut self) {
         if let Some(child) = self.child.as_mut() {
             child.set_should_draw();
+        } else if let Some(keyboard) = self.keyboard.as_mut() {
+            keyboard.set_should_draw();
+        } else if let Some(save_states) = self.save_states.as_mut() {
+            save_states.set_should_draw();
+        } else if let Some(launch_options) = self.launch_options.as_mut() {
+            launch_options.set_should_draw();
         } else {
             if let Some(menu) = self.menu.as_mut() {
                 menu.set_should_draw();
@@ -387,11 +592,84 @@ where
                 }
                 false => Ok(false),
             }
+        } else if let Some(keyboard) = self.keyboard.as_mut() {
+            if keyboard
+                .handle_key_event(event, commands.clone(), bubble)
+                .await?
+            {
+                let mut name = None;
+                bubble.retain_mut(|c| match c {
+                    Command::ValueChanged(_, Value::String(val)) => {
+                        name = Some(val.clone());
+                        false
+                    }
+                    Command::CloseView => {
+                        self.keyboard = None;
+                        false
+                    }
+                    _ => true,
+                });
+                if let Some(name) = name {
+                    match self.hide_path.take() {
+                        Some(path) => self.unlock_hidden(&path, &name, commands.clone()).await?,
+                        None => self.create_collection(&name)?,
+                    }
+                }
+                if self.keyboard.is_none() {
+                    commands.send(Command::Redraw).await?;
+                }
+            }
+            Ok(true)
+        } else if let Some(save_states) = self.save_states.as_mut() {
+            if save_states
+                .handle_key_event(event, commands.clone(), bubble)
+                .await?
+            {
+                bubble.retain_mut(|c| match c {
+                    Command::CloseView => {
+                        self.save_states = None;
+                        false
+                    }
+                    _ => true,
+                });
+                if self.save_states.is_none() {
+                    commands.send(Command::Redraw).await?;
+                }
+            }
+            Ok(true)
+        } else if let Some(launch_options) = self.launch_options.as_mut() {
+            if launch_options
+                .handle_key_event(event, commands.clone(), bubble)
+                .await?
+            {
+                bubble.retain_mut(|c| match c {
+                    Command::CloseView => {
+                        self.launch_options = None;
+                        false
+                    }
+                    _ => true,
+                });
+                if self.launch_options.is_none() {
+                    // The game's core may have changed
+                    self.load_entries()?;
+                    commands.send(Command::Redraw).await?;
+                }
+            }
+            Ok(true)
         } else if let Some(menu) = self.menu.as_mut() {
             match event {
                 KeyEvent::Pressed(Key::Left) => {
+                    if let Some(MenuEntry::Collection(_)) = self.menu_entries.get(menu.selected()) {
+                        if let Some(selection) = self.collection.as_mut() {
+                            selection.collection = selection.collection.saturating_sub(1);
+                        }
+                        let entry = self.collection_menu_entry()?;
+                        let menu = self.menu.as_mut().unwrap();
+                        menu.set_item(menu.selected(), entry.text(&self.res.get()));
+                        return Ok(true);
+                    }
                     if let Some(core) = self.core.as_mut() {
-                        let mut selected = MenuEntry::from_repr(menu.selected());
+                        let mut selected = self.menu_entries[menu.selected()].clone();
                         if let MenuEntry::Launch(ref mut launch_core) = selected {
                             core.core = core.core.saturating_sub(1);
                             let console_mapper = self.res.get::<ConsoleMapper>();
@@ -403,8 +681,18 @@ where
                     Ok(true) // trap tab focus
                 }
                 KeyEvent::Pressed(Key::Right) => {
+                    if let Some(MenuEntry::Collection(_)) = self.menu_entries.get(menu.selected()) {
+                        if let Some(selection) = self.collection.as_mut() {
+                            selection.collection =
+                                (selection.collection + 1).min(selection.collections.len());
+                        }
+                        let entry = self.collection_menu_entry()?;
+                        let menu = self.menu.as_mut().unwrap();
+                        menu.set_item(menu.selected(), entry.text(&self.res.get()));
+                        return Ok(true);
+                    }
                     if let Some(core) = self.core.as_mut() {
-                        let mut selected = MenuEntry::from_repr(menu.selected());
+                        let mut selected = self.menu_entries[menu.selected()].clone();
                         if let MenuEntry::Launch(ref mut launch_core) = selected {
                             core.core = (core.core + 1).min(core.cores.len() - 1);
                             let console_mapper = self.res.get::<ConsoleMapper>();
@@ -421,7 +709,7 @@ where
                     Ok(true)
                 }
                 KeyEvent::Pressed(Key::A) => {
-                    let selected = MenuEntry::from_repr(menu.selected());
+                    let selected = self.menu_entries[menu.selected()].clone();
                     match selected {
                         MenuEntry::Launch(_) => {
                             let entry = self.entries.get_mut(self.list.selected()).unwrap();
@@ -440,9 +728,11 @@ where
                                 Entry::Directory(_) | Entry::App(_) => {}
                                 Entry::Game(game) => {
                                     let command = self.res.get::<ConsoleMapper>().launch_game(
+                                        &self.res.get(),
                                         &self.res.get(),
                                         game,
                                         true,
+                                        None,
                                     )?;
                                     if let Some(cmd) = command {
                                         commands.send(cmd).await?;
@@ -450,6 +740,56 @@ where
                                 }
                             }
                         }
+                        MenuEntry::SaveStates => {
+                            if let Some(Entry::Game(game)) = self.entries.get(self.list.selected())
+                            {
+                                self.save_states = Some(SaveStates::new(
+                                    self.rect,
+                                    self.res.clone(),
+                                    game.clone(),
+                                )?);
+                            }
+                            commands.send(Command::Redraw).await?;
+                        }
+                        MenuEntry::LaunchOptions => {
+                            if let Some(Entry::Game(game)) = self.entries.get(self.list.selected())
+                            {
+                                self.launch_options =
+                                    LaunchOptions::new(self.rect, self.res.clone(), game.clone())?;
+                            }
+                            commands.send(Command::Redraw).await?;
+                        }
+                        MenuEntry::ToggleFavorite(_) => {
+                            if let Some(Entry::Game(game)) = self.entries.get(self.list.selected())
+                            {
+                                let db = self.res.get::<Database>();
+                                if db.is_favorite(&game.path)? {
+                                    db.remove_favorite(&game.path)?;
+                                } else {
+                                    db.add_favorite(&game.path)?;
+                                }
+                            }
+                            self.load_entries()?;
+                            commands.send(Command::Redraw).await?;
+                        }
+                        MenuEntry::Collection(_) => {
+                            if let (Some(selection), Some(Entry::Game(_))) = (
+                                self.collection.take(),
+                                self.entries.get(self.list.selected()),
+                            ) {
+                                if let Some(collection) =
+                                    selection.collections.get(selection.collection)
+                                {
+                                    self.toggle_collection(collection)?;
+                                    self.load_entries()?;
+                                } else {
+                                    self.hide_path = None;
+                                    self.keyboard =
+                                        Some(Keyboard::new(self.res.clone(), String::new(), false));
+                                }
+                            }
+                            commands.send(Command::Redraw).await?;
+                        }
                         MenuEntry::RemoveFromRecents => {
                             if let Some(Entry::Game(game)) = self.entries.get(self.list.selected())
                             {
@@ -464,22 +804,35 @@ where
                         }
                         MenuEntry::RepopulateDatabase => {
                             commands.send(Command::Redraw).await?;
-                            #[cfg(not(feature = "miyoo"))]
-                            {
-                                let message = self.res.get::<Locale>().t("populating-database");
-                                commands.send(Command::Toast(message, None)).await?;
-                            }
                             commands.send(Command::PopulateDb).await?;
-                            #[cfg(not(feature = "miyoo"))]
-                            {
-                                commands
-                                    .send(Command::Toast(
-                                        String::new(),
-                                        Some(std::time::Duration::ZERO),
-                                    ))
-                                    .await?;
-                            }
+                        }
+                        MenuEntry::RepairLibrary => {
+                            let text = self.repair_library();
+                            self.load_entries()?;
                             commands.send(Command::Redraw).await?;
+                            commands
+                                .send(Command::Toast(text, Some(Duration::from_secs(3))))
+                                .await?;
+                        }
+                        MenuEntry::ToggleHidden(_) => {
+                            if let Some(entry) = self.entries.get(self.list.selected()) {
+                                let path = entry.path().to_path_buf();
+                                let schedule = PowerSettings::load()?.schedule;
+                                let locked = self
+                                    .res
+                                    .get::<ConsoleMapper>()
+                                    .restrictions()
+                                    .is_locked(&schedule);
+                                if locked {
+                                    // Changing what is hidden needs the PIN, like Settings
+                                    self.hide_path = Some(path);
+                                    self.keyboard =
+                                        Some(Keyboard::new(self.res.clone(), String::new(), true));
+                                    commands.send(Command::Redraw).await?;
+                                } else {
+                                    self.toggle_hidden(&path, commands.clone()).await?;
+                                }
+                            }
                         }
                     }
                     self.menu = None;
@@ -576,24 +929,23 @@ where
     }
 }
 
+#[derive(Debug, Clone)]
 enum MenuEntry {
     Launch(Option<String>),
     Reset,
+    SaveStates,
+    LaunchOptions,
+    ToggleFavorite(bool),
+    /// Selected collection and whether the game is in it. None means a new collection.
+    Collection(Option<(String, bool)>),
     RemoveFromRecents,
     RepopulateDatabase,
+    RepairLibrary,
+    /// Whether the entry is hidden in restricted mode.
+    ToggleHidden(bool),
 }
 
 impl MenuEntry {
-    fn from_repr(i: usize) -> Self {
-        match i {
-            0 => MenuEntry::Launch(None),
-            1 => MenuEntry::Reset,
-            2 => MenuEntry::RemoveFromRecents,
-            3 => MenuEntry::RepopulateDatabase,
-            _ => unreachable!("invalid menu entry"),
-        }
-    }
-
     fn text(&self, locale: &Locale) -> String {
         match self {
             MenuEntry::Launch(core) => {
@@ -607,8 +959,28 @@ impl MenuEntry {
                 }
             }
             MenuEntry::Reset => locale.t("menu-reset"),
+            MenuEntry::SaveStates => locale.t("menu-save-states"),
+            MenuEntry::LaunchOptions => locale.t("menu-launch-options"),
+            MenuEntry::ToggleFavorite(false) => locale.t("menu-add-to-favorites"),
+            MenuEntry::ToggleFavorite(true) => locale.t("menu-remove-from-favorites"),
+            MenuEntry::Collection(None) => locale.t("menu-new-collection"),
+            MenuEntry::Collection(Some((collection, false))) => locale.ta(
+                "menu-add-to-collection",
+                &[("collection".to_string(), collection.as_str().into())]
+                    .into_iter()
+                    .collect(),
+            ),
+            MenuEntry::Collection(Some((collection, true))) => locale.ta(
+                "menu-remove-from-collection",
+                &[("collection".to_string(), collection.as_str().into())]
+                    .into_iter()
+                    .collect(),
+            ),
             MenuEntry::RemoveFromRecents => locale.t("menu-remove-from-recents"),
             MenuEntry::RepopulateDatabase => locale.t("menu-repopulate-database"),
+            MenuEntry::RepairLibrary => locale.t("menu-repair-library"),
+            MenuEntry::ToggleHidden(false) => locale.t("menu-hide"),
+            MenuEntry::ToggleHidden(true) => locale.t("menu-unhide"),
         }
     }
 }
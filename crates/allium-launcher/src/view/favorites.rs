@@ -0,0 +1,212 @@
+use std::cmp::Reverse;
+use std::collections::VecDeque;
+
+use anyhow::Result;
+use async_trait::async_trait;
+use common::command::Command;
+use common::database::Database;
+use common::geom::{Alignment, Point, Rect};
+use common::locale::Locale;
+use common::platform::{DefaultPlatform, Key, KeyEvent, Platform};
+use common::resources::Resources;
+use common::stylesheet::Stylesheet;
+use common::view::{ButtonHint, ButtonIcon, Row, View};
+use serde::{Deserialize, Serialize};
+use tokio::sync::mpsc::Sender;
+
+use crate::consoles::ConsoleMapper;
+use crate::entry::directory::Directory;
+use crate::entry::game::Game;
+use crate::entry::{Entry, Sort};
+use crate::view::entry_list::{EntryList, EntryListState};
+
+pub type FavoritesState = EntryListState<FavoritesSort>;
+
+#[derive(Debug)]
+pub struct Favorites {
+    rect: Rect,
+    list: EntryList<FavoritesSort>,
+    button_hints: Row<ButtonHint<String>>,
+}
+
+impl Favorites {
+    pub fn new(rect: Rect, res: Resources, list: EntryList<FavoritesSort>) -> Result<Self> {
+        let Rect { x, y, w: _w, h } = rect;
+
+        let styles = res.get::<Stylesheet>();
+
+        let button_hints = Row::new(
+            Point::new(
+                x + 12,
+                y + h as i32 - ButtonIcon::diameter(&styles) as i32 - 8,
+            ),
+            {
+                let locale = res.get::<Locale>();
+                vec![ButtonHint::new(
+                    res.clone(),
+                    Point::zero(),
+                    Key::X,
+                    locale.t("sort-search"),
+                    Alignment::Left,
+                )]
+            },
+            Alignment::Left,
+            12,
+        );
+
+        drop(styles);
+
+        Ok(Self {
+            rect,
+            list,
+            button_hints,
+        })
+    }
+
+    pub fn load_or_new(rect: Rect, res: Resources, state: Option<FavoritesState>) -> Result<Self> {
+        let list = if let Some(state) = state {
+            EntryList::load(rect, res.clone(), state)?
+        } else {
+            EntryList::new(rect, res.clone(), FavoritesSort::RecentlyAdded)?
+        };
+
+        Self::new(rect, res, list)
+    }
+
+    pub fn save(&self) -> FavoritesState {
+        self.list.save()
+    }
+
+    /// Reloads the list of favorites, e.g. after a favorite was toggled in another tab.
+    pub fn reload(&mut self) -> Result<()> {
+        self.list.reload()
+    }
+}
+
+#[async_trait(?Send)]
+impl View for Favorites {
+    fn draw(
+        &mut self,
+        display: &mut <DefaultPlatform as Platform>::Display,
+        styles: &Stylesheet,
+    ) -> Result<bool> {
+        let mut drawn = false;
+
+        if self.list.should_draw() {
+            drawn |= self.list.should_draw() && self.list.draw(display, styles)?;
+            self.button_hints.set_should_draw();
+        }
+        drawn |= self.button_hints.should_draw() && self.button_hints.draw(display, styles)?;
+
+        Ok(drawn)
+    }
+
+    fn should_draw(&self) -> bool {
+        self.list.should_draw() || self.button_hints.should_draw()
+    }
+
+    fn set_should_draw(&mut self) {
+        self.list.set_should_draw();
+        self.button_hints.set_should_draw();
+    }
+
+    async fn handle_key_event(
+        &mut self,
+        event: KeyEvent,
+        commands: Sender<Command>,
+        bubble: &mut VecDeque<Command>,
+    ) -> Result<bool> {
+        match event {
+            KeyEvent::Pressed(Key::X) => {
+                commands.send(Command::StartSearch).await?;
+                Ok(true)
+            }
+            _ => self.list.handle_key_event(event, commands, bubble).await,
+        }
+    }
+
+    fn children(&self) -> Vec<&dyn View> {
+        vec![&self.list]
+    }
+
+    fn children_mut(&mut self) -> Vec<&mut dyn View> {
+        vec![&mut self.list]
+    }
+
+    fn bounding_box(&mut self, _styles: &Stylesheet) -> Rect {
+        self.rect
+    }
+
+    fn set_position(&mut self, _point: Point) {
+        unimplemented!()
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum FavoritesSort {
+    RecentlyAdded,
+    Alphabetical,
+    LastPlayed,
+    MostPlayed,
+}
+
+impl Sort for FavoritesSort {
+    fn button_hint(&self, locale: &Locale) -> String {
+        match self {
+            FavoritesSort::RecentlyAdded => locale.t("sort-recently-added"),
+            FavoritesSort::Alphabetical => locale.t("sort-alphabetical"),
+            FavoritesSort::LastPlayed => locale.t("sort-last-played"),
+            FavoritesSort::MostPlayed => locale.t("sort-most-played"),
+        }
+    }
+
+    fn next(&self) -> Self {
+        match self {
+            FavoritesSort::RecentlyAdded => FavoritesSort::Alphabetical,
+            FavoritesSort::Alphabetical => FavoritesSort::LastPlayed,
+            FavoritesSort::LastPlayed => FavoritesSort::MostPlayed,
+            FavoritesSort::MostPlayed => FavoritesSort::RecentlyAdded,
+        }
+    }
+
+    fn with_directory(&self, _directory: Directory) -> Self {
+        unimplemented!();
+    }
+
+    fn entries(
+        &self,
+        database: &Database,
+        _console_mapper: &ConsoleMapper,
+        _locale: &Locale,
+    ) -> Result<Vec<Entry>> {
+        let mut games = match database.select_favorites() {
+            Ok(games) => games,
+            Err(err) => {
+                log::error!("Failed to select favorites: {}", err);
+                return Err(err);
+            }
+        };
+
+        match self {
+            FavoritesSort::RecentlyAdded => {}
+            FavoritesSort::Alphabetical => {
+                games.sort_by(|a, b| a.name.cmp(&b.name));
+            }
+            FavoritesSort::LastPlayed => {
+                games.sort_by_key(|g| Reverse(g.last_played));
+            }
+            FavoritesSort::MostPlayed => {
+                games.sort_by_key(|g| Reverse(g.play_time));
+            }
+        }
+
+        Ok(games
+            .into_iter()
+            .map(|game| Entry::Game(Game::from_db(game)))
+            .collect())
+    }
+
+    fn preserve_selection(&self) -> bool {
+        true
+    }
+}
@@ -5,7 +5,7 @@ use anyhow::Result;
 use async_trait::async_trait;
 use common::command::Command;
 use common::constants::ALLIUM_GAMES_DIR;
-use common::database::Database;
+use common::database::{Database, GroupBy};
 use common::geom::{Alignment, Point, Rect};
 use common::locale::Locale;
 use common::platform::{DefaultPlatform, Key, KeyEvent, Platform};
@@ -17,7 +17,7 @@ use serde::{Deserialize, Serialize};
 use tokio::sync::mpsc::Sender;
 
 use crate::consoles::ConsoleMapper;
-use crate::entry::directory::Directory;
+use crate::entry::directory::{Browse, Directory};
 use crate::entry::{Entry, Sort};
 use crate::view::entry_list::{EntryList, EntryListState};
 
@@ -208,6 +208,10 @@ impl Sort for GamesSort {
     ) -> Result<Vec<Entry>> {
         let mut entries = self.directory().entries(database, console_mapper, locale)?;
 
+        if self.directory().path == *ALLIUM_GAMES_DIR {
+            entries.extend(browse_roots(locale).into_iter().map(Entry::Directory));
+        }
+
         match self {
             GamesSort::Alphabetical(_) => {
                 entries.sort_unstable();
@@ -326,3 +330,16 @@ impl Sort for GamesSort {
         false
     }
 }
+
+/// Virtual directories at the root of the Games tab that list games by metadata.
+fn browse_roots(locale: &Locale) -> Vec<Directory> {
+    [
+        (GroupBy::Genre, "browse-by-genre"),
+        (GroupBy::Developer, "browse-by-developer"),
+        (GroupBy::Publisher, "browse-by-publisher"),
+        (GroupBy::Decade, "browse-by-decade"),
+    ]
+    .into_iter()
+    .map(|(group_by, key)| Directory::browse(Browse::Groups(group_by), locale.t(key)))
+    .collect()
+}
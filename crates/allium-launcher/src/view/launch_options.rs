@@ -0,0 +1,396 @@
+use std::collections::VecDeque;
+
+use anyhow::Result;
+use async_trait::async_trait;
+use common::command::{Command, Value};
+use common::constants::SELECTION_MARGIN;
+use common::database::Database;
+use common::display::Display;
+use common::geom::{Alignment, Point, Rect};
+use common::launch_profile::{LaunchProfile, LaunchProfileScope};
+use common::locale::Locale;
+use common::platform::{DefaultPlatform, Key, KeyEvent, Platform};
+use common::resources::Resources;
+use common::stylesheet::Stylesheet;
+use common::view::{ButtonHint, ButtonIcon, Row, Select, SettingsList, TextBox, View};
+use tokio::sync::mpsc::Sender;
+
+use crate::consoles::{Console, ConsoleMapper};
+use crate::entry::game::Game;
+
+/// cpufreq governors that can be picked, after "Default".
+const GOVERNORS: [&str; 4] = ["performance", "ondemand", "powersave", "conservative"];
+
+/// CPU clocks in MHz that can be picked, after "Default".
+const CPU_CLOCKS: [u32; 6] = [1000, 1200, 1300, 1400, 1500, 1600];
+
+/// Edits the launch profile of a game, or of the console it belongs to. Changes are saved as soon
+/// as they are made.
+#[derive(Debug)]
+pub struct LaunchOptions {
+    rect: Rect,
+    res: Resources,
+    game: Game,
+    console: Console,
+    scope: LaunchProfileScope,
+    profile: LaunchProfile,
+    /// Governors listed in the governor select, including one set outside of this view.
+    governors: Vec<String>,
+    /// Clocks listed in the clock select, including one set outside of this view.
+    clocks: Vec<u32>,
+    list: SettingsList,
+    button_hints: Row<ButtonHint<String>>,
+    dirty: bool,
+}
+
+impl LaunchOptions {
+    pub fn new(rect: Rect, res: Resources, game: Game) -> Result<Option<Self>> {
+        let Rect { x, y, w, h } = rect;
+
+        let Some(console) = res.get::<ConsoleMapper>().get_console(&game.path).cloned() else {
+            return Ok(None);
+        };
+
+        let styles = res.get::<Stylesheet>();
+        let locale = res.get::<Locale>();
+
+        let list = SettingsList::new(
+            Rect::new(
+                x + 12,
+                y + 8,
+                w - 24,
+                h - 8 - ButtonIcon::diameter(&styles) - 8,
+            ),
+            Vec::new(),
+            Vec::new(),
+            styles.ui_font.size + SELECTION_MARGIN,
+        );
+
+        let button_hints = Row::new(
+            Point::new(
+                x + w as i32 - 12,
+                y + h as i32 - ButtonIcon::diameter(&styles) as i32 - 8,
+            ),
+            vec![
+                ButtonHint::new(
+                    res.clone(),
+                    Point::zero(),
+                    Key::A,
+                    locale.t("button-edit"),
+                    Alignment::Right,
+                ),
+                ButtonHint::new(
+                    res.clone(),
+                    Point::zero(),
+                    Key::B,
+                    locale.t("button-back"),
+                    Alignment::Right,
+                ),
+            ],
+            Alignment::Right,
+            12,
+        );
+
+        drop(styles);
+        drop(locale);
+
+        let mut this = Self {
+            rect,
+            res,
+            game,
+            console,
+            scope: LaunchProfileScope::Game,
+            profile: LaunchProfile::default(),
+            governors: Vec::new(),
+            clocks: Vec::new(),
+            list,
+            button_hints,
+            dirty: true,
+        };
+        this.load()?;
+
+        Ok(Some(this))
+    }
+
+    /// Database key of the profile being edited.
+    fn key(&self) -> String {
+        match self.scope {
+            LaunchProfileScope::Game => self.game.path.display().to_string(),
+            LaunchProfileScope::Console => self.console.name.clone(),
+        }
+    }
+
+    /// Loads the profile of the current scope and rebuilds the list from it.
+    fn load(&mut self) -> Result<()> {
+        self.profile = self
+            .res
+            .get::<Database>()
+            .get_launch_profile(self.scope, &self.key())?;
+
+        let locale = self.res.get::<Locale>();
+        let console_mapper = self.res.get::<ConsoleMapper>();
+        let default = locale.t("launch-options-default");
+
+        let core = self
+            .profile
+            .core
+            .as_ref()
+            .and_then(|core| self.console.cores.iter().position(|c| c == core))
+            .map_or(0, |i| i + 1);
+        let cores = std::iter::once(default.clone())
+            .chain(
+                self.console
+                    .cores
+                    .iter()
+                    .map(|core| console_mapper.get_core_name(core)),
+            )
+            .collect();
+
+        self.governors = GOVERNORS.iter().map(|g| g.to_string()).collect();
+        if let Some(governor) = self.profile.cpu.governor.as_ref() {
+            if !self.governors.contains(governor) {
+                self.governors.push(governor.clone());
+            }
+        }
+        let governor = self
+            .profile
+            .cpu
+            .governor
+            .as_ref()
+            .and_then(|governor| self.governors.iter().position(|g| g == governor))
+            .map_or(0, |i| i + 1);
+
+        self.clocks = CPU_CLOCKS.to_vec();
+        if let Some(clock) = self.profile.cpu.max_clock_mhz {
+            if !self.clocks.contains(&clock) {
+                self.clocks.push(clock);
+                self.clocks.sort_unstable();
+            }
+        }
+        let clock = self
+            .profile
+            .cpu
+            .max_clock_mhz
+            .and_then(|clock| self.clocks.iter().position(|c| *c == clock))
+            .map_or(0, |i| i + 1);
+
+        let swap = match self.profile.swap {
+            None => 0,
+            Some(true) => 1,
+            Some(false) => 2,
+        };
+
+        let left = vec![
+            locale.t("launch-options-scope"),
+            locale.t("launch-options-core"),
+            locale.t("launch-options-retroarch-args"),
+            locale.t("launch-options-append-config"),
+            locale.t("launch-options-cpu-governor"),
+            locale.t("launch-options-cpu-clock"),
+            locale.t("launch-options-swap"),
+        ];
+        let right: Vec<Box<dyn View>> = vec![
+            Box::new(Select::new(
+                Point::zero(),
+                match self.scope {
+                    LaunchProfileScope::Game => 0,
+                    LaunchProfileScope::Console => 1,
+                },
+                vec![
+                    locale.t("launch-options-scope-game"),
+                    self.console.name.clone(),
+                ],
+                Alignment::Right,
+            )),
+            Box::new(Select::new(Point::zero(), core, cores, Alignment::Right)),
+            Box::new(TextBox::new(
+                Point::zero(),
+                self.res.clone(),
+                self.profile.retroarch_args.join(" "),
+                Alignment::Right,
+                false,
+            )),
+            Box::new(TextBox::new(
+                Point::zero(),
+                self.res.clone(),
+                self.profile
+                    .append_configs
+                    .iter()
+                    .map(|path| path.display().to_string())
+                    .collect::<Vec<_>>()
+                    .join("|"),
+                Alignment::Right,
+                false,
+            )),
+            Box::new(Select::new(
+                Point::zero(),
+                governor,
+                std::iter::once(default.clone())
+                    .chain(self.governors.iter().cloned())
+                    .collect(),
+                Alignment::Right,
+            )),
+            Box::new(Select::new(
+                Point::zero(),
+                clock,
+                std::iter::once(default.clone())
+                    .chain(self.clocks.iter().map(|clock| format!("{} MHz", clock)))
+                    .collect(),
+                Alignment::Right,
+            )),
+            Box::new(Select::new(
+                Point::zero(),
+                swap,
+                vec![
+                    default,
+                    locale.t("launch-options-on"),
+                    locale.t("launch-options-off"),
+                ],
+                Alignment::Right,
+            )),
+        ];
+
+        drop(locale);
+        drop(console_mapper);
+
+        let selected = self.list.selected();
+        self.list.set_items(left, right);
+        self.list.select(selected);
+        self.dirty = true;
+
+        Ok(())
+    }
+
+    /// Applies a changed value to the profile. Returns true if the list needs to be reloaded.
+    fn set_value(&mut self, i: usize, val: Value) -> Result<bool> {
+        // Select values are offset by one because of "Default"
+        let selected = |val: Value| (val.as_int().unwrap() as usize).checked_sub(1);
+        match i {
+            0 => {
+                self.scope = match val.as_int().unwrap() {
+                    0 => LaunchProfileScope::Game,
+                    _ => LaunchProfileScope::Console,
+                };
+                return Ok(true);
+            }
+            1 => {
+                self.profile.core = selected(val).map(|i| self.console.cores[i].clone());
+            }
+            2 => {
+                self.profile.retroarch_args = val
+                    .as_string()
+                    .unwrap()
+                    .split_whitespace()
+                    .map(str::to_string)
+                    .collect();
+            }
+            3 => {
+                self.profile.append_configs = val
+                    .as_string()
+                    .unwrap()
+                    .split('|')
+                    .map(str::trim)
+                    .filter(|path| !path.is_empty())
+                    .map(Into::into)
+                    .collect();
+            }
+            4 => {
+                self.profile.cpu.governor = selected(val).map(|i| self.governors[i].clone());
+            }
+            5 => self.profile.cpu.max_clock_mhz = selected(val).map(|i| self.clocks[i]),
+            6 => {
+                self.profile.swap = match val.as_int().unwrap() {
+                    0 => None,
+                    1 => Some(true),
+                    _ => Some(false),
+                };
+            }
+            _ => unreachable!("Invalid index"),
+        }
+
+        self.res
+            .get::<Database>()
+            .set_launch_profile(self.scope, &self.key(), &self.profile)?;
+        Ok(false)
+    }
+}
+
+#[async_trait(?Send)]
+impl View for LaunchOptions {
+    fn draw(
+        &mut self,
+        display: &mut <DefaultPlatform as Platform>::Display,
+        styles: &Stylesheet,
+    ) -> Result<bool> {
+        let mut drawn = false;
+
+        if self.dirty {
+            display.load(self.rect)?;
+            self.list.set_should_draw();
+            self.button_hints.set_should_draw();
+            self.dirty = false;
+            drawn = true;
+        }
+
+        drawn |= self.list.should_draw() && self.list.draw(display, styles)?;
+        drawn |= self.button_hints.should_draw() && self.button_hints.draw(display, styles)?;
+
+        Ok(drawn)
+    }
+
+    fn should_draw(&self) -> bool {
+        self.dirty || self.list.should_draw() || self.button_hints.should_draw()
+    }
+
+    fn set_should_draw(&mut self) {
+        self.dirty = true;
+    }
+
+    async fn handle_key_event(
+        &mut self,
+        event: KeyEvent,
+        commands: Sender<Command>,
+        bubble: &mut VecDeque<Command>,
+    ) -> Result<bool> {
+        if self
+            .list
+            .handle_key_event(event, commands.clone(), bubble)
+            .await?
+        {
+            let mut reload = false;
+            while let Some(command) = bubble.pop_front() {
+                if let Command::ValueChanged(i, val) = command {
+                    reload |= self.set_value(i, val)?;
+                }
+            }
+            if reload {
+                self.load()?;
+            }
+            return Ok(true);
+        }
+
+        match event {
+            KeyEvent::Pressed(Key::B) => {
+                bubble.push_back(Command::CloseView);
+                Ok(true)
+            }
+            _ => Ok(false),
+        }
+    }
+
+    fn children(&self) -> Vec<&dyn View> {
+        vec![&self.list, &self.button_hints]
+    }
+
+    fn children_mut(&mut self) -> Vec<&mut dyn View> {
+        vec![&mut self.list, &mut self.button_hints]
+    }
+
+    fn bounding_box(&mut self, _styles: &Stylesheet) -> Rect {
+        self.rect
+    }
+
+    fn set_position(&mut self, _point: Point) {
+        unimplemented!()
+    }
+}
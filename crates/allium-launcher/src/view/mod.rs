@@ -1,13 +1,18 @@
 mod app;
 mod apps;
 mod entry_list;
+mod favorites;
 mod games;
+mod launch_options;
 mod recents;
+mod save_states;
+mod search_help;
 mod settings;
 mod toast;
 
 pub use app::App;
 pub use apps::Apps;
+pub use favorites::Favorites;
 pub use games::Games;
 pub use recents::Recents;
 pub use settings::Settings;
@@ -1,5 +1,4 @@
 use std::collections::VecDeque;
-use std::time::Duration;
 
 use anyhow::Result;
 use async_trait::async_trait;
@@ -21,6 +20,7 @@ use crate::entry::game::Game;
 use crate::entry::lazy_image::LazyImage;
 use crate::entry::{Entry, Sort};
 use crate::view::entry_list::{EntryList, EntryListState};
+use crate::view::search_help::SearchHelp;
 
 pub type RecentsState = EntryListState<RecentsSort>;
 
@@ -31,6 +31,7 @@ pub struct Recents {
     list: EntryList<RecentsSort>,
     button_hints: Row<ButtonHint<String>>,
     keyboard: Option<Keyboard>,
+    help: Option<SearchHelp>,
 }
 
 impl Recents {
@@ -46,13 +47,22 @@ impl Recents {
             ),
             {
                 let locale = res.get::<Locale>();
-                vec![ButtonHint::new(
-                    res.clone(),
-                    Point::zero(),
-                    Key::X,
-                    locale.t("sort-search"),
-                    Alignment::Left,
-                )]
+                vec![
+                    ButtonHint::new(
+                        res.clone(),
+                        Point::zero(),
+                        Key::X,
+                        locale.t("sort-search"),
+                        Alignment::Left,
+                    ),
+                    ButtonHint::new(
+                        res.clone(),
+                        Point::zero(),
+                        Key::Start,
+                        locale.t("search-help-hint"),
+                        Alignment::Left,
+                    ),
+                ]
             },
             Alignment::Left,
             12,
@@ -66,6 +76,7 @@ impl Recents {
             list,
             button_hints,
             keyboard: None,
+            help: None,
         })
     }
 
@@ -89,12 +100,7 @@ impl Recents {
 
     pub async fn try_search(&mut self, commands: Sender<Command>, query: String) -> Result<()> {
         if !self.res.get::<Database>().has_indexed()? {
-            let toast = self.res.get::<Locale>().t("populating-database");
-            commands.send(Command::Toast(toast, None)).await?;
             commands.send(Command::PopulateDb).await?;
-            commands
-                .send(Command::Toast(String::new(), Some(Duration::ZERO)))
-                .await?;
         }
 
         commands.send(Command::Search(query)).await?;
@@ -130,6 +136,13 @@ impl View for Recents {
             drawn |= keyboard.should_draw() && keyboard.draw(display, styles)?;
         }
 
+        if let Some(help) = self.help.as_mut() {
+            if drawn {
+                help.set_should_draw();
+            }
+            drawn |= help.should_draw() && help.draw(display, styles)?;
+        }
+
         Ok(drawn)
     }
 
@@ -137,6 +150,7 @@ impl View for Recents {
         self.list.should_draw()
             || self.button_hints.should_draw()
             || self.keyboard.as_ref().map_or(false, |k| k.should_draw())
+            || self.help.as_ref().is_some_and(|h| h.should_draw())
     }
 
     fn set_should_draw(&mut self) {
@@ -145,6 +159,9 @@ impl View for Recents {
         if let Some(keyboard) = self.keyboard.as_mut() {
             keyboard.set_should_draw();
         }
+        if let Some(help) = self.help.as_mut() {
+            help.set_should_draw();
+        }
     }
 
     async fn handle_key_event(
@@ -153,6 +170,18 @@ impl View for Recents {
         commands: Sender<Command>,
         bubble: &mut VecDeque<Command>,
     ) -> Result<bool> {
+        if let Some(help) = self.help.as_mut() {
+            help.handle_key_event(event, commands.clone(), bubble)
+                .await?;
+            if bubble.iter().any(|c| matches!(c, Command::CloseView)) {
+                bubble.retain(|c| !matches!(c, Command::CloseView));
+                self.help = None;
+                self.list.set_should_draw();
+                commands.send(Command::Redraw).await?;
+            }
+            return Ok(true);
+        }
+
         if let Some(keyboard) = self.keyboard.as_mut() {
             if keyboard
                 .handle_key_event(event, commands.clone(), bubble)
@@ -190,6 +219,10 @@ impl View for Recents {
                 }
                 return Ok(true);
             }
+            KeyEvent::Pressed(Key::Start) => {
+                self.help = Some(SearchHelp::new(self.rect, self.res.clone()));
+                Ok(true)
+            }
             _ => self.list.handle_key_event(event, commands, bubble).await,
         }
     }
@@ -0,0 +1,520 @@
+use std::collections::{HashMap, VecDeque};
+
+use anyhow::Result;
+use async_trait::async_trait;
+use common::command::Command;
+use common::constants::{IMAGE_WIDTH, SELECTION_MARGIN};
+use common::display::Display;
+use common::geom::{Alignment, Point, Rect};
+use common::locale::Locale;
+use common::platform::{DefaultPlatform, Key, KeyEvent, Platform};
+use common::resources::Resources;
+use common::save_state::{Backup, SaveState, SaveStateSettings, AUTO_SLOT, LOW_BATTERY_SLOT};
+use common::stylesheet::Stylesheet;
+use common::view::{ButtonHint, ButtonIcon, Image, ImageMode, Label, Row, SettingsList, View};
+use log::error;
+use tokio::sync::mpsc::Sender;
+
+use crate::consoles::ConsoleMapper;
+use crate::entry::game::Game;
+
+/// Highest slot that states can be copied to.
+const MAX_SLOT: i8 = 9;
+
+/// Lists the save states of a game, with their screenshots. States can be deleted, copied to
+/// another slot, restored from a backup, or loaded by launching the game.
+#[derive(Debug)]
+pub struct SaveStates {
+    rect: Rect,
+    res: Resources,
+    game: Game,
+    core: String,
+    states: Vec<SaveState>,
+    list: SettingsList,
+    image: Image,
+    button_hints: Row<ButtonHint<String>>,
+    backup_hints: Row<ButtonHint<String>>,
+    /// Backups of the selected state, while choosing one to restore.
+    backups: Option<Vec<Backup>>,
+    /// Selected state while its backups are shown.
+    list_state: usize,
+    /// Target slot while copying the selected state.
+    copy_to: Option<i8>,
+    /// Whether Y has been pressed once to delete the selected state.
+    confirm_delete: bool,
+    dirty: bool,
+}
+
+impl SaveStates {
+    pub fn new(rect: Rect, res: Resources, game: Game) -> Result<Self> {
+        let Rect { x, y, w, h } = rect;
+
+        let core = {
+            let console_mapper = res.get::<ConsoleMapper>();
+            match console_mapper.get_console(&game.path) {
+                Some(console) => console_mapper
+                    .launch_profile(&res.get(), console, &game)?
+                    .core
+                    .unwrap_or_default(),
+                None => game.core.clone().unwrap_or_default(),
+            }
+        };
+
+        let styles = res.get::<Stylesheet>();
+        let locale = res.get::<Locale>();
+
+        let list = SettingsList::new(
+            Rect::new(
+                x + 12,
+                y + 8,
+                w - IMAGE_WIDTH - 12 - 12 - 24,
+                h - 8 - ButtonIcon::diameter(&styles) - 8,
+            ),
+            Vec::new(),
+            Vec::new(),
+            styles.ui_font.size + SELECTION_MARGIN,
+        );
+
+        let mut image = Image::empty(
+            Rect::new(
+                x + w as i32 - IMAGE_WIDTH as i32 - 24,
+                y + 8,
+                IMAGE_WIDTH,
+                h - 8 - 8 - 8 - ButtonIcon::diameter(&styles) - 8,
+            ),
+            ImageMode::Contain,
+        );
+        image.set_border_radius(12);
+        image.set_alignment(Alignment::Right);
+
+        let button_hints = Row::new(
+            Point::new(
+                x + w as i32 - 12,
+                y + h as i32 - ButtonIcon::diameter(&styles) as i32 - 8,
+            ),
+            vec![
+                ButtonHint::new(
+                    res.clone(),
+                    Point::zero(),
+                    Key::A,
+                    locale.t("save-states-load"),
+                    Alignment::Right,
+                ),
+                ButtonHint::new(
+                    res.clone(),
+                    Point::zero(),
+                    Key::X,
+                    locale.t("save-states-copy"),
+                    Alignment::Right,
+                ),
+                ButtonHint::new(
+                    res.clone(),
+                    Point::zero(),
+                    Key::Y,
+                    locale.t("save-states-delete"),
+                    Alignment::Right,
+                ),
+                ButtonHint::new(
+                    res.clone(),
+                    Point::zero(),
+                    Key::Start,
+                    locale.t("save-states-backups"),
+                    Alignment::Right,
+                ),
+                ButtonHint::new(
+                    res.clone(),
+                    Point::zero(),
+                    Key::B,
+                    locale.t("button-back"),
+                    Alignment::Right,
+                ),
+            ],
+            Alignment::Right,
+            12,
+        );
+
+        let backup_hints = Row::new(
+            Point::new(
+                x + w as i32 - 12,
+                y + h as i32 - ButtonIcon::diameter(&styles) as i32 - 8,
+            ),
+            vec![
+                ButtonHint::new(
+                    res.clone(),
+                    Point::zero(),
+                    Key::A,
+                    locale.t("save-states-restore"),
+                    Alignment::Right,
+                ),
+                ButtonHint::new(
+                    res.clone(),
+                    Point::zero(),
+                    Key::B,
+                    locale.t("button-back"),
+                    Alignment::Right,
+                ),
+            ],
+            Alignment::Right,
+            12,
+        );
+
+        drop(styles);
+        drop(locale);
+
+        let mut this = Self {
+            rect,
+            res,
+            game,
+            core,
+            states: Vec::new(),
+            list,
+            image,
+            button_hints,
+            backup_hints,
+            backups: None,
+            list_state: 0,
+            copy_to: None,
+            confirm_delete: false,
+            dirty: true,
+        };
+        this.load()?;
+
+        Ok(this)
+    }
+
+    fn load(&mut self) -> Result<()> {
+        self.states = SaveState::list(&self.game.path, &self.core)?;
+        self.backups = None;
+        self.copy_to = None;
+        self.confirm_delete = false;
+
+        let locale = self.res.get::<Locale>();
+        let (left, right) = if self.states.is_empty() {
+            (
+                vec![locale.t("save-states-empty")],
+                vec![Box::new(Label::new(
+                    Point::zero(),
+                    String::new(),
+                    Alignment::Right,
+                    None,
+                )) as Box<dyn View>],
+            )
+        } else {
+            self.states
+                .iter()
+                .map(|state| {
+                    (
+                        slot_name(&locale, state.slot),
+                        Box::new(Label::new(
+                            Point::zero(),
+                            state.modified.format("%Y-%m-%d %H:%M").to_string(),
+                            Alignment::Right,
+                            None,
+                        )) as Box<dyn View>,
+                    )
+                })
+                .unzip()
+        };
+
+        let selected = self.list.selected().min(left.len() - 1);
+        self.list.set_items(left, right);
+        self.list.select(selected);
+        self.dirty = true;
+
+        Ok(())
+    }
+
+    /// Shows the backups of the selected state instead of the states.
+    fn load_backups(&mut self) -> Result<()> {
+        let Some(state) = self.selected() else {
+            return Ok(());
+        };
+        let backups = state.backups()?;
+
+        let locale = self.res.get::<Locale>();
+        let left = if backups.is_empty() {
+            vec![locale.t("save-states-no-backups")]
+        } else {
+            backups
+                .iter()
+                .map(|backup| backup.modified.format("%Y-%m-%d %H:%M:%S").to_string())
+                .collect()
+        };
+        let right = left
+            .iter()
+            .map(|_| {
+                Box::new(Label::new(
+                    Point::zero(),
+                    String::new(),
+                    Alignment::Right,
+                    None,
+                )) as Box<dyn View>
+            })
+            .collect();
+        drop(locale);
+
+        self.list.set_items(left, right);
+        self.list.select(0);
+        self.backups = Some(backups);
+        self.dirty = true;
+
+        Ok(())
+    }
+
+    /// Restores the selected backup over the state it was listed for.
+    fn restore(&mut self, state: usize, backup: usize) -> Result<()> {
+        let (Some(state), Some(backup)) = (
+            self.states.get(state),
+            self.backups
+                .as_ref()
+                .and_then(|backups| backups.get(backup)),
+        ) else {
+            return Ok(());
+        };
+        let settings = SaveStateSettings::load()?;
+        if let Err(e) = state.restore(backup, &settings) {
+            error!("failed to restore save state: {}", e);
+        }
+        Ok(())
+    }
+
+    fn selected(&self) -> Option<&SaveState> {
+        self.states.get(self.list.selected())
+    }
+
+    /// Replaces the timestamp of the selected state with a prompt.
+    fn set_prompt(&mut self, text: String) {
+        let i = self.list.selected();
+        self.list.set_right(
+            i,
+            Box::new(Label::new(Point::zero(), text, Alignment::Right, None)),
+        );
+    }
+
+    fn set_copy_prompt(&mut self, slot: i8) {
+        let locale = self.res.get::<Locale>();
+        let mut map = HashMap::new();
+        map.insert("slot".to_string(), slot_name(&locale, slot).into());
+        let text = locale.ta("save-states-copy-to", &map);
+        drop(locale);
+        self.set_prompt(text);
+    }
+
+    async fn launch(&mut self, commands: Sender<Command>) -> Result<()> {
+        let Some(slot) = self.selected().map(|state| state.slot) else {
+            return Ok(());
+        };
+        let command = self.res.get::<ConsoleMapper>().launch_game(
+            &self.res.get(),
+            &self.res.get(),
+            &mut self.game,
+            false,
+            Some(slot),
+        )?;
+        if let Some(cmd) = command {
+            commands.send(cmd).await?;
+        }
+        Ok(())
+    }
+}
+
+fn slot_name(locale: &Locale, slot: i8) -> String {
+    if slot == AUTO_SLOT {
+        locale.t("ingame-menu-slot-auto")
+    } else if slot == LOW_BATTERY_SLOT {
+        locale.t("save-states-slot-low-battery")
+    } else {
+        let mut map = HashMap::new();
+        map.insert("slot".to_string(), slot.into());
+        locale.ta("ingame-menu-slot", &map)
+    }
+}
+
+#[async_trait(?Send)]
+impl View for SaveStates {
+    fn draw(
+        &mut self,
+        display: &mut <DefaultPlatform as Platform>::Display,
+        styles: &Stylesheet,
+    ) -> Result<bool> {
+        let mut drawn = false;
+
+        if self.dirty {
+            display.load(self.rect)?;
+            self.list.set_should_draw();
+            self.image.set_should_draw();
+            self.button_hints.set_should_draw();
+            self.backup_hints.set_should_draw();
+            self.dirty = false;
+            drawn = true;
+        }
+
+        drawn |= self.list.should_draw() && self.list.draw(display, styles)?;
+
+        if styles.enable_box_art {
+            let screenshot = match self.backups.as_ref() {
+                Some(backups) => backups
+                    .get(self.list.selected())
+                    .and_then(Backup::screenshot),
+                None => self.selected().and_then(SaveState::screenshot),
+            };
+            self.image.set_path(screenshot);
+            drawn |= self.image.should_draw() && self.image.draw(display, styles)?;
+        }
+
+        let button_hints = if self.backups.is_some() {
+            &mut self.backup_hints
+        } else {
+            &mut self.button_hints
+        };
+        drawn |= button_hints.should_draw() && button_hints.draw(display, styles)?;
+
+        Ok(drawn)
+    }
+
+    fn should_draw(&self) -> bool {
+        self.dirty
+            || self.list.should_draw()
+            || self.image.should_draw()
+            || if self.backups.is_some() {
+                self.backup_hints.should_draw()
+            } else {
+                self.button_hints.should_draw()
+            }
+    }
+
+    fn set_should_draw(&mut self) {
+        self.dirty = true;
+    }
+
+    async fn handle_key_event(
+        &mut self,
+        event: KeyEvent,
+        commands: Sender<Command>,
+        bubble: &mut VecDeque<Command>,
+    ) -> Result<bool> {
+        if let Some(slot) = self.copy_to {
+            match event {
+                KeyEvent::Pressed(Key::Left) | KeyEvent::Autorepeat(Key::Left) => {
+                    let slot = (slot - 1).max(0);
+                    self.copy_to = Some(slot);
+                    self.set_copy_prompt(slot);
+                }
+                KeyEvent::Pressed(Key::Right) | KeyEvent::Autorepeat(Key::Right) => {
+                    let slot = (slot + 1).min(MAX_SLOT);
+                    self.copy_to = Some(slot);
+                    self.set_copy_prompt(slot);
+                }
+                KeyEvent::Pressed(Key::A) => {
+                    if let Some(state) = self.selected() {
+                        if let Err(e) = state.copy_to(slot) {
+                            error!("failed to copy save state: {}", e);
+                        }
+                    }
+                    self.load()?;
+                }
+                KeyEvent::Pressed(Key::B | Key::X) => self.load()?,
+                _ => {}
+            }
+            return Ok(true);
+        }
+
+        if self.backups.is_some() {
+            return match event {
+                KeyEvent::Pressed(Key::A) => {
+                    // The states are listed in the same order as before opening the backups
+                    let backup = self.list.selected();
+                    let state = self.list_state;
+                    self.restore(state, backup)?;
+                    self.load()?;
+                    self.list.select(state);
+                    Ok(true)
+                }
+                KeyEvent::Pressed(Key::B | Key::Start) => {
+                    let state = self.list_state;
+                    self.load()?;
+                    self.list.select(state);
+                    Ok(true)
+                }
+                _ => self.list.handle_key_event(event, commands, bubble).await,
+            };
+        }
+
+        match event {
+            KeyEvent::Pressed(Key::A) => {
+                self.launch(commands).await?;
+                Ok(true)
+            }
+            KeyEvent::Pressed(Key::X) => {
+                if let Some(state) = self.selected() {
+                    let slot = if state.slot == AUTO_SLOT {
+                        0
+                    } else {
+                        (state.slot + 1).min(MAX_SLOT)
+                    };
+                    self.copy_to = Some(slot);
+                    self.set_copy_prompt(slot);
+                }
+                Ok(true)
+            }
+            KeyEvent::Pressed(Key::Y) => {
+                if let Some(state) = self.selected() {
+                    if self.confirm_delete {
+                        if let Err(e) = state.delete() {
+                            error!("failed to delete save state: {}", e);
+                        }
+                        self.load()?;
+                    } else {
+                        let text = self.res.get::<Locale>().t("save-states-confirm-delete");
+                        self.set_prompt(text);
+                        self.confirm_delete = true;
+                    }
+                }
+                Ok(true)
+            }
+            KeyEvent::Pressed(Key::Start) => {
+                if self.selected().is_some() {
+                    self.list_state = self.list.selected();
+                    self.load_backups()?;
+                }
+                Ok(true)
+            }
+            KeyEvent::Pressed(Key::B) => {
+                bubble.push_back(Command::CloseView);
+                Ok(true)
+            }
+            _ => {
+                if self.confirm_delete && matches!(event, KeyEvent::Pressed(_)) {
+                    self.load()?;
+                }
+                self.list.handle_key_event(event, commands, bubble).await
+            }
+        }
+    }
+
+    fn children(&self) -> Vec<&dyn View> {
+        vec![
+            &self.list,
+            &self.image,
+            &self.button_hints,
+            &self.backup_hints,
+        ]
+    }
+
+    fn children_mut(&mut self) -> Vec<&mut dyn View> {
+        vec![
+            &mut self.list,
+            &mut self.image,
+            &mut self.button_hints,
+            &mut self.backup_hints,
+        ]
+    }
+
+    fn bounding_box(&mut self, _styles: &Stylesheet) -> Rect {
+        self.rect
+    }
+
+    fn set_position(&mut self, _point: Point) {
+        unimplemented!()
+    }
+}
@@ -0,0 +1,115 @@
+use std::collections::VecDeque;
+
+use anyhow::Result;
+use async_trait::async_trait;
+
+use common::command::Command;
+use common::display::font::FontTextStyleBuilder;
+use common::geom::{Point, Rect};
+use common::locale::Locale;
+use common::platform::{DefaultPlatform, Key, KeyEvent, Platform};
+use common::resources::Resources;
+use common::stylesheet::Stylesheet;
+use common::view::View;
+use embedded_graphics::prelude::{Dimensions, Size};
+use embedded_graphics::primitives::{
+    CornerRadii, Primitive, PrimitiveStyle, Rectangle, RoundedRectangle,
+};
+use embedded_graphics::text::{Alignment, Text};
+use embedded_graphics::Drawable;
+use tokio::sync::mpsc::Sender;
+
+/// Lists the advanced search syntax. Closed with A, B or Start.
+#[derive(Debug, Clone)]
+pub struct SearchHelp {
+    rect: Rect,
+    text: String,
+    dirty: bool,
+}
+
+impl SearchHelp {
+    pub fn new(rect: Rect, res: Resources) -> Self {
+        Self {
+            rect,
+            text: res.get::<Locale>().t("search-help"),
+            dirty: true,
+        }
+    }
+}
+
+#[async_trait(?Send)]
+impl View for SearchHelp {
+    fn draw(
+        &mut self,
+        display: &mut <DefaultPlatform as Platform>::Display,
+        styles: &Stylesheet,
+    ) -> Result<bool> {
+        let text_style = FontTextStyleBuilder::new(styles.ui_font.font())
+            .font_fallback(styles.cjk_font.font())
+            .font_size(styles.ui_font.size * 3 / 4)
+            .background_color(styles.highlight_color)
+            .text_color(styles.foreground_color)
+            .build();
+
+        let text = Text::with_alignment(
+            &self.text,
+            Point::new(self.rect.x + 24, self.rect.y + 16).into(),
+            text_style,
+            Alignment::Left,
+        );
+
+        let rect = text.bounding_box();
+        let Size { width, height } = rect.size;
+        RoundedRectangle::new(
+            Rectangle::new(
+                Point::new(rect.top_left.x - 12, rect.top_left.y - 8).into(),
+                Size::new(width + 24, height + 16),
+            ),
+            CornerRadii::new(Size::new_equal(12)),
+        )
+        .into_styled(PrimitiveStyle::with_fill(styles.highlight_color))
+        .draw(display)?;
+
+        text.draw(display)?;
+
+        self.dirty = false;
+        Ok(true)
+    }
+
+    fn should_draw(&self) -> bool {
+        self.dirty
+    }
+
+    fn set_should_draw(&mut self) {
+        self.dirty = true;
+    }
+
+    async fn handle_key_event(
+        &mut self,
+        event: KeyEvent,
+        _commands: Sender<Command>,
+        bubble: &mut VecDeque<Command>,
+    ) -> Result<bool> {
+        if let KeyEvent::Pressed(Key::A | Key::B | Key::Start) = event {
+            bubble.push_back(Command::CloseView);
+        }
+        // Swallow all other keys while open
+        Ok(true)
+    }
+
+    fn children(&self) -> Vec<&dyn View> {
+        vec![]
+    }
+
+    fn children_mut(&mut self) -> Vec<&mut dyn View> {
+        vec![]
+    }
+
+    fn bounding_box(&mut self, _styles: &Stylesheet) -> Rect {
+        self.rect
+    }
+
+    fn set_position(&mut self, _point: Point) {
+        unimplemented!()
+    }
+}
@@ -0,0 +1,335 @@
+use std::collections::{HashMap, VecDeque};
+
+use anyhow::Result;
+use async_trait::async_trait;
+use chrono::{Duration, Utc};
+use common::battery_log::BatteryLog;
+use common::command::Command;
+use common::constants::SELECTION_MARGIN;
+use common::database::Database;
+use common::display::Display;
+use common::geom::{Alignment, Point, Rect};
+use common::locale::Locale;
+use common::platform::{DefaultPlatform, Key, KeyEvent, Platform};
+use common::resources::Resources;
+use common::stylesheet::{Stylesheet, StylesheetColor};
+use common::view::{ButtonHint, ButtonIcon, Label, Row, SettingsList, View};
+use embedded_graphics::prelude::Size;
+use embedded_graphics::primitives::{Primitive, PrimitiveStyle, Rectangle};
+use embedded_graphics::Drawable;
+use log::warn;
+use tokio::sync::mpsc::Sender;
+
+use crate::view::settings::{ChildState, SettingsChild};
+
+/// How far back the discharge graph goes.
+const GRAPH_HOURS: i64 = 24;
+
+/// Number of bars in the discharge graph, one per half hour.
+const GRAPH_BARS: usize = 48;
+
+const GRAPH_HEIGHT: u32 = 120;
+
+/// Shows the battery level from the battery log, the estimated time remaining, a graph of the
+/// level over the last day, and the games that drain the battery the fastest.
+pub struct Battery {
+    rect: Rect,
+    graph: BatteryGraph,
+    list: SettingsList,
+    button_hints: Row<ButtonHint<String>>,
+}
+
+impl Battery {
+    pub fn new(rect: Rect, res: Resources, state: Option<ChildState>) -> Self {
+        let Rect { x, y, w, h } = rect;
+
+        let log = BatteryLog::load(&res.get::<Database>()).unwrap_or_else(|e| {
+            warn!("failed to load battery log: {}", e);
+            BatteryLog::default()
+        });
+
+        let locale = res.get::<Locale>();
+        let styles = res.get::<Stylesheet>();
+
+        let now = Utc::now();
+        let graph = BatteryGraph::new(
+            Rect::new(x + 12, y + 8, w - 24, GRAPH_HEIGHT),
+            locale.t("settings-battery-history"),
+            log.history(now - Duration::hours(GRAPH_HOURS), now, GRAPH_BARS),
+        );
+
+        let (level, remaining) = match log.latest() {
+            Some(latest) if latest.charging => (
+                format!("{}%", latest.percentage),
+                locale.t("settings-battery-charging"),
+            ),
+            Some(latest) => (
+                format!("{}%", latest.percentage),
+                log.estimate_remaining(latest.percentage, latest.core.as_deref())
+                    .map_or_else(
+                        || locale.t("settings-battery-unknown"),
+                        |remaining| format_remaining(&locale, remaining),
+                    ),
+            ),
+            None => (
+                locale.t("settings-battery-unknown"),
+                locale.t("settings-battery-unknown"),
+            ),
+        };
+
+        let mut left = vec![
+            locale.t("settings-battery-level"),
+            locale.t("settings-battery-remaining"),
+        ];
+        let mut right = vec![level, remaining];
+
+        let ranking = log.game_ranking();
+        if !ranking.is_empty() {
+            left.push(locale.t("settings-battery-game-usage"));
+            right.push(String::new());
+        }
+        for game in ranking {
+            left.push(
+                game.path
+                    .file_stem()
+                    .map(|name| name.to_string_lossy().to_string())
+                    .unwrap_or_default(),
+            );
+            let mut map = HashMap::new();
+            map.insert(
+                "rate".to_string(),
+                format!("{:.1}", game.rate.percent_per_hour()).into(),
+            );
+            right.push(locale.ta("settings-battery-rate", &map));
+        }
+
+        let list_y = y + 8 + GRAPH_HEIGHT as i32 + 8;
+        let mut list = SettingsList::new(
+            Rect::new(
+                x + 12,
+                list_y,
+                w - 24,
+                (h as i32 - (list_y - y) - ButtonIcon::diameter(&styles) as i32 - 8) as u32,
+            ),
+            left,
+            right
+                .into_iter()
+                .map(|value| {
+                    Box::new(Label::new(Point::zero(), value, Alignment::Right, None))
+                        as Box<dyn View>
+                })
+                .collect(),
+            styles.ui_font.size + SELECTION_MARGIN,
+        );
+        if let Some(state) = state {
+            list.select(state.selected);
+        }
+
+        let button_hints = Row::new(
+            Point::new(
+                rect.x + rect.w as i32 - 12,
+                rect.y + rect.h as i32 - ButtonIcon::diameter(&styles) as i32 - 8,
+            ),
+            vec![ButtonHint::new(
+                res.clone(),
+                Point::zero(),
+                Key::B,
+                locale.t("button-back"),
+                Alignment::Right,
+            )],
+            Alignment::Right,
+            12,
+        );
+
+        drop(locale);
+        drop(styles);
+
+        Self {
+            rect,
+            graph,
+            list,
+            button_hints,
+        }
+    }
+}
+
+/// Formats the time remaining, e.g. "~3h 20m".
+fn format_remaining(locale: &Locale, remaining: Duration) -> String {
+    let mut map = HashMap::new();
+    map.insert("hours".to_string(), remaining.num_hours().into());
+    map.insert("minutes".to_string(), (remaining.num_minutes() % 60).into());
+    locale.ta("settings-battery-remaining-value", &map)
+}
+
+#[async_trait(?Send)]
+impl View for Battery {
+    fn draw(
+        &mut self,
+        display: &mut <DefaultPlatform as Platform>::Display,
+        styles: &Stylesheet,
+    ) -> Result<bool> {
+        let mut drawn = false;
+
+        drawn |= self.graph.should_draw() && self.graph.draw(display, styles)?;
+        drawn |= self.list.should_draw() && self.list.draw(display, styles)?;
+        drawn |= self.button_hints.should_draw() && self.button_hints.draw(display, styles)?;
+
+        Ok(drawn)
+    }
+
+    fn should_draw(&self) -> bool {
+        self.graph.should_draw() || self.list.should_draw() || self.button_hints.should_draw()
+    }
+
+    fn set_should_draw(&mut self) {
+        self.graph.set_should_draw();
+        self.list.set_should_draw();
+        self.button_hints.set_should_draw();
+    }
+
+    async fn handle_key_event(
+        &mut self,
+        event: KeyEvent,
+        commands: Sender<Command>,
+        bubble: &mut VecDeque<Command>,
+    ) -> Result<bool> {
+        match event {
+            KeyEvent::Pressed(Key::B) => {
+                bubble.push_back(Command::CloseView);
+                Ok(true)
+            }
+            _ => self.list.handle_key_event(event, commands, bubble).await,
+        }
+    }
+
+    fn children(&self) -> Vec<&dyn View> {
+        vec![&self.graph, &self.list, &self.button_hints]
+    }
+
+    fn children_mut(&mut self) -> Vec<&mut dyn View> {
+        vec![&mut self.graph, &mut self.list, &mut self.button_hints]
+    }
+
+    fn bounding_box(&mut self, _styles: &Stylesheet) -> Rect {
+        self.rect
+    }
+
+    fn set_position(&mut self, _point: Point) {
+        unimplemented!()
+    }
+}
+
+impl SettingsChild for Battery {
+    fn save(&self) -> ChildState {
+        ChildState {
+            selected: self.list.selected(),
+        }
+    }
+}
+
+/// A bar graph of the battery level over time, with gaps where there are no readings.
+#[derive(Debug)]
+struct BatteryGraph {
+    rect: Rect,
+    title: String,
+    history: Vec<Option<i32>>,
+    dirty: bool,
+}
+
+impl BatteryGraph {
+    fn new(rect: Rect, title: String, history: Vec<Option<i32>>) -> Self {
+        Self {
+            rect,
+            title,
+            history,
+            dirty: true,
+        }
+    }
+}
+
+#[async_trait(?Send)]
+impl View for BatteryGraph {
+    fn draw(
+        &mut self,
+        display: &mut <DefaultPlatform as Platform>::Display,
+        styles: &Stylesheet,
+    ) -> Result<bool> {
+        if !self.dirty {
+            return Ok(false);
+        }
+
+        let Rect { x, y, w, h } = self.rect;
+        display.load(self.rect)?;
+
+        let mut title = Label::new(
+            Point::new(x, y),
+            self.title.clone(),
+            Alignment::Left,
+            Some(w),
+        );
+        title
+            .font_size(styles.status_bar_font_size)
+            .color(StylesheetColor::Disabled);
+        title.draw(display, styles)?;
+
+        let font_size = styles.status_bar_font_size() as u32;
+        let chart_h = h.saturating_sub(font_size + 8);
+        let bottom = y + h as i32;
+        let bar_w = w / self.history.len().max(1) as u32;
+
+        for (i, &percentage) in self.history.iter().enumerate() {
+            let Some(percentage) = percentage else {
+                continue;
+            };
+            let bar_h = chart_h * percentage.clamp(0, 100) as u32 / 100;
+            if bar_h > 0 {
+                Rectangle::new(
+                    Point::new(x + (i as u32 * bar_w) as i32 + 1, bottom - bar_h as i32).into(),
+                    Size::new(bar_w.saturating_sub(2).max(1), bar_h),
+                )
+                .into_styled(PrimitiveStyle::with_fill(styles.highlight_color))
+                .draw(display)?;
+            }
+        }
+
+        Rectangle::new(Point::new(x, bottom).into(), Size::new(w, 1))
+            .into_styled(PrimitiveStyle::with_fill(styles.disabled_color))
+            .draw(display)?;
+
+        self.dirty = false;
+        Ok(true)
+    }
+
+    fn should_draw(&self) -> bool {
+        self.dirty
+    }
+
+    fn set_should_draw(&mut self) {
+        self.dirty = true;
+    }
+
+    async fn handle_key_event(
+        &mut self,
+        _event: KeyEvent,
+        _commands: Sender<Command>,
+        _bubble: &mut VecDeque<Command>,
+    ) -> Result<bool> {
+        Ok(false)
+    }
+
+    fn children(&self) -> Vec<&dyn View> {
+        vec![]
+    }
+
+    fn children_mut(&mut self) -> Vec<&mut dyn View> {
+        vec![]
+    }
+
+    fn bounding_box(&mut self, _styles: &Stylesheet) -> Rect {
+        self.rect
+    }
+
+    fn set_position(&mut self, _point: Point) {
+        unimplemented!()
+    }
+}
@@ -1,5 +1,6 @@
 use std::collections::VecDeque;
 use std::env;
+use std::time::Duration;
 
 use anyhow::Result;
 use async_trait::async_trait;
@@ -11,9 +12,10 @@ use common::display::Display as DisplayTrait;
 use common::geom::{Alignment, Point, Rect};
 use common::locale::Locale;
 use common::platform::{DefaultPlatform, Key, KeyEvent, Platform};
+use common::power::PowerSettings;
 use common::resources::Resources;
 use common::stylesheet::Stylesheet;
-use common::view::{ButtonHint, ButtonIcon, DateTime, Row, Select, SettingsList, View};
+use common::view::{ButtonHint, ButtonIcon, DateTime, Row, Select, SettingsList, TextBox, View};
 
 use tokio::fs::File;
 use tokio::io::AsyncWriteExt;
@@ -21,8 +23,13 @@ use tokio::sync::mpsc::Sender;
 
 use crate::view::settings::{ChildState, SettingsChild};
 
+/// Clock settings. As changing the time gets around the sleep schedule, the schedule PIN must be
+/// entered first if one is set.
 pub struct Clock {
     rect: Rect,
+    res: Resources,
+    power_settings: PowerSettings,
+    unlocked: bool,
     timezone: usize,
     list: SettingsList,
     button_hints: Row<ButtonHint<String>>,
@@ -121,38 +128,22 @@ impl Clock {
         let timezone = env::var("TZ")
             .map(|tz| TIMEZONE_VALUES.iter().position(|&s| s == tz).unwrap_or(0))
             .unwrap_or(0);
+        let power_settings = PowerSettings::load().unwrap_or_default();
+        let unlocked = power_settings.schedule.pin_hash.is_none();
         let locale = res.get::<Locale>();
         let styles = res.get::<Stylesheet>();
 
-        let mut list = SettingsList::new(
+        let list = SettingsList::new(
             Rect::new(
                 x + 12,
                 y + 8,
                 w - 24,
                 h - 8 - ButtonIcon::diameter(&styles) - 8,
             ),
-            vec![
-                locale.t("settings-clock-datetime"),
-                locale.t("settings-clock-timezone"),
-            ],
-            vec![
-                Box::new(DateTime::new(
-                    Point::zero(),
-                    Local::now().naive_local(),
-                    Alignment::Right,
-                )),
-                Box::new(Select::new(
-                    Point::zero(),
-                    timezone,
-                    TIMEZONE_NAMES.iter().map(|s| s.to_string()).collect(),
-                    Alignment::Right,
-                )),
-            ],
+            Vec::new(),
+            Vec::new(),
             styles.ui_font.size + SELECTION_MARGIN,
         );
-        if let Some(state) = state {
-            list.select(state.selected);
-        }
 
         let button_hints = Row::new(
             Point::new(
@@ -170,12 +161,76 @@ impl Clock {
             12,
         );
 
-        Self {
+        drop(styles);
+        drop(locale);
+
+        let mut this = Self {
             rect,
+            res,
+            power_settings,
+            unlocked,
             timezone,
             list,
             button_hints,
+        };
+        this.set_items();
+        if let Some(state) = state.filter(|_| this.unlocked) {
+            this.list.select(state.selected);
         }
+        this
+    }
+
+    fn set_items(&mut self) {
+        let locale = self.res.get::<Locale>();
+
+        if !self.unlocked {
+            self.list.set_items(
+                vec![locale.t("settings-schedule-enter-pin")],
+                vec![Box::new(TextBox::new(
+                    Point::zero(),
+                    self.res.clone(),
+                    String::new(),
+                    Alignment::Right,
+                    true,
+                ))],
+            );
+            return;
+        }
+
+        self.list.set_items(
+            vec![
+                locale.t("settings-clock-datetime"),
+                locale.t("settings-clock-timezone"),
+            ],
+            vec![
+                Box::new(DateTime::new(
+                    Point::zero(),
+                    Local::now().naive_local(),
+                    Alignment::Right,
+                )),
+                Box::new(Select::new(
+                    Point::zero(),
+                    self.timezone,
+                    TIMEZONE_NAMES.iter().map(|s| s.to_string()).collect(),
+                    Alignment::Right,
+                )),
+            ],
+        );
+    }
+
+    async fn unlock(&mut self, pin: &str, commands: Sender<Command>) -> Result<()> {
+        if self.power_settings.schedule.check_pin(pin) {
+            self.unlocked = true;
+            self.set_items();
+            self.list.select(0);
+        } else {
+            let text = self.res.get::<Locale>().t("settings-schedule-wrong-pin");
+            commands
+                .send(Command::Toast(text, Some(Duration::from_secs(2))))
+                .await?;
+            self.set_items();
+        }
+        Ok(())
     }
 }
 
@@ -225,6 +280,12 @@ impl View for Clock {
         {
             while let Some(command) = bubble.pop_front() {
                 if let Command::ValueChanged(i, val) = command {
+                    if !self.unlocked {
+                        self.unlock(&val.as_string().unwrap(), commands.clone())
+                            .await?;
+                        continue;
+                    }
+
                     match i {
                         0 => {
                             let datetime = val.as_datetime().unwrap();
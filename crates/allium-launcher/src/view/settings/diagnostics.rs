@@ -0,0 +1,271 @@
+use std::collections::VecDeque;
+use std::time::Duration;
+
+use anyhow::Result;
+use async_trait::async_trait;
+use common::command::Command;
+use common::constants::{
+    ALLIUM_CONFIG_CONSOLES, ALLIUM_CONFIG_CORES, ALLIUM_GAMES_DIR, ALLIUM_RETROARCH_CORES_DIR,
+    SELECTION_MARGIN,
+};
+use common::database::Database;
+use common::geom::{Alignment, Point, Rect};
+use common::locale::Locale;
+use common::platform::{DefaultPlatform, Key, KeyEvent, Platform};
+use common::resources::Resources;
+use common::stylesheet::Stylesheet;
+use common::view::{ButtonHint, ButtonIcon, Label, Row, SettingsList, View};
+use tokio::sync::mpsc::Sender;
+
+use crate::diagnostics::{Diagnostics as Report, Issue};
+use crate::view::settings::{ChildState, SettingsChild};
+
+/// Maximum characters per line of the details toast.
+const DETAILS_LINE_WIDTH: usize = 36;
+
+/// Lists problems with the console mapper config, followed by the console and core that each ROM
+/// folder resolves to. Pressing A shows the details of the selected row.
+pub struct Diagnostics {
+    rect: Rect,
+    list: SettingsList,
+    /// Details of each row, shown when it is selected.
+    details: Vec<String>,
+    button_hints: Row<ButtonHint<String>>,
+}
+
+impl Diagnostics {
+    pub fn new(rect: Rect, res: Resources, state: Option<ChildState>) -> Self {
+        let Rect { x, y, w, h } = rect;
+
+        let report = Report::run(
+            &ALLIUM_CONFIG_CONSOLES,
+            &ALLIUM_CONFIG_CORES,
+            &ALLIUM_RETROARCH_CORES_DIR,
+            &ALLIUM_GAMES_DIR,
+            Some(&res.get::<Database>()),
+        );
+
+        let locale = res.get::<Locale>();
+        let styles = res.get::<Stylesheet>();
+
+        let mut left = Vec::new();
+        let mut right = Vec::new();
+        let mut details = Vec::new();
+
+        if report.issues.is_empty() {
+            left.push(locale.t("settings-diagnostics-no-issues"));
+            right.push(String::new());
+            details.push(String::new());
+        }
+        for issue in &report.issues {
+            let (key, value) = match issue {
+                Issue::InvalidConfig { path, .. } => (
+                    "settings-diagnostics-invalid-config",
+                    path.file_name()
+                        .map(|name| name.to_string_lossy().to_string())
+                        .unwrap_or_default(),
+                ),
+                Issue::UnknownCore { core, .. } => {
+                    ("settings-diagnostics-unknown-core", core.clone())
+                }
+                Issue::MissingRetroArchCore { core, .. } => {
+                    ("settings-diagnostics-missing-core", core.clone())
+                }
+                Issue::MissingLaunchScript { core, .. } => {
+                    ("settings-diagnostics-missing-script", core.clone())
+                }
+                Issue::OverlappingPattern { pattern, .. } => {
+                    ("settings-diagnostics-overlapping-pattern", pattern.clone())
+                }
+                Issue::OverlappingExtension { extension, .. } => (
+                    "settings-diagnostics-overlapping-extension",
+                    format!(".{}", extension),
+                ),
+                Issue::OverlappingFileName { file_name, .. } => (
+                    "settings-diagnostics-overlapping-file-name",
+                    file_name.clone(),
+                ),
+            };
+            left.push(locale.t(key));
+            right.push(value);
+            details.push(issue.to_string());
+        }
+
+        for folder in &report.folders {
+            left.push(folder.name());
+            right.push(match folder.resolutions.first() {
+                None => locale.t("settings-diagnostics-no-games"),
+                Some(resolution) => {
+                    let mut text = match (&resolution.console, &resolution.core) {
+                        (Some(console), Some(core)) => format!("{} ({})", console, core),
+                        (Some(console), None) => console.clone(),
+                        (None, _) => locale.t("settings-diagnostics-no-console"),
+                    };
+                    if folder.resolutions.len() > 1 {
+                        text.push_str(&format!(" +{}", folder.resolutions.len() - 1));
+                    }
+                    text
+                }
+            });
+            details.push(
+                folder
+                    .resolutions
+                    .iter()
+                    .map(ToString::to_string)
+                    .collect::<Vec<_>>()
+                    .join("\n"),
+            );
+        }
+
+        let mut list = SettingsList::new(
+            Rect::new(
+                x + 12,
+                y + 8,
+                w - 24,
+                h - 8 - ButtonIcon::diameter(&styles) - 8,
+            ),
+            left,
+            right
+                .into_iter()
+                .map(|text| {
+                    Box::new(Label::new(Point::zero(), text, Alignment::Right, None))
+                        as Box<dyn View>
+                })
+                .collect(),
+            styles.ui_font.size + SELECTION_MARGIN,
+        );
+        if let Some(state) = state {
+            list.select(state.selected);
+        }
+
+        let button_hints = Row::new(
+            Point::new(
+                rect.x + rect.w as i32 - 12,
+                rect.y + rect.h as i32 - ButtonIcon::diameter(&styles) as i32 - 8,
+            ),
+            vec![
+                ButtonHint::new(
+                    res.clone(),
+                    Point::zero(),
+                    Key::A,
+                    locale.t("settings-diagnostics-details"),
+                    Alignment::Right,
+                ),
+                ButtonHint::new(
+                    res.clone(),
+                    Point::zero(),
+                    Key::B,
+                    locale.t("button-back"),
+                    Alignment::Right,
+                ),
+            ],
+            Alignment::Right,
+            12,
+        );
+
+        Self {
+            rect,
+            list,
+            details,
+            button_hints,
+        }
+    }
+}
+
+/// Wraps text at word boundaries so that it fits in a toast.
+fn wrap(text: &str) -> String {
+    let mut lines = Vec::new();
+    for paragraph in text.lines() {
+        let mut line = String::new();
+        for word in paragraph.split(' ') {
+            if !line.is_empty() && line.len() + 1 + word.len() > DETAILS_LINE_WIDTH {
+                lines.push(std::mem::take(&mut line));
+            }
+            if !line.is_empty() {
+                line.push(' ');
+            }
+            line.push_str(word);
+        }
+        lines.push(line);
+    }
+    lines.join("\n")
+}
+
+#[async_trait(?Send)]
+impl View for Diagnostics {
+    fn draw(
+        &mut self,
+        display: &mut <DefaultPlatform as Platform>::Display,
+        styles: &Stylesheet,
+    ) -> Result<bool> {
+        let mut drawn = false;
+
+        if self.list.should_draw() && self.list.draw(display, styles)? {
+            drawn = true;
+        }
+
+        if self.button_hints.should_draw() && self.button_hints.draw(display, styles)? {
+            drawn = true;
+        }
+
+        Ok(drawn)
+    }
+
+    fn should_draw(&self) -> bool {
+        self.list.should_draw() || self.button_hints.should_draw()
+    }
+
+    fn set_should_draw(&mut self) {
+        self.list.set_should_draw();
+        self.button_hints.set_should_draw();
+    }
+
+    async fn handle_key_event(
+        &mut self,
+        event: KeyEvent,
+        commands: Sender<Command>,
+        bubble: &mut VecDeque<Command>,
+    ) -> Result<bool> {
+        match event {
+            KeyEvent::Pressed(Key::A) => {
+                if let Some(details) = self.details.get(self.list.selected()) {
+                    if !details.is_empty() {
+                        commands
+                            .send(Command::Toast(wrap(details), Some(Duration::from_secs(5))))
+                            .await?;
+                    }
+                }
+                Ok(true)
+            }
+            KeyEvent::Pressed(Key::B) => {
+                bubble.push_back(Command::CloseView);
+                Ok(true)
+            }
+            _ => self.list.handle_key_event(event, commands, bubble).await,
+        }
+    }
+
+    fn children(&self) -> Vec<&dyn View> {
+        vec![&self.list, &self.button_hints]
+    }
+
+    fn children_mut(&mut self) -> Vec<&mut dyn View> {
+        vec![&mut self.list, &mut self.button_hints]
+    }
+
+    fn bounding_box(&mut self, _styles: &Stylesheet) -> Rect {
+        self.rect
+    }
+
+    fn set_position(&mut self, _point: Point) {
+        unimplemented!()
+    }
+}
+
+impl SettingsChild for Diagnostics {
+    fn save(&self) -> ChildState {
+        ChildState {
+            selected: self.list.selected(),
+        }
+    }
+}
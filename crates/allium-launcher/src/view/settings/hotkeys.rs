@@ -0,0 +1,212 @@
+use std::collections::VecDeque;
+
+use anyhow::Result;
+use async_trait::async_trait;
+use common::command::Command;
+use common::constants::SELECTION_MARGIN;
+use common::geom::{Alignment, Point, Rect};
+use common::hotkeys::{button_name, Hotkey, HotkeySettings, HOTKEY_BUTTONS};
+use common::locale::Locale;
+use common::platform::{DefaultPlatform, Key, KeyEvent, Platform};
+use common::resources::Resources;
+use common::stylesheet::Stylesheet;
+use common::view::{ButtonHint, ButtonIcon, Row, Select, SettingsList, View};
+use tokio::sync::mpsc::Sender;
+
+use crate::view::settings::{ChildState, SettingsChild};
+
+pub struct Hotkeys {
+    rect: Rect,
+    settings: HotkeySettings,
+    list: SettingsList,
+    button_hints: Row<ButtonHint<String>>,
+}
+
+impl Hotkeys {
+    pub fn new(rect: Rect, res: Resources, state: Option<ChildState>) -> Self {
+        let Rect { x, y, w, h } = rect;
+
+        let settings = HotkeySettings::load().unwrap();
+
+        let locale = res.get::<Locale>();
+        let styles = res.get::<Stylesheet>();
+
+        let mut list = SettingsList::new(
+            Rect::new(
+                x + 12,
+                y + 8,
+                w - 24,
+                h - 8 - ButtonIcon::diameter(&styles) - 8,
+            ),
+            settings
+                .hotkeys
+                .iter()
+                .map(|hotkey| hotkey.action.label(&locale))
+                .collect(),
+            settings
+                .hotkeys
+                .iter()
+                .map(|hotkey| {
+                    let (value, values) = chord_values(&locale, hotkey);
+                    Box::new(Select::new(Point::zero(), value, values, Alignment::Right))
+                        as Box<dyn View>
+                })
+                .collect(),
+            styles.ui_font.size + SELECTION_MARGIN,
+        );
+        if let Some(state) = state {
+            list.select(state.selected);
+        }
+
+        let button_hints = Row::new(
+            Point::new(
+                rect.x + rect.w as i32 - 12,
+                rect.y + rect.h as i32 - ButtonIcon::diameter(&styles) as i32 - 8,
+            ),
+            vec![
+                ButtonHint::new(
+                    res.clone(),
+                    Point::zero(),
+                    Key::A,
+                    locale.t("button-edit"),
+                    Alignment::Right,
+                ),
+                ButtonHint::new(
+                    res.clone(),
+                    Point::zero(),
+                    Key::B,
+                    locale.t("button-back"),
+                    Alignment::Right,
+                ),
+            ],
+            Alignment::Right,
+            12,
+        );
+
+        drop(locale);
+        drop(styles);
+
+        Self {
+            rect,
+            settings,
+            list,
+            button_hints,
+        }
+    }
+}
+
+/// Choices for the buttons of a hotkey: none, Menu with one other button, or the hotkey's own
+/// chord if it has more buttons than can be chosen here. Returns the index of the current choice.
+fn chord_values(locale: &Locale, hotkey: &Hotkey) -> (usize, Vec<String>) {
+    let mut values = Vec::with_capacity(HOTKEY_BUTTONS.len() + 2);
+    values.push(locale.t("settings-hotkeys-none"));
+    values.extend(
+        HOTKEY_BUTTONS
+            .iter()
+            .map(|&key| format!("{} + {}", button_name(Key::Menu), button_name(key))),
+    );
+
+    let value = match hotkey.buttons.as_slice() {
+        [] => 0,
+        [key] => HOTKEY_BUTTONS
+            .iter()
+            .position(|k| k == key)
+            .map_or(0, |i| i + 1),
+        _ => {
+            values.push(
+                hotkey
+                    .chord()
+                    .into_iter()
+                    .map(button_name)
+                    .collect::<Vec<_>>()
+                    .join(" + "),
+            );
+            values.len() - 1
+        }
+    };
+    (value, values)
+}
+
+#[async_trait(?Send)]
+impl View for Hotkeys {
+    fn draw(
+        &mut self,
+        display: &mut <DefaultPlatform as Platform>::Display,
+        styles: &Stylesheet,
+    ) -> Result<bool> {
+        let mut drawn = false;
+
+        drawn |= self.button_hints.should_draw() && self.button_hints.draw(display, styles)?;
+        drawn |= self.list.should_draw() && self.list.draw(display, styles)?;
+
+        Ok(drawn)
+    }
+
+    fn should_draw(&self) -> bool {
+        self.list.should_draw() || self.button_hints.should_draw()
+    }
+
+    fn set_should_draw(&mut self) {
+        self.list.set_should_draw();
+        self.button_hints.set_should_draw();
+    }
+
+    async fn handle_key_event(
+        &mut self,
+        event: KeyEvent,
+        commands: Sender<Command>,
+        bubble: &mut VecDeque<Command>,
+    ) -> Result<bool> {
+        if self
+            .list
+            .handle_key_event(event, commands.clone(), bubble)
+            .await?
+        {
+            while let Some(command) = bubble.pop_front() {
+                if let Command::ValueChanged(i, val) = command {
+                    let hotkey = &mut self.settings.hotkeys[i];
+                    match val.as_int().unwrap() as usize {
+                        0 => hotkey.buttons.clear(),
+                        // The hotkey's own chord, which is left as it is
+                        value if value > HOTKEY_BUTTONS.len() => {}
+                        value => hotkey.buttons = vec![HOTKEY_BUTTONS[value - 1]],
+                    }
+                    self.settings.save()?;
+                }
+            }
+            return Ok(true);
+        }
+
+        match event {
+            KeyEvent::Pressed(Key::B) => {
+                bubble.push_back(Command::CloseView);
+                Ok(true)
+            }
+            _ => Ok(false),
+        }
+    }
+
+    fn children(&self) -> Vec<&dyn View> {
+        vec![&self.list, &self.button_hints]
+    }
+
+    fn children_mut(&mut self) -> Vec<&mut dyn View> {
+        vec![&mut self.list, &mut self.button_hints]
+    }
+
+    fn bounding_box(&mut self, _styles: &Stylesheet) -> Rect {
+        self.rect
+    }
+
+    fn set_position(&mut self, _point: Point) {
+        unimplemented!()
+    }
+}
+
+impl SettingsChild for Hotkeys {
+    fn save(&self) -> ChildState {
+        ChildState {
+            selected: self.list.selected(),
+        }
+    }
+}
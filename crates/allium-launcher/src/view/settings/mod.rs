@@ -1,17 +1,33 @@
 mod about;
+mod battery;
 mod clock;
+mod diagnostics;
 mod display;
+mod hotkeys;
 mod language;
+mod parental;
 mod power;
+mod profiles;
+mod retroachievements;
+mod save_states;
+mod schedule;
 mod theme;
 mod wifi;
 
 use crate::view::settings::clock::Clock;
 
 use self::about::About;
+use self::battery::Battery;
+use self::diagnostics::Diagnostics;
 use self::display::Display;
+use self::hotkeys::Hotkeys;
 use self::language::Language;
+use self::parental::Parental;
 use self::power::Power;
+use self::profiles::Profiles;
+use self::retroachievements::RetroAchievements;
+use self::save_states::SaveStates;
+use self::schedule::Schedule;
 use self::theme::Theme;
 use self::wifi::Wifi;
 
@@ -72,15 +88,23 @@ impl Settings {
         let styles = res.get::<Stylesheet>();
 
         let has_wifi = DefaultPlatform::has_wifi();
-        let mut labels = Vec::with_capacity(7);
+        let mut labels = Vec::with_capacity(15);
         if has_wifi {
             labels.push(locale.t("settings-wifi"));
+            labels.push(locale.t("settings-retroachievements"));
         }
         labels.push(locale.t("settings-clock"));
         labels.push(locale.t("settings-power"));
+        labels.push(locale.t("settings-schedule"));
+        labels.push(locale.t("settings-parental"));
+        labels.push(locale.t("settings-battery"));
         labels.push(locale.t("settings-display"));
         labels.push(locale.t("settings-theme"));
         labels.push(locale.t("settings-language"));
+        labels.push(locale.t("settings-profiles"));
+        labels.push(locale.t("settings-save-states"));
+        labels.push(locale.t("settings-hotkeys"));
+        labels.push(locale.t("settings-diagnostics"));
         labels.push(locale.t("settings-about"));
 
         let mut list = ScrollList::new(
@@ -94,16 +118,28 @@ impl Settings {
         let child: Option<Box<dyn SettingsChild>> = if let Some(child) = state.child {
             let mut selected = state.selected;
             if !has_wifi {
-                selected += 1;
+                selected += 2;
             };
             match selected {
                 0 => Some(Box::new(Wifi::new(rect, res.clone(), Some(child)))),
-                1 => Some(Box::new(Clock::new(rect, res.clone(), Some(child)))),
-                2 => Some(Box::new(Power::new(rect, res.clone(), Some(child)))),
-                3 => Some(Box::new(Display::new(rect, res.clone(), Some(child)))),
-                4 => Some(Box::new(Theme::new(rect, res.clone(), Some(child)))),
-                5 => Some(Box::new(Language::new(rect, res.clone(), Some(child)))),
-                6 => Some(Box::new(About::new(rect, res.clone(), Some(child)))),
+                1 => Some(Box::new(RetroAchievements::new(
+                    rect,
+                    res.clone(),
+                    Some(child),
+                ))),
+                2 => Some(Box::new(Clock::new(rect, res.clone(), Some(child)))),
+                3 => Some(Box::new(Power::new(rect, res.clone(), Some(child)))),
+                4 => Some(Box::new(Schedule::new(rect, res.clone(), Some(child)))),
+                5 => Some(Box::new(Parental::new(rect, res.clone(), Some(child)))),
+                6 => Some(Box::new(Battery::new(rect, res.clone(), Some(child)))),
+                7 => Some(Box::new(Display::new(rect, res.clone(), Some(child)))),
+                8 => Some(Box::new(Theme::new(rect, res.clone(), Some(child)))),
+                9 => Some(Box::new(Language::new(rect, res.clone(), Some(child)))),
+                10 => Some(Box::new(Profiles::new(rect, res.clone(), Some(child)))),
+                11 => Some(Box::new(SaveStates::new(rect, res.clone(), Some(child)))),
+                12 => Some(Box::new(Hotkeys::new(rect, res.clone(), Some(child)))),
+                13 => Some(Box::new(Diagnostics::new(rect, res.clone(), Some(child)))),
+                14 => Some(Box::new(About::new(rect, res.clone(), Some(child)))),
                 _ => None,
             }
         } else {
@@ -158,16 +194,36 @@ impl Settings {
     async fn select_entry(&mut self, _commands: Sender<Command>) -> Result<()> {
         let mut selected = self.list.selected();
         if !self.has_wifi {
-            selected += 1
+            selected += 2
         };
         match selected {
             0 => self.child = Some(Box::new(Wifi::new(self.rect, self.res.clone(), None))),
-            1 => self.child = Some(Box::new(Clock::new(self.rect, self.res.clone(), None))),
-            2 => self.child = Some(Box::new(Power::new(self.rect, self.res.clone(), None))),
-            3 => self.child = Some(Box::new(Display::new(self.rect, self.res.clone(), None))),
-            4 => self.child = Some(Box::new(Theme::new(self.rect, self.res.clone(), None))),
-            5 => self.child = Some(Box::new(Language::new(self.rect, self.res.clone(), None))),
-            6 => self.child = Some(Box::new(About::new(self.rect, self.res.clone(), None))),
+            1 => {
+                self.child = Some(Box::new(RetroAchievements::new(
+                    self.rect,
+                    self.res.clone(),
+                    None,
+                )))
+            }
+            2 => self.child = Some(Box::new(Clock::new(self.rect, self.res.clone(), None))),
+            3 => self.child = Some(Box::new(Power::new(self.rect, self.res.clone(), None))),
+            4 => self.child = Some(Box::new(Schedule::new(self.rect, self.res.clone(), None))),
+            5 => self.child = Some(Box::new(Parental::new(self.rect, self.res.clone(), None))),
+            6 => self.child = Some(Box::new(Battery::new(self.rect, self.res.clone(), None))),
+            7 => self.child = Some(Box::new(Display::new(self.rect, self.res.clone(), None))),
+            8 => self.child = Some(Box::new(Theme::new(self.rect, self.res.clone(), None))),
+            9 => self.child = Some(Box::new(Language::new(self.rect, self.res.clone(), None))),
+            10 => self.child = Some(Box::new(Profiles::new(self.rect, self.res.clone(), None))),
+            11 => self.child = Some(Box::new(SaveStates::new(self.rect, self.res.clone(), None))),
+            12 => self.child = Some(Box::new(Hotkeys::new(self.rect, self.res.clone(), None))),
+            13 => {
+                self.child = Some(Box::new(Diagnostics::new(
+                    self.rect,
+                    self.res.clone(),
+                    None,
+                )))
+            }
+            14 => self.child = Some(Box::new(About::new(self.rect, self.res.clone(), None))),
             _ => unreachable!("Invalid index"),
         }
         self.dirty = true;
@@ -0,0 +1,261 @@
+use std::collections::VecDeque;
+use std::time::Duration;
+
+use anyhow::Result;
+use async_trait::async_trait;
+use common::command::Command;
+use common::constants::SELECTION_MARGIN;
+use common::geom::{Alignment, Point, Rect};
+use common::locale::Locale;
+use common::parental::ParentalSettings;
+use common::platform::{DefaultPlatform, Key, KeyEvent, Platform};
+use common::power::PowerSettings;
+use common::resources::Resources;
+use common::stylesheet::Stylesheet;
+use common::view::{ButtonHint, ButtonIcon, Label, Row, SettingsList, Toggle, View};
+use tokio::sync::mpsc::Sender;
+
+use crate::consoles::ConsoleMapper;
+use crate::view::settings::{ChildState, SettingsChild};
+
+/// Index of the first console in the list.
+const FIRST_CONSOLE: usize = 2;
+
+/// Restricted mode, and the consoles, directories and games it hides. Directories and games are
+/// hidden from their menu, and can be shown again here. Restricted mode uses the sleep schedule
+/// PIN.
+pub struct Parental {
+    rect: Rect,
+    res: Resources,
+    settings: ParentalSettings,
+    consoles: Vec<String>,
+    list: SettingsList,
+    button_hints: Row<ButtonHint<String>>,
+}
+
+impl Parental {
+    pub fn new(rect: Rect, res: Resources, state: Option<ChildState>) -> Self {
+        let Rect { x, y, w, h } = rect;
+
+        let console_mapper = res.get::<ConsoleMapper>();
+        let settings = console_mapper.restrictions().clone();
+        let consoles: Vec<String> = console_mapper
+            .consoles()
+            .iter()
+            .map(|console| console.name.clone())
+            .collect();
+        drop(console_mapper);
+
+        let locale = res.get::<Locale>();
+        let styles = res.get::<Stylesheet>();
+
+        let mut left = vec![
+            locale.t("settings-parental-enabled"),
+            locale.t("settings-parental-hidden-consoles"),
+        ];
+        let mut right: Vec<Box<dyn View>> = vec![
+            Box::new(Toggle::new(
+                Point::zero(),
+                settings.enabled,
+                Alignment::Right,
+            )),
+            Box::new(Label::new(
+                Point::zero(),
+                String::new(),
+                Alignment::Right,
+                None,
+            )),
+        ];
+        for console in &consoles {
+            left.push(console.clone());
+            right.push(Box::new(Toggle::new(
+                Point::zero(),
+                settings.hidden_consoles.contains(console),
+                Alignment::Right,
+            )));
+        }
+        if !settings.hidden_paths.is_empty() {
+            left.push(locale.t("settings-parental-hidden-paths"));
+            right.push(Box::new(Label::new(
+                Point::zero(),
+                String::new(),
+                Alignment::Right,
+                None,
+            )));
+        }
+        for path in &settings.hidden_paths {
+            left.push(
+                path.file_name()
+                    .map(|name| name.to_string_lossy().to_string())
+                    .unwrap_or_default(),
+            );
+            right.push(Box::new(Toggle::new(Point::zero(), true, Alignment::Right)));
+        }
+
+        let mut list = SettingsList::new(
+            Rect::new(
+                x + 12,
+                y + 8,
+                w - 24,
+                h - 8 - ButtonIcon::diameter(&styles) - 8,
+            ),
+            left,
+            right,
+            styles.ui_font.size + SELECTION_MARGIN,
+        );
+        if let Some(state) = state {
+            list.select(state.selected);
+        }
+
+        let button_hints = Row::new(
+            Point::new(
+                rect.x + rect.w as i32 - 12,
+                rect.y + rect.h as i32 - ButtonIcon::diameter(&styles) as i32 - 8,
+            ),
+            vec![
+                ButtonHint::new(
+                    res.clone(),
+                    Point::zero(),
+                    Key::A,
+                    locale.t("button-edit"),
+                    Alignment::Right,
+                ),
+                ButtonHint::new(
+                    res.clone(),
+                    Point::zero(),
+                    Key::B,
+                    locale.t("button-back"),
+                    Alignment::Right,
+                ),
+            ],
+            Alignment::Right,
+            12,
+        );
+
+        drop(locale);
+        drop(styles);
+
+        Self {
+            rect,
+            res,
+            settings,
+            consoles,
+            list,
+            button_hints,
+        }
+    }
+}
+
+#[async_trait(?Send)]
+impl View for Parental {
+    fn draw(
+        &mut self,
+        display: &mut <DefaultPlatform as Platform>::Display,
+        styles: &Stylesheet,
+    ) -> Result<bool> {
+        let mut drawn = false;
+
+        drawn |= self.list.should_draw() && self.list.draw(display, styles)?;
+        drawn |= self.button_hints.should_draw() && self.button_hints.draw(display, styles)?;
+
+        Ok(drawn)
+    }
+
+    fn should_draw(&self) -> bool {
+        self.list.should_draw() || self.button_hints.should_draw()
+    }
+
+    fn set_should_draw(&mut self) {
+        self.list.set_should_draw();
+        self.button_hints.set_should_draw();
+    }
+
+    async fn handle_key_event(
+        &mut self,
+        event: KeyEvent,
+        commands: Sender<Command>,
+        bubble: &mut VecDeque<Command>,
+    ) -> Result<bool> {
+        if self
+            .list
+            .handle_key_event(event, commands.clone(), bubble)
+            .await?
+        {
+            while let Some(command) = bubble.pop_front() {
+                if let Command::ValueChanged(i, val) = command {
+                    let first_path = FIRST_CONSOLE + self.consoles.len() + 1;
+                    match i {
+                        0 => {
+                            let enabled = val.as_bool().unwrap();
+                            let schedule = PowerSettings::load()?.schedule;
+                            if enabled && schedule.pin_hash.is_none() {
+                                // Without a PIN, restricted mode could be turned off again
+                                self.list.set_right(
+                                    0,
+                                    Box::new(Toggle::new(Point::zero(), false, Alignment::Right)),
+                                );
+                                let text = self.res.get::<Locale>().t("parental-pin-required");
+                                commands
+                                    .send(Command::Toast(text, Some(Duration::from_secs(3))))
+                                    .await?;
+                                continue;
+                            }
+                            self.settings.enabled = enabled;
+                        }
+                        i if (FIRST_CONSOLE..first_path - 1).contains(&i) => {
+                            let console = &self.consoles[i - FIRST_CONSOLE];
+                            if val.as_bool().unwrap() {
+                                self.settings.hidden_consoles.push(console.clone());
+                            } else {
+                                self.settings.hidden_consoles.retain(|c| c != console);
+                            }
+                        }
+                        i if i >= first_path => {
+                            let path = self.settings.hidden_paths[i - first_path].clone();
+                            self.settings.toggle_hidden_path(&path);
+                        }
+                        _ => unreachable!("Invalid index"),
+                    }
+                    commands
+                        .send(Command::SaveParentalSettings(Box::new(
+                            self.settings.clone(),
+                        )))
+                        .await?;
+                }
+            }
+            return Ok(true);
+        }
+
+        match event {
+            KeyEvent::Pressed(Key::B) => {
+                bubble.push_back(Command::CloseView);
+                Ok(true)
+            }
+            _ => Ok(false),
+        }
+    }
+
+    fn children(&self) -> Vec<&dyn View> {
+        vec![&self.list, &self.button_hints]
+    }
+
+    fn children_mut(&mut self) -> Vec<&mut dyn View> {
+        vec![&mut self.list, &mut self.button_hints]
+    }
+
+    fn bounding_box(&mut self, _styles: &Stylesheet) -> Rect {
+        self.rect
+    }
+
+    fn set_position(&mut self, _point: Point) {
+        unimplemented!()
+    }
+}
+
+impl SettingsChild for Parental {
+    fn save(&self) -> ChildState {
+        ChildState {
+            selected: self.list.selected(),
+        }
+    }
+}
@@ -35,6 +35,8 @@ impl Power {
 
         let auto_sleep_duration_disabled_label =
             locale.t("settings-power-auto-sleep-duration-disabled");
+        let low_battery_warning_disabled_label =
+            locale.t("settings-power-low-battery-warning-disabled");
         let mut list = SettingsList::new(
             Rect::new(
                 x + 12,
@@ -46,6 +48,8 @@ impl Power {
                 locale.t("settings-power-power-button-action"),
                 locale.t("settings-power-auto-sleep-when-charging"),
                 locale.t("settings-power-auto-sleep-duration-minutes"),
+                locale.t("settings-power-low-battery-warning"),
+                locale.t("settings-power-critical-battery"),
             ],
             vec![
                 Box::new(Select::new(
@@ -77,6 +81,28 @@ impl Power {
                     },
                     Alignment::Right,
                 )),
+                Box::new(Number::new(
+                    Point::zero(),
+                    power_settings.low_battery_warning_percentage,
+                    0,
+                    50,
+                    move |x: &i32| {
+                        if *x == 0 {
+                            low_battery_warning_disabled_label.clone()
+                        } else {
+                            format!("{x}%")
+                        }
+                    },
+                    Alignment::Right,
+                )),
+                Box::new(Number::new(
+                    Point::zero(),
+                    power_settings.critical_battery_percentage,
+                    1,
+                    20,
+                    |x: &i32| format!("{x}%"),
+                    Alignment::Right,
+                )),
             ],
             styles.ui_font.size + SELECTION_MARGIN,
         );
@@ -165,6 +191,13 @@ impl View for Power {
                         2 => {
                             self.power_settings.auto_sleep_duration_minutes = val.as_int().unwrap()
                         }
+                        3 => {
+                            self.power_settings.low_battery_warning_percentage =
+                                val.as_int().unwrap()
+                        }
+                        4 => {
+                            self.power_settings.critical_battery_percentage = val.as_int().unwrap()
+                        }
                         _ => unreachable!("Invalid index"),
                     }
                     self.power_settings.save()?;
@@ -0,0 +1,330 @@
+use std::collections::VecDeque;
+use std::time::Duration;
+
+use anyhow::Result;
+use async_trait::async_trait;
+use common::command::{Command, Value};
+use common::constants::SELECTION_MARGIN;
+use common::geom::{Alignment, Point, Rect};
+use common::locale::Locale;
+use common::platform::{DefaultPlatform, Key, KeyEvent, Platform};
+use common::profile::Profiles as ProfileManager;
+use common::resources::Resources;
+use common::stylesheet::Stylesheet;
+use common::view::{ButtonHint, ButtonIcon, Keyboard, Label, Row, SettingsList, View};
+use log::error;
+use tokio::sync::mpsc::Sender;
+
+use crate::view::settings::{ChildState, SettingsChild};
+
+/// What the keyboard is open for.
+#[derive(Debug, Clone)]
+enum Edit {
+    Create,
+    Rename(String),
+}
+
+pub struct Profiles {
+    rect: Rect,
+    res: Resources,
+    profiles: ProfileManager,
+    names: Vec<String>,
+    list: SettingsList,
+    button_hints: Row<ButtonHint<String>>,
+    keyboard: Option<(Keyboard, Edit)>,
+    /// Profile that will be deleted if Y is pressed again.
+    confirm_delete: Option<String>,
+}
+
+impl Profiles {
+    pub fn new(rect: Rect, res: Resources, state: Option<ChildState>) -> Self {
+        let Rect { x, y, w, h } = rect;
+
+        let locale = res.get::<Locale>();
+        let styles = res.get::<Stylesheet>();
+
+        let list = SettingsList::new(
+            Rect::new(
+                x + 12,
+                y + 8,
+                w - 24,
+                h - 8 - ButtonIcon::diameter(&styles) - 8,
+            ),
+            Vec::new(),
+            Vec::new(),
+            styles.ui_font.size + SELECTION_MARGIN,
+        );
+
+        let button_hints = Row::new(
+            Point::new(
+                rect.x + rect.w as i32 - 12,
+                rect.y + rect.h as i32 - ButtonIcon::diameter(&styles) as i32 - 8,
+            ),
+            vec![
+                ButtonHint::new(
+                    res.clone(),
+                    Point::zero(),
+                    Key::A,
+                    locale.t("settings-profiles-switch"),
+                    Alignment::Right,
+                ),
+                ButtonHint::new(
+                    res.clone(),
+                    Point::zero(),
+                    Key::X,
+                    locale.t("settings-profiles-rename"),
+                    Alignment::Right,
+                ),
+                ButtonHint::new(
+                    res.clone(),
+                    Point::zero(),
+                    Key::Y,
+                    locale.t("settings-profiles-delete"),
+                    Alignment::Right,
+                ),
+                ButtonHint::new(
+                    res.clone(),
+                    Point::zero(),
+                    Key::B,
+                    locale.t("button-back"),
+                    Alignment::Right,
+                ),
+            ],
+            Alignment::Right,
+            12,
+        );
+
+        drop(locale);
+        drop(styles);
+
+        let mut this = Self {
+            rect,
+            res,
+            profiles: ProfileManager::default(),
+            names: Vec::new(),
+            list,
+            button_hints,
+            keyboard: None,
+            confirm_delete: None,
+        };
+        this.load();
+        if let Some(state) = state {
+            this.list.select(state.selected);
+        }
+        this
+    }
+
+    fn load(&mut self) {
+        self.names = self.profiles.list().unwrap_or_else(|e| {
+            error!("failed to list profiles: {}", e);
+            vec![self.profiles.current()]
+        });
+
+        let locale = self.res.get::<Locale>();
+        let mut left = self.names.clone();
+        left.push(locale.t("settings-profiles-new-profile"));
+        let right = (0..left.len())
+            .map(|i| {
+                let text = if i == 0 {
+                    locale.t("settings-profiles-active")
+                } else {
+                    String::new()
+                };
+                Box::new(Label::new(Point::zero(), text, Alignment::Right, None)) as Box<dyn View>
+            })
+            .collect();
+
+        let selected = self.list.selected().min(left.len() - 1);
+        self.list.set_items(left, right);
+        self.list.select(selected);
+        self.confirm_delete = None;
+    }
+
+    fn selected_profile(&self) -> Option<&String> {
+        self.names.get(self.list.selected())
+    }
+
+    async fn toast(&self, commands: &Sender<Command>, key: &str) -> Result<()> {
+        let text = self.res.get::<Locale>().t(key);
+        commands
+            .send(Command::Toast(text, Some(Duration::from_secs(2))))
+            .await?;
+        Ok(())
+    }
+
+    async fn submit(&mut self, commands: &Sender<Command>, edit: Edit, name: String) -> Result<()> {
+        let result = match edit {
+            Edit::Create => self.profiles.create(&name),
+            Edit::Rename(old) => self.profiles.rename(&old, &name),
+        };
+        if let Err(e) = result {
+            error!("failed to save profile: {}", e);
+            self.toast(commands, "settings-profiles-invalid-name")
+                .await?;
+        }
+        self.load();
+        Ok(())
+    }
+}
+
+#[async_trait(?Send)]
+impl View for Profiles {
+    fn draw(
+        &mut self,
+        display: &mut <DefaultPlatform as Platform>::Display,
+        styles: &Stylesheet,
+    ) -> Result<bool> {
+        let mut drawn = false;
+
+        drawn |= self.list.should_draw() && self.list.draw(display, styles)?;
+        drawn |= self.button_hints.should_draw() && self.button_hints.draw(display, styles)?;
+
+        if let Some((keyboard, _)) = self.keyboard.as_mut() {
+            if drawn {
+                keyboard.set_should_draw();
+            }
+            drawn |= keyboard.should_draw() && keyboard.draw(display, styles)?;
+        }
+
+        Ok(drawn)
+    }
+
+    fn should_draw(&self) -> bool {
+        self.list.should_draw()
+            || self.button_hints.should_draw()
+            || self
+                .keyboard
+                .as_ref()
+                .is_some_and(|(keyboard, _)| keyboard.should_draw())
+    }
+
+    fn set_should_draw(&mut self) {
+        self.list.set_should_draw();
+        self.button_hints.set_should_draw();
+        if let Some((keyboard, _)) = self.keyboard.as_mut() {
+            keyboard.set_should_draw();
+        }
+    }
+
+    async fn handle_key_event(
+        &mut self,
+        event: KeyEvent,
+        commands: Sender<Command>,
+        bubble: &mut VecDeque<Command>,
+    ) -> Result<bool> {
+        if let Some((keyboard, edit)) = self.keyboard.as_mut() {
+            if keyboard
+                .handle_key_event(event, commands.clone(), bubble)
+                .await?
+            {
+                let edit = edit.clone();
+                let mut name = None;
+                let mut closed = false;
+                bubble.retain_mut(|c| match c {
+                    Command::ValueChanged(_, Value::String(val)) => {
+                        name = Some(val.clone());
+                        false
+                    }
+                    Command::CloseView => {
+                        closed = true;
+                        false
+                    }
+                    _ => true,
+                });
+                if closed {
+                    self.keyboard = None;
+                    commands.send(Command::Redraw).await?;
+                }
+                if let Some(name) = name {
+                    self.submit(&commands, edit, name).await?;
+                }
+            }
+            return Ok(true);
+        }
+
+        match event {
+            KeyEvent::Pressed(Key::A) => {
+                match self.selected_profile().cloned() {
+                    None => {
+                        let keyboard = Keyboard::new(self.res.clone(), String::new(), false);
+                        self.keyboard = Some((keyboard, Edit::Create));
+                    }
+                    Some(name) if name == self.profiles.current() => {}
+                    Some(name) => {
+                        self.profiles.request_switch(&name)?;
+                        // alliumd switches the profile and restarts the launcher
+                        commands.send(Command::Exit).await?;
+                    }
+                }
+                Ok(true)
+            }
+            KeyEvent::Pressed(Key::X) => {
+                if let Some(name) = self.selected_profile().cloned() {
+                    let keyboard = Keyboard::new(self.res.clone(), name.clone(), false);
+                    self.keyboard = Some((keyboard, Edit::Rename(name)));
+                }
+                Ok(true)
+            }
+            KeyEvent::Pressed(Key::Y) => {
+                let Some(name) = self.selected_profile().cloned() else {
+                    return Ok(true);
+                };
+                if name == self.profiles.current() {
+                    self.toast(&commands, "settings-profiles-cannot-delete-active")
+                        .await?;
+                } else if self.confirm_delete.as_ref() == Some(&name) {
+                    if let Err(e) = self.profiles.delete(&name) {
+                        error!("failed to delete profile: {}", e);
+                    }
+                    self.load();
+                } else {
+                    let text = self
+                        .res
+                        .get::<Locale>()
+                        .t("settings-profiles-confirm-delete");
+                    let i = self.list.selected();
+                    self.list.set_right(
+                        i,
+                        Box::new(Label::new(Point::zero(), text, Alignment::Right, None)),
+                    );
+                    self.confirm_delete = Some(name);
+                }
+                Ok(true)
+            }
+            KeyEvent::Pressed(Key::B) => {
+                bubble.push_back(Command::CloseView);
+                Ok(true)
+            }
+            _ => {
+                if matches!(event, KeyEvent::Pressed(_)) && self.confirm_delete.is_some() {
+                    self.load();
+                }
+                self.list.handle_key_event(event, commands, bubble).await
+            }
+        }
+    }
+
+    fn children(&self) -> Vec<&dyn View> {
+        vec![&self.list, &self.button_hints]
+    }
+
+    fn children_mut(&mut self) -> Vec<&mut dyn View> {
+        vec![&mut self.list, &mut self.button_hints]
+    }
+
+    fn bounding_box(&mut self, _styles: &Stylesheet) -> Rect {
+        self.rect
+    }
+
+    fn set_position(&mut self, _point: Point) {
+        unimplemented!()
+    }
+}
+
+impl SettingsChild for Profiles {
+    fn save(&self) -> ChildState {
+        ChildState {
+            selected: self.list.selected(),
+        }
+    }
+}
@@ -0,0 +1,268 @@
+use std::collections::VecDeque;
+
+use anyhow::Result;
+use async_trait::async_trait;
+use common::command::Command;
+use common::constants::SELECTION_MARGIN;
+use common::geom::{Alignment, Point, Rect};
+use common::locale::Locale;
+use common::platform::{DefaultPlatform, Key, KeyEvent, Platform};
+use common::resources::Resources;
+use common::retroachievements::RetroAchievementsSettings;
+use common::stylesheet::Stylesheet;
+use common::view::{ButtonHint, ButtonIcon, Label, Row, SettingsList, TextBox, Toggle, View};
+use log::warn;
+use tokio::sync::mpsc::Sender;
+
+use crate::view::settings::{ChildState, SettingsChild};
+
+pub struct RetroAchievements {
+    rect: Rect,
+    res: Resources,
+    settings: RetroAchievementsSettings,
+    list: SettingsList,
+    button_hints: Row<ButtonHint<String>>,
+}
+
+impl RetroAchievements {
+    pub fn new(rect: Rect, res: Resources, state: Option<ChildState>) -> Self {
+        let Rect { x, y, w, h } = rect;
+
+        let settings = RetroAchievementsSettings::load().unwrap();
+
+        let locale = res.get::<Locale>();
+        let styles = res.get::<Stylesheet>();
+
+        let status = if settings.is_logged_in() {
+            locale.t("settings-retroachievements-logged-in")
+        } else {
+            locale.t("settings-retroachievements-logged-out")
+        };
+
+        let mut list = SettingsList::new(
+            Rect::new(
+                x + 12,
+                y + 8,
+                w - 24,
+                h - 8 - ButtonIcon::diameter(&styles) - 8,
+            ),
+            vec![
+                locale.t("settings-retroachievements-enabled"),
+                locale.t("settings-retroachievements-username"),
+                locale.t("settings-retroachievements-password"),
+                locale.t("settings-retroachievements-hardcore"),
+                locale.t("settings-retroachievements-status"),
+            ],
+            vec![
+                Box::new(Toggle::new(
+                    Point::zero(),
+                    settings.enabled,
+                    Alignment::Right,
+                )),
+                Box::new(TextBox::new(
+                    Point::zero(),
+                    res.clone(),
+                    settings.username.clone(),
+                    Alignment::Right,
+                    false,
+                )),
+                Box::new(TextBox::new(
+                    Point::zero(),
+                    res.clone(),
+                    String::new(),
+                    Alignment::Right,
+                    true,
+                )),
+                Box::new(Toggle::new(
+                    Point::zero(),
+                    settings.hardcore,
+                    Alignment::Right,
+                )),
+                Box::new(Label::new(Point::zero(), status, Alignment::Right, None)),
+            ],
+            styles.ui_font.size + SELECTION_MARGIN,
+        );
+        if let Some(state) = state {
+            list.select(state.selected);
+        }
+
+        let button_hints = Row::new(
+            Point::new(
+                rect.x + rect.w as i32 - 12,
+                rect.y + rect.h as i32 - ButtonIcon::diameter(&styles) as i32 - 8,
+            ),
+            vec![
+                ButtonHint::new(
+                    res.clone(),
+                    Point::zero(),
+                    Key::A,
+                    locale.t("button-edit"),
+                    Alignment::Right,
+                ),
+                ButtonHint::new(
+                    res.clone(),
+                    Point::zero(),
+                    Key::B,
+                    locale.t("button-back"),
+                    Alignment::Right,
+                ),
+            ],
+            Alignment::Right,
+            12,
+        );
+
+        drop(locale);
+        drop(styles);
+
+        Self {
+            rect,
+            res,
+            settings,
+            list,
+            button_hints,
+        }
+    }
+
+    fn set_status(&mut self, key: &str) {
+        self.list.set_right(
+            4,
+            Box::new(Label::new(
+                Point::zero(),
+                self.res.get::<Locale>().t(key),
+                Alignment::Right,
+                None,
+            )),
+        );
+    }
+
+    /// Exchanges the password for a login token. The password itself is not kept.
+    async fn login(&mut self, password: String) -> Result<()> {
+        // Clear the password field regardless of whether logging in succeeds
+        self.list.set_right(
+            2,
+            Box::new(TextBox::new(
+                Point::zero(),
+                self.res.clone(),
+                String::new(),
+                Alignment::Right,
+                true,
+            )),
+        );
+        if password.is_empty() {
+            return Ok(());
+        }
+
+        let mut settings = self.settings.clone();
+        let username = settings.username.clone();
+        let result = tokio::task::spawn_blocking(move || {
+            settings.login(username, &password).map(|_| settings)
+        })
+        .await?;
+
+        match result {
+            Ok(settings) => {
+                self.settings = settings;
+                self.set_status("settings-retroachievements-logged-in");
+            }
+            Err(e) => {
+                warn!("failed to log in to RetroAchievements: {}", e);
+                self.settings.logout();
+                self.set_status("settings-retroachievements-login-failed");
+            }
+        }
+        Ok(())
+    }
+}
+
+#[async_trait(?Send)]
+impl View for RetroAchievements {
+    fn draw(
+        &mut self,
+        display: &mut <DefaultPlatform as Platform>::Display,
+        styles: &Stylesheet,
+    ) -> Result<bool> {
+        let mut drawn = false;
+
+        drawn |= self.button_hints.should_draw() && self.button_hints.draw(display, styles)?;
+        drawn |= self.list.should_draw() && self.list.draw(display, styles)?;
+
+        Ok(drawn)
+    }
+
+    fn should_draw(&self) -> bool {
+        self.list.should_draw() || self.button_hints.should_draw()
+    }
+
+    fn set_should_draw(&mut self) {
+        self.list.set_should_draw();
+        self.button_hints.set_should_draw();
+    }
+
+    async fn handle_key_event(
+        &mut self,
+        event: KeyEvent,
+        commands: Sender<Command>,
+        bubble: &mut VecDeque<Command>,
+    ) -> Result<bool> {
+        if self
+            .list
+            .handle_key_event(event, commands.clone(), bubble)
+            .await?
+        {
+            while let Some(command) = bubble.pop_front() {
+                if let Command::ValueChanged(i, val) = command {
+                    match i {
+                        0 => self.settings.enabled = val.as_bool().unwrap(),
+                        1 => {
+                            let username = val.as_string().unwrap().to_string();
+                            if username != self.settings.username {
+                                // The token belongs to the previous user
+                                self.settings.username = username;
+                                self.settings.logout();
+                                self.set_status("settings-retroachievements-logged-out");
+                            }
+                        }
+                        2 => self.login(val.as_string().unwrap().to_string()).await?,
+                        3 => self.settings.hardcore = val.as_bool().unwrap(),
+                        4 => {} // status
+                        _ => unreachable!("Invalid index"),
+                    }
+                }
+                self.settings.save()?;
+            }
+            return Ok(true);
+        }
+
+        match event {
+            KeyEvent::Pressed(Key::B) => {
+                bubble.push_back(Command::CloseView);
+                Ok(true)
+            }
+            _ => Ok(false),
+        }
+    }
+
+    fn children(&self) -> Vec<&dyn View> {
+        vec![&self.list, &self.button_hints]
+    }
+
+    fn children_mut(&mut self) -> Vec<&mut dyn View> {
+        vec![&mut self.list, &mut self.button_hints]
+    }
+
+    fn bounding_box(&mut self, _styles: &Stylesheet) -> Rect {
+        self.rect
+    }
+
+    fn set_position(&mut self, _point: Point) {
+        unimplemented!()
+    }
+}
+
+impl SettingsChild for RetroAchievements {
+    fn save(&self) -> ChildState {
+        ChildState {
+            selected: self.list.selected(),
+        }
+    }
+}
@@ -0,0 +1,199 @@
+use std::collections::VecDeque;
+
+use anyhow::Result;
+use async_trait::async_trait;
+use common::command::Command;
+use common::constants::SELECTION_MARGIN;
+
+use common::display::Display as DisplayTrait;
+use common::geom::{Alignment, Point, Rect};
+use common::locale::Locale;
+use common::platform::{DefaultPlatform, Key, KeyEvent, Platform};
+use common::resources::Resources;
+use common::save_state::SaveStateSettings;
+use common::stylesheet::Stylesheet;
+use common::view::{ButtonHint, ButtonIcon, Number, Row, SettingsList, View};
+
+use tokio::sync::mpsc::Sender;
+
+use crate::view::settings::{ChildState, SettingsChild};
+
+pub struct SaveStates {
+    rect: Rect,
+    settings: SaveStateSettings,
+    list: SettingsList,
+    button_hints: Row<ButtonHint<String>>,
+}
+
+impl SaveStates {
+    pub fn new(rect: Rect, res: Resources, state: Option<ChildState>) -> Self {
+        let Rect { x, y, w, h } = rect;
+
+        let locale = res.get::<Locale>();
+        let styles = res.get::<Stylesheet>();
+        let settings = SaveStateSettings::load().unwrap_or_default();
+
+        let backups_disabled_label = locale.t("settings-save-states-backups-disabled");
+        let max_age_forever_label = locale.t("settings-save-states-backup-max-age-forever");
+        let mut list = SettingsList::new(
+            Rect::new(
+                x + 12,
+                y + 8,
+                w - 24,
+                h - 8 - ButtonIcon::diameter(&styles) - 8,
+            ),
+            vec![
+                locale.t("settings-save-states-backups-per-slot"),
+                locale.t("settings-save-states-backup-max-age-days"),
+            ],
+            vec![
+                Box::new(Number::new(
+                    Point::zero(),
+                    settings.backups_per_slot,
+                    0,
+                    20,
+                    move |x: &i32| {
+                        if *x == 0 {
+                            backups_disabled_label.clone()
+                        } else {
+                            x.to_string()
+                        }
+                    },
+                    Alignment::Right,
+                )),
+                Box::new(Number::new(
+                    Point::zero(),
+                    settings.backup_max_age_days,
+                    0,
+                    365,
+                    move |x: &i32| {
+                        if *x == 0 {
+                            max_age_forever_label.clone()
+                        } else {
+                            x.to_string()
+                        }
+                    },
+                    Alignment::Right,
+                )),
+            ],
+            styles.ui_font.size + SELECTION_MARGIN,
+        );
+        if let Some(state) = state {
+            list.select(state.selected);
+        }
+
+        let button_hints = Row::new(
+            Point::new(
+                rect.x + rect.w as i32 - 12,
+                rect.y + rect.h as i32 - ButtonIcon::diameter(&styles) as i32 - 8,
+            ),
+            vec![ButtonHint::new(
+                res.clone(),
+                Point::zero(),
+                Key::B,
+                locale.t("button-back"),
+                Alignment::Right,
+            )],
+            Alignment::Right,
+            12,
+        );
+
+        Self {
+            rect,
+            settings,
+            list,
+            button_hints,
+        }
+    }
+}
+
+#[async_trait(?Send)]
+impl View for SaveStates {
+    fn draw(
+        &mut self,
+        display: &mut <DefaultPlatform as Platform>::Display,
+        styles: &Stylesheet,
+    ) -> Result<bool> {
+        let mut drawn = false;
+
+        drawn |= self.list.should_draw() && self.list.draw(display, styles)?;
+
+        if self.button_hints.should_draw() {
+            display.load(Rect::new(
+                self.rect.x,
+                self.rect.y + self.rect.h as i32 - ButtonIcon::diameter(styles) as i32 - 8,
+                self.rect.w,
+                ButtonIcon::diameter(styles),
+            ))?;
+            drawn |= self.button_hints.draw(display, styles)?;
+        }
+
+        Ok(drawn)
+    }
+
+    fn should_draw(&self) -> bool {
+        self.list.should_draw() || self.button_hints.should_draw()
+    }
+
+    fn set_should_draw(&mut self) {
+        self.list.set_should_draw();
+        self.button_hints.set_should_draw();
+    }
+
+    async fn handle_key_event(
+        &mut self,
+        event: KeyEvent,
+        commands: Sender<Command>,
+        bubble: &mut VecDeque<Command>,
+    ) -> Result<bool> {
+        if self
+            .list
+            .handle_key_event(event, commands.clone(), bubble)
+            .await?
+        {
+            while let Some(command) = bubble.pop_front() {
+                if let Command::ValueChanged(i, val) = command {
+                    match i {
+                        0 => self.settings.backups_per_slot = val.as_int().unwrap(),
+                        1 => self.settings.backup_max_age_days = val.as_int().unwrap(),
+                        _ => unreachable!("Invalid index"),
+                    }
+                    self.settings.save()?;
+                }
+            }
+            return Ok(true);
+        }
+
+        match event {
+            KeyEvent::Pressed(Key::B) => {
+                bubble.push_back(Command::CloseView);
+                Ok(true)
+            }
+            _ => Ok(false),
+        }
+    }
+
+    fn children(&self) -> Vec<&dyn View> {
+        vec![&self.list, &self.button_hints]
+    }
+
+    fn children_mut(&mut self) -> Vec<&mut dyn View> {
+        vec![&mut self.list, &mut self.button_hints]
+    }
+
+    fn bounding_box(&mut self, _styles: &Stylesheet) -> Rect {
+        self.rect
+    }
+
+    fn set_position(&mut self, _point: Point) {
+        unimplemented!()
+    }
+}
+
+impl SettingsChild for SaveStates {
+    fn save(&self) -> ChildState {
+        ChildState {
+            selected: self.list.selected(),
+        }
+    }
+}
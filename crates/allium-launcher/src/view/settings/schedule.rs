@@ -0,0 +1,312 @@
+use std::collections::{HashMap, VecDeque};
+use std::time::Duration;
+
+use anyhow::Result;
+use async_trait::async_trait;
+use chrono::{NaiveTime, Timelike};
+use common::command::Command;
+use common::constants::SELECTION_MARGIN;
+use common::geom::{Alignment, Point, Rect};
+use common::locale::Locale;
+use common::platform::{DefaultPlatform, Key, KeyEvent, Platform};
+use common::power::PowerSettings;
+use common::resources::Resources;
+use common::stylesheet::Stylesheet;
+use common::view::{ButtonHint, ButtonIcon, Row, Select, SettingsList, TextBox, Toggle, View};
+use tokio::sync::mpsc::Sender;
+
+use crate::view::settings::{ChildState, SettingsChild};
+
+/// Times of day that can be picked, in minutes.
+const TIME_STEP_MINUTES: u32 = 30;
+
+/// Daily play time that can be picked, in steps of `TIME_STEP_MINUTES`.
+const MAX_BUDGET_STEPS: i32 = 16;
+
+/// Sleep schedule settings. If a PIN is set, it must be entered before anything can be changed.
+pub struct Schedule {
+    rect: Rect,
+    res: Resources,
+    power_settings: PowerSettings,
+    unlocked: bool,
+    list: SettingsList,
+    button_hints: Row<ButtonHint<String>>,
+}
+
+impl Schedule {
+    pub fn new(rect: Rect, res: Resources, state: Option<ChildState>) -> Self {
+        let power_settings = PowerSettings::load().unwrap_or_default();
+        let unlocked = power_settings.schedule.pin_hash.is_none();
+
+        let styles = res.get::<Stylesheet>();
+        let locale = res.get::<Locale>();
+
+        let list = SettingsList::new(
+            Rect::new(
+                rect.x + 12,
+                rect.y + 8,
+                rect.w - 24,
+                rect.h - 8 - ButtonIcon::diameter(&styles) - 8,
+            ),
+            Vec::new(),
+            Vec::new(),
+            styles.ui_font.size + SELECTION_MARGIN,
+        );
+
+        let button_hints = Row::new(
+            Point::new(
+                rect.x + rect.w as i32 - 12,
+                rect.y + rect.h as i32 - ButtonIcon::diameter(&styles) as i32 - 8,
+            ),
+            vec![
+                ButtonHint::new(
+                    res.clone(),
+                    Point::zero(),
+                    Key::A,
+                    locale.t("button-edit"),
+                    Alignment::Right,
+                ),
+                ButtonHint::new(
+                    res.clone(),
+                    Point::zero(),
+                    Key::B,
+                    locale.t("button-back"),
+                    Alignment::Right,
+                ),
+            ],
+            Alignment::Right,
+            12,
+        );
+
+        drop(styles);
+        drop(locale);
+
+        let mut this = Self {
+            rect,
+            res,
+            power_settings,
+            unlocked,
+            list,
+            button_hints,
+        };
+        this.set_items();
+        if let Some(state) = state.filter(|_| this.unlocked) {
+            this.list.select(state.selected);
+        }
+        this
+    }
+
+    fn set_items(&mut self) {
+        let locale = self.res.get::<Locale>();
+
+        if !self.unlocked {
+            self.list.set_items(
+                vec![locale.t("settings-schedule-enter-pin")],
+                vec![Box::new(TextBox::new(
+                    Point::zero(),
+                    self.res.clone(),
+                    String::new(),
+                    Alignment::Right,
+                    true,
+                ))],
+            );
+            return;
+        }
+
+        let schedule = &self.power_settings.schedule;
+        let times: Vec<String> = (0..24 * 60 / TIME_STEP_MINUTES)
+            .map(|i| time_value(i as usize).format("%H:%M").to_string())
+            .collect();
+        let time_select = |time: NaiveTime| {
+            Box::new(Select::new(
+                Point::zero(),
+                time_index(time),
+                times.clone(),
+                Alignment::Right,
+            )) as Box<dyn View>
+        };
+        let budgets = (0..=MAX_BUDGET_STEPS)
+            .map(|i| match i {
+                0 => locale.t("settings-schedule-unlimited"),
+                i => {
+                    let minutes = i * TIME_STEP_MINUTES as i32;
+                    let mut map = HashMap::new();
+                    map.insert("hours".to_string(), (minutes / 60).into());
+                    map.insert("minutes".to_string(), (minutes % 60).into());
+                    locale.ta("settings-schedule-play-time-value", &map)
+                }
+            })
+            .collect();
+
+        self.list.set_items(
+            vec![
+                locale.t("settings-schedule-enabled"),
+                locale.t("settings-schedule-weekdays-start"),
+                locale.t("settings-schedule-weekdays-bedtime"),
+                locale.t("settings-schedule-weekends-start"),
+                locale.t("settings-schedule-weekends-bedtime"),
+                locale.t("settings-schedule-daily-play-time"),
+                locale.t("settings-schedule-pin"),
+            ],
+            vec![
+                Box::new(Toggle::new(
+                    Point::zero(),
+                    schedule.enabled,
+                    Alignment::Right,
+                )),
+                time_select(schedule.weekdays.start),
+                time_select(schedule.weekdays.bedtime),
+                time_select(schedule.weekends.start),
+                time_select(schedule.weekends.bedtime),
+                Box::new(Select::new(
+                    Point::zero(),
+                    (schedule.daily_budget_minutes / TIME_STEP_MINUTES as i32)
+                        .clamp(0, MAX_BUDGET_STEPS) as usize,
+                    budgets,
+                    Alignment::Right,
+                )),
+                Box::new(TextBox::new(
+                    Point::zero(),
+                    self.res.clone(),
+                    String::new(),
+                    Alignment::Right,
+                    true,
+                )),
+            ],
+        );
+    }
+
+    async fn unlock(&mut self, pin: &str, commands: Sender<Command>) -> Result<()> {
+        if self.power_settings.schedule.check_pin(pin) {
+            self.unlocked = true;
+            self.set_items();
+            self.list.select(0);
+        } else {
+            let text = self.res.get::<Locale>().t("settings-schedule-wrong-pin");
+            commands
+                .send(Command::Toast(text, Some(Duration::from_secs(2))))
+                .await?;
+            self.set_items();
+        }
+        Ok(())
+    }
+}
+
+fn time_value(index: usize) -> NaiveTime {
+    let minutes = index as u32 * TIME_STEP_MINUTES;
+    NaiveTime::from_hms_opt(minutes / 60, minutes % 60, 0).unwrap()
+}
+
+fn time_index(time: NaiveTime) -> usize {
+    ((time.hour() * 60 + time.minute()) / TIME_STEP_MINUTES) as usize
+}
+
+#[async_trait(?Send)]
+impl View for Schedule {
+    fn draw(
+        &mut self,
+        display: &mut <DefaultPlatform as Platform>::Display,
+        styles: &Stylesheet,
+    ) -> Result<bool> {
+        let mut drawn = false;
+
+        drawn |= self.list.should_draw() && self.list.draw(display, styles)?;
+        drawn |= self.button_hints.should_draw() && self.button_hints.draw(display, styles)?;
+
+        Ok(drawn)
+    }
+
+    fn should_draw(&self) -> bool {
+        self.list.should_draw() || self.button_hints.should_draw()
+    }
+
+    fn set_should_draw(&mut self) {
+        self.list.set_should_draw();
+        self.button_hints.set_should_draw();
+    }
+
+    async fn handle_key_event(
+        &mut self,
+        event: KeyEvent,
+        commands: Sender<Command>,
+        bubble: &mut VecDeque<Command>,
+    ) -> Result<bool> {
+        if self
+            .list
+            .handle_key_event(event, commands.clone(), bubble)
+            .await?
+        {
+            while let Some(command) = bubble.pop_front() {
+                if let Command::ValueChanged(i, val) = command {
+                    if !self.unlocked {
+                        self.unlock(&val.as_string().unwrap(), commands.clone())
+                            .await?;
+                        continue;
+                    }
+
+                    let schedule = &mut self.power_settings.schedule;
+                    match i {
+                        0 => schedule.enabled = val.as_bool().unwrap(),
+                        1 => schedule.weekdays.start = time_value(val.as_int().unwrap() as usize),
+                        2 => schedule.weekdays.bedtime = time_value(val.as_int().unwrap() as usize),
+                        3 => schedule.weekends.start = time_value(val.as_int().unwrap() as usize),
+                        4 => schedule.weekends.bedtime = time_value(val.as_int().unwrap() as usize),
+                        5 => {
+                            schedule.daily_budget_minutes =
+                                val.as_int().unwrap() * TIME_STEP_MINUTES as i32
+                        }
+                        6 => {
+                            schedule.set_pin(&val.as_string().unwrap());
+                            // The PIN is not shown once it is set
+                            self.list.set_right(
+                                6,
+                                Box::new(TextBox::new(
+                                    Point::zero(),
+                                    self.res.clone(),
+                                    String::new(),
+                                    Alignment::Right,
+                                    true,
+                                )),
+                            );
+                        }
+                        _ => unreachable!("Invalid index"),
+                    }
+                    self.power_settings.save()?;
+                }
+            }
+            return Ok(true);
+        }
+
+        match event {
+            KeyEvent::Pressed(Key::B) => {
+                bubble.push_back(Command::CloseView);
+                Ok(true)
+            }
+            _ => Ok(false),
+        }
+    }
+
+    fn children(&self) -> Vec<&dyn View> {
+        vec![&self.list, &self.button_hints]
+    }
+
+    fn children_mut(&mut self) -> Vec<&mut dyn View> {
+        vec![&mut self.list, &mut self.button_hints]
+    }
+
+    fn bounding_box(&mut self, _styles: &Stylesheet) -> Rect {
+        self.rect
+    }
+
+    fn set_position(&mut self, _point: Point) {
+        unimplemented!()
+    }
+}
+
+impl SettingsChild for Schedule {
+    fn save(&self) -> ChildState {
+        ChildState {
+            selected: self.list.selected(),
+        }
+    }
+}
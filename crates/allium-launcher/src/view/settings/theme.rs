@@ -1,29 +1,37 @@
 use std::collections::VecDeque;
 use std::path::PathBuf;
+use std::time::Duration;
 
 use anyhow::Result;
 use async_trait::async_trait;
-use common::command::Command;
-use common::constants::SELECTION_MARGIN;
+use common::command::{Command, Value};
+use common::constants::{IMAGE_WIDTH, SELECTION_MARGIN};
+use common::display::Display;
 use common::geom::{Alignment, Point, Rect};
 use common::locale::Locale;
 use common::platform::{DefaultPlatform, Key, KeyEvent, Platform};
 use common::resources::Resources;
 use common::stylesheet::{Stylesheet, StylesheetFont};
+use common::theme::{ThemePackage, Themes};
 use common::view::{
-    ButtonHint, ButtonIcon, ColorPicker, Number, Percentage, Row, Select, SettingsList, Toggle,
-    View,
+    ButtonHint, ButtonIcon, ColorPicker, Image, ImageMode, Keyboard, Label, Number, Percentage,
+    Row, Select, SettingsList, Toggle, View,
 };
+use log::error;
 use tokio::sync::mpsc::Sender;
 
 use crate::view::settings::{ChildState, SettingsChild};
 
 pub struct Theme {
     rect: Rect,
+    res: Resources,
     stylesheet: Stylesheet,
     fonts: Vec<PathBuf>,
     list: SettingsList,
     button_hints: Row<ButtonHint<String>>,
+    browser: Option<ThemeBrowser>,
+    /// Keyboard for naming the current customisation when saving it as a theme.
+    keyboard: Option<Keyboard>,
 }
 
 impl Theme {
@@ -210,6 +218,20 @@ impl Theme {
                     locale.t("button-edit"),
                     Alignment::Right,
                 ),
+                ButtonHint::new(
+                    res.clone(),
+                    Point::zero(),
+                    Key::X,
+                    locale.t("settings-theme-browse"),
+                    Alignment::Right,
+                ),
+                ButtonHint::new(
+                    res.clone(),
+                    Point::zero(),
+                    Key::Y,
+                    locale.t("settings-theme-save"),
+                    Alignment::Right,
+                ),
                 ButtonHint::new(
                     res.clone(),
                     Point::zero(),
@@ -222,14 +244,35 @@ impl Theme {
             12,
         );
 
+        drop(locale);
+        drop(styles);
+
         Self {
             rect,
+            res,
             stylesheet,
             fonts,
             list,
             button_hints,
+            browser: None,
+            keyboard: None,
         }
     }
+
+    async fn save_theme(&self, commands: &Sender<Command>, name: &str) -> Result<()> {
+        let key = match Themes::default().save(name, &self.stylesheet) {
+            Ok(_) => "settings-theme-saved",
+            Err(e) => {
+                error!("failed to save theme: {}", e);
+                "settings-theme-invalid-name"
+            }
+        };
+        let text = self.res.get::<Locale>().t(key);
+        commands
+            .send(Command::Toast(text, Some(Duration::from_secs(2))))
+            .await?;
+        Ok(())
+    }
 }
 
 #[async_trait(?Send)]
@@ -239,6 +282,10 @@ impl View for Theme {
         display: &mut <DefaultPlatform as Platform>::Display,
         styles: &Stylesheet,
     ) -> Result<bool> {
+        if let Some(browser) = self.browser.as_mut() {
+            return browser.draw(display, styles);
+        }
+
         let mut drawn = false;
 
         if self.list.should_draw() && self.list.draw(display, styles)? {
@@ -249,16 +296,35 @@ impl View for Theme {
             drawn = true;
         }
 
+        if let Some(keyboard) = self.keyboard.as_mut() {
+            if drawn {
+                keyboard.set_should_draw();
+            }
+            drawn |= keyboard.should_draw() && keyboard.draw(display, styles)?;
+        }
+
         Ok(drawn)
     }
 
     fn should_draw(&self) -> bool {
-        self.list.should_draw() || self.button_hints.should_draw()
+        if let Some(browser) = self.browser.as_ref() {
+            return browser.should_draw();
+        }
+        self.list.should_draw()
+            || self.button_hints.should_draw()
+            || self.keyboard.as_ref().is_some_and(Keyboard::should_draw)
     }
 
     fn set_should_draw(&mut self) {
+        if let Some(browser) = self.browser.as_mut() {
+            browser.set_should_draw();
+            return;
+        }
         self.list.set_should_draw();
         self.button_hints.set_should_draw();
+        if let Some(keyboard) = self.keyboard.as_mut() {
+            keyboard.set_should_draw();
+        }
     }
 
     async fn handle_key_event(
@@ -267,6 +333,56 @@ impl View for Theme {
         commands: Sender<Command>,
         bubble: &mut VecDeque<Command>,
     ) -> Result<bool> {
+        if let Some(browser) = self.browser.as_mut() {
+            if browser
+                .handle_key_event(event, commands.clone(), bubble)
+                .await?
+            {
+                let mut closed = false;
+                bubble.retain(|c| match c {
+                    Command::CloseView => {
+                        closed = true;
+                        false
+                    }
+                    _ => true,
+                });
+                if closed {
+                    self.browser = None;
+                    commands.send(Command::Redraw).await?;
+                }
+            }
+            return Ok(true);
+        }
+
+        if let Some(keyboard) = self.keyboard.as_mut() {
+            if keyboard
+                .handle_key_event(event, commands.clone(), bubble)
+                .await?
+            {
+                let mut name = None;
+                let mut closed = false;
+                bubble.retain_mut(|c| match c {
+                    Command::ValueChanged(_, Value::String(val)) => {
+                        name = Some(val.clone());
+                        false
+                    }
+                    Command::CloseView => {
+                        closed = true;
+                        false
+                    }
+                    _ => true,
+                });
+                if closed {
+                    self.keyboard = None;
+                    commands.send(Command::Redraw).await?;
+                }
+                if let Some(name) = name {
+                    self.save_theme(&commands, &name).await?;
+                }
+            }
+            return Ok(true);
+        }
+
         if self
             .list
             .handle_key_event(event, commands.clone(), bubble)
@@ -394,6 +510,14 @@ impl View for Theme {
         }
 
         match event {
+            KeyEvent::Pressed(Key::X) => {
+                self.browser = Some(ThemeBrowser::new(self.rect, self.res.clone()));
+                Ok(true)
+            }
+            KeyEvent::Pressed(Key::Y) => {
+                self.keyboard = Some(Keyboard::new(self.res.clone(), String::new(), false));
+                Ok(true)
+            }
             KeyEvent::Pressed(Key::B) => {
                 bubble.push_back(Command::CloseView);
                 Ok(true)
@@ -426,3 +550,208 @@ impl SettingsChild for Theme {
         }
     }
 }
+
+/// Lists the installed theme packages with their previews, and applies the selected one.
+struct ThemeBrowser {
+    rect: Rect,
+    res: Resources,
+    themes: Vec<ThemePackage>,
+    list: SettingsList,
+    image: Image,
+    button_hints: Row<ButtonHint<String>>,
+    dirty: bool,
+}
+
+impl ThemeBrowser {
+    fn new(rect: Rect, res: Resources) -> Self {
+        let Rect { x, y, w, h } = rect;
+
+        let themes = Themes::default().list().unwrap_or_else(|e| {
+            error!("failed to list themes: {}", e);
+            Vec::new()
+        });
+
+        let locale = res.get::<Locale>();
+        let styles = res.get::<Stylesheet>();
+
+        let left = if themes.is_empty() {
+            vec![locale.t("settings-theme-no-themes")]
+        } else {
+            themes.iter().map(|theme| theme.name.clone()).collect()
+        };
+        let right = left
+            .iter()
+            .map(|_| {
+                Box::new(Label::new(
+                    Point::zero(),
+                    String::new(),
+                    Alignment::Right,
+                    None,
+                )) as Box<dyn View>
+            })
+            .collect();
+        let list = SettingsList::new(
+            Rect::new(
+                x + 12,
+                y + 8,
+                w - IMAGE_WIDTH - 12 - 12 - 24,
+                h - 8 - ButtonIcon::diameter(&styles) - 8,
+            ),
+            left,
+            right,
+            styles.ui_font.size + SELECTION_MARGIN,
+        );
+
+        let mut image = Image::empty(
+            Rect::new(
+                x + w as i32 - IMAGE_WIDTH as i32 - 24,
+                y + 8,
+                IMAGE_WIDTH,
+                h - 8 - 8 - 8 - ButtonIcon::diameter(&styles) - 8,
+            ),
+            ImageMode::Contain,
+        );
+        image.set_border_radius(12);
+        image.set_alignment(Alignment::Right);
+
+        let button_hints = Row::new(
+            Point::new(
+                x + w as i32 - 12,
+                y + h as i32 - ButtonIcon::diameter(&styles) as i32 - 8,
+            ),
+            vec![
+                ButtonHint::new(
+                    res.clone(),
+                    Point::zero(),
+                    Key::A,
+                    locale.t("settings-theme-apply"),
+                    Alignment::Right,
+                ),
+                ButtonHint::new(
+                    res.clone(),
+                    Point::zero(),
+                    Key::B,
+                    locale.t("button-back"),
+                    Alignment::Right,
+                ),
+            ],
+            Alignment::Right,
+            12,
+        );
+
+        drop(locale);
+        drop(styles);
+
+        Self {
+            rect,
+            res,
+            themes,
+            list,
+            image,
+            button_hints,
+            dirty: true,
+        }
+    }
+
+    /// Applies the selected theme. The theme is fully loaded before anything is saved, so a broken
+    /// theme leaves the current stylesheet untouched.
+    async fn apply(&self, commands: &Sender<Command>) -> Result<()> {
+        let Some(theme) = self.themes.get(self.list.selected()) else {
+            return Ok(());
+        };
+        match theme.stylesheet() {
+            Ok(stylesheet) => {
+                commands
+                    .send(Command::SaveStylesheet(Box::new(stylesheet)))
+                    .await?;
+            }
+            Err(e) => {
+                error!("failed to load theme {}: {}", theme.name, e);
+                let text = self.res.get::<Locale>().t("settings-theme-apply-failed");
+                commands
+                    .send(Command::Toast(text, Some(Duration::from_secs(2))))
+                    .await?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[async_trait(?Send)]
+impl View for ThemeBrowser {
+    fn draw(
+        &mut self,
+        display: &mut <DefaultPlatform as Platform>::Display,
+        styles: &Stylesheet,
+    ) -> Result<bool> {
+        let mut drawn = false;
+
+        if self.dirty {
+            display.load(self.rect)?;
+            self.list.set_should_draw();
+            self.image.set_should_draw();
+            self.button_hints.set_should_draw();
+            self.dirty = false;
+            drawn = true;
+        }
+
+        drawn |= self.list.should_draw() && self.list.draw(display, styles)?;
+
+        self.image.set_path(
+            self.themes
+                .get(self.list.selected())
+                .and_then(ThemePackage::preview),
+        );
+        drawn |= self.image.should_draw() && self.image.draw(display, styles)?;
+
+        drawn |= self.button_hints.should_draw() && self.button_hints.draw(display, styles)?;
+
+        Ok(drawn)
+    }
+
+    fn should_draw(&self) -> bool {
+        self.dirty
+            || self.list.should_draw()
+            || self.image.should_draw()
+            || self.button_hints.should_draw()
+    }
+
+    fn set_should_draw(&mut self) {
+        self.dirty = true;
+    }
+
+    async fn handle_key_event(
+        &mut self,
+        event: KeyEvent,
+        commands: Sender<Command>,
+        bubble: &mut VecDeque<Command>,
+    ) -> Result<bool> {
+        match event {
+            KeyEvent::Pressed(Key::A) => {
+                self.apply(&commands).await?;
+                Ok(true)
+            }
+            KeyEvent::Pressed(Key::B) => {
+                bubble.push_back(Command::CloseView);
+                Ok(true)
+            }
+            _ => self.list.handle_key_event(event, commands, bubble).await,
+        }
+    }
+
+    fn children(&self) -> Vec<&dyn View> {
+        vec![&self.list, &self.image, &self.button_hints]
+    }
+
+    fn children_mut(&mut self) -> Vec<&mut dyn View> {
+        vec![&mut self.list, &mut self.image, &mut self.button_hints]
+    }
+
+    fn bounding_box(&mut self, _styles: &Stylesheet) -> Rect {
+        self.rect
+    }
+
+    fn set_position(&mut self, _point: Point) {
+        unimplemented!()
+    }
+}
@@ -51,6 +51,8 @@ impl Wifi {
                 locale.t("settings-wifi-web-file-explorer"),
                 locale.t("settings-wifi-telnet-enabled"),
                 locale.t("settings-wifi-ftp-enabled"),
+                locale.t("settings-wifi-api-enabled"),
+                locale.t("settings-wifi-api-token"),
             ],
             vec![
                 Box::new(Toggle::new(Point::zero(), settings.wifi, Alignment::Right)),
@@ -86,6 +88,13 @@ impl Wifi {
                     Alignment::Right,
                 )),
                 Box::new(Toggle::new(Point::zero(), settings.ftp, Alignment::Right)),
+                Box::new(Toggle::new(Point::zero(), settings.api, Alignment::Right)),
+                Box::new(Label::new(
+                    Point::zero(),
+                    settings.api_token.clone(),
+                    Alignment::Right,
+                    None,
+                )),
             ],
             res.get::<Stylesheet>().ui_font.size + SELECTION_MARGIN,
         );
@@ -240,6 +249,19 @@ impl View for Wifi {
                             .toggle_web_file_browser(val.as_bool().unwrap())?,
                         6 => self.settings.toggle_telnet(val.as_bool().unwrap())?,
                         7 => self.settings.toggle_ftp(val.as_bool().unwrap())?,
+                        8 => {
+                            self.settings.toggle_api(val.as_bool().unwrap())?;
+                            self.list.set_right(
+                                9,
+                                Box::new(Label::new(
+                                    Point::zero(),
+                                    self.settings.api_token.clone(),
+                                    Alignment::Right,
+                                    None,
+                                )),
+                            );
+                        }
+                        9 => {} // api token
                         _ => unreachable!("Invalid index"),
                     }
                 }
@@ -1,9 +1,9 @@
 use std::collections::VecDeque;
+use std::time::Instant;
 
 use anyhow::Result;
-use base32::encode;
 use common::command::Command;
-use common::constants::{ALLIUM_SCREENSHOTS_DIR, SAVE_STATE_IMAGE_WIDTH};
+use common::constants::SAVE_STATE_IMAGE_WIDTH;
 use common::database::Database;
 use common::display::Display;
 use common::game_info::GameInfo;
@@ -11,11 +11,11 @@ use common::geom;
 use common::locale::{Locale, LocaleSettings};
 use common::platform::{DefaultPlatform, Platform};
 use common::resources::Resources;
+use common::save_state;
 use common::stylesheet::Stylesheet;
 use common::view::View;
 use embedded_graphics::prelude::*;
 use log::{info, warn};
-use sha2::{Digest, Sha256};
 use type_map::TypeMap;
 
 use crate::retroarch_info::RetroArchInfo;
@@ -70,13 +70,21 @@ impl AlliumMenu<DefaultPlatform> {
 
         let (tx, mut rx) = tokio::sync::mpsc::channel(100);
 
+        let mut frame_interval = tokio::time::interval(tokio::time::Duration::from_micros(166_667));
+
+        let mut last_frame = Instant::now();
         loop {
+            let dt = last_frame.elapsed();
+            self.view.update(dt);
+            last_frame = Instant::now();
+
             if self.view.should_draw() && self.view.draw(&mut self.display, &self.res.get())? {
                 self.display.flush()?;
             }
 
             #[cfg(unix)]
             tokio::select! {
+                _ = frame_interval.tick() => {}
                 _ = sigterm.recv() => {
                     self.handle_command(Command::Exit)?;
                 }
@@ -92,6 +100,7 @@ impl AlliumMenu<DefaultPlatform> {
 
             #[cfg(not(unix))]
             tokio::select! {
+                _ = frame_interval.tick() => {}
                 Some(command) = rx.recv() => {
                     self.handle_command(command)?;
                 }
@@ -122,14 +131,7 @@ impl AlliumMenu<DefaultPlatform> {
                 if self.display.pop() {
                     self.display.load(self.display.bounding_box().into())?;
                     self.display.flush()?;
-                    let mut hasher = Sha256::new();
-                    hasher.update(path);
-                    hasher.update(core);
-                    hasher.update(slot.to_le_bytes());
-                    let hash = hasher.finalize();
-                    let base32 = encode(base32::Alphabet::Crockford, &hash);
-                    let file_name = format!("{}.png", base32);
-                    let path = ALLIUM_SCREENSHOTS_DIR.join(file_name);
+                    let path = save_state::screenshot_path(&path, &core, slot);
                     info!("saving screenshot to {:?}", path);
                     std::process::Command::new("screenshot")
                         .arg(path)
@@ -0,0 +1,303 @@
+use std::collections::{HashMap, VecDeque};
+use std::time::Duration;
+
+use anyhow::Result;
+use async_trait::async_trait;
+use common::command::Command;
+use common::constants::SELECTION_MARGIN;
+use common::display::Display;
+use common::game_info::GameInfo;
+use common::geom::{Alignment, Point, Rect};
+use common::locale::Locale;
+use common::platform::{DefaultPlatform, Key, KeyEvent, Platform};
+use common::resources::Resources;
+use common::retroachievements::{GameAchievements, RetroAchievementsSettings};
+use common::stylesheet::{Stylesheet, StylesheetColor};
+use common::view::{ButtonHint, ButtonIcon, Label, Row, SettingsList, View};
+use log::warn;
+use tokio::sync::mpsc::Sender;
+use tokio::sync::oneshot::{self, error::TryRecvError};
+
+type AchievementsResult = Result<Option<GameAchievements>>;
+
+/// Lists the achievements of the current game, and whether they have been unlocked. They are
+/// fetched in the background, as the server may take a while to respond.
+pub struct Achievements {
+    rect: Rect,
+    res: Resources,
+    title: Label<String>,
+    summary: Label<String>,
+    list: SettingsList,
+    description: Label<String>,
+    button_hints: Row<ButtonHint<String>>,
+    achievements: Option<GameAchievements>,
+    receiver: Option<oneshot::Receiver<AchievementsResult>>,
+    dirty: bool,
+}
+
+impl Achievements {
+    pub fn new(rect: Rect, res: Resources, settings: RetroAchievementsSettings) -> Self {
+        let Rect { x, y, w, h } = rect;
+
+        let game_info = res.get::<GameInfo>();
+        let locale = res.get::<Locale>();
+        let styles = res.get::<Stylesheet>();
+
+        let line_h = styles.ui_font.size as i32 + 8;
+
+        let mut title = Label::new(
+            Point::new(x + 12, y + 8),
+            game_info.name.clone(),
+            Alignment::Left,
+            Some(w - 24),
+        );
+        title.color(StylesheetColor::Highlight);
+
+        let summary = Label::new(
+            Point::new(x + 12, y + 8 + line_h),
+            locale.t("ingame-menu-achievements-loading"),
+            Alignment::Left,
+            Some(w - 24),
+        );
+
+        let list_y = y + 8 + line_h * 2;
+        let list = SettingsList::new(
+            Rect::new(
+                x + 12,
+                list_y,
+                w - 24,
+                (h as i32 - (list_y - y) - ButtonIcon::diameter(&styles) as i32 - 16) as u32,
+            ),
+            Vec::new(),
+            Vec::new(),
+            styles.ui_font.size + SELECTION_MARGIN,
+        );
+
+        let mut description = Label::new(
+            Point::new(
+                x + 12,
+                y + h as i32 - ButtonIcon::diameter(&styles) as i32 - 8,
+            ),
+            String::new(),
+            Alignment::Left,
+            Some(w * 2 / 3),
+        );
+        description.color(StylesheetColor::Disabled);
+
+        let button_hints = Row::new(
+            Point::new(
+                x + w as i32 - 12,
+                y + h as i32 - ButtonIcon::diameter(&styles) as i32 - 8,
+            ),
+            vec![ButtonHint::new(
+                res.clone(),
+                Point::zero(),
+                Key::B,
+                locale.t("button-back"),
+                Alignment::Right,
+            )],
+            Alignment::Right,
+            12,
+        );
+
+        let (tx, rx) = oneshot::channel();
+        let path = game_info.path.clone();
+        tokio::task::spawn_blocking(move || {
+            tx.send(settings.game_achievements(&path)).ok();
+        });
+
+        drop(game_info);
+        drop(locale);
+        drop(styles);
+
+        Self {
+            rect,
+            res,
+            title,
+            summary,
+            list,
+            description,
+            button_hints,
+            achievements: None,
+            receiver: Some(rx),
+            dirty: true,
+        }
+    }
+
+    fn set_achievements(&mut self, result: AchievementsResult) {
+        let locale = self.res.get::<Locale>();
+        match result {
+            Ok(Some(game)) => {
+                let mut map = HashMap::new();
+                map.insert("unlocked".to_string(), game.unlocked().into());
+                map.insert("total".to_string(), game.achievements.len().into());
+                map.insert("points".to_string(), game.unlocked_points().into());
+                map.insert("total_points".to_string(), game.points().into());
+                self.summary
+                    .set_text(locale.ta("ingame-menu-achievements-summary", &map));
+
+                let left = game.achievements.iter().map(|a| a.title.clone()).collect();
+                let right = game
+                    .achievements
+                    .iter()
+                    .map(|a| {
+                        let mut map = HashMap::new();
+                        map.insert("points".to_string(), a.points.into());
+                        let key = if a.unlocked {
+                            "ingame-menu-achievements-unlocked"
+                        } else {
+                            "ingame-menu-achievements-locked"
+                        };
+                        let mut label =
+                            Label::new(Point::zero(), locale.ta(key, &map), Alignment::Right, None);
+                        if !a.unlocked {
+                            label.color(StylesheetColor::Disabled);
+                        }
+                        Box::new(label) as Box<dyn View>
+                    })
+                    .collect();
+                self.list.set_items(left, right);
+                for (i, a) in game.achievements.iter().enumerate() {
+                    if !a.unlocked {
+                        self.list.left_mut(i).color(StylesheetColor::Disabled);
+                    }
+                }
+                self.achievements = Some(game);
+            }
+            Ok(None) => {
+                self.summary
+                    .set_text(locale.t("ingame-menu-achievements-none"));
+            }
+            Err(e) => {
+                warn!("failed to load achievements: {}", e);
+                self.summary
+                    .set_text(locale.t("ingame-menu-achievements-failed"));
+            }
+        }
+        drop(locale);
+
+        self.update_description();
+        self.set_should_draw();
+    }
+
+    fn update_description(&mut self) {
+        let description = self
+            .achievements
+            .as_ref()
+            .and_then(|game| game.achievements.get(self.list.selected()))
+            .map(|a| a.description.clone())
+            .unwrap_or_default();
+        self.description.set_text(description);
+    }
+}
+
+#[async_trait(?Send)]
+impl View for Achievements {
+    fn update(&mut self, _dt: Duration) {
+        let Some(receiver) = self.receiver.as_mut() else {
+            return;
+        };
+        match receiver.try_recv() {
+            Ok(result) => {
+                self.receiver = None;
+                self.set_achievements(result);
+            }
+            Err(TryRecvError::Empty) => {}
+            Err(TryRecvError::Closed) => {
+                self.receiver = None;
+                self.set_achievements(Err(anyhow::anyhow!("request was cancelled")));
+            }
+        }
+    }
+
+    fn draw(
+        &mut self,
+        display: &mut <DefaultPlatform as Platform>::Display,
+        styles: &Stylesheet,
+    ) -> Result<bool> {
+        let mut drawn = false;
+
+        if self.dirty {
+            display.load(self.rect)?;
+            self.dirty = false;
+            drawn = true;
+        }
+
+        drawn |= self.title.should_draw() && self.title.draw(display, styles)?;
+        drawn |= self.summary.should_draw() && self.summary.draw(display, styles)?;
+        drawn |= self.list.should_draw() && self.list.draw(display, styles)?;
+        drawn |= self.description.should_draw() && self.description.draw(display, styles)?;
+        drawn |= self.button_hints.should_draw() && self.button_hints.draw(display, styles)?;
+
+        Ok(drawn)
+    }
+
+    fn should_draw(&self) -> bool {
+        self.dirty
+            || self.title.should_draw()
+            || self.summary.should_draw()
+            || self.list.should_draw()
+            || self.description.should_draw()
+            || self.button_hints.should_draw()
+    }
+
+    fn set_should_draw(&mut self) {
+        self.dirty = true;
+        self.title.set_should_draw();
+        self.summary.set_should_draw();
+        self.list.set_should_draw();
+        self.description.set_should_draw();
+        self.button_hints.set_should_draw();
+    }
+
+    async fn handle_key_event(
+        &mut self,
+        event: KeyEvent,
+        commands: Sender<Command>,
+        bubble: &mut VecDeque<Command>,
+    ) -> Result<bool> {
+        match event {
+            KeyEvent::Pressed(Key::B) => {
+                bubble.push_back(Command::CloseView);
+                Ok(true)
+            }
+            // Achievements can't be selected
+            KeyEvent::Pressed(Key::A) => Ok(true),
+            event => {
+                let consumed = self.list.handle_key_event(event, commands, bubble).await?;
+                if consumed {
+                    self.update_description();
+                }
+                Ok(consumed)
+            }
+        }
+    }
+
+    fn children(&self) -> Vec<&dyn View> {
+        vec![
+            &self.title,
+            &self.summary,
+            &self.list,
+            &self.description,
+            &self.button_hints,
+        ]
+    }
+
+    fn children_mut(&mut self) -> Vec<&mut dyn View> {
+        vec![
+            &mut self.title,
+            &mut self.summary,
+            &mut self.list,
+            &mut self.description,
+            &mut self.button_hints,
+        ]
+    }
+
+    fn bounding_box(&mut self, _styles: &Stylesheet) -> Rect {
+        self.rect
+    }
+
+    fn set_position(&mut self, _point: Point) {
+        unimplemented!()
+    }
+}
@@ -2,33 +2,34 @@ use std::collections::{HashMap, VecDeque};
 use std::fs;
 use std::fs::File;
 use std::path::PathBuf;
+use std::time::Duration;
 
 use anyhow::Result;
 use async_trait::async_trait;
-use base32::encode;
 use common::battery::Battery;
 use common::command::Command;
-use common::constants::{
-    ALLIUM_MENU_STATE, ALLIUM_SCREENSHOTS_DIR, SAVE_STATE_IMAGE_WIDTH, SELECTION_MARGIN,
-};
+use common::constants::{ALLIUM_MENU_STATE, SAVE_STATE_IMAGE_WIDTH, SELECTION_MARGIN};
+use common::database::Database;
 use common::display::Display;
 use common::game_info::GameInfo;
 use common::geom::{Alignment, Point, Rect};
 use common::locale::Locale;
 use common::platform::{DefaultPlatform, Key, KeyEvent, Platform};
 use common::resources::Resources;
+use common::retroachievements::RetroAchievementsSettings;
 use common::retroarch::RetroArchCommand;
+use common::save_state;
 use common::stylesheet::Stylesheet;
 use common::view::{
     BatteryIndicator, ButtonHint, ButtonIcon, Image, ImageMode, Label, NullView, Row, SettingsList,
-    View,
+    Toggle, View,
 };
 use log::warn;
 use serde::{Deserialize, Serialize};
-use sha2::{Digest, Sha256};
 use tokio::sync::mpsc::Sender;
 
 use crate::retroarch_info::RetroArchInfo;
+use crate::view::achievements::Achievements;
 use crate::view::text_reader::TextReader;
 
 #[derive(Serialize, Deserialize, Default)]
@@ -36,6 +37,28 @@ pub struct IngameMenuState {
     is_text_reader_open: bool,
 }
 
+/// A page opened from the menu, which takes over the screen until it is closed.
+enum Child {
+    TextReader(Box<TextReader>),
+    Achievements(Box<Achievements>),
+}
+
+impl Child {
+    fn view(&self) -> &dyn View {
+        match self {
+            Child::TextReader(view) => view.as_ref(),
+            Child::Achievements(view) => view.as_ref(),
+        }
+    }
+
+    fn view_mut(&mut self) -> &mut dyn View {
+        match self {
+            Child::TextReader(view) => view.as_mut(),
+            Child::Achievements(view) => view.as_mut(),
+        }
+    }
+}
+
 pub struct IngameMenu<B>
 where
     B: Battery + 'static,
@@ -45,7 +68,7 @@ where
     name: Label<String>,
     battery_indicator: BatteryIndicator<B>,
     menu: SettingsList,
-    child: Option<TextReader>,
+    child: Option<Child>,
     button_hints: Row<ButtonHint<String>>,
     entries: Vec<MenuEntry>,
     retroarch_info: Option<RetroArchInfo>,
@@ -85,7 +108,10 @@ where
             styles.show_battery_level,
         );
 
-        let entries = MenuEntry::entries(&retroarch_info);
+        let has_achievements = RetroAchievementsSettings::load()
+            .map(|settings| settings.is_logged_in())
+            .unwrap_or_default();
+        let entries = MenuEntry::entries(&retroarch_info, has_achievements);
         let mut menu = SettingsList::new(
             Rect::new(
                 x + 12,
@@ -116,6 +142,17 @@ where
             }
         }
 
+        if let Some(i) = entries.iter().position(|e| *e == MenuEntry::Favorite) {
+            let is_favorite = res
+                .get::<Database>()
+                .is_favorite(&game_info.path)
+                .unwrap_or_default();
+            menu.set_right(
+                i,
+                Box::new(Toggle::new(Point::zero(), is_favorite, Alignment::Right)),
+            );
+        }
+
         let mut image = Image::empty(
             Rect::new(
                 x + w as i32 - SAVE_STATE_IMAGE_WIDTH as i32 - 24,
@@ -157,7 +194,11 @@ where
         if state.is_text_reader_open {
             if let Some(guide) = game_info.guide.as_ref() {
                 menu.select(MenuEntry::Guide as usize);
-                child = Some(TextReader::new(rect, res.clone(), guide.clone()));
+                child = Some(Child::TextReader(Box::new(TextReader::new(
+                    rect,
+                    res.clone(),
+                    guide.clone(),
+                ))));
             }
         }
 
@@ -204,15 +245,24 @@ where
     pub fn save(&self) -> Result<()> {
         let file = File::create(ALLIUM_MENU_STATE.as_path())?;
         let state = IngameMenuState {
-            is_text_reader_open: self.child.is_some(),
+            is_text_reader_open: matches!(self.child, Some(Child::TextReader(_))),
         };
-        if let Some(child) = self.child.as_ref() {
+        if let Some(Child::TextReader(child)) = self.child.as_ref() {
             child.save_cursor();
         }
         serde_json::to_writer(file, &state)?;
         Ok(())
     }
 
+    /// Backs up the state in a slot before RetroArch overwrites it. Saving should not be blocked
+    /// by a failed backup.
+    fn backup_state(&self, slot: i8) {
+        let core = self.res.get::<GameInfo>().core.clone();
+        if let Err(e) = save_state::backup(&self.path, &core, slot) {
+            warn!("failed to back up save state: {}", e);
+        }
+    }
+
     async fn select_entry(&mut self, commands: Sender<Command>) -> Result<bool> {
         let selected = self.entries[self.menu.selected()];
         match selected {
@@ -221,6 +271,7 @@ where
             }
             MenuEntry::Save => {
                 let slot = self.retroarch_info.as_ref().unwrap().state_slot.unwrap();
+                self.backup_state(slot);
                 RetroArchCommand::SaveStateSlot(slot).send().await?;
                 commands
                     .send(Command::SaveStateScreenshot {
@@ -245,9 +296,36 @@ where
             }
             MenuEntry::Guide => {
                 if let Some(guide) = self.res.get::<GameInfo>().guide.as_ref() {
-                    self.child = Some(TextReader::new(self.rect, self.res.clone(), guide.clone()));
+                    self.child = Some(Child::TextReader(Box::new(TextReader::new(
+                        self.rect,
+                        self.res.clone(),
+                        guide.clone(),
+                    ))));
                 }
             }
+            MenuEntry::Achievements => {
+                self.child = Some(Child::Achievements(Box::new(Achievements::new(
+                    self.rect,
+                    self.res.clone(),
+                    RetroAchievementsSettings::load()?,
+                ))));
+            }
+            MenuEntry::Favorite => {
+                let is_favorite = {
+                    let database = self.res.get::<Database>();
+                    let is_favorite = !database.is_favorite(&self.path)?;
+                    if is_favorite {
+                        database.add_favorite(&self.path)?;
+                    } else {
+                        database.remove_favorite(&self.path)?;
+                    }
+                    is_favorite
+                };
+                self.menu.set_right(
+                    self.menu.selected(),
+                    Box::new(Toggle::new(Point::zero(), is_favorite, Alignment::Right)),
+                );
+            }
             MenuEntry::Settings => {
                 RetroArchCommand::Unpause.send().await?;
                 RetroArchCommand::MenuToggle.send().await?;
@@ -255,6 +333,7 @@ where
             }
             MenuEntry::Quit => {
                 if self.retroarch_info.is_some() {
+                    self.backup_state(save_state::AUTO_SLOT);
                     commands
                         .send(Command::SaveStateScreenshot {
                             path: self.path.canonicalize()?.to_string_lossy().to_string(),
@@ -309,27 +388,10 @@ where
             .to_string();
         let slot = self.retroarch_info.as_ref().unwrap().state_slot.unwrap();
 
-        let mut hasher = Sha256::new();
-        hasher.update(&path);
-        hasher.update(&self.res.get::<GameInfo>().core);
-        hasher.update(slot.to_le_bytes());
-        let hash = hasher.finalize();
-        let base32 = encode(base32::Alphabet::Crockford, &hash);
-        let file_name = format!("{}.png", base32);
-        let mut screenshot_path = ALLIUM_SCREENSHOTS_DIR.join(file_name);
-
-        // Previously, the hash did not include the core name. We try looking for that path as well.
-        if !screenshot_path.exists() {
-            let mut hasher = Sha256::new();
-            hasher.update(&path);
-            hasher.update(slot.to_le_bytes());
-            let hash = hasher.finalize();
-            let base32 = encode(base32::Alphabet::Crockford, &hash);
-            let file_name = format!("{}.png", base32);
-            screenshot_path = ALLIUM_SCREENSHOTS_DIR.join(file_name);
-        }
+        let screenshot_path =
+            save_state::find_screenshot(&path, &self.res.get::<GameInfo>().core, slot);
 
-        self.image.set_path(Some(screenshot_path));
+        self.image.set_path(screenshot_path);
     }
 }
 
@@ -338,6 +400,14 @@ impl<B> View for IngameMenu<B>
 where
     B: Battery,
 {
+    fn update(&mut self, dt: Duration) {
+        if let Some(child) = self.child.as_mut() {
+            child.view_mut().update(dt);
+        } else {
+            self.children_mut().iter_mut().for_each(|c| c.update(dt));
+        }
+    }
+
     fn draw(
         &mut self,
         display: &mut <DefaultPlatform as Platform>::Display,
@@ -350,7 +420,7 @@ where
             self.dirty = false;
         }
 
-        if let Some(child) = self.child.as_mut() {
+        if let Some(child) = self.child.as_mut().map(Child::view_mut) {
             drawn |= child.should_draw() && child.draw(display, styles)?;
         } else {
             drawn |= self.name.should_draw() && self.name.draw(display, styles)?;
@@ -366,7 +436,7 @@ where
 
     fn should_draw(&self) -> bool {
         if let Some(child) = self.child.as_ref() {
-            self.dirty || child.should_draw()
+            self.dirty || child.view().should_draw()
         } else {
             self.dirty
                 || self.name.should_draw()
@@ -379,7 +449,7 @@ where
     fn set_should_draw(&mut self) {
         self.dirty = true;
         if let Some(child) = self.child.as_mut() {
-            child.set_should_draw();
+            child.view_mut().set_should_draw();
         } else {
             self.name.set_should_draw();
             self.battery_indicator.set_should_draw();
@@ -396,6 +466,7 @@ where
     ) -> Result<bool> {
         if let Some(child) = self.child.as_mut() {
             if child
+                .view_mut()
                 .handle_key_event(event, commands.clone(), bubble)
                 .await?
             {
@@ -573,6 +644,8 @@ pub enum MenuEntry {
     Guide,
     Settings,
     Quit,
+    Favorite,
+    Achievements,
 }
 
 impl MenuEntry {
@@ -585,11 +658,13 @@ impl MenuEntry {
             MenuEntry::Guide => locale.t("ingame-menu-guide"),
             MenuEntry::Settings => locale.t("ingame-menu-settings"),
             MenuEntry::Quit => locale.t("ingame-menu-quit"),
+            MenuEntry::Favorite => locale.t("ingame-menu-favorite"),
+            MenuEntry::Achievements => locale.t("ingame-menu-achievements"),
         }
     }
 
-    fn entries(info: &Option<RetroArchInfo>) -> Vec<Self> {
-        match info {
+    fn entries(info: &Option<RetroArchInfo>, has_achievements: bool) -> Vec<Self> {
+        let mut entries = match info {
             Some(RetroArchInfo {
                 state_slot: Some(_),
                 ..
@@ -600,6 +675,7 @@ impl MenuEntry {
                 MenuEntry::Guide,
                 MenuEntry::Settings,
                 MenuEntry::Reset,
+                MenuEntry::Favorite,
                 MenuEntry::Quit,
             ],
             Some(_) => vec![
@@ -607,9 +683,87 @@ impl MenuEntry {
                 MenuEntry::Reset,
                 MenuEntry::Guide,
                 MenuEntry::Settings,
+                MenuEntry::Favorite,
                 MenuEntry::Quit,
             ],
-            None => vec![MenuEntry::Continue, MenuEntry::Guide, MenuEntry::Quit],
+            None => vec![
+                MenuEntry::Continue,
+                MenuEntry::Guide,
+                MenuEntry::Favorite,
+                MenuEntry::Quit,
+            ],
+        };
+        if has_achievements {
+            if let Some(i) = entries.iter().position(|e| *e == MenuEntry::Guide) {
+                entries.insert(i + 1, MenuEntry::Achievements);
+            }
         }
+        entries
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use common::platform::headless::{test_locale, test_stylesheet, HeadlessPlatform};
+    use common::snapshot_path;
+    use embedded_graphics::prelude::*;
+    use type_map::TypeMap;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_ingame_menu_snapshot() -> Result<()> {
+        let mut platform = HeadlessPlatform::new()?;
+        platform.set_battery(20, true);
+        let mut display = platform.display()?;
+
+        let mut res = TypeMap::new();
+        res.insert(Database::in_memory()?);
+        res.insert(GameInfo::new(
+            "Golden Sun".to_string(),
+            PathBuf::from("/nonexistent/Roms/GBA/Golden Sun.gba"),
+            "mgba_libretro".to_string(),
+            None,
+            "retroarch".to_string(),
+            Vec::new(),
+            true,
+            false,
+        ));
+        res.insert(test_stylesheet());
+        res.insert(test_locale());
+        res.insert(Into::<common::geom::Size>::into(display.size()));
+        let res = Resources::new(res);
+
+        let info = RetroArchInfo {
+            max_disk_slots: 2,
+            disk_slot: 0,
+            state_slot: Some(0),
+        };
+        let mut menu = IngameMenu::new(
+            display.bounding_box().into(),
+            Default::default(),
+            res.clone(),
+            platform.battery()?,
+            Some(info),
+        );
+
+        display.clear(res.get::<Stylesheet>().background_color)?;
+        display.save()?;
+        menu.draw(&mut display, &res.get::<Stylesheet>())?;
+        display.assert_snapshot(&snapshot_path!("ingame_menu"));
+
+        platform.press(Key::Down);
+        let (tx, _rx) = tokio::sync::mpsc::channel(100);
+        while platform.has_key_events() {
+            let event = platform.poll().await;
+            menu.handle_key_event(event, tx.clone(), &mut VecDeque::new())
+                .await?;
+        }
+        while menu.should_draw() {
+            menu.draw(&mut display, &res.get::<Stylesheet>())?;
+        }
+        display.assert_snapshot(&snapshot_path!("ingame_menu_save"));
+
+        Ok(())
     }
 }
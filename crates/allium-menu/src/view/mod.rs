@@ -1,2 +1,3 @@
+mod achievements;
 pub mod ingame_menu;
 mod text_reader;
@@ -1,29 +1,41 @@
+use std::collections::HashMap;
 use std::fs::{self, File};
 use std::io::Write;
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
 use std::time::Instant;
 
 use anyhow::Result;
-use chrono::{DateTime, Duration, Utc};
+use chrono::{DateTime, Duration, Local, Utc};
 use common::battery::Battery;
 use common::constants::{
-    ALLIUMD_STATE, ALLIUM_GAME_INFO, ALLIUM_MENU, ALLIUM_SD_ROOT, ALLIUM_VERSION,
-    BATTERY_SHUTDOWN_THRESHOLD, BATTERY_UPDATE_INTERVAL, IDLE_TIMEOUT, LONG_PRESS_DURATION,
+    ALLIUMD_STATE, ALLIUM_GAMES_DIR, ALLIUM_GAME_INFO, ALLIUM_MENU, ALLIUM_SD_ROOT, ALLIUM_VERSION,
+    BATTERY_LOG_INTERVAL, BATTERY_LOG_RETENTION, BATTERY_UPDATE_INTERVAL, IDLE_TIMEOUT,
+    LONG_PRESS_DURATION, LOW_BATTERY_RESTORE_TIMEOUT, SCHEDULE_WARNING_TIME, WARNING_DURATION,
 };
 use common::display::settings::DisplaySettings;
+use common::hotkeys::{HotkeyAction, HotkeySettings};
+use common::launcher_request::LauncherRequest;
 use common::locale::{Locale, LocaleSettings};
-use common::power::{PowerButtonAction, PowerSettings};
+use common::power::{self, PowerButtonAction, PowerSettings, ScheduleStatus};
+use common::profile::Profiles;
 use common::retroarch::RetroArchCommand;
+use common::save_state::{self, LowBatterySave, LOW_BATTERY_SLOT};
 use common::wifi::WiFiSettings;
 use enum_map::EnumMap;
 use log::{debug, error, info, trace, warn};
 use serde::{Deserialize, Serialize};
 use tokio::process::{Child, Command};
+use tokio::sync::{mpsc, watch};
+use tokio::task::JoinHandle;
 
-use common::database::Database;
+use common::database::{BatteryReading, Database, Session};
 use common::game_info::GameInfo;
+use common::launch_profile::CpuSettings;
 use common::platform::{DefaultPlatform, Key, KeyEvent, Platform};
 
+use crate::server::{self, Action, BatteryStatus, ServerState};
+
 #[cfg(unix)]
 use {
     nix::sys::signal::kill, nix::sys::signal::Signal, nix::unistd::Pid,
@@ -38,6 +50,16 @@ pub struct AlliumDState {
     brightness: u8,
 }
 
+/// Sessions that count towards today's play time while a game is running.
+#[derive(Debug)]
+struct ScheduleSessions {
+    /// The running game and when it was started.
+    path: PathBuf,
+    start: DateTime<Utc>,
+    /// Sessions recorded before the running game was started.
+    recorded: Vec<Session>,
+}
+
 #[derive(Debug)]
 pub struct AlliumD<P: Platform> {
     platform: P,
@@ -50,6 +72,20 @@ pub struct AlliumD<P: Platform> {
     state: AlliumDState,
     locale: Locale,
     power_settings: PowerSettings,
+    /// Whether the low battery warning was shown since the battery was last above the warning
+    /// level or charging.
+    low_battery_warned: bool,
+    /// Whether the warning that play time is ending was shown for the running game.
+    schedule_warned: bool,
+    /// Play sessions for the sleep schedule, loaded once per game as it is checked during play.
+    schedule_sessions: Option<ScheduleSessions>,
+    /// Reloaded whenever Menu is pressed, so that changes apply without a restart.
+    hotkeys: HotkeySettings,
+    /// HTTP API server task and the token it accepts, while it is enabled.
+    server: Option<(String, JoinHandle<()>)>,
+    battery_status: watch::Sender<BatteryStatus>,
+    actions_tx: mpsc::Sender<Action>,
+    actions: mpsc::Receiver<Action>,
 }
 
 impl AlliumDState {
@@ -99,14 +135,23 @@ impl AlliumDState {
     }
 }
 
-async fn spawn_main() -> Result<Child> {
+/// Spawns the launcher, or resumes the game that was running. If `state_slot` is given, the game
+/// loads that state instead of the auto state.
+#[cfg_attr(not(feature = "miyoo"), allow(unused_variables))]
+async fn spawn_main(state_slot: Option<i8>) -> Result<Child> {
     #[cfg(feature = "miyoo")]
     return Ok(match GameInfo::load()? {
         Some(mut game_info) => {
             debug!("found game info, resuming game");
             game_info.start_time = Utc::now();
             game_info.save()?;
-            game_info.command().into()
+            if let Err(e) = game_info.cpu.apply() {
+                warn!("failed to apply cpu settings: {}", e);
+            }
+            match state_slot {
+                Some(slot) => game_info.command_with_state_slot(slot).into(),
+                None => game_info.command().into(),
+            }
         }
         None => {
             debug!("no game info found, launching launcher");
@@ -125,11 +170,20 @@ async fn spawn_main() -> Result<Child> {
 
 impl AlliumD<DefaultPlatform> {
     pub async fn new() -> Result<AlliumD<DefaultPlatform>> {
-        let platform = DefaultPlatform::new()?;
+        let mut platform = DefaultPlatform::new()?;
         let state = AlliumDState::load()?;
-        let main = spawn_main().await?;
+        if !Path::new(&*ALLIUM_GAME_INFO).exists() {
+            switch_profile();
+        }
         let locale = Locale::new(&LocaleSettings::load()?.lang);
+        let state_slot = offer_low_battery_restore(&mut platform, &locale)
+            .await?
+            .then_some(LOW_BATTERY_SLOT);
+        let main = spawn_main(state_slot).await?;
         let power_settings = PowerSettings::load()?;
+        let hotkeys = HotkeySettings::load()?;
+        let (battery_status, _) = watch::channel(BatteryStatus::default());
+        let (actions_tx, actions) = mpsc::channel(8);
 
         Ok(AlliumD {
             platform,
@@ -142,6 +196,14 @@ impl AlliumD<DefaultPlatform> {
             state,
             locale,
             power_settings,
+            low_battery_warned: false,
+            schedule_warned: false,
+            schedule_sessions: None,
+            hotkeys,
+            server: None,
+            battery_status,
+            actions_tx,
+            actions,
         })
     }
 
@@ -170,10 +232,15 @@ impl AlliumD<DefaultPlatform> {
             let mut sigterm = tokio::signal::unix::signal(SignalKind::terminate())?;
 
             let mut battery_interval = Instant::now();
+            let mut battery_log_interval = Instant::now();
 
             // If battery is charging, suspend.
             let mut battery = self.platform.battery()?;
             battery.update()?;
+            self.battery_status
+                .send_replace(BatteryStatus::new(&battery));
+            self.log_battery(&battery);
+            self.update_server();
             if battery.charging() {
                 self.handle_charging().await?;
             }
@@ -193,10 +260,20 @@ impl AlliumD<DefaultPlatform> {
                     if let Err(e) = battery.update() {
                         error!("failed to update battery: {}", e);
                     }
-                    if battery.percentage() <= BATTERY_SHUTDOWN_THRESHOLD && !battery.charging() {
-                        warn!("battery is low, shutting down");
-                        self.handle_quit().await?;
+                    self.battery_status
+                        .send_replace(BatteryStatus::new(&battery));
+                    // The API is toggled by the launcher, pick up the change
+                    self.update_server();
+                    if battery_log_interval.elapsed() >= BATTERY_LOG_INTERVAL {
+                        battery_log_interval = Instant::now();
+                        self.log_battery(&battery);
                     }
+                    // Power settings are changed by the launcher, pick up the change
+                    if let Ok(power_settings) = PowerSettings::load() {
+                        self.power_settings = power_settings;
+                    }
+                    self.check_low_battery(&battery).await?;
+                    self.check_schedule().await?;
                 }
 
                 let auto_sleep_duration = match self.power_settings.auto_sleep_duration_minutes {
@@ -218,11 +295,12 @@ impl AlliumD<DefaultPlatform> {
                     _ = self.main.wait() => {
                         if !self.is_terminating {
                             info!("main process terminated, recording play time");
-                            self.update_play_time()?;
-                            GameInfo::delete()?;
-                            self.main = spawn_main().await?;
+                            self.restart_main().await?;
                         }
                     }
+                    Some(action) = self.actions.recv() => {
+                        self.handle_action(action).await?;
+                    }
                     _ = sigint.recv() => self.handle_quit().await?,
                     _ = sigterm.recv() => self.handle_quit().await?,
                 }
@@ -252,6 +330,10 @@ impl AlliumD<DefaultPlatform> {
             KeyEvent::Pressed(Key::Menu) => {
                 self.is_menu_pressed_alone = true;
                 self.pressed_menu = Instant::now();
+                match HotkeySettings::load() {
+                    Ok(hotkeys) => self.hotkeys = hotkeys,
+                    Err(e) => warn!("failed to load hotkeys: {}", e),
+                }
             }
             KeyEvent::Pressed(_) => {
                 self.is_menu_pressed_alone = false;
@@ -296,39 +378,12 @@ impl AlliumD<DefaultPlatform> {
                         }
                     }
                 }
-                KeyEvent::Pressed(Key::Up | Key::VolUp)
-                | KeyEvent::Autorepeat(Key::Up | Key::VolUp) => {
-                    self.add_brightness(5)?;
-                }
-                KeyEvent::Pressed(Key::Down | Key::VolDown)
-                | KeyEvent::Autorepeat(Key::Down | Key::VolDown) => {
-                    self.add_brightness(-5)?;
-                }
-                KeyEvent::Pressed(Key::Left) | KeyEvent::Autorepeat(Key::Left) => {
-                    self.add_volume(-1)?;
-                }
-                KeyEvent::Pressed(Key::Right) | KeyEvent::Autorepeat(Key::Right) => {
-                    self.add_volume(1)?;
-                }
-                KeyEvent::Released(Key::Power) => {
-                    let game_info = GameInfo::load()?;
-                    let name = match game_info.as_ref() {
-                        Some(game_info) => game_info.name.as_str(),
-                        None => "Allium",
-                    };
-                    let file_name = format!(
-                        "{}-{}.png",
-                        chrono::Local::now().format("%Y-%m-%d_%H-%M-%S"),
-                        name,
-                    );
-                    Command::new("screenshot")
-                        .arg(ALLIUM_SD_ROOT.join("Screenshots").join(file_name))
-                        .arg("--rumble")
-                        .spawn()?
-                        .wait()
-                        .await?;
+                key_event => {
+                    if let Some(hotkey) = self.hotkeys.find(key_event, &self.keys) {
+                        let action = hotkey.action.clone();
+                        self.handle_hotkey(action).await?;
+                    }
                 }
-                _ => {}
             }
         } else {
             match key_event {
@@ -380,6 +435,60 @@ impl AlliumD<DefaultPlatform> {
         Ok(())
     }
 
+    async fn handle_hotkey(&mut self, action: HotkeyAction) -> Result<()> {
+        debug!("handling hotkey: {:?}", action);
+        match action {
+            HotkeyAction::BrightnessUp => self.add_brightness(5)?,
+            HotkeyAction::BrightnessDown => self.add_brightness(-5)?,
+            HotkeyAction::VolumeUp => self.add_volume(1)?,
+            HotkeyAction::VolumeDown => self.add_volume(-1)?,
+            HotkeyAction::Screenshot => {
+                let game_info = GameInfo::load()?;
+                let name = match game_info.as_ref() {
+                    Some(game_info) => game_info.name.as_str(),
+                    None => "Allium",
+                };
+                let file_name = format!(
+                    "{}-{}.png",
+                    chrono::Local::now().format("%Y-%m-%d_%H-%M-%S"),
+                    name,
+                );
+                Command::new("screenshot")
+                    .arg(ALLIUM_SD_ROOT.join("Screenshots").join(file_name))
+                    .arg("--rumble")
+                    .spawn()?
+                    .wait()
+                    .await?;
+            }
+            HotkeyAction::QuickSave => {
+                if self.is_ingame() {
+                    RetroArchCommand::SaveState.send().await?;
+                }
+            }
+            HotkeyAction::QuickLoad => {
+                if self.is_ingame() {
+                    RetroArchCommand::LoadState.send().await?;
+                }
+            }
+            HotkeyAction::FastForward => {
+                if self.is_ingame() {
+                    RetroArchCommand::FastForward.send().await?;
+                }
+            }
+            HotkeyAction::LaunchApp { path } => {
+                self.request_launcher(LauncherRequest::LaunchApp {
+                    path: ALLIUM_SD_ROOT.join(path),
+                })
+                .await?;
+            }
+            HotkeyAction::Suspend => {
+                #[cfg(unix)]
+                self.handle_suspend().await?;
+            }
+        }
+        Ok(())
+    }
+
     #[cfg(unix)]
     async fn handle_charging(&mut self) -> Result<()> {
         info!("charging...");
@@ -421,6 +530,148 @@ impl AlliumD<DefaultPlatform> {
         self.platform.unsuspend(ctx)
     }
 
+    /// Warns once when the battery drops to the warning level. At the critical level, saves the
+    /// running game to `LOW_BATTERY_SLOT` and shuts down.
+    #[cfg(unix)]
+    async fn check_low_battery(&mut self, battery: &impl Battery) -> Result<()> {
+        let percentage = battery.percentage();
+        if battery.charging() {
+            self.low_battery_warned = false;
+            return Ok(());
+        }
+
+        if percentage <= self.power_settings.critical_battery_percentage {
+            warn!("battery is critical, shutting down");
+            Command::new("say")
+                .arg(self.locale.t("battery-critical"))
+                .arg("--bg")
+                .arg("--top")
+                .spawn()?
+                .wait()
+                .await?;
+            self.save_low_battery_state().await;
+            self.handle_quit().await?;
+        } else if percentage > self.power_settings.low_battery_warning_percentage {
+            self.low_battery_warned = false;
+        } else if !self.low_battery_warned {
+            info!("battery is low, warning");
+            self.low_battery_warned = true;
+
+            let mut map = HashMap::new();
+            map.insert("percentage".to_string(), percentage.into());
+            self.show_warning(self.locale.ta("battery-low", &map))
+                .await?;
+        }
+        Ok(())
+    }
+
+    /// Warns before play time runs out under the sleep schedule. When it has run out, quits the
+    /// running game, which saves its auto state, and suspends.
+    #[cfg(unix)]
+    async fn check_schedule(&mut self) -> Result<()> {
+        if !self.power_settings.schedule.enabled || !self.is_ingame() {
+            self.schedule_warned = false;
+            self.schedule_sessions = None;
+            return Ok(());
+        }
+
+        let played_today = self.played_today()?;
+        let warning_time = Duration::from_std(SCHEDULE_WARNING_TIME)?;
+        match self
+            .power_settings
+            .schedule
+            .status(&Local::now(), played_today)
+        {
+            ScheduleStatus::Allowed(Some(remaining)) if remaining <= warning_time => {
+                if !self.schedule_warned {
+                    info!(
+                        "play time is ending in {}s, warning",
+                        remaining.num_seconds()
+                    );
+                    self.schedule_warned = true;
+                    let mut map = HashMap::new();
+                    map.insert("minutes".to_string(), (remaining.num_minutes() + 1).into());
+                    self.show_warning(self.locale.ta("schedule-ending", &map))
+                        .await?;
+                }
+            }
+            ScheduleStatus::Allowed(_) => self.schedule_warned = false,
+            status => {
+                info!("play time is over ({:?}), saving and suspending", status);
+                let text = if status == ScheduleStatus::Bedtime {
+                    self.locale.t("schedule-bedtime")
+                } else {
+                    self.locale.t("schedule-budget-spent")
+                };
+                Command::new("say")
+                    .arg(text)
+                    .arg("--bg")
+                    .spawn()?
+                    .wait()
+                    .await?;
+                tokio::time::sleep(WARNING_DURATION).await;
+
+                backup_auto_state()?;
+                if let Some(mut menu) = self.menu.take() {
+                    terminate(&mut menu).await?;
+                }
+                terminate(&mut self.main).await?;
+                self.restart_main().await?;
+                self.schedule_warned = false;
+
+                self.handle_suspend().await?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Shows a warning at the top of the screen for a few seconds. The main process is stopped so
+    /// that it doesn't draw over the warning, and the screen is restored before it continues.
+    #[cfg(unix)]
+    async fn show_warning(&self, text: String) -> Result<()> {
+        signal(&self.main, Signal::SIGSTOP)?;
+        let said = Command::new("say")
+            .arg(text)
+            .arg("--bg")
+            .arg("--top")
+            .arg("--restore-after")
+            .arg(WARNING_DURATION.as_secs().to_string())
+            .spawn();
+        if let Ok(mut said) = said {
+            said.wait().await.ok();
+        }
+        signal(&self.main, Signal::SIGCONT)?;
+        Ok(())
+    }
+
+    /// Saves the running game to `LOW_BATTERY_SLOT`, so that it can be restored on the next boot
+    /// even if the device loses power before RetroArch saves the auto state.
+    async fn save_low_battery_state(&self) {
+        let Ok(Some(game_info)) = GameInfo::load() else {
+            return;
+        };
+        if !game_info.has_menu {
+            return;
+        }
+
+        if let Err(e) = save_state::backup(&game_info.path, &game_info.core, LOW_BATTERY_SLOT) {
+            warn!("failed to back up save state: {}", e);
+        }
+        if let Err(e) = RetroArchCommand::SaveStateSlot(LOW_BATTERY_SLOT)
+            .send()
+            .await
+        {
+            warn!("failed to save state: {}", e);
+            return;
+        }
+        // RetroArch saves asynchronously, give it time to write the state before it is terminated
+        tokio::time::sleep(std::time::Duration::from_secs(1)).await;
+
+        if let Err(e) = LowBatterySave::new(game_info.path, game_info.core).save() {
+            warn!("failed to record low battery save: {}", e);
+        }
+    }
+
     #[cfg(unix)]
     async fn handle_suspend(&mut self) -> Result<()> {
         info!("suspending...");
@@ -464,6 +715,8 @@ impl AlliumD<DefaultPlatform> {
         if self.is_ingame() {
             self.update_play_time()?;
 
+            backup_auto_state()?;
+
             if let Some(menu) = self.menu.as_mut() {
                 terminate(menu).await?;
             }
@@ -486,6 +739,152 @@ impl AlliumD<DefaultPlatform> {
         Ok(())
     }
 
+    /// Starts or stops the HTTP API server to match the Wi-Fi settings. The server is restarted
+    /// when its token changes.
+    fn update_server(&mut self) {
+        let settings = match WiFiSettings::load() {
+            Ok(mut settings) => {
+                // Settings saved before tokens were added
+                if settings.api && settings.api_token.is_empty() {
+                    settings.reset_api_token();
+                    if let Err(e) = settings.save() {
+                        error!("failed to save wifi settings: {}", e);
+                    }
+                }
+                settings
+            }
+            Err(e) => {
+                error!("failed to load wifi settings: {}", e);
+                WiFiSettings::new()
+            }
+        };
+
+        if let Some((token, server)) = &self.server {
+            if !settings.api || server.is_finished() || *token != settings.api_token {
+                info!("stopping API server");
+                server.abort();
+                self.server = None;
+            }
+        }
+
+        if settings.api && self.server.is_none() {
+            let state = ServerState {
+                database: Arc::new(Database::new),
+                game_info: ALLIUM_GAME_INFO.clone(),
+                screenshots: ALLIUM_SD_ROOT.join("Screenshots"),
+                games_dir: ALLIUM_GAMES_DIR.clone(),
+                token: settings.api_token.clone(),
+                battery: self.battery_status.subscribe(),
+                actions: self.actions_tx.clone(),
+            };
+            self.server = Some((
+                settings.api_token,
+                tokio::spawn(async move {
+                    if let Err(e) = server::serve(state).await {
+                        error!("API server failed: {}", e);
+                    }
+                }),
+            ));
+        }
+    }
+
+    async fn handle_action(&mut self, action: Action) -> Result<()> {
+        info!("handling API action: {:?}", action);
+        match action {
+            Action::Launch(path) => {
+                self.request_launcher(LauncherRequest::Launch { path })
+                    .await?
+            }
+            Action::Quit => self.quit_game().await?,
+            Action::Rescan => {
+                // If a game is running, this waits until it is quit
+                LauncherRequest::Rescan.save()?;
+                if !self.is_ingame() {
+                    self.notify_launcher()?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Sends a request that replaces the running game, if any, e.g. to launch something else.
+    async fn request_launcher(&mut self, request: LauncherRequest) -> Result<()> {
+        request.save()?;
+        if self.is_ingame() {
+            // The launcher handles the request once it restarts
+            self.quit_game().await?;
+        } else {
+            self.notify_launcher()?;
+        }
+        Ok(())
+    }
+
+    /// Quits the running game. The event loop restarts the launcher once it has exited.
+    async fn quit_game(&mut self) -> Result<()> {
+        if !self.is_ingame() {
+            return Ok(());
+        }
+
+        backup_auto_state()?;
+
+        if let Some(mut menu) = self.menu.take() {
+            terminate(&mut menu).await?;
+        }
+        terminate(&mut self.main).await
+    }
+
+    /// Signals the launcher to handle a pending `LauncherRequest`.
+    fn notify_launcher(&self) -> Result<()> {
+        #[cfg(unix)]
+        signal(&self.main, Signal::SIGUSR1)?;
+        Ok(())
+    }
+
+    /// Records the play time of the game that ended, and starts the launcher.
+    async fn restart_main(&mut self) -> Result<()> {
+        self.update_play_time()?;
+        GameInfo::delete()?;
+        if let Err(e) = CpuSettings::restore() {
+            warn!("failed to restore cpu settings: {}", e);
+        }
+        switch_profile();
+        // The next game may be in another profile
+        self.schedule_sessions = None;
+        self.main = spawn_main(None).await?;
+        Ok(())
+    }
+
+    /// Play time today, including the running game, counted from the same sessions that
+    /// `update_play_time` records. The sessions and the start of the running game are only read
+    /// once per game.
+    #[allow(unused)]
+    fn played_today(&mut self) -> Result<Duration> {
+        if self.schedule_sessions.is_none() {
+            // Read directly, as `GameInfo::load` enables swap
+            let file = File::open(ALLIUM_GAME_INFO.as_path())?;
+            let game_info: GameInfo = serde_json::from_reader(file)?;
+            let recorded =
+                Database::new()?.select_sessions(game_info.start_time - Duration::days(1))?;
+            self.schedule_sessions = Some(ScheduleSessions {
+                path: game_info.path,
+                start: game_info.start_time,
+                recorded,
+            });
+        }
+
+        let cached = self.schedule_sessions.as_ref().unwrap();
+        let end = Utc::now();
+        let mut sessions = cached.recorded.clone();
+        sessions.push(Session {
+            path: cached.path.clone(),
+            core: None,
+            start: cached.start,
+            end,
+            duration: end.signed_duration_since(cached.start),
+        });
+        Ok(power::played_today(&sessions, Local::now()))
+    }
+
     #[allow(unused)]
     fn update_play_time(&self) -> Result<()> {
         if !self.is_ingame() {
@@ -493,20 +892,57 @@ impl AlliumD<DefaultPlatform> {
         }
 
         let file = File::open(ALLIUM_GAME_INFO.as_path())?;
-        let mut game_info: GameInfo = serde_json::from_reader(file)?;
+        let game_info: GameInfo = serde_json::from_reader(file)?;
+
+        let end = Utc::now();
+        let play_time = end.signed_duration_since(game_info.start_time);
 
         // As a sanity check, don't add play time if the game was played for more than 24 hours
-        if game_info.play_time() > Duration::hours(24) {
+        if play_time > Duration::hours(24) {
             warn!("play time is too long, not adding to database");
             return Ok(());
         }
 
         let database = Database::new()?;
-        database.add_play_time(game_info.path.as_path(), game_info.play_time());
+        database.add_play_time(game_info.path.as_path(), play_time)?;
+        database.add_session(&Session {
+            path: game_info.path,
+            core: Some(game_info.core).filter(|core| !core.is_empty()),
+            start: game_info.start_time,
+            end,
+            duration: play_time,
+        })?;
 
         Ok(())
     }
 
+    /// Adds a reading to the battery log, along with the game that is running.
+    fn log_battery(&self, battery: &impl Battery) {
+        let game_info = if self.is_ingame() {
+            File::open(ALLIUM_GAME_INFO.as_path())
+                .ok()
+                .and_then(|file| serde_json::from_reader::<_, GameInfo>(file).ok())
+        } else {
+            None
+        };
+
+        let time = Utc::now();
+        let reading = BatteryReading {
+            time,
+            percentage: battery.percentage(),
+            charging: battery.charging(),
+            path: game_info.as_ref().map(|game_info| game_info.path.clone()),
+            core: game_info
+                .map(|game_info| game_info.core)
+                .filter(|core| !core.is_empty()),
+        };
+        let keep_since = time - Duration::from_std(BATTERY_LOG_RETENTION).unwrap_or_default();
+        if let Err(e) = Database::new().and_then(|db| db.add_battery_reading(&reading, keep_since))
+        {
+            warn!("failed to log battery: {}", e);
+        }
+    }
+
     fn is_ingame(&self) -> bool {
         Path::new(&*ALLIUM_GAME_INFO).exists()
     }
@@ -526,6 +962,71 @@ impl AlliumD<DefaultPlatform> {
     }
 }
 
+/// Offers to load the state saved before the last shutdown on a critical battery, if the game it
+/// was saved from is being resumed. Returns whether it should be loaded.
+async fn offer_low_battery_restore(
+    platform: &mut DefaultPlatform,
+    locale: &Locale,
+) -> Result<bool> {
+    let Some(save) = LowBatterySave::load()? else {
+        return Ok(false);
+    };
+    LowBatterySave::delete()?;
+    let Some(game_info) = GameInfo::load()? else {
+        return Ok(false);
+    };
+    if game_info.path != save.path || !game_info.has_menu {
+        return Ok(false);
+    }
+
+    info!("offering to restore low battery save");
+    let mut map = HashMap::new();
+    map.insert("game".to_string(), game_info.name.into());
+    Command::new("show").arg("-c").spawn()?.wait().await?;
+    Command::new("say")
+        .arg(locale.ta("battery-restore-low-battery-save", &map))
+        .spawn()?
+        .wait()
+        .await?;
+
+    let timeout = tokio::time::sleep(LOW_BATTERY_RESTORE_TIMEOUT);
+    tokio::pin!(timeout);
+    let restore = loop {
+        tokio::select! {
+            key_event = platform.poll() => match key_event {
+                KeyEvent::Pressed(Key::A) => break true,
+                KeyEvent::Pressed(Key::B) => break false,
+                _ => {}
+            },
+            _ = &mut timeout => break false,
+        }
+    };
+
+    Command::new("show").arg("-c").spawn()?.wait().await?;
+    Ok(restore)
+}
+
+/// Backs up the auto state of the running game, which RetroArch overwrites when it is terminated.
+fn backup_auto_state() -> Result<()> {
+    if let Some(game_info) = GameInfo::load()?.filter(|game_info| game_info.has_menu) {
+        if let Err(e) = save_state::backup(&game_info.path, &game_info.core, save_state::AUTO_SLOT)
+        {
+            warn!("failed to back up save state: {}", e);
+        }
+    }
+    Ok(())
+}
+
+/// Completes a profile switch requested by the launcher. Must only be called while no game or
+/// launcher is running.
+fn switch_profile() {
+    match Profiles::default().complete_switch() {
+        Ok(true) => info!("switched profile to {}", Profiles::default().current()),
+        Ok(false) => {}
+        Err(e) => error!("failed to switch profile: {}", e),
+    }
+}
+
 #[allow(clippy::needless_pass_by_ref_mut)]
 async fn terminate(child: &mut Child) -> Result<()> {
     #[cfg(unix)]
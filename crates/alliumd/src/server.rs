@@ -0,0 +1,627 @@
+//! HTTP/JSON API for managing Allium from another device on the same network. It is enabled in
+//! the Wi-Fi settings, and served on `ALLIUM_API_PORT`. Every request must send the token shown in
+//! the Wi-Fi settings as `Authorization: Bearer <token>`.
+//!
+//! - `GET /api/games?q=<query>&limit=<n>`: games in the library, optionally filtered using the
+//!   search syntax. Games hidden by parental controls are left out, here and in the statistics.
+//! - `GET /api/stats`: play statistics
+//! - `GET /api/game`: the running game, or `null`
+//! - `GET /api/battery`: battery percentage and whether it is charging
+//! - `GET /api/screenshots`: screenshot file names, newest first
+//! - `GET /api/screenshots/<name>`: a screenshot as PNG
+//! - `POST /api/launch` with `{"path": "<path>"}`: launches a game in `ALLIUM_GAMES_DIR`, quitting
+//!   the running game
+//! - `POST /api/quit`: quits the running game
+//! - `POST /api/rescan`: indexes games that were added, removed or renamed
+//!
+//! Errors are returned as `{"error": "<message>"}`.
+
+use std::fs::{self, File};
+use std::net::{Ipv4Addr, SocketAddr};
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use anyhow::Result;
+use axum::extract::{Path, Query, Request, State};
+use axum::http::{header, StatusCode};
+use axum::middleware::{self, Next};
+use axum::response::{IntoResponse, Response};
+use axum::routing::{get, post};
+use axum::{Json, Router};
+use common::battery::Battery;
+use common::constants::ALLIUM_API_PORT;
+use common::database::{Database, Game};
+use common::game_info::GameInfo;
+use log::{error, info};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use tokio::sync::{mpsc, watch};
+
+/// Number of games listed in each of the statistics.
+const STATS_LIMIT: i64 = 10;
+
+/// Requests that have to be handled by the event loop, because they manage processes.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Action {
+    Launch(PathBuf),
+    Quit,
+    Rescan,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct BatteryStatus {
+    pub percentage: i32,
+    pub charging: bool,
+}
+
+impl BatteryStatus {
+    pub fn new(battery: &impl Battery) -> Self {
+        Self {
+            percentage: battery.percentage(),
+            charging: battery.charging(),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct ServerState {
+    /// Opens the database. It is opened per request, because switching profiles replaces it.
+    pub database: Arc<dyn Fn() -> Result<Database> + Send + Sync>,
+    /// Path to the current game info. It is read directly, as `GameInfo::load` enables swap.
+    pub game_info: PathBuf,
+    pub screenshots: PathBuf,
+    /// Only games in this directory can be launched.
+    pub games_dir: PathBuf,
+    /// Token that requests must send, see the module documentation.
+    pub token: String,
+    pub battery: watch::Receiver<BatteryStatus>,
+    pub actions: mpsc::Sender<Action>,
+}
+
+pub fn router(state: ServerState) -> Router {
+    Router::new()
+        .route("/api/games", get(games))
+        .route("/api/stats", get(stats))
+        .route("/api/game", get(current_game))
+        .route("/api/battery", get(battery))
+        .route("/api/screenshots", get(screenshots))
+        .route("/api/screenshots/:name", get(screenshot))
+        .route("/api/launch", post(launch))
+        .route("/api/quit", post(quit))
+        .route("/api/rescan", post(rescan))
+        .route_layer(middleware::from_fn_with_state(state.clone(), authorize))
+        .with_state(state)
+}
+
+pub async fn serve(state: ServerState) -> Result<()> {
+    let addr = SocketAddr::from((Ipv4Addr::UNSPECIFIED, ALLIUM_API_PORT));
+    let listener = tokio::net::TcpListener::bind(addr).await?;
+    info!("serving API on {}", addr);
+    axum::serve(listener, router(state)).await?;
+    Ok(())
+}
+
+struct ApiError(StatusCode, String);
+
+impl From<anyhow::Error> for ApiError {
+    fn from(e: anyhow::Error) -> Self {
+        error!("API request failed: {}", e);
+        Self(StatusCode::INTERNAL_SERVER_ERROR, e.to_string())
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        (self.0, Json(json!({ "error": self.1 }))).into_response()
+    }
+}
+
+type ApiResult<T> = std::result::Result<T, ApiError>;
+
+async fn authorize(State(state): State<ServerState>, request: Request, next: Next) -> Response {
+    let token = request
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "));
+    if state.token.is_empty() || token != Some(state.token.as_str()) {
+        return ApiError(
+            StatusCode::UNAUTHORIZED,
+            "missing or invalid API token".to_string(),
+        )
+        .into_response();
+    }
+    next.run(request).await
+}
+
+/// Runs a query on a blocking thread, as the database can't be shared between threads.
+async fn with_database<T, F>(state: &ServerState, f: F) -> ApiResult<T>
+where
+    T: Send + 'static,
+    F: FnOnce(&Database) -> Result<T> + Send + 'static,
+{
+    let open = Arc::clone(&state.database);
+    let result = tokio::task::spawn_blocking(move || f(&open()?))
+        .await
+        .map_err(anyhow::Error::from)?;
+    Ok(result?)
+}
+
+#[derive(Debug, Serialize)]
+struct GameJson {
+    name: String,
+    path: PathBuf,
+    image: Option<PathBuf>,
+    play_count: i64,
+    /// Play time in seconds.
+    play_time: i64,
+    /// Unix timestamp, 0 if never played.
+    last_played: i64,
+    core: Option<String>,
+    rating: Option<u8>,
+    release_date: Option<String>,
+    developer: Option<String>,
+    publisher: Option<String>,
+    genres: Vec<String>,
+}
+
+impl From<Game> for GameJson {
+    fn from(game: Game) -> Self {
+        Self {
+            name: game.name,
+            path: game.path,
+            image: game.image,
+            play_count: game.play_count,
+            play_time: game.play_time.num_seconds(),
+            last_played: game.last_played,
+            core: game.core,
+            rating: game.rating,
+            release_date: game.release_date.map(|date| date.to_string()),
+            developer: game.developer,
+            publisher: game.publisher,
+            genres: game.genres,
+        }
+    }
+}
+
+fn to_json(games: Vec<Game>) -> Vec<GameJson> {
+    games.into_iter().map(GameJson::from).collect()
+}
+
+#[derive(Debug, Deserialize)]
+struct GamesQuery {
+    q: Option<String>,
+    limit: Option<i64>,
+}
+
+async fn games(
+    State(state): State<ServerState>,
+    Query(query): Query<GamesQuery>,
+) -> ApiResult<Json<Vec<GameJson>>> {
+    let limit = query.limit.unwrap_or(i64::MAX).max(0);
+    let games = with_database(&state, move |database| {
+        match query.q.as_deref().filter(|q| !q.trim().is_empty()) {
+            Some(q) => database.search(q, limit),
+            None => {
+                let mut games = database.select_visible_games()?;
+                games.sort_unstable_by(|a, b| a.name.cmp(&b.name));
+                games.truncate(limit.try_into().unwrap_or(usize::MAX));
+                Ok(games)
+            }
+        }
+    })
+    .await?;
+    Ok(Json(to_json(games)))
+}
+
+#[derive(Debug, Serialize)]
+struct Stats {
+    games: usize,
+    played: usize,
+    /// Total play time in seconds.
+    play_time: i64,
+    most_played: Vec<GameJson>,
+    last_played: Vec<GameJson>,
+}
+
+async fn stats(State(state): State<ServerState>) -> ApiResult<Json<Stats>> {
+    let stats = with_database(&state, |database| {
+        let games = database.select_visible_games()?;
+        Ok(Stats {
+            games: games.len(),
+            played: games.iter().filter(|game| game.last_played > 0).count(),
+            play_time: games.iter().map(|game| game.play_time.num_seconds()).sum(),
+            most_played: to_json(database.select_most_played(STATS_LIMIT)?),
+            last_played: to_json(database.select_last_played(STATS_LIMIT)?),
+        })
+    })
+    .await?;
+    Ok(Json(stats))
+}
+
+#[derive(Debug, Serialize)]
+struct CurrentGame {
+    name: String,
+    path: PathBuf,
+    core: String,
+    /// Time since the game was launched, in seconds.
+    play_time: i64,
+}
+
+fn load_game_info(state: &ServerState) -> Result<Option<GameInfo>> {
+    if !state.game_info.exists() {
+        return Ok(None);
+    }
+    let file = File::open(&state.game_info)?;
+    Ok(serde_json::from_reader(file).ok())
+}
+
+async fn current_game(State(state): State<ServerState>) -> ApiResult<Json<Option<CurrentGame>>> {
+    let game = load_game_info(&state)?.map(|game_info| CurrentGame {
+        play_time: game_info.play_time().num_seconds(),
+        name: game_info.name,
+        path: game_info.path,
+        core: game_info.core,
+    });
+    Ok(Json(game))
+}
+
+async fn battery(State(state): State<ServerState>) -> Json<BatteryStatus> {
+    Json(*state.battery.borrow())
+}
+
+async fn screenshots(State(state): State<ServerState>) -> ApiResult<Json<Vec<String>>> {
+    let mut names = Vec::new();
+    if state.screenshots.is_dir() {
+        for entry in fs::read_dir(&state.screenshots).map_err(anyhow::Error::from)? {
+            let name = entry
+                .map_err(anyhow::Error::from)?
+                .file_name()
+                .to_string_lossy()
+                .to_string();
+            if name.ends_with(".png") {
+                names.push(name);
+            }
+        }
+    }
+    // Screenshots are named after the time they were taken
+    names.sort_unstable_by(|a, b| b.cmp(a));
+    Ok(Json(names))
+}
+
+async fn screenshot(
+    State(state): State<ServerState>,
+    Path(name): Path<String>,
+) -> ApiResult<impl IntoResponse> {
+    if name.starts_with('.') || name.contains(['/', '\\']) || !name.ends_with(".png") {
+        return Err(ApiError(
+            StatusCode::BAD_REQUEST,
+            "invalid screenshot name".to_string(),
+        ));
+    }
+    let Ok(data) = fs::read(state.screenshots.join(&name)) else {
+        return Err(ApiError(
+            StatusCode::NOT_FOUND,
+            format!("screenshot {} not found", name),
+        ));
+    };
+    Ok(([(header::CONTENT_TYPE, "image/png")], data))
+}
+
+#[derive(Debug, Deserialize)]
+struct LaunchRequest {
+    path: PathBuf,
+}
+
+async fn launch(
+    State(state): State<ServerState>,
+    Json(request): Json<LaunchRequest>,
+) -> ApiResult<StatusCode> {
+    let Ok(path) = request.path.canonicalize() else {
+        return Err(ApiError(
+            StatusCode::NOT_FOUND,
+            format!("game {} not found", request.path.display()),
+        ));
+    };
+    let games_dir = state
+        .games_dir
+        .canonicalize()
+        .map_err(anyhow::Error::from)?;
+    if !path.starts_with(games_dir) {
+        return Err(ApiError(
+            StatusCode::FORBIDDEN,
+            format!("{} is not in the games directory", request.path.display()),
+        ));
+    }
+    send(&state, Action::Launch(path)).await
+}
+
+async fn quit(State(state): State<ServerState>) -> ApiResult<StatusCode> {
+    if !state.game_info.exists() {
+        return Err(ApiError(
+            StatusCode::CONFLICT,
+            "no game is running".to_string(),
+        ));
+    }
+    send(&state, Action::Quit).await
+}
+
+async fn rescan(State(state): State<ServerState>) -> ApiResult<StatusCode> {
+    send(&state, Action::Rescan).await
+}
+
+async fn send(state: &ServerState, action: Action) -> ApiResult<StatusCode> {
+    state
+        .actions
+        .send(action)
+        .await
+        .map_err(anyhow::Error::from)?;
+    Ok(StatusCode::ACCEPTED)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use axum::body::{to_bytes, Body};
+    use axum::http::Request;
+    use common::database::NewGame;
+    use common::platform::{DefaultPlatform, Platform};
+    use serde_json::Value;
+    use tower::ServiceExt;
+
+    use super::*;
+
+    struct Fixture {
+        dir: PathBuf,
+        router: Router,
+        actions: mpsc::Receiver<Action>,
+    }
+
+    impl Fixture {
+        fn new(name: &str) -> Result<Self> {
+            let dir = std::env::temp_dir().join(name);
+            let _ = fs::remove_dir_all(&dir);
+            fs::create_dir_all(dir.join("Screenshots"))?;
+            fs::create_dir_all(dir.join("Roms"))?;
+
+            let database = dir.join("allium.db");
+            Database::open(&database)?.update_games(&[NewGame {
+                name: "Tetris".to_string(),
+                path: dir.join("Roms/Tetris.gb"),
+                image: None,
+                core: None,
+                rating: None,
+                release_date: None,
+                developer: None,
+                publisher: None,
+                genres: Vec::new(),
+            }])?;
+            fs::write(dir.join("Roms/Tetris.gb"), "")?;
+
+            let platform = DefaultPlatform::new()?;
+            let (_, battery) = watch::channel(BatteryStatus::new(&platform.battery()?));
+            let (tx, actions) = mpsc::channel(1);
+
+            let router = router(ServerState {
+                database: Arc::new(move || Database::open(&database)),
+                game_info: dir.join("current_game"),
+                screenshots: dir.join("Screenshots"),
+                games_dir: dir.join("Roms"),
+                token: "secret".to_string(),
+                battery,
+                actions: tx,
+            });
+
+            Ok(Self {
+                dir,
+                router,
+                actions,
+            })
+        }
+
+        async fn request(
+            &self,
+            method: &str,
+            uri: &str,
+            body: Option<Value>,
+        ) -> (StatusCode, Vec<u8>) {
+            let request = Request::builder()
+                .method(method)
+                .uri(uri)
+                .header(header::AUTHORIZATION, "Bearer secret");
+            let request = match body {
+                Some(body) => request
+                    .header(header::CONTENT_TYPE, "application/json")
+                    .body(Body::from(body.to_string())),
+                None => request.body(Body::empty()),
+            }
+            .unwrap();
+            let response = self.router.clone().oneshot(request).await.unwrap();
+            let status = response.status();
+            let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+            (status, body.to_vec())
+        }
+
+        async fn get_json(&self, uri: &str) -> (StatusCode, Value) {
+            let (status, body) = self.request("GET", uri, None).await;
+            (status, serde_json::from_slice(&body).unwrap())
+        }
+    }
+
+    impl Drop for Fixture {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.dir);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_library() -> Result<()> {
+        let fixture = Fixture::new("allium-test-server-library")?;
+
+        let (status, games) = fixture.get_json("/api/games").await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(games[0]["name"], "Tetris");
+        assert_eq!(games[0]["play_count"], 0);
+
+        let (_, games) = fixture.get_json("/api/games?q=tetris").await;
+        assert_eq!(games.as_array().unwrap().len(), 1);
+        let (_, games) = fixture.get_json("/api/games?q=zelda").await;
+        assert_eq!(games.as_array().unwrap().len(), 0);
+
+        let (status, stats) = fixture.get_json("/api/stats").await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(stats["games"], 1);
+        assert_eq!(stats["played"], 0);
+
+        let (_, game) = fixture.get_json("/api/game").await;
+        assert_eq!(game, Value::Null);
+
+        let (status, battery) = fixture.get_json("/api/battery").await;
+        assert_eq!(status, StatusCode::OK);
+        assert!(battery["percentage"].is_number());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_hidden_games() -> Result<()> {
+        let fixture = Fixture::new("allium-test-server-hidden-games")?;
+        let hidden = fixture.dir.join("Roms/Horror.gb");
+        // The launcher hides games from its own connection
+        let database = Database::open(&fixture.dir.join("allium.db"))?;
+        database.update_games(&[NewGame {
+            name: "Horror".to_string(),
+            path: hidden.clone(),
+            image: None,
+            core: None,
+            rating: None,
+            release_date: None,
+            developer: None,
+            publisher: None,
+            genres: Vec::new(),
+        }])?;
+        database.set_hidden_games(&[hidden])?;
+
+        let (_, games) = fixture.get_json("/api/games").await;
+        assert_eq!(games.as_array().unwrap().len(), 1);
+        assert_eq!(games[0]["name"], "Tetris");
+        let (_, games) = fixture.get_json("/api/games?q=horror").await;
+        assert_eq!(games.as_array().unwrap().len(), 0);
+        let (_, stats) = fixture.get_json("/api/stats").await;
+        assert_eq!(stats["games"], 1);
+
+        database.set_hidden_games(&[])?;
+        let (_, games) = fixture.get_json("/api/games").await;
+        assert_eq!(games.as_array().unwrap().len(), 2);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_screenshots() -> Result<()> {
+        let fixture = Fixture::new("allium-test-server-screenshots")?;
+        let screenshots = fixture.dir.join("Screenshots");
+        fs::write(screenshots.join("2024-01-01_10-00-00-Tetris.png"), "old")?;
+        fs::write(screenshots.join("2024-02-01_10-00-00-Tetris.png"), "new")?;
+
+        let (_, names) = fixture.get_json("/api/screenshots").await;
+        assert_eq!(
+            names,
+            json!([
+                "2024-02-01_10-00-00-Tetris.png",
+                "2024-01-01_10-00-00-Tetris.png"
+            ])
+        );
+
+        let (status, body) = fixture
+            .request(
+                "GET",
+                "/api/screenshots/2024-01-01_10-00-00-Tetris.png",
+                None,
+            )
+            .await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body, b"old");
+
+        let (status, _) = fixture
+            .request("GET", "/api/screenshots/..%2Fallium.db", None)
+            .await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        let (status, _) = fixture
+            .request("GET", "/api/screenshots/missing.png", None)
+            .await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_actions() -> Result<()> {
+        let mut fixture = Fixture::new("allium-test-server-actions")?;
+        let game = fixture.dir.join("Roms/Tetris.gb");
+        fs::write(fixture.dir.join("allium.gb"), "")?;
+
+        let (status, _) = fixture
+            .request("POST", "/api/launch", Some(json!({ "path": game })))
+            .await;
+        assert_eq!(status, StatusCode::ACCEPTED);
+        assert_eq!(
+            fixture.actions.recv().await,
+            Some(Action::Launch(game.canonicalize()?))
+        );
+
+        // Only games in the games directory can be launched
+        for path in [
+            fixture.dir.join("allium.gb"),
+            fixture.dir.join("Roms/../allium.gb"),
+        ] {
+            let (status, _) = fixture
+                .request("POST", "/api/launch", Some(json!({ "path": path })))
+                .await;
+            assert_eq!(status, StatusCode::FORBIDDEN);
+        }
+
+        let (status, _) = fixture
+            .request(
+                "POST",
+                "/api/launch",
+                Some(json!({ "path": Path::new("/missing.gb") })),
+            )
+            .await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+
+        let (status, _) = fixture.request("POST", "/api/quit", None).await;
+        assert_eq!(status, StatusCode::CONFLICT);
+
+        let (status, _) = fixture.request("POST", "/api/rescan", None).await;
+        assert_eq!(status, StatusCode::ACCEPTED);
+        assert_eq!(fixture.actions.recv().await, Some(Action::Rescan));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_authorization() -> Result<()> {
+        let fixture = Fixture::new("allium-test-server-authorization")?;
+
+        for authorization in [None, Some("Bearer wrong"), Some("secret")] {
+            let request = Request::builder().uri("/api/games");
+            let request = match authorization {
+                Some(authorization) => request.header(header::AUTHORIZATION, authorization),
+                None => request,
+            };
+            let response = fixture
+                .router
+                .clone()
+                .oneshot(request.body(Body::empty())?)
+                .await?;
+            assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        }
+
+        let (status, _) = fixture.get_json("/api/games").await;
+        assert_eq!(status, StatusCode::OK);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,291 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use anyhow::Result;
+use chrono::{DateTime, Duration, Utc};
+
+use crate::constants::BATTERY_LOG_RETENTION;
+use crate::database::{BatteryReading, Database};
+
+/// Consecutive readings further apart than this are not compared, as the device was asleep or
+/// turned off in between.
+const MAX_READING_GAP_SECONDS: i64 = 5 * 60;
+
+/// How much discharge a rate must be based on before it is used for estimates.
+const MIN_RATE_SECONDS: i64 = 10 * 60;
+
+/// Estimates only use discharge from this long before the latest reading.
+const RECENT_HOURS: i64 = 24;
+
+/// Battery percentage lost over a period of discharge.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DischargeRate {
+    /// Percentage points lost.
+    pub drop: i32,
+    /// Time spent discharging.
+    pub duration: Duration,
+}
+
+impl DischargeRate {
+    fn add(&mut self, from: &BatteryReading, to: &BatteryReading) {
+        self.drop += from.percentage - to.percentage;
+        self.duration += to.time - from.time;
+    }
+
+    /// Whether there is enough discharge for the rate to be meaningful.
+    pub fn is_reliable(&self) -> bool {
+        self.drop > 0 && self.duration.num_seconds() >= MIN_RATE_SECONDS
+    }
+
+    pub fn percent_per_hour(&self) -> f64 {
+        match self.duration.num_seconds() {
+            0 => 0.0,
+            seconds => self.drop as f64 * 3600.0 / seconds as f64,
+        }
+    }
+
+    /// Time until the battery is empty, starting at `percentage`.
+    pub fn remaining(&self, percentage: i32) -> Option<Duration> {
+        if !self.is_reliable() {
+            return None;
+        }
+        Some(Duration::seconds(
+            percentage.max(0) as i64 * self.duration.num_seconds() / self.drop as i64,
+        ))
+    }
+}
+
+/// How fast a game drains the battery.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GameConsumption {
+    pub path: PathBuf,
+    pub core: Option<String>,
+    pub rate: DischargeRate,
+}
+
+/// The rolling battery log written by alliumd, oldest reading first.
+#[derive(Debug, Clone, Default)]
+pub struct BatteryLog {
+    readings: Vec<BatteryReading>,
+}
+
+impl BatteryLog {
+    pub fn new(readings: Vec<BatteryReading>) -> Self {
+        Self { readings }
+    }
+
+    /// Loads every reading that is still kept in the log.
+    pub fn load(database: &Database) -> Result<Self> {
+        let since = Utc::now() - Duration::from_std(BATTERY_LOG_RETENTION)?;
+        Ok(Self::new(database.select_battery_log(since)?))
+    }
+
+    pub fn readings(&self) -> &[BatteryReading] {
+        &self.readings
+    }
+
+    pub fn latest(&self) -> Option<&BatteryReading> {
+        self.readings.last()
+    }
+
+    /// Pairs of consecutive readings between which the battery was discharging while the same
+    /// game (or none) was running.
+    fn discharges(&self) -> impl Iterator<Item = (&BatteryReading, &BatteryReading)> {
+        self.readings.windows(2).filter_map(|pair| {
+            let [from, to] = pair else { unreachable!() };
+            let gap = (to.time - from.time).num_seconds();
+            (!from.charging
+                && !to.charging
+                && (0..=MAX_READING_GAP_SECONDS).contains(&gap)
+                && from.path == to.path)
+                .then_some((from, to))
+        })
+    }
+
+    /// Recent discharge rate while running `core`, or while no game was running if `None`.
+    pub fn discharge_rate(&self, core: Option<&str>) -> DischargeRate {
+        self.recent_rate(|reading| reading.core.as_deref() == core)
+    }
+
+    /// Recent discharge rate, regardless of what was running.
+    pub fn overall_rate(&self) -> DischargeRate {
+        self.recent_rate(|_| true)
+    }
+
+    fn recent_rate(&self, filter: impl Fn(&BatteryReading) -> bool) -> DischargeRate {
+        let Some(latest) = self.latest() else {
+            return DischargeRate::default();
+        };
+        let since = latest.time - Duration::hours(RECENT_HOURS);
+
+        let mut rate = DischargeRate::default();
+        for (from, to) in self.discharges() {
+            if from.time >= since && filter(from) {
+                rate.add(from, to);
+            }
+        }
+        rate
+    }
+
+    /// Estimates how long the battery will last at `percentage` while running `core`. Falls back
+    /// to the overall discharge rate if `core` hasn't been running long enough recently.
+    pub fn estimate_remaining(&self, percentage: i32, core: Option<&str>) -> Option<Duration> {
+        self.discharge_rate(core)
+            .remaining(percentage)
+            .or_else(|| self.overall_rate().remaining(percentage))
+    }
+
+    /// Games ordered by how fast they drain the battery, fastest first. Games that haven't been
+    /// played long enough for a reliable rate are left out.
+    pub fn game_ranking(&self) -> Vec<GameConsumption> {
+        let mut games: HashMap<&PathBuf, GameConsumption> = HashMap::new();
+        for (from, to) in self.discharges() {
+            let Some(path) = from.path.as_ref() else {
+                continue;
+            };
+            games
+                .entry(path)
+                .or_insert_with(|| GameConsumption {
+                    path: path.clone(),
+                    core: from.core.clone(),
+                    rate: DischargeRate::default(),
+                })
+                .rate
+                .add(from, to);
+        }
+
+        let mut games: Vec<_> = games
+            .into_values()
+            .filter(|game| game.rate.is_reliable())
+            .collect();
+        games.sort_by(|a, b| {
+            b.rate
+                .percent_per_hour()
+                .total_cmp(&a.rate.percent_per_hour())
+                .then_with(|| a.path.cmp(&b.path))
+        });
+        games
+    }
+
+    /// Battery percentage between `start` and `end`, split into `buckets` equal periods. Each
+    /// period holds its last reading, or `None` if there were none.
+    pub fn history(
+        &self,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+        buckets: usize,
+    ) -> Vec<Option<i32>> {
+        let mut history = vec![None; buckets];
+        let span = (end - start).num_seconds();
+        if buckets == 0 || span <= 0 {
+            return history;
+        }
+
+        for reading in &self.readings {
+            let offset = (reading.time - start).num_seconds();
+            if (0..span).contains(&offset) {
+                let bucket = (offset as i128 * buckets as i128 / span as i128) as usize;
+                history[bucket] = Some(reading.percentage);
+            }
+        }
+        history
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn reading(
+        minutes: i64,
+        percentage: i32,
+        charging: bool,
+        game: Option<&str>,
+    ) -> BatteryReading {
+        BatteryReading {
+            time: DateTime::from_timestamp(minutes * 60, 0).unwrap(),
+            percentage,
+            charging,
+            path: game.map(|game| PathBuf::from(format!("Roms/{game}"))),
+            core: game.map(|game| game.split('.').next_back().unwrap().to_string()),
+        }
+    }
+
+    #[test]
+    fn test_battery_log() {
+        let log = BatteryLog::new(vec![
+            // Menus: 2% per 20 minutes
+            reading(0, 100, false, None),
+            reading(5, 99, false, None),
+            reading(10, 99, false, None),
+            reading(15, 98, false, None),
+            reading(20, 98, false, None),
+            // Game One: 6% per 30 minutes
+            reading(21, 98, false, Some("Game One.gba")),
+            reading(26, 97, false, Some("Game One.gba")),
+            reading(31, 96, false, Some("Game One.gba")),
+            reading(36, 95, false, Some("Game One.gba")),
+            reading(41, 94, false, Some("Game One.gba")),
+            reading(46, 93, false, Some("Game One.gba")),
+            reading(51, 92, false, Some("Game One.gba")),
+            // Asleep for an hour, not counted
+            reading(111, 90, false, Some("Game One.gba")),
+            // Charging, not counted
+            reading(112, 91, true, None),
+            reading(117, 95, true, None),
+            // Game Two: 3% per 20 minutes
+            reading(122, 95, false, Some("Game Two.gba")),
+            reading(127, 94, false, Some("Game Two.gba")),
+            reading(132, 94, false, Some("Game Two.gba")),
+            reading(137, 93, false, Some("Game Two.gba")),
+            reading(142, 92, false, Some("Game Two.gba")),
+            // Game Three: too short to rank
+            reading(143, 92, false, Some("Game Three.sfc")),
+            reading(144, 91, false, Some("Game Three.sfc")),
+        ]);
+
+        let rate = log.discharge_rate(None);
+        assert_eq!(rate.drop, 2);
+        assert_eq!(rate.duration, Duration::minutes(20));
+        assert_eq!(
+            log.estimate_remaining(50, None),
+            Some(Duration::minutes(500))
+        );
+
+        let rate = log.discharge_rate(Some("gba"));
+        assert_eq!(rate.drop, 9);
+        assert_eq!(rate.duration, Duration::minutes(50));
+        assert_eq!(rate.percent_per_hour(), 10.8);
+        assert_eq!(
+            log.estimate_remaining(9, Some("gba")),
+            Some(Duration::minutes(50))
+        );
+
+        // Falls back to the overall rate: 12% per 71 minutes
+        assert!(!log.discharge_rate(Some("sfc")).is_reliable());
+        assert_eq!(
+            log.estimate_remaining(12, Some("sfc")),
+            Some(Duration::minutes(71))
+        );
+        assert_eq!(BatteryLog::default().estimate_remaining(50, None), None);
+
+        let ranking = log.game_ranking();
+        assert_eq!(
+            ranking
+                .iter()
+                .map(|game| game.path.to_str().unwrap())
+                .collect::<Vec<_>>(),
+            vec!["Roms/Game One.gba", "Roms/Game Two.gba"]
+        );
+        assert_eq!(ranking[0].core.as_deref(), Some("gba"));
+        assert_eq!(ranking[0].rate.percent_per_hour(), 12.0);
+        assert_eq!(ranking[1].rate.percent_per_hour(), 9.0);
+
+        let start = DateTime::from_timestamp(0, 0).unwrap();
+        let end = DateTime::from_timestamp(150 * 60, 0).unwrap();
+        assert_eq!(
+            log.history(start, end, 5),
+            vec![Some(97), Some(92), None, Some(95), Some(91)]
+        );
+    }
+}
@@ -2,6 +2,7 @@ use std::time::Duration;
 
 use crate::display::color::Color;
 use crate::locale::LocaleSettings;
+use crate::parental::ParentalSettings;
 use crate::{display::settings::DisplaySettings, stylesheet::Stylesheet};
 
 #[derive(Debug)]
@@ -11,6 +12,7 @@ pub enum Command {
     SaveStylesheet(Box<Stylesheet>),
     SaveDisplaySettings(Box<DisplaySettings>),
     SaveLocaleSettings(LocaleSettings),
+    SaveParentalSettings(Box<ParentalSettings>),
     CloseView,
     ValueChanged(usize, Value),
     TrapFocus,
@@ -20,6 +22,13 @@ pub enum Command {
     Search(String),
     Toast(String, Option<Duration>),
     PopulateDb,
+    IndexProgress {
+        indexed: usize,
+        total: usize,
+    },
+    IndexFinished {
+        changed: bool,
+    },
     SaveStateScreenshot {
         path: String,
         core: String,
@@ -30,6 +30,11 @@ lazy_static! {
     pub static ref ALLIUM_LOCALES_DIR: PathBuf = ALLIUM_BASE_DIR.join("locales");
     pub static ref ALLIUM_IMAGES_DIR: PathBuf = ALLIUM_BASE_DIR.join("images");
     pub static ref ALLIUM_SCREENSHOTS_DIR: PathBuf = ALLIUM_BASE_DIR.join("screenshots");
+    pub static ref ALLIUM_COLLECTIONS_DIR: PathBuf = ALLIUM_BASE_DIR.join("collections");
+    pub static ref ALLIUM_DATS_DIR: PathBuf = ALLIUM_BASE_DIR.join("dats");
+    pub static ref ALLIUM_THEMES_DIR: PathBuf = ALLIUM_BASE_DIR.join("themes");
+    pub static ref ALLIUM_EXPORT_DIR: PathBuf = ALLIUM_BASE_DIR.join("export");
+    pub static ref ALLIUM_IMPORT_DIR: PathBuf = ALLIUM_BASE_DIR.join("import");
 
     // Config
     pub static ref ALLIUM_CONFIG_CONSOLES: PathBuf = ALLIUM_BASE_DIR.join("config/consoles.toml");
@@ -42,22 +47,41 @@ lazy_static! {
     pub static ref ALLIUM_MENU_STATE: PathBuf =
         ALLIUM_BASE_DIR.join("state/allium-menu.json");
     pub static ref ALLIUM_GAME_INFO: PathBuf = ALLIUM_BASE_DIR.join("state/current_game");
+    pub static ref ALLIUM_LOW_BATTERY_SAVE: PathBuf =
+        ALLIUM_BASE_DIR.join("state/low_battery_save.json");
+    pub static ref ALLIUM_CPU_DEFAULTS: PathBuf = ALLIUM_BASE_DIR.join("state/cpu-defaults.json");
     pub static ref ALLIUM_STYLESHEET: PathBuf = ALLIUM_BASE_DIR.join("state/stylesheet.json");
     pub static ref ALLIUM_DISPLAY_SETTINGS: PathBuf = ALLIUM_BASE_DIR.join("state/display.json");
     pub static ref ALLIUM_LOCALE_SETTINGS: PathBuf = ALLIUM_BASE_DIR.join("state/locale.json");
     pub static ref ALLIUM_POWER_SETTINGS: PathBuf = ALLIUM_BASE_DIR.join("state/power.json");
+    pub static ref ALLIUM_SAVE_STATE_SETTINGS: PathBuf =
+        ALLIUM_BASE_DIR.join("state/save-states.json");
     pub static ref ALLIUM_WIFI_SETTINGS: PathBuf = ALLIUM_BASE_DIR.join("state/wifi.json");
+    pub static ref ALLIUM_RETROACHIEVEMENTS_SETTINGS: PathBuf =
+        ALLIUM_BASE_DIR.join("state/retroachievements.json");
+    pub static ref ALLIUM_HOTKEY_SETTINGS: PathBuf = ALLIUM_BASE_DIR.join("state/hotkeys.json");
+    pub static ref ALLIUM_PARENTAL_SETTINGS: PathBuf =
+        ALLIUM_BASE_DIR.join("state/parental.json");
     pub static ref ALLIUM_TIMEZONE: PathBuf = ALLIUM_BASE_DIR.join("state/timezone");
+    pub static ref ALLIUM_PROFILE_SWITCH: PathBuf = ALLIUM_BASE_DIR.join("state/profile-switch");
+    pub static ref ALLIUM_LAUNCHER_REQUEST: PathBuf =
+        ALLIUM_BASE_DIR.join("state/launcher-request.json");
 
     // Database
     pub static ref ALLIUM_DATABASE: PathBuf = env::var("ALLIUM_DATABASE")
         .map(PathBuf::from)
         .unwrap_or_else(|_| ALLIUM_SD_ROOT.join("Saves/CurrentProfile/allium.db"));
+    pub static ref ALLIUM_SAVE_STATES_DIR: PathBuf = ALLIUM_SD_ROOT.join("Saves/CurrentProfile/states");
 
     // Binaries & Scripts
     pub static ref ALLIUM_LAUNCHER: PathBuf = ALLIUM_BASE_DIR.join("bin/allium-launcher");
     pub static ref ALLIUM_MENU: PathBuf = ALLIUM_BASE_DIR.join("bin/allium-menu");
     pub static ref ALLIUM_RETROARCH: PathBuf = ALLIUM_BASE_DIR.join("cores/retroarch/launch.sh");
+    pub static ref ALLIUM_RETROARCH_CORES_DIR: PathBuf =
+        ALLIUM_SD_ROOT.join("RetroArch/.retroarch/cores");
+    /// Appended to the RetroArch config by the launcher, with the launch profile's configs.
+    pub static ref ALLIUM_RETROARCH_CHEEVOS_CONFIG: PathBuf =
+        ALLIUM_SD_ROOT.join("RetroArch/.retroarch/cheevos.cfg");
 }
 
 // Styles
@@ -65,12 +89,32 @@ pub const IMAGE_WIDTH: u32 = 250;
 pub const SELECTION_MARGIN: u32 = 8;
 pub const SAVE_STATE_IMAGE_WIDTH: u32 = 333;
 
-/// After the battery level drops below this threshold, the device will shut down.
+/// Port that alliumd serves the HTTP API on.
+pub const ALLIUM_API_PORT: u16 = 8080;
+
+/// After the battery level drops below this threshold, the device will shut down. This is the
+/// default of the critical battery level in power settings.
 pub const BATTERY_SHUTDOWN_THRESHOLD: i32 = 5;
 
 /// The interval at which the battery level is updated.
 pub const BATTERY_UPDATE_INTERVAL: Duration = Duration::from_secs(10);
 
+/// How long warnings from alliumd, e.g. low battery, are shown for.
+pub const WARNING_DURATION: Duration = Duration::from_secs(3);
+
+/// How long before play time runs out under the sleep schedule to warn.
+pub const SCHEDULE_WARNING_TIME: Duration = Duration::from_secs(5 * 60);
+
+/// How long to wait for an answer when offering to restore the state saved on a critical battery,
+/// before resuming from the auto state.
+pub const LOW_BATTERY_RESTORE_TIMEOUT: Duration = Duration::from_secs(15);
+
+/// The interval at which the battery level is written to the battery log.
+pub const BATTERY_LOG_INTERVAL: Duration = Duration::from_secs(60);
+
+/// How long battery readings are kept in the battery log.
+pub const BATTERY_LOG_RETENTION: Duration = Duration::from_secs(7 * 24 * 60 * 60);
+
 /// The interval at which the clock is updated.
 pub const CLOCK_UPDATE_INTERVAL: Duration = Duration::from_secs(60);
 
@@ -86,6 +130,13 @@ pub const MAXIMUM_FRAME_TIME: Duration = Duration::from_millis(100);
 /// Maximum number of recent games to retrieve from the database.
 pub const RECENT_GAMES_LIMIT: i64 = 100;
 
+/// ROMs larger than this are not hashed while indexing, as reading them from the SD card is too slow.
+/// Disc images are identified by their .cue instead.
+pub const MAX_HASH_SIZE: u64 = 128 * 1024 * 1024;
+
+/// Default RetroAchievements server.
+pub const RETROACHIEVEMENTS_SERVER_URL: &str = "https://retroachievements.org";
+
 /// RetroArch network command interface.
 pub const RETROARCH_UDP_SOCKET: &str = "127.0.0.1:55355";
 
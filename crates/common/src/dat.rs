@@ -0,0 +1,528 @@
+use std::collections::HashMap;
+use std::ffi::OsStr;
+use std::fs;
+use std::iter::Peekable;
+use std::path::Path;
+use std::str::CharIndices;
+
+use anyhow::{anyhow, bail, Result};
+use chrono::{NaiveDate, NaiveDateTime};
+use log::{debug, error};
+use serde::Deserialize;
+
+use crate::hash::RomHash;
+
+/// A game from a No-Intro or Redump DAT.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DatGame {
+    /// Full name, e.g. "Pokemon - Emerald Version (USA, Europe) (Rev 1)".
+    pub name: String,
+    /// Canonical title without tags, e.g. "Pokemon - Emerald Version".
+    pub title: String,
+    /// e.g. "USA, Europe".
+    pub region: Option<String>,
+    /// e.g. "Rev 1" or "v1.1".
+    pub revision: Option<String>,
+}
+
+impl DatGame {
+    /// Parses a name following the No-Intro naming convention, where the region is the first tag.
+    pub fn from_name(name: String) -> Self {
+        let (title, tags) = match name.find(" (") {
+            Some(i) => (name[..i].trim(), &name[i..]),
+            None => (name.trim(), ""),
+        };
+
+        let mut tags = tags
+            .split(['(', ')'])
+            .map(str::trim)
+            .filter(|tag| !tag.is_empty());
+        let region = tags.next().map(str::to_string);
+        let revision = tags
+            .find(|tag| {
+                tag.starts_with("Rev ")
+                    || (tag.starts_with('v') && tag[1..].starts_with(|c: char| c.is_ascii_digit()))
+            })
+            .map(str::to_string);
+
+        Self {
+            title: title.to_string(),
+            region,
+            revision,
+            name,
+        }
+    }
+}
+
+/// No-Intro and Redump DATs, indexed by hash. Both the Logiqx XML format and clrmamepro DATs are
+/// supported.
+#[derive(Debug, Default)]
+pub struct Dat {
+    games: Vec<DatGame>,
+    by_sha1: HashMap<String, usize>,
+    by_md5: HashMap<String, usize>,
+    by_crc32: HashMap<(u32, u64), usize>,
+}
+
+#[derive(Debug, Deserialize)]
+struct DataFile {
+    #[serde(default, rename = "game")]
+    games: Vec<DataFileGame>,
+}
+
+#[derive(Debug, Deserialize)]
+struct DataFileGame {
+    #[serde(rename = "@name")]
+    name: String,
+    #[serde(default, rename = "rom")]
+    roms: Vec<DataFileRom>,
+}
+
+#[derive(Debug, Deserialize)]
+struct DataFileRom {
+    #[serde(default, rename = "@size")]
+    size: Option<u64>,
+    #[serde(default, rename = "@crc")]
+    crc: Option<String>,
+    #[serde(default, rename = "@md5")]
+    md5: Option<String>,
+    #[serde(default, rename = "@sha1")]
+    sha1: Option<String>,
+}
+
+impl Dat {
+    /// Loads every .dat and .xml file in a directory. Files that fail to parse are skipped.
+    pub fn load_dir(dir: &Path) -> Result<Self> {
+        let mut dat = Self::default();
+        if !dir.is_dir() {
+            return Ok(dat);
+        }
+
+        for entry in fs::read_dir(dir)? {
+            let path = entry?.path();
+            if !matches!(
+                path.extension().and_then(OsStr::to_str),
+                Some("dat") | Some("xml")
+            ) {
+                continue;
+            }
+            if let Err(e) = dat.load(&path) {
+                error!("failed to load DAT {}: {}", path.display(), e);
+            }
+        }
+
+        Ok(dat)
+    }
+
+    pub fn load(&mut self, path: &Path) -> Result<()> {
+        let count = self.games.len();
+        self.parse(&fs::read_to_string(path)?)?;
+        debug!(
+            "loaded {} games from {}",
+            self.games.len() - count,
+            path.display()
+        );
+        Ok(())
+    }
+
+    pub fn parse(&mut self, s: &str) -> Result<()> {
+        if !s.trim_start().starts_with('<') {
+            for game in parse_clrmamepro(s)? {
+                let Some(name) = game.name else {
+                    continue;
+                };
+                let i = self.games.len();
+                for rom in game.roms {
+                    self.insert_rom(i, rom.size, rom.crc, rom.md5, rom.sha1);
+                }
+                self.games.push(DatGame::from_name(name));
+            }
+            return Ok(());
+        }
+
+        let file: DataFile = quick_xml::de::from_str(s)?;
+
+        for game in file.games {
+            let i = self.games.len();
+            for rom in game.roms {
+                let crc = rom.crc.and_then(|crc| u32::from_str_radix(&crc, 16).ok());
+                self.insert_rom(i, rom.size, crc, rom.md5, rom.sha1);
+            }
+            self.games.push(DatGame::from_name(game.name));
+        }
+
+        Ok(())
+    }
+
+    fn insert_rom(
+        &mut self,
+        i: usize,
+        size: Option<u64>,
+        crc: Option<u32>,
+        md5: Option<String>,
+        sha1: Option<String>,
+    ) {
+        if let Some(sha1) = sha1 {
+            self.by_sha1.entry(sha1.to_lowercase()).or_insert(i);
+        }
+        if let Some(md5) = md5 {
+            self.by_md5.entry(md5.to_lowercase()).or_insert(i);
+        }
+        if let (Some(crc), Some(size)) = (crc, size) {
+            self.by_crc32.entry((crc, size)).or_insert(i);
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.games.is_empty()
+    }
+
+    /// Finds a game by SHA1, falling back to MD5, then CRC32 and size.
+    pub fn find(&self, hash: &RomHash) -> Option<&DatGame> {
+        self.by_sha1
+            .get(&hash.sha1)
+            .or_else(|| self.by_md5.get(&hash.md5))
+            .or_else(|| self.by_crc32.get(&(hash.crc32, hash.size)))
+            .map(|&i| &self.games[i])
+    }
+}
+
+/// A game from a clrmamepro DAT, with the metadata that libretro-database's `metadat/` directory
+/// adds to it.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ClrMameProGame {
+    pub name: Option<String>,
+    pub developer: Option<String>,
+    pub publisher: Option<String>,
+    pub genres: Vec<String>,
+    /// Rating out of 10, like `crate::gamelist::Game::rating`.
+    pub rating: Option<u8>,
+    pub release_date: Option<NaiveDateTime>,
+    pub roms: Vec<ClrMameProRom>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ClrMameProRom {
+    pub name: Option<String>,
+    pub size: Option<u64>,
+    pub crc: Option<u32>,
+    pub md5: Option<String>,
+    pub sha1: Option<String>,
+}
+
+/// Parses a clrmamepro DAT, as used by libretro-database's `dat/` and `metadat/` directories.
+///
+/// ```text
+/// game (
+///     name "Advance Wars (USA)"
+///     developer "Intelligent Systems"
+///     releaseyear "2001"
+///     rom ( name "Advance Wars (USA).gba" size 8388608 crc 5AD0E571 )
+/// )
+/// ```
+pub fn parse_clrmamepro(s: &str) -> Result<Vec<ClrMameProGame>> {
+    let mut tokens = Tokenizer::new(s).peekable();
+    let mut games = Vec::new();
+
+    while let Some(key) = tokens.next() {
+        let key = key?;
+        let value = parse_value(&mut tokens)?;
+        if let (Token::Word(key), Value::List(fields)) = (key, value) {
+            if key == "game" || key == "machine" {
+                games.push(parse_game(fields));
+            }
+        }
+    }
+
+    Ok(games)
+}
+
+fn parse_game(fields: Vec<(String, Value)>) -> ClrMameProGame {
+    let mut game = ClrMameProGame::default();
+    let mut year = None;
+    let mut month = None;
+    let mut day = None;
+
+    for (key, value) in fields {
+        match (key.as_str(), value) {
+            ("name", Value::String(name)) => game.name = Some(name),
+            ("developer", Value::String(developer)) => game.developer = Some(developer),
+            ("publisher", Value::String(publisher)) => game.publisher = Some(publisher),
+            ("genre", Value::String(genre)) => game
+                .genres
+                .extend(genre.split(',').map(str::trim).map(str::to_string)),
+            ("releaseyear", Value::String(s)) => year = s.parse::<i32>().ok(),
+            ("releasemonth", Value::String(s)) => month = s.parse::<u32>().ok(),
+            ("releaseday", Value::String(s)) => day = s.parse::<u32>().ok(),
+            ("rating", Value::String(s)) => {
+                // Either 0-1 like gamelist.xml, or 0-10.
+                game.rating = s.parse::<f32>().ok().map(|rating| {
+                    if rating <= 1.0 {
+                        (rating * 10.0).round() as u8
+                    } else {
+                        rating.min(10.0).round() as u8
+                    }
+                })
+            }
+            ("edge_rating", Value::String(s)) if game.rating.is_none() => {
+                game.rating = s.parse::<u8>().ok().map(|rating| rating.min(10));
+            }
+            ("famitsu_rating", Value::String(s)) if game.rating.is_none() => {
+                game.rating = s.parse::<u8>().ok().map(|rating| rating.min(40) / 4);
+            }
+            ("rom", Value::List(fields)) => {
+                let mut rom = ClrMameProRom::default();
+                for (key, value) in fields {
+                    match (key.as_str(), value) {
+                        ("name", Value::String(name)) => rom.name = Some(name),
+                        ("size", Value::String(size)) => rom.size = size.parse().ok(),
+                        ("crc", Value::String(crc)) => rom.crc = u32::from_str_radix(&crc, 16).ok(),
+                        ("md5", Value::String(md5)) => rom.md5 = Some(md5),
+                        ("sha1", Value::String(sha1)) => rom.sha1 = Some(sha1),
+                        _ => {}
+                    }
+                }
+                game.roms.push(rom);
+            }
+            _ => {}
+        }
+    }
+
+    game.release_date = year
+        .and_then(|year| NaiveDate::from_ymd_opt(year, month.unwrap_or(1), day.unwrap_or(1)))
+        .map(|date| NaiveDateTime::new(date, Default::default()));
+
+    game
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Value {
+    String(String),
+    List(Vec<(String, Value)>),
+}
+
+fn parse_value<'a>(tokens: &mut Peekable<Tokenizer<'a>>) -> Result<Value> {
+    match tokens.next().transpose()? {
+        Some(Token::Word(s)) | Some(Token::String(s)) => Ok(Value::String(s)),
+        Some(Token::Open) => {
+            let mut fields = Vec::new();
+            loop {
+                match tokens.next().transpose()? {
+                    Some(Token::Close) => return Ok(Value::List(fields)),
+                    Some(Token::Word(key)) | Some(Token::String(key)) => {
+                        fields.push((key, parse_value(tokens)?));
+                    }
+                    Some(Token::Open) => bail!("unexpected '('"),
+                    None => bail!("unexpected end of file, expected ')'"),
+                }
+            }
+        }
+        Some(Token::Close) => bail!("unexpected ')'"),
+        None => Err(anyhow!("unexpected end of file, expected value")),
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Token {
+    Open,
+    Close,
+    Word(String),
+    String(String),
+}
+
+struct Tokenizer<'a> {
+    s: &'a str,
+    chars: Peekable<CharIndices<'a>>,
+}
+
+impl<'a> Tokenizer<'a> {
+    fn new(s: &'a str) -> Self {
+        Self {
+            s,
+            chars: s.char_indices().peekable(),
+        }
+    }
+}
+
+impl<'a> Iterator for Tokenizer<'a> {
+    type Item = Result<Token>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.chars.next_if(|(_, c)| c.is_whitespace()).is_some() {}
+
+        let (start, c) = self.chars.next()?;
+        Some(match c {
+            '(' => Ok(Token::Open),
+            ')' => Ok(Token::Close),
+            '"' => {
+                let mut s = String::new();
+                loop {
+                    match self.chars.next() {
+                        Some((_, '"')) => break Ok(Token::String(s)),
+                        Some((_, '\\')) => {
+                            if let Some((_, c)) = self.chars.next() {
+                                s.push(c);
+                            }
+                        }
+                        Some((_, c)) => s.push(c),
+                        None => break Err(anyhow!("unterminated string at {}", start)),
+                    }
+                }
+            }
+            _ => {
+                let mut end = start + c.len_utf8();
+                while let Some((i, c)) = self
+                    .chars
+                    .next_if(|(_, c)| !c.is_whitespace() && *c != '(' && *c != ')')
+                {
+                    end = i + c.len_utf8();
+                }
+                Ok(Token::Word(self.s[start..end].to_string()))
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_name() {
+        let game =
+            DatGame::from_name("Pokemon - Emerald Version (USA, Europe) (Rev 1)".to_string());
+        assert_eq!(game.title, "Pokemon - Emerald Version");
+        assert_eq!(game.region.as_deref(), Some("USA, Europe"));
+        assert_eq!(game.revision.as_deref(), Some("Rev 1"));
+
+        let game = DatGame::from_name("Tetris (World) (En,Ja) (v1.1)".to_string());
+        assert_eq!(game.title, "Tetris");
+        assert_eq!(game.region.as_deref(), Some("World"));
+        assert_eq!(game.revision.as_deref(), Some("v1.1"));
+
+        let game = DatGame::from_name("Vib-Ribbon".to_string());
+        assert_eq!(game.title, "Vib-Ribbon");
+        assert_eq!(game.region, None);
+        assert_eq!(game.revision, None);
+    }
+
+    #[test]
+    fn test_find() -> Result<()> {
+        let mut dat = Dat::default();
+        dat.parse(
+            r#"<?xml version="1.0"?>
+<!DOCTYPE datafile PUBLIC "-//Logiqx//DTD ROM Management Datafile//EN" "http://www.logiqx.com/Dats/datafile.dtd">
+<datafile>
+	<header>
+		<name>Nintendo - Game Boy Advance</name>
+		<version>20230101-000000</version>
+	</header>
+	<game name="Tetris (World) (v1.1)">
+		<description>Tetris (World) (v1.1)</description>
+		<rom name="Tetris (World) (v1.1).gba" size="3" crc="352441C2" md5="900150983CD24FB0D6963F7D28E17F72" sha1="A9993E364706816ABA3E25717850C26C9CD0D89D" status="verified"/>
+	</game>
+	<game name="Final Fantasy VII (USA) (Disc 1)">
+		<category>Games</category>
+		<description>Final Fantasy VII (USA) (Disc 1)</description>
+		<rom name="Final Fantasy VII (USA) (Disc 1).cue" size="84" crc="7fa2c5bc"/>
+		<rom name="Final Fantasy VII (USA) (Disc 1).bin" size="747435024" crc="1459cbef"/>
+	</game>
+</datafile>"#,
+        )?;
+        assert!(!dat.is_empty());
+
+        let tetris = RomHash {
+            size: 3,
+            crc32: 0x352441c2,
+            md5: "900150983cd24fb0d6963f7d28e17f72".to_string(),
+            sha1: "a9993e364706816aba3e25717850c26c9cd0d89d".to_string(),
+        };
+        assert_eq!(dat.find(&tetris).unwrap().title, "Tetris");
+
+        let cue = RomHash {
+            size: 84,
+            crc32: 0x7fa2c5bc,
+            md5: String::new(),
+            sha1: String::new(),
+        };
+        let game = dat.find(&cue).unwrap();
+        assert_eq!(game.title, "Final Fantasy VII");
+        assert_eq!(game.region.as_deref(), Some("USA"));
+
+        // CRC32 only matches with the same size
+        assert_eq!(dat.find(&RomHash { size: 85, ..cue }), None);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_clrmamepro() {
+        let dat = r#"
+clrmamepro (
+	name "Nintendo - Game Boy Advance"
+	version "2023.01.01"
+)
+
+game (
+	name "Advance Wars (USA)"
+	description "Advance Wars (USA)"
+	developer "Intelligent Systems"
+	publisher "Nintendo"
+	genre "Strategy"
+	releaseyear "2001"
+	releasemonth "9"
+	edge_rating "8"
+	rom ( name "Advance Wars (USA).gba" size 8388608 crc 5AD0E571 md5 4A1A4B6A serial "AWRE" )
+)
+
+game (
+	name "Golden Sun (USA, Europe)"
+	rom ( name "Golden Sun (USA, Europe).gba" size 8388608 crc 3D0C49B1 )
+)
+"#;
+        let games = parse_clrmamepro(dat).unwrap();
+        assert_eq!(games.len(), 2);
+
+        assert_eq!(games[0].name.as_deref(), Some("Advance Wars (USA)"));
+        assert_eq!(games[0].developer.as_deref(), Some("Intelligent Systems"));
+        assert_eq!(games[0].publisher.as_deref(), Some("Nintendo"));
+        assert_eq!(games[0].genres, vec!["Strategy".to_string()]);
+        assert_eq!(games[0].rating, Some(8));
+        assert_eq!(
+            games[0].release_date,
+            NaiveDate::from_ymd_opt(2001, 9, 1)
+                .unwrap()
+                .and_hms_opt(0, 0, 0)
+        );
+        assert_eq!(
+            games[0].roms,
+            vec![ClrMameProRom {
+                name: Some("Advance Wars (USA).gba".to_string()),
+                size: Some(8388608),
+                crc: Some(0x5AD0E571),
+                md5: Some("4A1A4B6A".to_string()),
+                sha1: None,
+            }]
+        );
+
+        assert_eq!(games[1].name.as_deref(), Some("Golden Sun (USA, Europe)"));
+        assert_eq!(games[1].release_date, None);
+        assert_eq!(games[1].roms[0].crc, Some(0x3D0C49B1));
+
+        let mut dat_file = Dat::default();
+        dat_file.parse(dat).unwrap();
+        let advance_wars = RomHash {
+            size: 8388608,
+            crc32: 0x5AD0E571,
+            md5: String::new(),
+            sha1: String::new(),
+        };
+        assert_eq!(dat_file.find(&advance_wars).unwrap().title, "Advance Wars");
+    }
+
+    #[test]
+    fn test_parse_clrmamepro_unterminated() {
+        assert!(parse_clrmamepro("game ( name \"Advance Wars").is_err());
+        assert!(parse_clrmamepro("game ( name \"Advance Wars\"").is_err());
+    }
+}
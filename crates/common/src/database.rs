@@ -4,12 +4,21 @@ use std::{
 };
 
 use anyhow::{Context, Result};
-use chrono::{Duration, NaiveDate};
+use chrono::{DateTime, Duration, NaiveDate, Utc};
 use log::{info, trace};
-use rusqlite::{params, Connection, OptionalExtension, Row};
+use rusqlite::types::Value;
+use rusqlite::{params, params_from_iter, Connection, OptionalExtension, Row};
 use rusqlite_migration::{Migrations, M};
+use serde::{Deserialize, Serialize};
 
 use crate::constants::{ALLIUM_BASE_DIR, ALLIUM_DATABASE};
+use crate::hash::RomHash;
+use crate::launch_profile::{LaunchProfile, LaunchProfileScope};
+use crate::search::Query;
+
+/// Condition that leaves out games hidden by parental controls, see
+/// [`Database::set_hidden_games`].
+const NOT_HIDDEN: &str = "games.path NOT IN (SELECT path FROM hidden_games)";
 
 #[derive(Debug, Clone, Default)]
 pub struct Database {
@@ -32,6 +41,83 @@ pub struct Game {
     pub genres: Vec<String>,
 }
 
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Collection {
+    pub id: i64,
+    pub name: String,
+}
+
+/// Metadata column that games can be grouped by.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum GroupBy {
+    Genre,
+    Developer,
+    Publisher,
+    /// Groups are the first year of each decade, e.g. "1990".
+    Decade,
+}
+
+/// Size and modification time of a directory's gamelist when it was last parsed. Both are compared,
+/// as a gamelist edited in place may keep the same size.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GamelistFingerprint {
+    pub size: u64,
+    /// Modification time in nanoseconds since the Unix epoch.
+    pub mtime: i64,
+}
+
+/// Hashes of a game's ROM, and its canonical title, region and revision if it was found in a DAT.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GameHash {
+    pub path: PathBuf,
+    pub hash: RomHash,
+    pub title: Option<String>,
+    pub region: Option<String>,
+    pub revision: Option<String>,
+}
+
+/// Play statistics, metadata and ROM hashes of a game, as exported to move play history between
+/// devices.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ExportedGame {
+    pub path: PathBuf,
+    pub name: String,
+    pub play_count: i64,
+    /// Play time in seconds.
+    pub play_time: i64,
+    pub last_played: i64,
+    pub core: Option<String>,
+    pub rating: Option<u8>,
+    pub release_date: Option<NaiveDate>,
+    pub developer: Option<String>,
+    pub publisher: Option<String>,
+    pub genres: Vec<String>,
+    pub size: Option<u64>,
+    pub crc32: Option<u32>,
+    pub md5: Option<String>,
+    pub sha1: Option<String>,
+}
+
+/// A single play session of a game.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Session {
+    pub path: PathBuf,
+    pub core: Option<String>,
+    pub start: DateTime<Utc>,
+    pub end: DateTime<Utc>,
+    pub duration: Duration,
+}
+
+/// A battery reading, with the game that was running at the time.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BatteryReading {
+    pub time: DateTime<Utc>,
+    pub percentage: i32,
+    pub charging: bool,
+    pub path: Option<PathBuf>,
+    pub core: Option<String>,
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub struct NewGame {
     pub name: String,
@@ -55,8 +141,14 @@ impl Database {
             }
         }
 
-        let mut conn = Connection::open(ALLIUM_DATABASE.as_path())
-            .with_context(|| format!("{}", ALLIUM_DATABASE.display()))?;
+        Self::open(&ALLIUM_DATABASE)
+    }
+
+    /// Opens the database at `path`, creating it if it doesn't exist.
+    pub fn open(path: &Path) -> Result<Self> {
+        let mut conn = Connection::open(path).with_context(|| format!("{}", path.display()))?;
+        // The library indexer writes from its own connection while the launcher is in use
+        conn.busy_timeout(std::time::Duration::from_secs(5))?;
         Self::migrations().to_latest(&mut conn)?;
         Ok(Self {
             conn: Some(Rc::new(conn)),
@@ -146,6 +238,86 @@ CREATE TRIGGER games_fts_au AFTER UPDATE ON games BEGIN
     INSERT INTO games_fts(games_fts, rowid, name, path, developer, publisher) VALUES ('delete', old.id, old.name, old.path, old.developer, old.publisher);
     INSERT INTO games_fts(rowid, name, path, developer, publisher) VALUES (new.id, new.name, new.path, new.developer, new.publisher);
 END;"),
+        M::up("
+CREATE TABLE IF NOT EXISTS favorites (
+    id INTEGER PRIMARY KEY,
+    path TEXT NOT NULL UNIQUE,
+    added INTEGER NOT NULL
+);"),
+        M::up("
+CREATE TABLE IF NOT EXISTS collections (
+    id INTEGER PRIMARY KEY,
+    name TEXT NOT NULL UNIQUE
+);
+
+CREATE TABLE IF NOT EXISTS collection_games (
+    collection_id INTEGER NOT NULL,
+    path TEXT NOT NULL,
+    position INTEGER NOT NULL,
+    PRIMARY KEY (collection_id, path)
+);"),
+        M::up("
+ALTER TABLE games ADD COLUMN size INTEGER;
+ALTER TABLE games ADD COLUMN crc32 INTEGER;
+ALTER TABLE games ADD COLUMN md5 TEXT;
+ALTER TABLE games ADD COLUMN sha1 TEXT;
+ALTER TABLE games ADD COLUMN title TEXT;
+ALTER TABLE games ADD COLUMN region TEXT;
+ALTER TABLE games ADD COLUMN revision TEXT;
+
+CREATE INDEX IF NOT EXISTS games_sha1 ON games(sha1);
+"),
+        M::up("
+CREATE TABLE IF NOT EXISTS launch_profiles (
+    scope TEXT NOT NULL,
+    key TEXT NOT NULL,
+    profile TEXT NOT NULL,
+    PRIMARY KEY (scope, key)
+);"),
+        M::up("
+ALTER TABLE directories ADD COLUMN mtime INTEGER;
+
+CREATE TABLE IF NOT EXISTS indexed_files (
+    path TEXT PRIMARY KEY,
+    directory TEXT NOT NULL,
+    size INTEGER NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS indexed_files_directory ON indexed_files(directory);
+"),
+        M::up("
+CREATE TABLE IF NOT EXISTS sessions (
+    id INTEGER PRIMARY KEY,
+    path TEXT NOT NULL,
+    core TEXT,
+    start INTEGER NOT NULL,
+    end INTEGER NOT NULL,
+    duration INTEGER NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS sessions_path ON sessions(path);
+CREATE INDEX IF NOT EXISTS sessions_start ON sessions(start);
+"),
+        M::up("
+CREATE TABLE IF NOT EXISTS battery_log (
+    id INTEGER PRIMARY KEY,
+    time INTEGER NOT NULL,
+    percentage INTEGER NOT NULL,
+    charging INTEGER NOT NULL,
+    path TEXT,
+    core TEXT
+);
+
+CREATE INDEX IF NOT EXISTS battery_log_time ON battery_log(time);
+"),
+        M::up("
+ALTER TABLE directories ADD COLUMN gamelist_mtime INTEGER;
+"),
+        M::up("
+CREATE TABLE IF NOT EXISTS hidden_games (
+    path TEXT PRIMARY KEY NOT NULL
+);
+"),
                 ])
     }
 
@@ -154,6 +326,10 @@ END;"),
             "UPDATE games SET play_count = 0, play_time = 0, last_played = 0 WHERE path = ?",
             params![path.display().to_string()],
         )?;
+        self.conn.as_ref().unwrap().execute(
+            "DELETE FROM sessions WHERE path = ?",
+            params![path.display().to_string()],
+        )?;
         Ok(())
     }
 
@@ -167,6 +343,31 @@ END;"),
             new.display().to_string(),
             old.display().to_string()
         ])?;
+
+        self.conn.as_ref().unwrap().execute(
+            "UPDATE favorites SET path = ? WHERE path = ?",
+            params![new.display().to_string(), old.display().to_string()],
+        )?;
+        self.conn.as_ref().unwrap().execute(
+            "UPDATE collection_games SET path = ? WHERE path = ?",
+            params![new.display().to_string(), old.display().to_string()],
+        )?;
+        self.conn.as_ref().unwrap().execute(
+            "UPDATE sessions SET path = ? WHERE path = ?",
+            params![new.display().to_string(), old.display().to_string()],
+        )?;
+        self.conn.as_ref().unwrap().execute(
+            "UPDATE battery_log SET path = ? WHERE path = ?",
+            params![new.display().to_string(), old.display().to_string()],
+        )?;
+        self.conn.as_ref().unwrap().execute(
+            "UPDATE launch_profiles SET key = ? WHERE scope = ? AND key = ?",
+            params![
+                new.display().to_string(),
+                LaunchProfileScope::Game.as_str(),
+                old.display().to_string()
+            ],
+        )?;
         Ok(())
     }
 
@@ -218,7 +419,7 @@ ON CONFLICT(path) DO UPDATE SET name = ?, image = ?, core = ?, rating = ?, relea
             .conn
             .as_ref()
             .unwrap()
-            .prepare("SELECT name, path, image, play_count, play_time, last_played, core, rating, release_date, developer, publisher, genres FROM games WHERE last_played > 0 ORDER BY play_time DESC LIMIT ?")?;
+            .prepare(&format!("SELECT name, path, image, play_count, play_time, last_played, core, rating, release_date, developer, publisher, genres FROM games WHERE last_played > 0 AND {NOT_HIDDEN} ORDER BY play_time DESC LIMIT ?"))?;
 
         let results = stmt
             .query_map([limit], map_game)?
@@ -234,7 +435,7 @@ ON CONFLICT(path) DO UPDATE SET name = ?, image = ?, core = ?, rating = ?, relea
             .conn
             .as_ref()
             .unwrap()
-            .prepare("SELECT name, path, image, play_count, play_time, last_played, core, rating, release_date, developer, publisher, genres FROM games WHERE last_played > 0 ORDER BY last_played DESC LIMIT ?")?;
+            .prepare(&format!("SELECT name, path, image, play_count, play_time, last_played, core, rating, release_date, developer, publisher, genres FROM games WHERE last_played > 0 AND {NOT_HIDDEN} ORDER BY last_played DESC LIMIT ?"))?;
 
         let results = stmt
             .query_map([limit], map_game)?
@@ -250,7 +451,7 @@ ON CONFLICT(path) DO UPDATE SET name = ?, image = ?, core = ?, rating = ?, relea
             .conn
             .as_ref()
             .unwrap()
-            .prepare("SELECT name, path, image, play_count, play_time, last_played, core, rating, release_date, developer, publisher, genres FROM games ORDER BY rating DESC LIMIT ?")?;
+            .prepare(&format!("SELECT name, path, image, play_count, play_time, last_played, core, rating, release_date, developer, publisher, genres FROM games WHERE {NOT_HIDDEN} ORDER BY rating DESC LIMIT ?"))?;
 
         let results = stmt
             .query_map([limit], map_game)?
@@ -266,7 +467,7 @@ ON CONFLICT(path) DO UPDATE SET name = ?, image = ?, core = ?, rating = ?, relea
             .conn
             .as_ref()
             .unwrap()
-            .prepare("SELECT name, path, image, play_count, play_time, last_played, core, rating, release_date, developer, publisher, genres FROM games ORDER BY release_date DESC LIMIT ?")?;
+            .prepare(&format!("SELECT name, path, image, play_count, play_time, last_played, core, rating, release_date, developer, publisher, genres FROM games WHERE {NOT_HIDDEN} ORDER BY release_date DESC LIMIT ?"))?;
 
         let results = stmt
             .query_map([limit], map_game)?
@@ -282,7 +483,7 @@ ON CONFLICT(path) DO UPDATE SET name = ?, image = ?, core = ?, rating = ?, relea
             .conn
             .as_ref()
             .unwrap()
-            .prepare("SELECT name, path, image, play_count, play_time, last_played, core, rating, release_date, developer, publisher, genres FROM games WHERE id IN (SELECT id FROM games ORDER BY RANDOM() LIMIT ?)")?;
+            .prepare(&format!("SELECT name, path, image, play_count, play_time, last_played, core, rating, release_date, developer, publisher, genres FROM games WHERE id IN (SELECT id FROM games WHERE {NOT_HIDDEN} ORDER BY RANDOM() LIMIT ?)"))?;
 
         let results = stmt
             .query_map([limit], map_game)?
@@ -292,31 +493,44 @@ ON CONFLICT(path) DO UPDATE SET name = ?, image = ?, core = ?, rating = ?, relea
         Ok(results)
     }
 
-    /// Search for games by name. The query is a prefix search on words, so "Fi" will match both "Fire Emblem" and "Pokemon Fire Red".
+    /// Search for games. Text is a prefix search on words of the name, developer and publisher, so
+    /// "Fi" will match both "Fire Emblem" and "Pokemon Fire Red". See [`Query`] for the supported
+    /// filters.
     pub fn search(&self, query: &str, limit: i64) -> Result<Vec<Game>> {
-        if query.is_empty() {
+        let Some((condition, mut params)) = Query::parse(query).to_sql() else {
             return Ok(Vec::new());
-        }
+        };
+        params.push(Value::Integer(limit));
 
         let conn = self.conn.as_ref().unwrap();
 
-        let mut stmt = conn.prepare("SELECT games.name, games.path, image, play_count, play_time, last_played, core, rating, release_date, games.developer, games.publisher, genres FROM games JOIN games_fts ON games.id = games_fts.rowid WHERE games_fts MATCH ? LIMIT ?")?;
+        let mut stmt = conn.prepare(&format!("SELECT name, path, image, play_count, play_time, last_played, core, rating, release_date, developer, publisher, genres FROM games WHERE ({condition}) AND {NOT_HIDDEN} LIMIT ?"))?;
 
-        let query =
-            format!("name:\"{query}\" * OR developer:\"{query}\" * OR publisher:\"{query}\" *");
         let results = stmt
-            .query_map(params![query, limit], map_game)?
+            .query_map(params_from_iter(params), map_game)?
             .filter_map(|r| r.ok())
             .collect();
 
         Ok(results)
     }
 
+    /// Games directly inside `path`, including games hidden by parental controls, as the indexer
+    /// needs them to find removed and renamed games.
     pub fn select_games_in_directory(&self, path: &Path) -> Result<Vec<Game>> {
         trace!("select_games_in_directory({:?})", path);
+        self.games_in_directory(path, "1")
+    }
+
+    /// Like `select_games_in_directory`, but leaves out games hidden by parental controls.
+    pub fn select_visible_games_in_directory(&self, path: &Path) -> Result<Vec<Game>> {
+        trace!("select_visible_games_in_directory({:?})", path);
+        self.games_in_directory(path, NOT_HIDDEN)
+    }
+
+    fn games_in_directory(&self, path: &Path, condition: &str) -> Result<Vec<Game>> {
         let conn = self.conn.as_ref().unwrap();
 
-        let mut stmt = conn.prepare("SELECT games.name, games.path, image, play_count, play_time, last_played, core, rating, release_date, games.developer, games.publisher, genres FROM games JOIN games_fts ON games.id = games_fts.rowid WHERE games_fts.path LIKE ? AND games_fts.path NOT LIKE ?")?;
+        let mut stmt = conn.prepare(&format!("SELECT games.name, games.path, image, play_count, play_time, last_played, core, rating, release_date, games.developer, games.publisher, genres FROM games JOIN games_fts ON games.id = games_fts.rowid WHERE games_fts.path LIKE ? AND games_fts.path NOT LIKE ? AND {condition}"))?;
 
         let results = stmt
             .query_map(
@@ -375,6 +589,178 @@ ON CONFLICT(path) DO UPDATE SET name = ?, image = ?, core = ?, rating = ?, relea
         Ok(results)
     }
 
+    /// Like `select_all_games`, but leaves out games hidden by parental controls.
+    pub fn select_visible_games(&self) -> Result<Vec<Game>> {
+        let mut stmt = self.conn.as_ref().unwrap().prepare(&format!(
+            "SELECT name, path, image, play_count, play_time, last_played, core, rating, release_date, developer, publisher, genres FROM games WHERE {NOT_HIDDEN}",
+        ))?;
+
+        let results = stmt
+            .query_map([], map_game)?
+            .filter_map(|r| r.ok())
+            .collect();
+
+        Ok(results)
+    }
+
+    /// Sets the hashes of games that are already in the database.
+    pub fn update_game_hashes(&self, hashes: &[GameHash]) -> Result<()> {
+        let tx = self.conn.as_ref().unwrap().unchecked_transaction()?; // safe because single-threaded
+
+        let mut stmt = tx.prepare(
+            "UPDATE games SET size = ?, crc32 = ?, md5 = ?, sha1 = ?, title = ?, region = ?, revision = ? WHERE path = ?",
+        )?;
+
+        for hash in hashes {
+            stmt.execute(params![
+                hash.hash.size as i64,
+                hash.hash.crc32,
+                hash.hash.md5,
+                hash.hash.sha1,
+                hash.title,
+                hash.region,
+                hash.revision,
+                hash.path.display().to_string(),
+            ])?;
+        }
+
+        drop(stmt);
+
+        tx.commit()?;
+
+        Ok(())
+    }
+
+    /// Selects the hashes of all games that have been hashed.
+    pub fn select_game_hashes(&self) -> Result<Vec<GameHash>> {
+        let mut stmt = self.conn.as_ref().unwrap().prepare(
+            "SELECT path, size, crc32, md5, sha1, title, region, revision FROM games WHERE sha1 IS NOT NULL",
+        )?;
+
+        let results = stmt
+            .query_map([], map_game_hash)?
+            .filter_map(|r| r.ok())
+            .collect();
+
+        Ok(results)
+    }
+
+    pub fn select_game_hash(&self, path: &Path) -> Result<Option<GameHash>> {
+        let hash = self
+            .conn
+            .as_ref()
+            .unwrap()
+            .query_row(
+                "SELECT path, size, crc32, md5, sha1, title, region, revision FROM games WHERE path = ? AND sha1 IS NOT NULL LIMIT 1",
+                [path.display().to_string()],
+                map_game_hash,
+            )
+            .optional()?;
+
+        Ok(hash)
+    }
+
+    /// Selects all games with their play statistics, metadata and hashes.
+    pub fn select_exported_games(&self) -> Result<Vec<ExportedGame>> {
+        let mut stmt = self.conn.as_ref().unwrap().prepare(
+            "SELECT path, name, play_count, play_time, last_played, core, rating, release_date, developer, publisher, genres, size, crc32, md5, sha1 FROM games ORDER BY path",
+        )?;
+
+        let results = stmt
+            .query_map([], |row| {
+                Ok(ExportedGame {
+                    path: PathBuf::from(row.get::<_, String>(0)?),
+                    name: row.get(1)?,
+                    play_count: row.get(2)?,
+                    play_time: row.get(3)?,
+                    last_played: row.get(4)?,
+                    core: row.get(5)?,
+                    rating: row.get(6)?,
+                    release_date: row.get(7)?,
+                    developer: row.get(8)?,
+                    publisher: row.get(9)?,
+                    genres: serde_json::from_str(&row.get::<_, String>(10)?).unwrap_or_default(),
+                    size: row.get::<_, Option<i64>>(11)?.map(|size| size as u64),
+                    crc32: row.get(12)?,
+                    md5: row.get(13)?,
+                    sha1: row.get(14)?,
+                })
+            })?
+            .filter_map(|r| r.ok())
+            .collect();
+
+        Ok(results)
+    }
+
+    /// Finds a game by the SHA-1 or MD5 hash of its ROM.
+    pub fn select_game_path_by_hash(
+        &self,
+        sha1: Option<&str>,
+        md5: Option<&str>,
+    ) -> Result<Option<PathBuf>> {
+        if sha1.is_none() && md5.is_none() {
+            return Ok(None);
+        }
+
+        let path = self
+            .conn
+            .as_ref()
+            .unwrap()
+            .query_row(
+                "SELECT path FROM games WHERE sha1 = ? OR md5 = ? ORDER BY sha1 = ? DESC LIMIT 1",
+                params![sha1, md5, sha1],
+                |row| row.get::<_, String>(0),
+            )
+            .optional()?;
+
+        Ok(path.map(PathBuf::from))
+    }
+
+    /// Merges an exported game into the game at `path`, inserting it if it doesn't exist. Play
+    /// counts and play time are added up and the latest last played is kept. Metadata and hashes
+    /// are only filled in where the game has none.
+    pub fn merge_exported_game(&self, path: &Path, game: &ExportedGame) -> Result<()> {
+        let genres = serde_json::to_string(&game.genres)?;
+        self.conn.as_ref().unwrap().execute(
+            "
+INSERT INTO games (name, path, image, play_count, play_time, last_played, core, rating, release_date, developer, publisher, genres, size, crc32, md5, sha1)
+VALUES (?1, ?2, NULL, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15)
+ON CONFLICT(path) DO UPDATE SET
+    play_count = play_count + ?3,
+    play_time = play_time + ?4,
+    last_played = MAX(last_played, ?5),
+    core = IFNULL(core, ?6),
+    rating = IFNULL(rating, ?7),
+    release_date = IFNULL(release_date, ?8),
+    developer = IFNULL(developer, ?9),
+    publisher = IFNULL(publisher, ?10),
+    genres = CASE WHEN genres IS NULL OR genres = '[]' THEN ?11 ELSE genres END,
+    size = IFNULL(size, ?12),
+    crc32 = IFNULL(crc32, ?13),
+    md5 = IFNULL(md5, ?14),
+    sha1 = IFNULL(sha1, ?15)",
+            params![
+                game.name,
+                path.display().to_string(),
+                game.play_count,
+                game.play_time,
+                game.last_played,
+                game.core,
+                game.rating,
+                game.release_date,
+                game.developer,
+                game.publisher,
+                genres,
+                game.size.map(|size| size as i64),
+                game.crc32,
+                game.md5,
+                game.sha1,
+            ],
+        )?;
+
+        Ok(())
+    }
+
     /// Increment the play count of a game, inserting a new row if it doesn't exist.
     pub fn increment_play_count(&self, game: &NewGame) -> Result<()> {
         self.conn.as_ref().unwrap().execute(
@@ -412,6 +798,97 @@ ON CONFLICT(path) DO UPDATE SET play_count = play_count + 1;",
         Ok(())
     }
 
+    /// Records a play session.
+    pub fn add_session(&self, session: &Session) -> Result<()> {
+        self.conn.as_ref().unwrap().execute(
+            "INSERT INTO sessions (path, core, start, end, duration) VALUES (?, ?, ?, ?, ?)",
+            params![
+                session.path.display().to_string(),
+                session.core,
+                session.start.timestamp(),
+                session.end.timestamp(),
+                session.duration.num_seconds(),
+            ],
+        )?;
+
+        Ok(())
+    }
+
+    /// Selects the sessions that started at or after `since`, oldest first.
+    pub fn select_sessions(&self, since: DateTime<Utc>) -> Result<Vec<Session>> {
+        let mut stmt = self.conn.as_ref().unwrap().prepare(
+            "SELECT path, core, start, end, duration FROM sessions WHERE start >= ? ORDER BY start",
+        )?;
+
+        let results = stmt
+            .query_map([since.timestamp()], map_session)?
+            .filter_map(|r| r.ok())
+            .collect();
+
+        Ok(results)
+    }
+
+    /// Selects the sessions of a game, most recent first.
+    pub fn select_game_sessions(&self, path: &Path, limit: i64) -> Result<Vec<Session>> {
+        let mut stmt = self.conn.as_ref().unwrap().prepare(
+            "SELECT path, core, start, end, duration FROM sessions WHERE path = ? ORDER BY start DESC LIMIT ?",
+        )?;
+
+        let results = stmt
+            .query_map(params![path.display().to_string(), limit], map_session)?
+            .filter_map(|r| r.ok())
+            .collect();
+
+        Ok(results)
+    }
+
+    /// Logs a battery reading, and removes readings from before `keep_since`.
+    pub fn add_battery_reading(
+        &self,
+        reading: &BatteryReading,
+        keep_since: DateTime<Utc>,
+    ) -> Result<()> {
+        let conn = self.conn.as_ref().unwrap();
+        conn.execute(
+            "INSERT INTO battery_log (time, percentage, charging, path, core) VALUES (?, ?, ?, ?, ?)",
+            params![
+                reading.time.timestamp(),
+                reading.percentage,
+                reading.charging,
+                reading.path.as_ref().map(|path| path.display().to_string()),
+                reading.core,
+            ],
+        )?;
+        conn.execute(
+            "DELETE FROM battery_log WHERE time < ?",
+            [keep_since.timestamp()],
+        )?;
+
+        Ok(())
+    }
+
+    /// Selects the battery readings taken at or after `since`, oldest first.
+    pub fn select_battery_log(&self, since: DateTime<Utc>) -> Result<Vec<BatteryReading>> {
+        let mut stmt = self.conn.as_ref().unwrap().prepare(
+            "SELECT time, percentage, charging, path, core FROM battery_log WHERE time >= ? ORDER BY time",
+        )?;
+
+        let results = stmt
+            .query_map([since.timestamp()], |row| {
+                Ok(BatteryReading {
+                    time: DateTime::from_timestamp(row.get(0)?, 0).unwrap_or_default(),
+                    percentage: row.get(1)?,
+                    charging: row.get(2)?,
+                    path: row.get::<_, Option<String>>(3)?.map(PathBuf::from),
+                    core: row.get(4)?,
+                })
+            })?
+            .filter_map(|r| r.ok())
+            .collect();
+
+        Ok(results)
+    }
+
     pub fn get_guide_cursor(&self, path: &Path) -> Result<u64> {
         let cursor = self
             .conn
@@ -437,42 +914,160 @@ ON CONFLICT(path) DO UPDATE SET play_count = play_count + 1;",
         Ok(())
     }
 
-    /// Deletes a game from the database.
-    pub fn delete_game(&self, path: &Path) -> Result<()> {
-        self.conn.as_ref().unwrap().execute(
-            "DELETE FROM games WHERE path = ?",
-            [path.display().to_string()],
-        )?;
-
-        Ok(())
-    }
+    /// Selects the paths of guides that have a reading position.
+    pub fn select_guides(&self) -> Result<Vec<PathBuf>> {
+        let mut stmt = self
+            .conn
+            .as_ref()
+            .unwrap()
+            .prepare("SELECT path FROM guides ORDER BY id")?;
 
-    /// Deletes all games that have no play time, play count.
-    pub fn delete_all_unplayed_games(&self) -> Result<()> {
-        self.conn.as_ref().unwrap().execute(
-            "DELETE FROM games WHERE last_played = 0 AND play_time = 0",
-            [],
-        )?;
+        let results = stmt
+            .query_map([], |row| row.get::<_, String>(0))?
+            .filter_map(|r| r.ok())
+            .map(PathBuf::from)
+            .collect();
 
-        Ok(())
+        Ok(results)
     }
 
-    /// Deletes all directories
-    pub fn delete_all_directories(&self) -> Result<()> {
-        self.conn
+    /// Selects the reading positions of all guides.
+    pub fn select_guide_cursors(&self) -> Result<Vec<(PathBuf, u64)>> {
+        let mut stmt = self
+            .conn
             .as_ref()
             .unwrap()
-            .execute("DELETE FROM directories", [])?;
+            .prepare("SELECT path, cursor FROM guides ORDER BY id")?;
+
+        let results = stmt
+            .query_map([], |row| {
+                Ok((PathBuf::from(row.get::<_, String>(0)?), row.get(1)?))
+            })?
+            .filter_map(|r| r.ok())
+            .collect();
+
+        Ok(results)
+    }
+
+    /// Sets the reading position of a guide, unless it already has a position further along.
+    pub fn merge_guide_cursor(&self, path: &Path, cursor: u64) -> Result<()> {
+        self.conn.as_ref().unwrap().execute(
+            "INSERT INTO guides (path, cursor) VALUES (?, ?) ON CONFLICT(path) DO UPDATE SET cursor = MAX(cursor, excluded.cursor)",
+            params![path.display().to_string(), cursor],
+        )?;
 
         Ok(())
     }
 
-    pub fn set_has_indexed(&self, has_indexed: bool) -> Result<()> {
-        self
-            .conn
-            .as_ref()
-            .unwrap()
-            .execute("INSERT INTO key_value (key, value) VALUES ('has_indexed', 1) ON CONFLICT(key) DO UPDATE SET value = ?", [if has_indexed { "1" } else {"0"}])?;
+    /// Moves the reading position of a guide to a new path, unless the new path already has one.
+    pub fn update_guide_path(&self, old: &Path, new: &Path) -> Result<()> {
+        self.conn.as_ref().unwrap().execute(
+            "UPDATE OR IGNORE guides SET path = ? WHERE path = ?",
+            params![new.display().to_string(), old.display().to_string()],
+        )?;
+
+        Ok(())
+    }
+
+    /// Deletes a game from the database.
+    pub fn delete_game(&self, path: &Path) -> Result<()> {
+        self.conn.as_ref().unwrap().execute(
+            "DELETE FROM games WHERE path = ?",
+            [path.display().to_string()],
+        )?;
+        self.conn.as_ref().unwrap().execute(
+            "DELETE FROM favorites WHERE path = ?",
+            [path.display().to_string()],
+        )?;
+        self.conn.as_ref().unwrap().execute(
+            "DELETE FROM collection_games WHERE path = ?",
+            [path.display().to_string()],
+        )?;
+        self.conn.as_ref().unwrap().execute(
+            "DELETE FROM launch_profiles WHERE scope = ? AND key = ?",
+            params![
+                LaunchProfileScope::Game.as_str(),
+                path.display().to_string()
+            ],
+        )?;
+
+        Ok(())
+    }
+
+    /// Merges games into another game, e.g. the discs of a multi-disc game into its playlist.
+    /// Play counts and play time are added up, sessions are moved over, and favorites, collections
+    /// and launch profiles are moved over unless the target already has them. The merged games are
+    /// then deleted.
+    pub fn merge_games(&self, from: &[&Path], into: &Path) -> Result<()> {
+        let conn = self.conn.as_ref().unwrap();
+        let into = into.display().to_string();
+        for path in from {
+            let path = path.display().to_string();
+            if path == into {
+                continue;
+            }
+            conn.execute(
+                "
+UPDATE games SET
+    play_count = play_count + IFNULL((SELECT play_count FROM games WHERE path = ?1), 0),
+    play_time = play_time + IFNULL((SELECT play_time FROM games WHERE path = ?1), 0),
+    last_played = MAX(last_played, IFNULL((SELECT last_played FROM games WHERE path = ?1), 0)),
+    core = IFNULL(core, (SELECT core FROM games WHERE path = ?1))
+WHERE path = ?2",
+                params![path, into],
+            )?;
+            conn.execute(
+                "UPDATE OR IGNORE favorites SET path = ? WHERE path = ?",
+                params![into, path],
+            )?;
+            conn.execute(
+                "UPDATE OR IGNORE collection_games SET path = ? WHERE path = ?",
+                params![into, path],
+            )?;
+            conn.execute(
+                "UPDATE OR IGNORE launch_profiles SET key = ? WHERE scope = ? AND key = ?",
+                params![into, LaunchProfileScope::Game.as_str(), path],
+            )?;
+            conn.execute(
+                "UPDATE sessions SET path = ? WHERE path = ?",
+                params![into, path],
+            )?;
+            conn.execute(
+                "UPDATE battery_log SET path = ? WHERE path = ?",
+                params![into, path],
+            )?;
+            self.delete_game(Path::new(&path))?;
+        }
+
+        Ok(())
+    }
+
+    /// Deletes all games that have no play time, play count.
+    pub fn delete_all_unplayed_games(&self) -> Result<()> {
+        self.conn.as_ref().unwrap().execute(
+            "DELETE FROM games WHERE last_played = 0 AND play_time = 0",
+            [],
+        )?;
+
+        Ok(())
+    }
+
+    /// Deletes all directories
+    pub fn delete_all_directories(&self) -> Result<()> {
+        self.conn
+            .as_ref()
+            .unwrap()
+            .execute("DELETE FROM directories", [])?;
+
+        Ok(())
+    }
+
+    pub fn set_has_indexed(&self, has_indexed: bool) -> Result<()> {
+        self
+            .conn
+            .as_ref()
+            .unwrap()
+            .execute("INSERT INTO key_value (key, value) VALUES ('has_indexed', 1) ON CONFLICT(key) DO UPDATE SET value = ?", [if has_indexed { "1" } else {"0"}])?;
 
         Ok(())
     }
@@ -492,26 +1087,68 @@ ON CONFLICT(path) DO UPDATE SET play_count = play_count + 1;",
         Ok(matches!(value.as_deref(), Some("1")))
     }
 
-    pub fn set_gamelist_fingerprint(&self, path: &Path, fingerprint: u64) -> Result<()> {
-        trace!("set_gamelist_fingerprint({:?}, {})", path, fingerprint);
+    /// Selects all key-value pairs.
+    pub fn select_key_values(&self) -> Result<Vec<(String, String)>> {
+        let mut stmt = self
+            .conn
+            .as_ref()
+            .unwrap()
+            .prepare("SELECT key, value FROM key_value ORDER BY key")?;
+
+        let results = stmt
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+            .filter_map(|r| r.ok())
+            .collect();
+
+        Ok(results)
+    }
+
+    /// Sets a key-value pair, unless the key is already set.
+    pub fn insert_key_value(&self, key: &str, value: &str) -> Result<bool> {
+        let inserted = self.conn.as_ref().unwrap().execute(
+            "INSERT OR IGNORE INTO key_value (key, value) VALUES (?, ?)",
+            params![key, value],
+        )?;
+
+        Ok(inserted > 0)
+    }
+
+    pub fn set_gamelist_fingerprint(
+        &self,
+        path: &Path,
+        fingerprint: GamelistFingerprint,
+    ) -> Result<()> {
+        trace!("set_gamelist_fingerprint({:?}, {:?})", path, fingerprint);
         self.conn.as_ref().unwrap().execute(
-            "INSERT INTO directories (path, gamelist_fingerprint) VALUES (?, ?) ON CONFLICT(path) DO UPDATE SET gamelist_fingerprint = ?",
-            params![path.display().to_string(), fingerprint, fingerprint],
+            "INSERT INTO directories (path, gamelist_fingerprint, gamelist_mtime) VALUES (?1, ?2, ?3) ON CONFLICT(path) DO UPDATE SET gamelist_fingerprint = ?2, gamelist_mtime = ?3",
+            params![path.display().to_string(), fingerprint.size, fingerprint.mtime],
         )?;
 
         Ok(())
     }
 
-    pub fn get_gamelist_fingerprint(&self, path: &Path) -> Result<Option<u64>> {
+    pub fn get_gamelist_fingerprint(&self, path: &Path) -> Result<Option<GamelistFingerprint>> {
         trace!("get_gamelist_fingerprint({:?})", path);
         let fingerprint = self
             .conn
             .as_ref()
             .unwrap()
             .query_row(
-                "SELECT gamelist_fingerprint FROM directories WHERE path = ?",
+                "SELECT gamelist_fingerprint, gamelist_mtime FROM directories WHERE path = ?",
                 [path.display().to_string()],
-                |row| row.get::<_, Option<u64>>(0),
+                |row| {
+                    Ok(row
+                        .get::<_, Option<u64>>(0)?
+                        .map(|size| GamelistFingerprint {
+                            size,
+                            // Missing if the gamelist was last parsed before mtimes were stored
+                            mtime: row
+                                .get::<_, Option<i64>>(1)
+                                .ok()
+                                .flatten()
+                                .unwrap_or_default(),
+                        }))
+                },
             )
             .optional()?
             .flatten();
@@ -519,6 +1156,107 @@ ON CONFLICT(path) DO UPDATE SET play_count = play_count + 1;",
         Ok(fingerprint)
     }
 
+    /// Modification time of a directory when it was last indexed.
+    pub fn get_directory_mtime(&self, path: &Path) -> Result<Option<i64>> {
+        let mtime = self
+            .conn
+            .as_ref()
+            .unwrap()
+            .query_row(
+                "SELECT mtime FROM directories WHERE path = ?",
+                [path.display().to_string()],
+                |row| row.get::<_, Option<i64>>(0),
+            )
+            .optional()?
+            .flatten();
+
+        Ok(mtime)
+    }
+
+    pub fn set_directory_mtime(&self, path: &Path, mtime: i64) -> Result<()> {
+        self.conn.as_ref().unwrap().execute(
+            "INSERT INTO directories (path, mtime) VALUES (?, ?) ON CONFLICT(path) DO UPDATE SET mtime = ?",
+            params![path.display().to_string(), mtime, mtime],
+        )?;
+
+        Ok(())
+    }
+
+    /// Selects the directories that have been indexed.
+    pub fn select_indexed_directories(&self) -> Result<Vec<PathBuf>> {
+        let mut stmt = self
+            .conn
+            .as_ref()
+            .unwrap()
+            .prepare("SELECT path FROM directories WHERE mtime IS NOT NULL")?;
+
+        let results = stmt
+            .query_map([], |row| row.get::<_, String>(0))?
+            .filter_map(|r| r.ok())
+            .map(PathBuf::from)
+            .collect();
+
+        Ok(results)
+    }
+
+    /// Selects the files in a directory and their sizes, as of when it was last indexed.
+    pub fn select_indexed_files(&self, directory: &Path) -> Result<Vec<(PathBuf, u64)>> {
+        let mut stmt = self
+            .conn
+            .as_ref()
+            .unwrap()
+            .prepare("SELECT path, size FROM indexed_files WHERE directory = ?")?;
+
+        let results = stmt
+            .query_map([directory.display().to_string()], |row| {
+                Ok((
+                    PathBuf::from(row.get::<_, String>(0)?),
+                    row.get::<_, i64>(1)? as u64,
+                ))
+            })?
+            .filter_map(|r| r.ok())
+            .collect();
+
+        Ok(results)
+    }
+
+    /// Replaces the indexed files of a directory.
+    pub fn set_indexed_files(&self, directory: &Path, files: &[(PathBuf, u64)]) -> Result<()> {
+        let tx = self.conn.as_ref().unwrap().unchecked_transaction()?; // safe because single-threaded
+
+        let directory = directory.display().to_string();
+        tx.execute(
+            "DELETE FROM indexed_files WHERE directory = ?",
+            [&directory],
+        )?;
+
+        let mut stmt = tx.prepare(
+            "INSERT OR REPLACE INTO indexed_files (path, directory, size) VALUES (?, ?, ?)",
+        )?;
+        for (path, size) in files {
+            stmt.execute(params![path.display().to_string(), directory, *size as i64])?;
+        }
+        drop(stmt);
+
+        tx.commit()?;
+
+        Ok(())
+    }
+
+    /// Deletes a directory and its indexed files.
+    pub fn delete_directory(&self, path: &Path) -> Result<()> {
+        self.conn.as_ref().unwrap().execute(
+            "DELETE FROM directories WHERE path = ?",
+            [path.display().to_string()],
+        )?;
+        self.conn.as_ref().unwrap().execute(
+            "DELETE FROM indexed_files WHERE directory = ?",
+            [path.display().to_string()],
+        )?;
+
+        Ok(())
+    }
+
     pub fn get_core(&self, path: &Path) -> Result<Option<String>> {
         let core = self
             .conn
@@ -543,6 +1281,337 @@ ON CONFLICT(path) DO UPDATE SET play_count = play_count + 1;",
 
         Ok(())
     }
+
+    /// Returns the launch profile of a game (by path) or console (by name). The core of a game's
+    /// profile is the one set with `set_core`.
+    pub fn get_launch_profile(
+        &self,
+        scope: LaunchProfileScope,
+        key: &str,
+    ) -> Result<LaunchProfile> {
+        let profile = self
+            .conn
+            .as_ref()
+            .unwrap()
+            .query_row(
+                "SELECT profile FROM launch_profiles WHERE scope = ? AND key = ?",
+                params![scope.as_str(), key],
+                |row| row.get::<_, String>(0),
+            )
+            .optional()?;
+
+        let mut profile = match profile {
+            Some(profile) => serde_json::from_str(&profile)
+                .with_context(|| format!("invalid launch profile for {}", key))?,
+            None => LaunchProfile::default(),
+        };
+        if scope == LaunchProfileScope::Game {
+            profile.core = self.get_core(Path::new(key))?;
+        }
+
+        Ok(profile)
+    }
+
+    /// Saves the launch profile of a game (by path) or console (by name). Empty profiles are
+    /// removed.
+    pub fn set_launch_profile(
+        &self,
+        scope: LaunchProfileScope,
+        key: &str,
+        profile: &LaunchProfile,
+    ) -> Result<()> {
+        let mut profile = profile.clone();
+        if scope == LaunchProfileScope::Game {
+            self.conn.as_ref().unwrap().execute(
+                "UPDATE games SET core = ? WHERE path = ?",
+                params![profile.core.take(), key],
+            )?;
+        }
+
+        if profile.is_empty() {
+            self.conn.as_ref().unwrap().execute(
+                "DELETE FROM launch_profiles WHERE scope = ? AND key = ?",
+                params![scope.as_str(), key],
+            )?;
+        } else {
+            self.conn.as_ref().unwrap().execute(
+                "INSERT INTO launch_profiles (scope, key, profile) VALUES (?, ?, ?) ON CONFLICT(scope, key) DO UPDATE SET profile = excluded.profile",
+                params![scope.as_str(), key, serde_json::to_string(&profile)?],
+            )?;
+        }
+
+        Ok(())
+    }
+
+    /// Adds a game to the favorites. Does nothing if it is already a favorite.
+    pub fn add_favorite(&self, path: &Path) -> Result<()> {
+        self.conn.as_ref().unwrap().execute(
+            "INSERT INTO favorites (path, added) VALUES (?, (SELECT IFNULL(MAX(added), 0) + 1 FROM favorites)) ON CONFLICT(path) DO NOTHING",
+            [path.display().to_string()],
+        )?;
+
+        Ok(())
+    }
+
+    /// Removes a game from the favorites.
+    pub fn remove_favorite(&self, path: &Path) -> Result<()> {
+        self.conn.as_ref().unwrap().execute(
+            "DELETE FROM favorites WHERE path = ?",
+            [path.display().to_string()],
+        )?;
+
+        Ok(())
+    }
+
+    pub fn is_favorite(&self, path: &Path) -> Result<bool> {
+        let favorite = self
+            .conn
+            .as_ref()
+            .unwrap()
+            .query_row(
+                "SELECT 1 FROM favorites WHERE path = ?",
+                [path.display().to_string()],
+                |_| Ok(()),
+            )
+            .optional()?;
+
+        Ok(favorite.is_some())
+    }
+
+    /// Selects favorite games sorted by most recently added first.
+    pub fn select_favorites(&self) -> Result<Vec<Game>> {
+        let mut stmt = self
+            .conn
+            .as_ref()
+            .unwrap()
+            .prepare(&format!("SELECT games.name, games.path, image, play_count, play_time, last_played, core, rating, release_date, developer, publisher, genres FROM games JOIN favorites ON games.path = favorites.path WHERE {NOT_HIDDEN} ORDER BY favorites.added DESC"))?;
+
+        let results = stmt
+            .query_map([], map_game)?
+            .filter_map(|r| r.ok())
+            .collect();
+
+        Ok(results)
+    }
+
+    /// Creates a collection, returning its id. If a collection with the same name already exists,
+    /// its id is returned instead.
+    pub fn create_collection(&self, name: &str) -> Result<i64> {
+        let conn = self.conn.as_ref().unwrap();
+        conn.execute(
+            "INSERT INTO collections (name) VALUES (?) ON CONFLICT(name) DO NOTHING",
+            [name],
+        )?;
+        let id = conn.query_row("SELECT id FROM collections WHERE name = ?", [name], |row| {
+            row.get(0)
+        })?;
+
+        Ok(id)
+    }
+
+    pub fn rename_collection(&self, id: i64, name: &str) -> Result<()> {
+        self.conn.as_ref().unwrap().execute(
+            "UPDATE collections SET name = ? WHERE id = ?",
+            params![name, id],
+        )?;
+
+        Ok(())
+    }
+
+    /// Deletes a collection. The games themselves are left untouched.
+    pub fn delete_collection(&self, id: i64) -> Result<()> {
+        let tx = self.conn.as_ref().unwrap().unchecked_transaction()?; // safe because single-threaded
+        tx.execute("DELETE FROM collection_games WHERE collection_id = ?", [id])?;
+        tx.execute("DELETE FROM collections WHERE id = ?", [id])?;
+        tx.commit()?;
+
+        Ok(())
+    }
+
+    /// Selects all collections sorted by name.
+    pub fn select_collections(&self) -> Result<Vec<Collection>> {
+        let mut stmt = self
+            .conn
+            .as_ref()
+            .unwrap()
+            .prepare("SELECT id, name FROM collections ORDER BY name")?;
+
+        let results = stmt
+            .query_map([], |row| {
+                Ok(Collection {
+                    id: row.get(0)?,
+                    name: row.get(1)?,
+                })
+            })?
+            .filter_map(|r| r.ok())
+            .collect();
+
+        Ok(results)
+    }
+
+    pub fn select_collection(&self, name: &str) -> Result<Option<Collection>> {
+        let collection = self
+            .conn
+            .as_ref()
+            .unwrap()
+            .query_row(
+                "SELECT id, name FROM collections WHERE name = ?",
+                [name],
+                |row| {
+                    Ok(Collection {
+                        id: row.get(0)?,
+                        name: row.get(1)?,
+                    })
+                },
+            )
+            .optional()?;
+
+        Ok(collection)
+    }
+
+    /// Appends a game to the end of a collection. Does nothing if it is already in the collection.
+    pub fn add_to_collection(&self, id: i64, path: &Path) -> Result<()> {
+        self.conn.as_ref().unwrap().execute(
+            "INSERT INTO collection_games (collection_id, path, position) VALUES (?, ?, (SELECT IFNULL(MAX(position), 0) + 1 FROM collection_games WHERE collection_id = ?)) ON CONFLICT(collection_id, path) DO NOTHING",
+            params![id, path.display().to_string(), id],
+        )?;
+
+        Ok(())
+    }
+
+    pub fn remove_from_collection(&self, id: i64, path: &Path) -> Result<()> {
+        self.conn.as_ref().unwrap().execute(
+            "DELETE FROM collection_games WHERE collection_id = ? AND path = ?",
+            params![id, path.display().to_string()],
+        )?;
+
+        Ok(())
+    }
+
+    pub fn is_in_collection(&self, id: i64, path: &Path) -> Result<bool> {
+        let found = self
+            .conn
+            .as_ref()
+            .unwrap()
+            .query_row(
+                "SELECT 1 FROM collection_games WHERE collection_id = ? AND path = ?",
+                params![id, path.display().to_string()],
+                |_| Ok(()),
+            )
+            .optional()?;
+
+        Ok(found.is_some())
+    }
+
+    /// Replaces the games of a collection, keeping the given order.
+    pub fn set_collection_games(&self, id: i64, paths: &[PathBuf]) -> Result<()> {
+        let tx = self.conn.as_ref().unwrap().unchecked_transaction()?; // safe because single-threaded
+
+        tx.execute("DELETE FROM collection_games WHERE collection_id = ?", [id])?;
+
+        let mut stmt = tx.prepare(
+            "INSERT INTO collection_games (collection_id, path, position) VALUES (?, ?, ?) ON CONFLICT(collection_id, path) DO NOTHING",
+        )?;
+        for (i, path) in paths.iter().enumerate() {
+            stmt.execute(params![id, path.display().to_string(), i as i64 + 1])?;
+        }
+
+        drop(stmt);
+
+        tx.commit()?;
+
+        Ok(())
+    }
+
+    /// Selects the paths of the games in a collection, in order. Games that are not in the
+    /// database are included as well.
+    pub fn select_collection_paths(&self, id: i64) -> Result<Vec<PathBuf>> {
+        let mut stmt = self.conn.as_ref().unwrap().prepare(
+            "SELECT path FROM collection_games WHERE collection_id = ? ORDER BY position",
+        )?;
+
+        let results = stmt
+            .query_map([id], |row| Ok(PathBuf::from(row.get::<_, String>(0)?)))?
+            .filter_map(|r| r.ok())
+            .collect();
+
+        Ok(results)
+    }
+
+    /// Selects the games in a collection, in order.
+    pub fn select_games_in_collection(&self, id: i64) -> Result<Vec<Game>> {
+        let mut stmt = self
+            .conn
+            .as_ref()
+            .unwrap()
+            .prepare(&format!("SELECT games.name, games.path, image, play_count, play_time, last_played, core, rating, release_date, developer, publisher, genres FROM games JOIN collection_games ON games.path = collection_games.path WHERE collection_games.collection_id = ? AND {NOT_HIDDEN} ORDER BY collection_games.position"))?;
+
+        let results = stmt
+            .query_map([id], map_game)?
+            .filter_map(|r| r.ok())
+            .collect();
+
+        Ok(results)
+    }
+
+    /// Selects the distinct values of a metadata column, sorted case-insensitively.
+    pub fn select_groups(&self, group_by: GroupBy) -> Result<Vec<String>> {
+        let query = match group_by {
+            GroupBy::Genre => format!("SELECT DISTINCT json_each.value FROM games, json_each(games.genres) WHERE json_each.value != '' AND {NOT_HIDDEN} ORDER BY json_each.value COLLATE NOCASE"),
+            GroupBy::Developer => format!("SELECT DISTINCT developer FROM games WHERE developer IS NOT NULL AND developer != '' AND {NOT_HIDDEN} ORDER BY developer COLLATE NOCASE"),
+            GroupBy::Publisher => format!("SELECT DISTINCT publisher FROM games WHERE publisher IS NOT NULL AND publisher != '' AND {NOT_HIDDEN} ORDER BY publisher COLLATE NOCASE"),
+            GroupBy::Decade => format!("SELECT DISTINCT substr(release_date, 1, 3) || '0' AS decade FROM games WHERE release_date IS NOT NULL AND {NOT_HIDDEN} ORDER BY decade"),
+        };
+
+        let mut stmt = self.conn.as_ref().unwrap().prepare(&query)?;
+        let results = stmt
+            .query_map([], |row| row.get(0))?
+            .filter_map(|r| r.ok())
+            .collect();
+
+        Ok(results)
+    }
+
+    /// Selects the games in a group returned by `select_groups`, sorted by name.
+    pub fn select_games_in_group(&self, group_by: GroupBy, group: &str) -> Result<Vec<Game>> {
+        let condition = match group_by {
+            GroupBy::Genre => {
+                "EXISTS (SELECT 1 FROM json_each(games.genres) WHERE json_each.value = ?)"
+            }
+            GroupBy::Developer => "developer = ?",
+            GroupBy::Publisher => "publisher = ?",
+            GroupBy::Decade => "substr(release_date, 1, 3) || '0' = ?",
+        };
+
+        let mut stmt = self.conn.as_ref().unwrap().prepare(&format!("SELECT name, path, image, play_count, play_time, last_played, core, rating, release_date, developer, publisher, genres FROM games WHERE {condition} AND {NOT_HIDDEN} ORDER BY name COLLATE NOCASE"))?;
+        let results = stmt
+            .query_map([group], map_game)?
+            .filter_map(|r| r.ok())
+            .collect();
+
+        Ok(results)
+    }
+
+    /// Hides games from the queries that list, search or group games, replacing the games that
+    /// were hidden before. Looking up a game by its path still finds it. The hidden games are
+    /// stored in the database, so they are hidden from every connection, such as the HTTP API.
+    pub fn set_hidden_games(&self, paths: &[PathBuf]) -> Result<()> {
+        let tx = self.conn.as_ref().unwrap().unchecked_transaction()?; // safe because single-threaded
+
+        tx.execute("DELETE FROM hidden_games", [])?;
+
+        let mut stmt =
+            tx.prepare("INSERT INTO hidden_games (path) VALUES (?) ON CONFLICT(path) DO NOTHING")?;
+        for path in paths {
+            stmt.execute([path.display().to_string()])?;
+        }
+
+        drop(stmt);
+
+        tx.commit()?;
+
+        Ok(())
+    }
 }
 
 fn map_game(row: &Row<'_>) -> rusqlite::Result<Game> {
@@ -562,6 +1631,31 @@ fn map_game(row: &Row<'_>) -> rusqlite::Result<Game> {
     })
 }
 
+fn map_session(row: &Row<'_>) -> rusqlite::Result<Session> {
+    Ok(Session {
+        path: PathBuf::from(row.get::<_, String>(0)?),
+        core: row.get(1)?,
+        start: DateTime::from_timestamp(row.get(2)?, 0).unwrap_or_default(),
+        end: DateTime::from_timestamp(row.get(3)?, 0).unwrap_or_default(),
+        duration: Duration::seconds(row.get(4)?),
+    })
+}
+
+fn map_game_hash(row: &Row<'_>) -> rusqlite::Result<GameHash> {
+    Ok(GameHash {
+        path: PathBuf::from(row.get::<_, String>(0)?),
+        hash: RomHash {
+            size: row.get::<_, i64>(1)? as u64,
+            crc32: row.get(2)?,
+            md5: row.get(3)?,
+            sha1: row.get(4)?,
+        },
+        title: row.get(5)?,
+        region: row.get(6)?,
+        revision: row.get(7)?,
+    })
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -825,6 +1919,92 @@ mod tests {
         assert_eq!(results[0].path, games[1].path);
     }
 
+    #[test]
+    fn test_search_filters() -> Result<()> {
+        let database = Database::in_memory()?;
+
+        let games = vec![
+            NewGame {
+                name: "Chrono Trigger".to_string(),
+                path: PathBuf::from("Roms/SFC/Chrono Trigger.sfc"),
+                image: None,
+                core: None,
+                rating: Some(10),
+                release_date: Some(NaiveDate::from_ymd_opt(1995, 3, 11).unwrap()),
+                developer: Some("Square".to_string()),
+                publisher: Some("Square".to_string()),
+                genres: vec!["Role-playing (RPG)".to_string()],
+            },
+            NewGame {
+                name: "Mega Man Zero".to_string(),
+                path: PathBuf::from("Roms/Game Boy Advance (GBA)/Mega Man Zero.gba"),
+                image: None,
+                core: None,
+                rating: Some(7),
+                release_date: Some(NaiveDate::from_ymd_opt(2002, 4, 26).unwrap()),
+                developer: Some("Inti Creates".to_string()),
+                publisher: Some("Capcom".to_string()),
+                genres: vec!["Platform".to_string()],
+            },
+            NewGame {
+                name: "Super Mario World".to_string(),
+                path: PathBuf::from("Roms/SFC/Super Mario World.sfc"),
+                image: None,
+                core: None,
+                rating: None,
+                release_date: None,
+                developer: Some("Nintendo".to_string()),
+                publisher: Some("Nintendo".to_string()),
+                genres: vec!["Platform".to_string()],
+            },
+        ];
+        database.update_games(&games)?;
+        database.add_play_time(&games[1].path, Duration::hours(3))?;
+
+        let search = |query| -> Result<Vec<String>> {
+            let mut names: Vec<String> = database
+                .search(query, 100)?
+                .into_iter()
+                .map(|game| game.name)
+                .collect();
+            names.sort();
+            Ok(names)
+        };
+
+        assert_eq!(search("dev:squ")?, vec!["Chrono Trigger"]);
+        assert_eq!(search("pub:capcom zero")?, vec!["Mega Man Zero"]);
+        assert_eq!(search("genre:rpg")?, vec!["Chrono Trigger"]);
+        assert_eq!(
+            search("console:sfc")?,
+            vec!["Chrono Trigger", "Super Mario World"]
+        );
+        assert_eq!(search("console:gba")?, vec!["Mega Man Zero"]);
+        assert_eq!(
+            search("console:\"game boy advance\"")?,
+            vec!["Mega Man Zero"]
+        );
+        assert_eq!(search("year:1994..1998")?, vec!["Chrono Trigger"]);
+        assert_eq!(search("year:>2000")?, vec!["Mega Man Zero"]);
+        assert_eq!(search("played:>2h")?, vec!["Mega Man Zero"]);
+        assert_eq!(search("rating:>=8")?, vec!["Chrono Trigger"]);
+        assert_eq!(
+            search("-rating:>=8")?,
+            vec!["Mega Man Zero", "Super Mario World"]
+        );
+        assert_eq!(search("genre:platform -mario")?, vec!["Mega Man Zero"]);
+        assert_eq!(search("\"mario world\"")?, vec!["Super Mario World"]);
+        assert_eq!(search("\"world mario\"")?, Vec::<String>::new());
+        assert_eq!(
+            search("-\"mega man\" -dev:square")?,
+            vec!["Super Mario World"]
+        );
+        // Nothing to search for
+        assert_eq!(search("?")?, Vec::<String>::new());
+        assert_eq!(search("-")?, Vec::<String>::new());
+
+        Ok(())
+    }
+
     #[test]
     fn test_select_games() {
         let database = Database::in_memory().unwrap();
@@ -992,6 +2172,104 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_launch_profiles() -> Result<()> {
+        let db = Database::in_memory().unwrap();
+
+        let game = NewGame {
+            name: "Game One".to_string(),
+            path: PathBuf::from("test_directory/Game One.rom"),
+            image: None,
+            core: None,
+            rating: None,
+            release_date: None,
+            developer: None,
+            publisher: None,
+            genres: Vec::new(),
+        };
+        db.update_games(std::slice::from_ref(&game))?;
+        let key = game.path.display().to_string();
+
+        assert!(db
+            .get_launch_profile(LaunchProfileScope::Console, "Test")?
+            .is_empty());
+
+        let profile = LaunchProfile {
+            core: Some("CORE".to_string()),
+            retroarch_args: vec!["--verbose".to_string()],
+            swap: Some(true),
+            ..Default::default()
+        };
+        db.set_launch_profile(LaunchProfileScope::Game, &key, &profile)?;
+        db.set_launch_profile(LaunchProfileScope::Console, &key, &LaunchProfile::default())?;
+        assert_eq!(
+            db.get_launch_profile(LaunchProfileScope::Game, &key)?,
+            profile
+        );
+        assert_eq!(db.get_core(&game.path)?, Some("CORE".to_string()));
+        assert!(db
+            .get_launch_profile(LaunchProfileScope::Console, &key)?
+            .is_empty());
+
+        let new_path = PathBuf::from("test_directory/Game Two.rom");
+        db.update_game_path(&game.path, &new_path)?;
+        let new_key = new_path.display().to_string();
+        assert_eq!(
+            db.get_launch_profile(LaunchProfileScope::Game, &new_key)?,
+            profile
+        );
+
+        db.set_launch_profile(
+            LaunchProfileScope::Game,
+            &new_key,
+            &LaunchProfile::default(),
+        )?;
+        assert!(db
+            .get_launch_profile(LaunchProfileScope::Game, &new_key)?
+            .is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_merge_games() -> Result<()> {
+        let db = Database::in_memory().unwrap();
+
+        let new_game = |path: &str| NewGame {
+            name: "Game".to_string(),
+            path: PathBuf::from(path),
+            image: None,
+            core: None,
+            rating: None,
+            release_date: None,
+            developer: None,
+            publisher: None,
+            genres: Vec::new(),
+        };
+        let disc1 = new_game("PS/Game (Disc 1).cue");
+        let disc2 = new_game("PS/Game (Disc 2).cue");
+        let playlist = new_game("PS/Game.m3u");
+        db.update_games(&[disc1.clone(), disc2.clone(), playlist.clone()])?;
+
+        db.increment_play_count(&disc1)?;
+        db.add_play_time(&disc1.path, Duration::minutes(30))?;
+        db.increment_play_count(&disc2)?;
+        db.add_play_time(&disc2.path, Duration::minutes(15))?;
+        db.add_favorite(&disc2.path)?;
+
+        db.merge_games(&[&disc1.path, &disc2.path], &playlist.path)?;
+
+        let game = db.select_game(&playlist.path)?.unwrap();
+        assert_eq!(game.play_count, 2);
+        assert_eq!(game.play_time, Duration::minutes(45));
+        assert!(db.is_favorite(&playlist.path)?);
+        assert_eq!(db.select_game(&disc1.path)?, None);
+        assert_eq!(db.select_game(&disc2.path)?, None);
+        assert!(!db.is_favorite(&disc2.path)?);
+
+        Ok(())
+    }
+
     #[test]
     fn test_set_genres() -> Result<()> {
         let db = Database::in_memory().unwrap();
@@ -1027,4 +2305,443 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn test_favorites() -> Result<()> {
+        let db = Database::in_memory().unwrap();
+
+        let games = vec![
+            NewGame {
+                name: "Game One".to_string(),
+                path: PathBuf::from("test_directory/Game One.rom"),
+                image: None,
+                core: None,
+                rating: None,
+                release_date: None,
+                developer: None,
+                publisher: None,
+                genres: Vec::new(),
+            },
+            NewGame {
+                name: "Game Two".to_string(),
+                path: PathBuf::from("test_directory/Game Two.rom"),
+                image: None,
+                core: None,
+                rating: None,
+                release_date: None,
+                developer: None,
+                publisher: None,
+                genres: Vec::new(),
+            },
+        ];
+
+        db.update_games(&games)?;
+        assert!(db.select_favorites()?.is_empty());
+
+        db.add_favorite(&games[0].path)?;
+        db.add_favorite(&games[1].path)?;
+        db.add_favorite(&games[0].path)?;
+        assert!(db.is_favorite(&games[0].path)?);
+        let favorites = db.select_favorites()?;
+        assert_eq!(favorites.len(), 2);
+        assert_eq!(favorites[0].path, games[1].path);
+        assert_eq!(favorites[1].path, games[0].path);
+
+        let new_path = PathBuf::from("test_directory/Renamed.rom");
+        db.update_game_path(&games[1].path, &new_path)?;
+        assert!(db.is_favorite(&new_path)?);
+
+        db.remove_favorite(&games[0].path)?;
+        assert!(!db.is_favorite(&games[0].path)?);
+        let favorites = db.select_favorites()?;
+        assert_eq!(favorites.len(), 1);
+        assert_eq!(favorites[0].path, new_path);
+
+        db.delete_game(&new_path)?;
+        assert!(db.select_favorites()?.is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_collections() -> Result<()> {
+        let db = Database::in_memory().unwrap();
+
+        let games = vec![
+            NewGame {
+                name: "Game One".to_string(),
+                path: PathBuf::from("test_directory/Game One.rom"),
+                image: None,
+                core: None,
+                rating: None,
+                release_date: None,
+                developer: None,
+                publisher: None,
+                genres: Vec::new(),
+            },
+            NewGame {
+                name: "Game Two".to_string(),
+                path: PathBuf::from("other_directory/Game Two.rom"),
+                image: None,
+                core: None,
+                rating: None,
+                release_date: None,
+                developer: None,
+                publisher: None,
+                genres: Vec::new(),
+            },
+        ];
+        db.update_games(&games)?;
+
+        let rpgs = db.create_collection("RPGs")?;
+        let party = db.create_collection("Party")?;
+        assert_eq!(db.create_collection("RPGs")?, rpgs);
+        assert_eq!(
+            db.select_collections()?
+                .into_iter()
+                .map(|c| c.name)
+                .collect::<Vec<_>>(),
+            vec!["Party".to_string(), "RPGs".to_string()]
+        );
+
+        db.add_to_collection(rpgs, &games[1].path)?;
+        db.add_to_collection(rpgs, &games[0].path)?;
+        db.add_to_collection(rpgs, &games[1].path)?;
+        db.add_to_collection(party, &games[0].path)?;
+        let in_collection = db.select_games_in_collection(rpgs)?;
+        assert_eq!(in_collection.len(), 2);
+        assert_eq!(in_collection[0].path, games[1].path);
+        assert_eq!(in_collection[1].path, games[0].path);
+        assert!(db.is_in_collection(party, &games[0].path)?);
+        assert!(!db.is_in_collection(party, &games[1].path)?);
+
+        db.remove_from_collection(rpgs, &games[1].path)?;
+        assert_eq!(
+            db.select_collection_paths(rpgs)?,
+            vec![games[0].path.clone()]
+        );
+
+        let missing = PathBuf::from("test_directory/Missing.rom");
+        db.set_collection_games(rpgs, &[missing.clone(), games[1].path.clone()])?;
+        assert_eq!(
+            db.select_collection_paths(rpgs)?,
+            vec![missing, games[1].path.clone()]
+        );
+        assert_eq!(db.select_games_in_collection(rpgs)?.len(), 1);
+
+        db.rename_collection(party, "Party Games")?;
+        assert_eq!(db.select_collection("Party")?, None);
+        assert_eq!(
+            db.select_collection("Party Games")?,
+            Some(Collection {
+                id: party,
+                name: "Party Games".to_string()
+            })
+        );
+
+        db.delete_collection(party)?;
+        assert_eq!(db.select_collections()?.len(), 1);
+        assert!(!db.is_in_collection(party, &games[0].path)?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_game_hashes() -> Result<()> {
+        let db = Database::in_memory().unwrap();
+
+        let game = NewGame {
+            name: "Tetris".to_string(),
+            path: PathBuf::from("test_directory/Tetris.gb"),
+            image: None,
+            core: None,
+            rating: None,
+            release_date: None,
+            developer: None,
+            publisher: None,
+            genres: Vec::new(),
+        };
+        db.update_games(std::slice::from_ref(&game))?;
+        assert!(db.select_game_hashes()?.is_empty());
+        assert_eq!(db.select_game_hash(&game.path)?, None);
+
+        let hash = GameHash {
+            path: game.path.clone(),
+            hash: RomHash {
+                size: 32768,
+                crc32: 0xaa58_e6a4,
+                md5: "982ed5d2b12a0377eb14bcdc4123744e".to_string(),
+                sha1: "74591cc9501af93873f9a5d3eb12da12c0723bbc".to_string(),
+            },
+            title: Some("Tetris".to_string()),
+            region: Some("World".to_string()),
+            revision: Some("Rev 1".to_string()),
+        };
+        db.update_game_hashes(std::slice::from_ref(&hash))?;
+        assert_eq!(db.select_game_hashes()?, vec![hash.clone()]);
+        assert_eq!(db.select_game_hash(&game.path)?, Some(hash));
+
+        // Re-indexing keeps the hashes
+        db.update_games(std::slice::from_ref(&game))?;
+        assert_eq!(db.select_game_hashes()?.len(), 1);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_groups() -> Result<()> {
+        let db = Database::in_memory().unwrap();
+
+        let games = vec![
+            NewGame {
+                name: "Golden Sun".to_string(),
+                path: PathBuf::from("test_directory/Golden Sun.gba"),
+                image: None,
+                core: None,
+                rating: None,
+                release_date: Some(NaiveDate::from_ymd_opt(2001, 8, 1).unwrap()),
+                developer: Some("Camelot".to_string()),
+                publisher: Some("Nintendo".to_string()),
+                genres: vec!["RPG".to_string()],
+            },
+            NewGame {
+                name: "Chrono Trigger".to_string(),
+                path: PathBuf::from("test_directory/Chrono Trigger.sfc"),
+                image: None,
+                core: None,
+                rating: None,
+                release_date: Some(NaiveDate::from_ymd_opt(1995, 3, 11).unwrap()),
+                developer: Some("Square".to_string()),
+                publisher: Some("Square".to_string()),
+                genres: vec!["RPG".to_string(), "Adventure".to_string()],
+            },
+            NewGame {
+                name: "Unknown".to_string(),
+                path: PathBuf::from("test_directory/Unknown.gb"),
+                image: None,
+                core: None,
+                rating: None,
+                release_date: None,
+                developer: None,
+                publisher: None,
+                genres: Vec::new(),
+            },
+        ];
+        db.update_games(&games)?;
+
+        assert_eq!(db.select_groups(GroupBy::Genre)?, vec!["Adventure", "RPG"]);
+        assert_eq!(
+            db.select_groups(GroupBy::Developer)?,
+            vec!["Camelot", "Square"]
+        );
+        assert_eq!(
+            db.select_groups(GroupBy::Publisher)?,
+            vec!["Nintendo", "Square"]
+        );
+        assert_eq!(db.select_groups(GroupBy::Decade)?, vec!["1990", "2000"]);
+
+        let names = |games: Vec<Game>| games.into_iter().map(|g| g.name).collect::<Vec<_>>();
+        assert_eq!(
+            names(db.select_games_in_group(GroupBy::Genre, "RPG")?),
+            vec!["Chrono Trigger", "Golden Sun"]
+        );
+        assert_eq!(
+            names(db.select_games_in_group(GroupBy::Genre, "Adventure")?),
+            vec!["Chrono Trigger"]
+        );
+        assert_eq!(
+            names(db.select_games_in_group(GroupBy::Developer, "Camelot")?),
+            vec!["Golden Sun"]
+        );
+        assert_eq!(
+            names(db.select_games_in_group(GroupBy::Publisher, "Square")?),
+            vec!["Chrono Trigger"]
+        );
+        assert_eq!(
+            names(db.select_games_in_group(GroupBy::Decade, "2000")?),
+            vec!["Golden Sun"]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_indexed_files() -> Result<()> {
+        let db = Database::in_memory().unwrap();
+        let dir = Path::new("test_directory");
+
+        assert_eq!(db.get_directory_mtime(dir)?, None);
+        let fingerprint = GamelistFingerprint {
+            size: 42,
+            mtime: 2000,
+        };
+        db.set_gamelist_fingerprint(dir, fingerprint)?;
+        db.set_directory_mtime(dir, 1000)?;
+        assert_eq!(db.get_directory_mtime(dir)?, Some(1000));
+        assert_eq!(db.get_gamelist_fingerprint(dir)?, Some(fingerprint));
+        assert_eq!(db.select_indexed_directories()?, vec![dir.to_path_buf()]);
+
+        let files = vec![
+            (dir.join("Game One.rom"), 1024),
+            (dir.join("Game Two.rom"), 2048),
+        ];
+        db.set_indexed_files(dir, &files)?;
+        db.set_indexed_files(dir, &files[1..])?;
+        assert_eq!(db.select_indexed_files(dir)?, files[1..].to_vec());
+
+        db.delete_directory(dir)?;
+        assert_eq!(db.get_directory_mtime(dir)?, None);
+        assert!(db.select_indexed_files(dir)?.is_empty());
+        assert!(db.select_indexed_directories()?.is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_battery_log() -> Result<()> {
+        let db = Database::in_memory().unwrap();
+        let reading = |time: i64, percentage: i32, path: Option<&str>| BatteryReading {
+            time: DateTime::from_timestamp(time, 0).unwrap(),
+            percentage,
+            charging: false,
+            path: path.map(PathBuf::from),
+            core: path.map(|_| "mgba".to_string()),
+        };
+
+        let since = DateTime::from_timestamp(0, 0).unwrap();
+        db.add_battery_reading(&reading(1000, 90, None), since)?;
+        db.add_battery_reading(
+            &reading(2000, 85, Some("test_directory/Game One.rom")),
+            since,
+        )?;
+        db.add_battery_reading(
+            &reading(3000, 80, Some("test_directory/Game One.rom")),
+            DateTime::from_timestamp(1500, 0).unwrap(),
+        )?;
+
+        assert_eq!(
+            db.select_battery_log(since)?,
+            vec![
+                reading(2000, 85, Some("test_directory/Game One.rom")),
+                reading(3000, 80, Some("test_directory/Game One.rom")),
+            ]
+        );
+        assert_eq!(
+            db.select_battery_log(DateTime::from_timestamp(2500, 0).unwrap())?,
+            vec![reading(3000, 80, Some("test_directory/Game One.rom"))]
+        );
+
+        db.update_game_path(
+            Path::new("test_directory/Game One.rom"),
+            Path::new("test_directory/Game One (USA).rom"),
+        )?;
+        assert_eq!(
+            db.select_battery_log(since)?[0].path,
+            Some(PathBuf::from("test_directory/Game One (USA).rom"))
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_sessions() -> Result<()> {
+        let db = Database::in_memory().unwrap();
+        let session = |path: &str, start: i64, minutes: i64| {
+            let start = DateTime::from_timestamp(start, 0).unwrap();
+            Session {
+                path: PathBuf::from(path),
+                core: Some("mgba".to_string()),
+                start,
+                end: start + Duration::minutes(minutes),
+                duration: Duration::minutes(minutes),
+            }
+        };
+
+        let sessions = vec![
+            session("test_directory/Game One.rom", 1000, 30),
+            session("test_directory/Game Two.rom", 5000, 10),
+            session("test_directory/Game One.rom", 9000, 45),
+        ];
+        for session in &sessions {
+            db.add_session(session)?;
+        }
+
+        assert_eq!(
+            db.select_sessions(DateTime::from_timestamp(2000, 0).unwrap())?,
+            sessions[1..].to_vec()
+        );
+        assert_eq!(
+            db.select_game_sessions(Path::new("test_directory/Game One.rom"), 10)?,
+            vec![sessions[2].clone(), sessions[0].clone()]
+        );
+
+        db.update_game_path(
+            Path::new("test_directory/Game One.rom"),
+            Path::new("test_directory/Game One (USA).rom"),
+        )?;
+        assert_eq!(
+            db.select_game_sessions(Path::new("test_directory/Game One (USA).rom"), 1)?,
+            vec![session("test_directory/Game One (USA).rom", 9000, 45)]
+        );
+
+        db.reset_game(Path::new("test_directory/Game One (USA).rom"))?;
+        assert!(db
+            .select_game_sessions(Path::new("test_directory/Game One (USA).rom"), 10)?
+            .is_empty());
+        assert_eq!(db.select_sessions(DateTime::UNIX_EPOCH)?.len(), 1);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_hidden_games() -> Result<()> {
+        let db = Database::in_memory().unwrap();
+        let game = |name: &str, genre: &str| NewGame {
+            name: name.to_string(),
+            path: PathBuf::from(format!("test_directory/{name}.rom")),
+            image: None,
+            core: None,
+            rating: None,
+            release_date: None,
+            developer: None,
+            publisher: None,
+            genres: vec![genre.to_string()],
+        };
+
+        let games = vec![game("Game One", "Action"), game("Game Two", "Horror")];
+        db.update_games(&games)?;
+        for game in &games {
+            db.increment_play_count(game)?;
+            db.add_favorite(&game.path)?;
+        }
+
+        db.set_hidden_games(&[games[1].path.clone()])?;
+        let names = |games: Vec<Game>| games.into_iter().map(|g| g.name).collect::<Vec<_>>();
+        assert_eq!(names(db.select_last_played(10)?), vec!["Game One"]);
+        assert_eq!(names(db.select_most_played(10)?), vec!["Game One"]);
+        assert_eq!(names(db.select_random(10)?), vec!["Game One"]);
+        assert_eq!(names(db.search("Game", 10)?), vec!["Game One"]);
+        assert_eq!(names(db.select_favorites()?), vec!["Game One"]);
+        assert_eq!(
+            names(db.select_visible_games_in_directory(Path::new("test_directory"))?),
+            vec!["Game One"]
+        );
+        assert_eq!(
+            db.select_games_in_directory(Path::new("test_directory"))?
+                .len(),
+            2
+        );
+        assert_eq!(db.select_groups(GroupBy::Genre)?, vec!["Action"]);
+        assert!(db
+            .select_games_in_group(GroupBy::Genre, "Horror")?
+            .is_empty());
+        assert!(db.select_game(&games[1].path)?.is_some());
+        assert_eq!(names(db.select_visible_games()?), vec!["Game One"]);
+        assert_eq!(db.select_all_games()?.len(), 2);
+
+        db.set_hidden_games(&[])?;
+        assert_eq!(db.search("Game", 10)?.len(), 2);
+
+        Ok(())
+    }
 }
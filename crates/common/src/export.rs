@@ -0,0 +1,471 @@
+use std::ffi::OsString;
+use std::fmt;
+use std::fs::{self, File};
+use std::path::{Path, PathBuf};
+
+use anyhow::{anyhow, Result};
+use chrono::NaiveDate;
+use log::{debug, error, info};
+use serde::{Deserialize, Serialize};
+
+use crate::constants::ALLIUM_EXPORT_DIR;
+use crate::database::{Database, ExportedGame};
+
+/// File names of the tables in a CSV export, which is a directory with one file per table.
+const GAMES_CSV: &str = "games.csv";
+const GUIDES_CSV: &str = "guides.csv";
+const KEY_VALUE_CSV: &str = "key_value.csv";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ExportFormat {
+    /// A single JSON file.
+    Json,
+    /// A directory with a CSV file per table.
+    Csv,
+}
+
+impl ExportFormat {
+    /// Where exports in this format are written to by default.
+    pub fn default_path(&self) -> PathBuf {
+        match self {
+            Self::Json => ALLIUM_EXPORT_DIR.join("play-history.json"),
+            Self::Csv => ALLIUM_EXPORT_DIR.join("play-history"),
+        }
+    }
+
+    /// The format of an existing export: CSV if it is a directory, JSON otherwise.
+    pub fn detect(path: &Path) -> Self {
+        if path.is_dir() {
+            Self::Csv
+        } else {
+            Self::Json
+        }
+    }
+}
+
+/// Play statistics and library metadata, used to move play history between devices. Paths are
+/// relative to the SD card, so that they are the same on every device.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Export {
+    pub games: Vec<ExportedGame>,
+    pub guides: Vec<ExportedGuide>,
+    pub key_value: Vec<ExportedKeyValue>,
+}
+
+/// Reading position of a guide.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ExportedGuide {
+    pub path: PathBuf,
+    pub cursor: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ExportedKeyValue {
+    pub key: String,
+    pub value: String,
+}
+
+/// What was imported by `Export::merge_into`.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct ImportReport {
+    /// Games whose play history was added to an existing game.
+    pub merged: usize,
+    /// Games that were not in the database, but whose ROM exists.
+    pub added: usize,
+    /// Games that were not in the database, and whose ROM doesn't exist.
+    pub skipped: usize,
+    /// Guide reading positions that were imported.
+    pub guides: usize,
+    /// Key-value pairs that were not set yet.
+    pub key_value: usize,
+}
+
+impl ImportReport {
+    fn add(&mut self, other: ImportReport) {
+        self.merged += other.merged;
+        self.added += other.added;
+        self.skipped += other.skipped;
+        self.guides += other.guides;
+        self.key_value += other.key_value;
+    }
+}
+
+impl fmt::Display for ImportReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} games merged, {} added, {} skipped, {} guides, {} settings",
+            self.merged, self.added, self.skipped, self.guides, self.key_value
+        )
+    }
+}
+
+impl Export {
+    /// Reads the games, guide reading positions and key-value pairs from the database. Paths are
+    /// made relative to `root`.
+    pub fn from_database(database: &Database, root: &Path) -> Result<Self> {
+        let games = database
+            .select_exported_games()?
+            .into_iter()
+            .map(|game| ExportedGame {
+                path: relative_path(&game.path, root),
+                ..game
+            })
+            .collect();
+        let guides = database
+            .select_guide_cursors()?
+            .into_iter()
+            .map(|(path, cursor)| ExportedGuide {
+                path: relative_path(&path, root),
+                cursor,
+            })
+            .collect();
+        let key_value = database
+            .select_key_values()?
+            .into_iter()
+            .map(|(key, value)| ExportedKeyValue { key, value })
+            .collect();
+
+        Ok(Self {
+            games,
+            guides,
+            key_value,
+        })
+    }
+
+    /// Merges the export into the database. Games are matched by the hash of their ROM, or by
+    /// their path relative to `root`. Play counts and play time are added up, so the same export
+    /// should only be imported once.
+    pub fn merge_into(&self, database: &Database, root: &Path) -> Result<ImportReport> {
+        let mut report = ImportReport::default();
+
+        for game in &self.games {
+            let by_hash =
+                database.select_game_path_by_hash(game.sha1.as_deref(), game.md5.as_deref())?;
+            let path = match by_hash {
+                Some(path) => path,
+                None => root.join(&game.path),
+            };
+
+            if database.select_game(&path)?.is_some() {
+                report.merged += 1;
+            } else if path.exists() {
+                report.added += 1;
+            } else {
+                debug!("skipping {}, ROM not found", path.display());
+                report.skipped += 1;
+                continue;
+            }
+            database.merge_exported_game(&path, game)?;
+        }
+
+        for guide in &self.guides {
+            database.merge_guide_cursor(&root.join(&guide.path), guide.cursor)?;
+            report.guides += 1;
+        }
+
+        for ExportedKeyValue { key, value } in &self.key_value {
+            if database.insert_key_value(key, value)? {
+                report.key_value += 1;
+            }
+        }
+
+        Ok(report)
+    }
+
+    /// Writes the export to `path`, a file for JSON or a directory for CSV.
+    pub fn write(&self, path: &Path, format: ExportFormat) -> Result<()> {
+        match format {
+            ExportFormat::Json => {
+                if let Some(parent) = path.parent() {
+                    fs::create_dir_all(parent)?;
+                }
+                serde_json::to_writer_pretty(File::create(path)?, self)?;
+            }
+            ExportFormat::Csv => {
+                fs::create_dir_all(path)?;
+                write_csv(
+                    &path.join(GAMES_CSV),
+                    self.games.iter().cloned().map(GameRecord::from),
+                )?;
+                write_csv(&path.join(GUIDES_CSV), self.guides.iter())?;
+                write_csv(&path.join(KEY_VALUE_CSV), self.key_value.iter())?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Reads an export written by `Export::write`.
+    pub fn read(path: &Path) -> Result<Self> {
+        match ExportFormat::detect(path) {
+            ExportFormat::Json => Ok(serde_json::from_reader(File::open(path)?)?),
+            ExportFormat::Csv => {
+                let games_csv = path.join(GAMES_CSV);
+                if !games_csv.exists() {
+                    return Err(anyhow!("{} not found", games_csv.display()));
+                }
+                Ok(Self {
+                    games: read_csv::<GameRecord>(&games_csv)?
+                        .into_iter()
+                        .map(ExportedGame::from)
+                        .collect(),
+                    guides: read_csv(&path.join(GUIDES_CSV))?,
+                    key_value: read_csv(&path.join(KEY_VALUE_CSV))?,
+                })
+            }
+        }
+    }
+}
+
+/// Imports every export in `dir`: JSON files, and directories of CSV files. Imported exports are
+/// renamed with an `.imported` suffix, so that they are not imported twice. Returns the number of
+/// exports imported, and what they changed.
+pub fn import_dir(database: &Database, root: &Path, dir: &Path) -> Result<(usize, ImportReport)> {
+    let mut count = 0;
+    let mut report = ImportReport::default();
+    if !dir.is_dir() {
+        return Ok((count, report));
+    }
+
+    let mut paths = fs::read_dir(dir)?
+        .filter_map(std::result::Result::ok)
+        .map(|entry| entry.path())
+        .filter(|path| {
+            if path.is_dir() {
+                path.join(GAMES_CSV).exists()
+            } else {
+                path.extension().is_some_and(|ext| ext == "json")
+            }
+        })
+        .collect::<Vec<_>>();
+    paths.sort();
+
+    for path in paths {
+        match Export::read(&path).and_then(|export| export.merge_into(database, root)) {
+            Ok(imported) => {
+                info!("imported {}: {}", path.display(), imported);
+                report.add(imported);
+                count += 1;
+                let mut name = path.file_name().map(OsString::from).unwrap_or_default();
+                name.push(".imported");
+                fs::rename(&path, path.with_file_name(name))?;
+            }
+            Err(e) => error!("failed to import {}: {}", path.display(), e),
+        }
+    }
+
+    Ok((count, report))
+}
+
+/// A row of games.csv. Genres are separated by semicolons, as CSV cells can't hold lists.
+#[derive(Debug, Serialize, Deserialize)]
+struct GameRecord {
+    path: PathBuf,
+    name: String,
+    play_count: i64,
+    play_time: i64,
+    last_played: i64,
+    core: Option<String>,
+    rating: Option<u8>,
+    release_date: Option<NaiveDate>,
+    developer: Option<String>,
+    publisher: Option<String>,
+    genres: String,
+    size: Option<u64>,
+    crc32: Option<u32>,
+    md5: Option<String>,
+    sha1: Option<String>,
+}
+
+impl From<ExportedGame> for GameRecord {
+    fn from(game: ExportedGame) -> Self {
+        Self {
+            path: game.path,
+            name: game.name,
+            play_count: game.play_count,
+            play_time: game.play_time,
+            last_played: game.last_played,
+            core: game.core,
+            rating: game.rating,
+            release_date: game.release_date,
+            developer: game.developer,
+            publisher: game.publisher,
+            genres: game.genres.join(";"),
+            size: game.size,
+            crc32: game.crc32,
+            md5: game.md5,
+            sha1: game.sha1,
+        }
+    }
+}
+
+impl From<GameRecord> for ExportedGame {
+    fn from(record: GameRecord) -> Self {
+        Self {
+            path: record.path,
+            name: record.name,
+            play_count: record.play_count,
+            play_time: record.play_time,
+            last_played: record.last_played,
+            core: record.core,
+            rating: record.rating,
+            release_date: record.release_date,
+            developer: record.developer,
+            publisher: record.publisher,
+            genres: record
+                .genres
+                .split(';')
+                .map(str::trim)
+                .filter(|genre| !genre.is_empty())
+                .map(str::to_string)
+                .collect(),
+            size: record.size,
+            crc32: record.crc32,
+            md5: record.md5,
+            sha1: record.sha1,
+        }
+    }
+}
+
+fn write_csv<T: Serialize>(path: &Path, records: impl Iterator<Item = T>) -> Result<()> {
+    let mut writer = csv::Writer::from_path(path)?;
+    for record in records {
+        writer.serialize(record)?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+/// Reads the records of a CSV file. A missing file has no records.
+fn read_csv<T: for<'de> Deserialize<'de>>(path: &Path) -> Result<Vec<T>> {
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+    let mut reader = csv::Reader::from_path(path)?;
+    Ok(reader.deserialize().collect::<Result<_, _>>()?)
+}
+
+fn relative_path(path: &Path, root: &Path) -> PathBuf {
+    path.strip_prefix(root).unwrap_or(path).to_path_buf()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::database::{GameHash, NewGame};
+    use crate::hash::RomHash;
+
+    use super::*;
+
+    #[test]
+    fn test_export_import() -> Result<()> {
+        let root = std::env::temp_dir().join("allium-test-export");
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(root.join("Roms/GBA"))?;
+        fs::write(root.join("Roms/GBA/Game One.gba"), "one")?;
+        fs::write(root.join("Roms/GBA/Game Two.gba"), "two")?;
+
+        let new_game = |name: &str, path: PathBuf| NewGame {
+            name: name.to_string(),
+            path,
+            image: None,
+            core: Some("mgba".to_string()),
+            rating: None,
+            release_date: None,
+            developer: Some("Nintendo".to_string()),
+            publisher: None,
+            genres: vec!["RPG".to_string(), "Action".to_string()],
+        };
+
+        // The device that is exported from
+        let source = Database::in_memory()?;
+        let one = new_game("Game One", root.join("Roms/GBA/Game One.gba"));
+        let two = new_game("Game Two", root.join("Roms/GBA/Game Two.gba"));
+        let missing = new_game("Missing", root.join("Roms/GBA/Missing.gba"));
+        source.update_games(&[one.clone(), two.clone(), missing.clone()])?;
+        for game in [&one, &two, &missing] {
+            source.increment_play_count(game)?;
+            source.add_play_time(&game.path, chrono::Duration::minutes(30))?;
+        }
+        source.update_guide_cursor(&root.join("Roms/GBA/Guides/Game One.txt"), 100)?;
+        source.insert_key_value("theme", "dark")?;
+
+        let export = Export::from_database(&source, &root)?;
+        assert_eq!(export.games[0].path, Path::new("Roms/GBA/Game One.gba"));
+
+        let json = root.join("export.json");
+        let csv = root.join("export-csv");
+        export.write(&json, ExportFormat::Json)?;
+        export.write(&csv, ExportFormat::Csv)?;
+        assert_eq!(Export::read(&json)?, export);
+        assert_eq!(Export::read(&csv)?, export);
+
+        // The device that is imported into, where Game One was renamed and played
+        let target = Database::in_memory()?;
+        let renamed = new_game("Game One", root.join("Roms/GBA/Game One (USA).gba"));
+        target.update_games(std::slice::from_ref(&renamed))?;
+        target.increment_play_count(&renamed)?;
+        target.add_play_time(&renamed.path, chrono::Duration::minutes(10))?;
+        target.update_game_hashes(&[GameHash {
+            path: renamed.path.clone(),
+            hash: RomHash {
+                size: 3,
+                crc32: 0,
+                md5: String::new(),
+                sha1: "0123456789abcdef".to_string(),
+            },
+            title: None,
+            region: None,
+            revision: None,
+        }])?;
+        target.update_guide_cursor(&root.join("Roms/GBA/Guides/Game One.txt"), 200)?;
+        target.insert_key_value("theme", "light")?;
+
+        let mut export = Export::read(&csv)?;
+        export.games[0].sha1 = Some("0123456789abcdef".to_string());
+        let report = export.merge_into(&target, &root)?;
+        assert_eq!(
+            report,
+            ImportReport {
+                merged: 1,
+                added: 1,
+                skipped: 1,
+                guides: 1,
+                key_value: 0,
+            }
+        );
+
+        let game = target.select_game(&renamed.path)?.unwrap();
+        assert_eq!(game.play_count, 2);
+        assert_eq!(game.play_time, chrono::Duration::minutes(40));
+        let game = target
+            .select_game(&root.join("Roms/GBA/Game Two.gba"))?
+            .unwrap();
+        assert_eq!(game.play_count, 1);
+        assert_eq!(game.genres, vec!["RPG".to_string(), "Action".to_string()]);
+        assert!(target
+            .select_game(&root.join("Roms/GBA/Missing.gba"))?
+            .is_none());
+        assert_eq!(
+            target.get_guide_cursor(&root.join("Roms/GBA/Guides/Game One.txt"))?,
+            200
+        );
+        assert_eq!(
+            target.select_key_values()?,
+            vec![("theme".to_string(), "light".to_string())]
+        );
+
+        // Exports in the import directory are only imported once
+        let import = root.join("import");
+        fs::create_dir_all(&import)?;
+        fs::rename(&json, import.join("export.json"))?;
+        let target = Database::in_memory()?;
+        let (count, report) = import_dir(&target, &root, &import)?;
+        assert_eq!((count, report.added), (1, 2));
+        assert!(import.join("export.json.imported").exists());
+        assert_eq!(import_dir(&target, &root, &import)?.0, 0);
+
+        fs::remove_dir_all(&root)?;
+        Ok(())
+    }
+}
@@ -10,6 +10,7 @@ use log::debug;
 use serde::{Deserialize, Serialize};
 
 use crate::constants::{ALLIUM_GAMES_DIR, ALLIUM_GAME_INFO, ALLIUM_SCRIPTS_DIR};
+use crate::launch_profile::CpuSettings;
 
 #[derive(Debug, Serialize, Deserialize)]
 /// Information about a game. Used to restore a game after a restart, and to calculate playtime.
@@ -28,6 +29,9 @@ pub struct GameInfo {
     pub has_menu: bool,
     /// Whether swap should be enabled.
     pub needs_swap: bool,
+    /// CPU settings to apply while the game is running.
+    #[serde(default)]
+    pub cpu: CpuSettings,
     /// Path to the image.
     pub image: Option<PathBuf>,
     /// Path to the guide text file.
@@ -46,6 +50,7 @@ impl Default for GameInfo {
             args: Vec::new(),
             has_menu: false,
             needs_swap: false,
+            cpu: CpuSettings::default(),
             image: None,
             guide: None,
             start_time: Utc::now(),
@@ -76,6 +81,7 @@ impl GameInfo {
             args,
             has_menu,
             needs_swap,
+            cpu: CpuSettings::default(),
             image,
             guide,
             start_time: Utc::now(),
@@ -124,6 +130,22 @@ impl GameInfo {
         command
     }
 
+    /// Command to run the game, loading the state in `slot` instead of the auto state. Only
+    /// RetroArch games can load a state, other games are run as usual.
+    pub fn command_with_state_slot(self, slot: i8) -> Command {
+        if !self.has_menu {
+            return self.command();
+        }
+        let launch =
+            Path::new(&self.command).with_file_name("launch_without_savestate_auto_load.sh");
+        let mut command = Command::new(launch);
+        command
+            .args(self.args)
+            .arg("--entryslot")
+            .arg(slot.to_string());
+        command
+    }
+
     /// How long the game has been running.
     pub fn play_time(&self) -> Duration {
         Utc::now().signed_duration_since(self.start_time)
@@ -1,9 +1,13 @@
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
+use anyhow::Result;
 use chrono::NaiveDateTime;
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Serialize, Serializer};
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+/// Format of `releasedate` in gamelist.xml.
+const RELEASE_DATE_FORMAT: &str = "%Y%m%dT%H%M%S";
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(rename_all = "kebab-case")]
 pub struct GameList {
     #[serde(default, rename = "game")]
@@ -12,37 +16,102 @@ pub struct GameList {
     pub folders: Vec<Folder>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+impl GameList {
+    /// Parses a gamelist.xml.
+    pub fn from_xml(s: &str) -> Result<Self> {
+        match quick_xml::de::from_str(s) {
+            Ok(gamelist) => Ok(gamelist),
+            Err(quick_xml::DeError::InvalidXml(quick_xml::Error::EscapeError(
+                quick_xml::escape::EscapeError::UnterminatedEntity(..),
+            ))) => {
+                // Some scrapers produce ill-formed XML where ampersands (&) are not escaped,
+                // so we try to failover to replacing them to &amp;
+                // (https://github.com/RReverser/serde-xml-rs/issues/106)
+                let s = s.replace('&', "&amp;");
+                Ok(quick_xml::de::from_str(&s)?)
+            }
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Serializes to a gamelist.xml that can be read by `GameList::from_xml` and EmulationStation.
+    pub fn to_xml(&self) -> Result<String> {
+        let mut s = String::from("<?xml version=\"1.0\"?>\n");
+        let mut serializer = quick_xml::se::Serializer::with_root(&mut s, Some("gameList"))?;
+        serializer.indent(' ', 4);
+        self.serialize(serializer)?;
+        s.push('\n');
+        Ok(s)
+    }
+
+    /// Returns the game with the given path, if any.
+    pub fn game_mut(&mut self, path: &Path) -> Option<&mut Game> {
+        self.games.iter_mut().find(|game| game.path == path)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(rename_all = "kebab-case")]
 pub struct Game {
     pub path: PathBuf,
     pub name: String,
-    #[serde(default, deserialize_with = "optional_path_buf_deserializer")]
+    #[serde(
+        default,
+        deserialize_with = "optional_path_buf_deserializer",
+        skip_serializing_if = "Option::is_none"
+    )]
     pub image: Option<PathBuf>,
-    #[serde(default, deserialize_with = "optional_path_buf_deserializer")]
+    #[serde(
+        default,
+        deserialize_with = "optional_path_buf_deserializer",
+        skip_serializing_if = "Option::is_none"
+    )]
     pub thumbnail: Option<PathBuf>,
-    #[serde(default, deserialize_with = "rating_deserializer")]
+    #[serde(
+        default,
+        deserialize_with = "rating_deserializer",
+        serialize_with = "rating_serializer",
+        skip_serializing_if = "Option::is_none"
+    )]
     pub rating: Option<u8>,
     #[serde(
         default,
         rename = "releasedate",
-        deserialize_with = "optional_naivedatetime_deserializer"
+        deserialize_with = "optional_naivedatetime_deserializer",
+        serialize_with = "optional_naivedatetime_serializer",
+        skip_serializing_if = "Option::is_none"
     )]
     pub release_date: Option<NaiveDateTime>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub developer: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub publisher: Option<String>,
-    #[serde(default, rename = "genre", deserialize_with = "genre_deserializer")]
+    #[serde(
+        default,
+        rename = "genre",
+        deserialize_with = "genre_deserializer",
+        serialize_with = "genre_serializer",
+        skip_serializing_if = "Vec::is_empty"
+    )]
     pub genres: Vec<String>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(rename_all = "kebab-case")]
 pub struct Folder {
     pub path: PathBuf,
     pub name: String,
-    #[serde(default, deserialize_with = "optional_path_buf_deserializer")]
+    #[serde(
+        default,
+        deserialize_with = "optional_path_buf_deserializer",
+        skip_serializing_if = "Option::is_none"
+    )]
     pub image: Option<PathBuf>,
-    #[serde(default, deserialize_with = "optional_path_buf_deserializer")]
+    #[serde(
+        default,
+        deserialize_with = "optional_path_buf_deserializer",
+        skip_serializing_if = "Option::is_none"
+    )]
     pub thumbnail: Option<PathBuf>,
 }
 
@@ -65,6 +134,13 @@ where
     Ok(s.split(',').map(str::trim).map(str::to_string).collect())
 }
 
+fn genre_serializer<S>(genres: &[String], s: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    s.serialize_str(&genres.join(", "))
+}
+
 fn rating_deserializer<'de, D>(d: D) -> Result<Option<u8>, D::Error>
 where
     D: serde::Deserializer<'de>,
@@ -73,12 +149,35 @@ where
     Ok(s.parse::<f32>().map(|rating| (rating * 10.0) as u8).ok())
 }
 
+fn rating_serializer<S>(rating: &Option<u8>, s: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    match rating {
+        Some(rating) => s.serialize_str(&format!("{:.2}", *rating as f32 / 10.0)),
+        None => s.serialize_none(),
+    }
+}
+
 fn optional_naivedatetime_deserializer<'de, D>(d: D) -> Result<Option<NaiveDateTime>, D::Error>
 where
     D: serde::Deserializer<'de>,
 {
     let s = String::deserialize(d)?;
-    Ok(NaiveDateTime::parse_from_str(&s, "%Y%m%dT%H%M%S").ok())
+    Ok(NaiveDateTime::parse_from_str(&s, RELEASE_DATE_FORMAT).ok())
+}
+
+fn optional_naivedatetime_serializer<S>(
+    date: &Option<NaiveDateTime>,
+    s: S,
+) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    match date {
+        Some(date) => s.serialize_str(&date.format(RELEASE_DATE_FORMAT).to_string()),
+        None => s.serialize_none(),
+    }
 }
 
 #[cfg(test)]
@@ -177,19 +276,42 @@ mod tests {
     #[test]
     fn test_ampersand() {
         let s = include_str!("test/gamelist.xml");
-        let s = &s.replace('&', "&amp;");
-        let _: GameList = match quick_xml::de::from_str(s) {
-            Ok(gamelist) => gamelist,
-            Err(quick_xml::DeError::InvalidXml(quick_xml::Error::EscapeError(
-                quick_xml::escape::EscapeError::UnterminatedEntity(..),
-            ))) => {
-                // Some scrapers produce ill-formed XML where ampersands (&) are not escaped,
-                // so we try to failover to replacing them to &amp;
-                // (https://github.com/RReverser/serde-xml-rs/issues/106)
-                let s = s.replace('&', "&amp;");
-                quick_xml::de::from_str(&s).unwrap()
-            }
-            Err(e) => panic!("{:?}", e),
+        GameList::from_xml(&s.replace('&', "&amp;")).unwrap();
+        GameList::from_xml(s).unwrap();
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let game_list = GameList {
+            games: (0..=10)
+                .map(|rating| Game {
+                    path: PathBuf::from(format!("./Game & {}.gba", rating)),
+                    name: format!("Game <{}>", rating),
+                    image: Some(PathBuf::from(format!("./Imgs/Game & {}.png", rating))),
+                    thumbnail: None,
+                    rating: Some(rating),
+                    release_date: NaiveDate::from_ymd_opt(2001, 3, 21)
+                        .unwrap()
+                        .and_hms_opt(0, 0, 0),
+                    developer: Some("Nintendo R&D1".to_string()),
+                    publisher: None,
+                    genres: vec!["Platform".to_string(), "Action".to_string()],
+                })
+                .collect(),
+            folders: vec![Folder {
+                path: PathBuf::from("./Hacks"),
+                name: "Hacks".to_string(),
+                image: None,
+                thumbnail: None,
+            }],
         };
+
+        let xml = game_list.to_xml().unwrap();
+        assert!(xml.contains("<gameList>"));
+        assert!(xml.contains("<releasedate>20010321T000000</releasedate>"));
+        assert!(!xml.contains("<publisher"));
+
+        let parsed = GameList::from_xml(&xml).unwrap();
+        assert_eq!(parsed, game_list);
     }
 }
@@ -0,0 +1,173 @@
+use std::ffi::OsStr;
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+
+use anyhow::Result;
+use md5::Md5;
+use sha1::{Digest, Sha1};
+use sha2::Sha256;
+
+use crate::constants::MAX_HASH_SIZE;
+
+/// Size and hashes of a ROM, as listed in No-Intro and Redump DATs.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct RomHash {
+    pub size: u64,
+    pub crc32: u32,
+    /// Lowercase hex MD5.
+    pub md5: String,
+    /// Lowercase hex SHA1.
+    pub sha1: String,
+}
+
+/// Hashes a ROM. Zip archives are hashed by their largest file, as DATs describe the uncompressed
+/// ROM. Returns None if the ROM is larger than `MAX_HASH_SIZE`, or if the archive is empty.
+pub fn hash_rom(path: &Path) -> Result<Option<RomHash>> {
+    if is_zip(path) {
+        let mut archive = zip::ZipArchive::new(File::open(path)?)?;
+        let Some((i, size)) = largest_file(&mut archive)? else {
+            return Ok(None);
+        };
+        if size > MAX_HASH_SIZE {
+            return Ok(None);
+        }
+        let hash = hash_reader(archive.by_index(i)?, size)?;
+        Ok(Some(hash))
+    } else {
+        let size = path.metadata()?.len();
+        if size > MAX_HASH_SIZE {
+            return Ok(None);
+        }
+        hash_reader(File::open(path)?, size).map(Some)
+    }
+}
+
+/// Reads a ROM into memory, along with its file name. Zip archives are read by their largest file,
+/// like `hash_rom`. Returns None if the ROM is larger than `MAX_HASH_SIZE`, or if the archive is
+/// empty.
+pub fn read_rom(path: &Path) -> Result<Option<(String, Vec<u8>)>> {
+    if is_zip(path) {
+        let mut archive = zip::ZipArchive::new(File::open(path)?)?;
+        let Some((i, size)) = largest_file(&mut archive)? else {
+            return Ok(None);
+        };
+        if size > MAX_HASH_SIZE {
+            return Ok(None);
+        }
+        let mut file = archive.by_index(i)?;
+        let mut data = Vec::with_capacity(size as usize);
+        file.read_to_end(&mut data)?;
+        Ok(Some((file.name().to_string(), data)))
+    } else {
+        if path.metadata()?.len() > MAX_HASH_SIZE {
+            return Ok(None);
+        }
+        let name = path
+            .file_name()
+            .and_then(OsStr::to_str)
+            .unwrap_or_default()
+            .to_string();
+        Ok(Some((name, std::fs::read(path)?)))
+    }
+}
+
+/// Returns the size of a ROM as it would be hashed, without reading all of it. This is the size of
+/// the largest file in zip archives.
+pub fn rom_size(path: &Path) -> Result<Option<u64>> {
+    if is_zip(path) {
+        let mut archive = zip::ZipArchive::new(File::open(path)?)?;
+        Ok(largest_file(&mut archive)?.map(|(_, size)| size))
+    } else {
+        Ok(Some(path.metadata()?.len()))
+    }
+}
+
+fn is_zip(path: &Path) -> bool {
+    path.extension()
+        .and_then(OsStr::to_str)
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("zip"))
+}
+
+/// Returns the index and uncompressed size of the largest file in an archive.
+fn largest_file(archive: &mut zip::ZipArchive<File>) -> Result<Option<(usize, u64)>> {
+    let mut largest: Option<(usize, u64)> = None;
+    for i in 0..archive.len() {
+        let file = archive.by_index_raw(i)?;
+        if file.is_file() && !matches!(largest, Some((_, size)) if size >= file.size()) {
+            largest = Some((i, file.size()));
+        }
+    }
+    Ok(largest)
+}
+
+fn hash_reader(mut reader: impl Read, size: u64) -> Result<RomHash> {
+    let mut crc32 = crc32fast::Hasher::new();
+    let mut md5 = Md5::new();
+    let mut sha1 = Sha1::new();
+
+    let mut buf = vec![0; 64 * 1024];
+    loop {
+        let n = reader.read(&mut buf)?;
+        if n == 0 {
+            break;
+        }
+        crc32.update(&buf[..n]);
+        md5.update(&buf[..n]);
+        sha1.update(&buf[..n]);
+    }
+
+    Ok(RomHash {
+        size,
+        crc32: crc32.finalize(),
+        md5: hex(&md5.finalize()),
+        sha1: hex(&sha1.finalize()),
+    })
+}
+
+/// Lowercase hex SHA-256 of a PIN, as stored in settings that are protected by one.
+pub fn hash_pin(pin: &str) -> String {
+    hex(&Sha256::digest(pin.as_bytes()))
+}
+
+pub(crate) fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::io::Write;
+
+    use super::*;
+
+    #[test]
+    fn test_hash_rom() -> Result<()> {
+        let dir = std::env::temp_dir().join("allium-test-hash-rom");
+        fs::create_dir_all(&dir)?;
+
+        let expected = RomHash {
+            size: 3,
+            crc32: 0x352441c2,
+            md5: "900150983cd24fb0d6963f7d28e17f72".to_string(),
+            sha1: "a9993e364706816aba3e25717850c26c9cd0d89d".to_string(),
+        };
+
+        let path = dir.join("abc.gba");
+        fs::write(&path, "abc")?;
+        assert_eq!(hash_rom(&path)?, Some(expected.clone()));
+
+        let path = dir.join("abc.zip");
+        let mut zip = zip::ZipWriter::new(File::create(&path)?);
+        zip.start_file("readme.txt", Default::default())?;
+        zip.write_all(b"a")?;
+        zip.start_file("abc.gba", Default::default())?;
+        zip.write_all(b"abc")?;
+        zip.finish()?;
+        assert_eq!(hash_rom(&path)?, Some(expected));
+        assert_eq!(rom_size(&path)?, Some(3));
+
+        fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,296 @@
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::PathBuf;
+
+use anyhow::Result;
+use enum_map::EnumMap;
+use log::{debug, warn};
+use serde::{Deserialize, Serialize};
+
+use crate::constants::ALLIUM_HOTKEY_SETTINGS;
+use crate::locale::Locale;
+use crate::platform::{Key, KeyEvent};
+
+/// Buttons that can be bound to a hotkey, in the order they are listed in settings.
+pub const HOTKEY_BUTTONS: [Key; 17] = [
+    Key::Up,
+    Key::Down,
+    Key::Left,
+    Key::Right,
+    Key::A,
+    Key::B,
+    Key::X,
+    Key::Y,
+    Key::Start,
+    Key::Select,
+    Key::L,
+    Key::R,
+    Key::L2,
+    Key::R2,
+    Key::Power,
+    Key::VolDown,
+    Key::VolUp,
+];
+
+/// Name of a button, as printed on the device.
+pub fn button_name(key: Key) -> &'static str {
+    match key {
+        Key::Up => "Up",
+        Key::Down => "Down",
+        Key::Left => "Left",
+        Key::Right => "Right",
+        Key::A => "A",
+        Key::B => "B",
+        Key::X => "X",
+        Key::Y => "Y",
+        Key::Start => "Start",
+        Key::Select => "Select",
+        Key::L => "L",
+        Key::R => "R",
+        Key::Menu => "Menu",
+        Key::L2 => "L2",
+        Key::R2 => "R2",
+        Key::Power => "Power",
+        Key::VolDown => "Vol-",
+        Key::VolUp => "Vol+",
+        Key::Unknown => "?",
+    }
+}
+
+/// What alliumd does when a hotkey is pressed.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum HotkeyAction {
+    BrightnessUp,
+    BrightnessDown,
+    VolumeUp,
+    VolumeDown,
+    Screenshot,
+    /// Saves to the current state slot of the running game.
+    QuickSave,
+    /// Loads from the current state slot of the running game.
+    QuickLoad,
+    FastForward,
+    /// Launches the app in `path`, e.g. `Apps/Activity Tracker.pak`.
+    LaunchApp {
+        path: PathBuf,
+    },
+    Suspend,
+}
+
+impl HotkeyAction {
+    /// Whether the action is repeated while the buttons are held.
+    pub fn is_repeatable(&self) -> bool {
+        matches!(
+            self,
+            HotkeyAction::BrightnessUp
+                | HotkeyAction::BrightnessDown
+                | HotkeyAction::VolumeUp
+                | HotkeyAction::VolumeDown
+        )
+    }
+
+    pub fn label(&self, locale: &Locale) -> String {
+        match self {
+            HotkeyAction::BrightnessUp => locale.t("hotkeys-brightness-up"),
+            HotkeyAction::BrightnessDown => locale.t("hotkeys-brightness-down"),
+            HotkeyAction::VolumeUp => locale.t("hotkeys-volume-up"),
+            HotkeyAction::VolumeDown => locale.t("hotkeys-volume-down"),
+            HotkeyAction::Screenshot => locale.t("hotkeys-screenshot"),
+            HotkeyAction::QuickSave => locale.t("hotkeys-quick-save"),
+            HotkeyAction::QuickLoad => locale.t("hotkeys-quick-load"),
+            HotkeyAction::FastForward => locale.t("hotkeys-fast-forward"),
+            HotkeyAction::LaunchApp { path } => {
+                let name = path
+                    .file_stem()
+                    .map(|name| name.to_string_lossy().to_string())
+                    .unwrap_or_default();
+                let mut map = HashMap::new();
+                map.insert("app".to_string(), name.into());
+                locale.ta("hotkeys-launch-app", &map)
+            }
+            HotkeyAction::Suspend => locale.t("hotkeys-suspend"),
+        }
+    }
+}
+
+/// A global hotkey: buttons pressed while holding Menu.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Hotkey {
+    /// Buttons pressed together with Menu. The last one triggers the hotkey, the others must
+    /// already be held. The hotkey is disabled if this is empty.
+    pub buttons: Vec<Key>,
+    pub action: HotkeyAction,
+}
+
+impl Hotkey {
+    pub fn new(buttons: Vec<Key>, action: HotkeyAction) -> Self {
+        Self { buttons, action }
+    }
+
+    /// All buttons of the hotkey, including Menu.
+    pub fn chord(&self) -> Vec<Key> {
+        std::iter::once(Key::Menu)
+            .chain(self.buttons.iter().copied())
+            .collect()
+    }
+
+    /// Whether the key event triggers this hotkey, given the buttons that are held.
+    pub fn matches(&self, event: KeyEvent, keys: &EnumMap<Key, bool>) -> bool {
+        let Some((&trigger, held)) = self.buttons.split_last() else {
+            return false;
+        };
+        // Power acts when it is released, as it does on its own
+        let triggered = match event {
+            KeyEvent::Pressed(key) => key == trigger && key != Key::Power,
+            KeyEvent::Autorepeat(key) => {
+                key == trigger && key != Key::Power && self.action.is_repeatable()
+            }
+            KeyEvent::Released(key) => key == trigger && key == Key::Power,
+        };
+        triggered && keys[Key::Menu] && held.iter().all(|&key| keys[key])
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HotkeySettings {
+    pub hotkeys: Vec<Hotkey>,
+}
+
+impl HotkeySettings {
+    pub fn new() -> Self {
+        Self {
+            hotkeys: vec![
+                Hotkey::new(vec![Key::Power], HotkeyAction::Screenshot),
+                Hotkey::new(vec![Key::Up], HotkeyAction::BrightnessUp),
+                Hotkey::new(vec![Key::Down], HotkeyAction::BrightnessDown),
+                Hotkey::new(vec![Key::Right], HotkeyAction::VolumeUp),
+                Hotkey::new(vec![Key::Left], HotkeyAction::VolumeDown),
+                Hotkey::new(vec![Key::VolUp], HotkeyAction::BrightnessUp),
+                Hotkey::new(vec![Key::VolDown], HotkeyAction::BrightnessDown),
+                Hotkey::new(vec![], HotkeyAction::QuickSave),
+                Hotkey::new(vec![], HotkeyAction::QuickLoad),
+                Hotkey::new(vec![], HotkeyAction::FastForward),
+                Hotkey::new(vec![], HotkeyAction::Suspend),
+            ],
+        }
+    }
+
+    pub fn load() -> Result<Self> {
+        if ALLIUM_HOTKEY_SETTINGS.exists() {
+            debug!("found state, loading from file");
+            if let Ok(json) = fs::read_to_string(ALLIUM_HOTKEY_SETTINGS.as_path()) {
+                if let Ok(json) = serde_json::from_str(&json) {
+                    return Ok(json);
+                }
+            }
+            warn!("failed to read state file, removing");
+            fs::remove_file(ALLIUM_HOTKEY_SETTINGS.as_path())?;
+        }
+        Ok(Self::new())
+    }
+
+    pub fn save(&self) -> Result<()> {
+        let json = serde_json::to_string(&self).unwrap();
+        File::create(ALLIUM_HOTKEY_SETTINGS.as_path())?.write_all(json.as_bytes())?;
+        Ok(())
+    }
+
+    /// Returns the hotkey triggered by the key event, if any.
+    pub fn find(&self, event: KeyEvent, keys: &EnumMap<Key, bool>) -> Option<&Hotkey> {
+        self.hotkeys.iter().find(|h| h.matches(event, keys))
+    }
+
+    /// Hotkeys that have buttons bound to them.
+    pub fn enabled(&self) -> impl Iterator<Item = &Hotkey> {
+        self.hotkeys.iter().filter(|h| !h.buttons.is_empty())
+    }
+}
+
+impl Default for HotkeySettings {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn held(keys: &[Key]) -> EnumMap<Key, bool> {
+        let mut map = EnumMap::default();
+        for &key in keys {
+            map[key] = true;
+        }
+        map
+    }
+
+    #[test]
+    fn test_hotkeys() -> Result<()> {
+        let settings = HotkeySettings {
+            hotkeys: vec![
+                Hotkey::new(vec![Key::Up], HotkeyAction::BrightnessUp),
+                Hotkey::new(vec![Key::L, Key::A], HotkeyAction::QuickSave),
+                Hotkey::new(vec![Key::A], HotkeyAction::QuickLoad),
+                Hotkey::new(
+                    vec![Key::Select],
+                    HotkeyAction::LaunchApp {
+                        path: PathBuf::from("Apps/Activity Tracker.pak"),
+                    },
+                ),
+                Hotkey::new(vec![Key::Power], HotkeyAction::Screenshot),
+                Hotkey::new(vec![], HotkeyAction::Suspend),
+            ],
+        };
+
+        let action = |event, keys: &[Key]| {
+            settings
+                .find(event, &held(keys))
+                .map(|hotkey| hotkey.action.clone())
+        };
+
+        assert_eq!(
+            action(KeyEvent::Pressed(Key::Up), &[Key::Menu, Key::Up]),
+            Some(HotkeyAction::BrightnessUp)
+        );
+        assert_eq!(
+            action(KeyEvent::Autorepeat(Key::Up), &[Key::Menu, Key::Up]),
+            Some(HotkeyAction::BrightnessUp)
+        );
+        assert_eq!(action(KeyEvent::Pressed(Key::Up), &[Key::Up]), None);
+        assert_eq!(action(KeyEvent::Released(Key::Up), &[Key::Menu]), None);
+
+        // Chords match before their last button alone, and are not repeated
+        assert_eq!(
+            action(KeyEvent::Pressed(Key::A), &[Key::Menu, Key::L, Key::A]),
+            Some(HotkeyAction::QuickSave)
+        );
+        assert_eq!(
+            action(KeyEvent::Autorepeat(Key::A), &[Key::Menu, Key::L, Key::A]),
+            None
+        );
+        assert_eq!(
+            action(KeyEvent::Pressed(Key::A), &[Key::Menu, Key::A]),
+            Some(HotkeyAction::QuickLoad)
+        );
+
+        assert_eq!(
+            action(KeyEvent::Pressed(Key::Power), &[Key::Menu, Key::Power]),
+            None
+        );
+        assert_eq!(
+            action(KeyEvent::Released(Key::Power), &[Key::Menu]),
+            Some(HotkeyAction::Screenshot)
+        );
+
+        assert_eq!(settings.enabled().count(), 5);
+        assert_eq!(settings.hotkeys[1].chord(), vec![Key::Menu, Key::L, Key::A]);
+
+        let json = serde_json::to_string(&settings)?;
+        assert!(json.contains(r#"{"type":"launch_app","path":"Apps/Activity Tracker.pak"}"#));
+        assert_eq!(serde_json::from_str::<HotkeySettings>(&json)?, settings);
+
+        Ok(())
+    }
+}
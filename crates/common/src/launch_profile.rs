@@ -0,0 +1,223 @@
+use std::fs::{self, File};
+use std::path::{Path, PathBuf};
+
+use anyhow::Result;
+use log::{debug, warn};
+use serde::{Deserialize, Serialize};
+
+use crate::constants::ALLIUM_CPU_DEFAULTS;
+
+/// Directory containing the cpufreq settings of the CPU.
+const CPUFREQ_DIR: &str = "/sys/devices/system/cpu/cpu0/cpufreq";
+
+/// Options that change how a game is launched. Everything is optional, so that a profile only
+/// overrides what it sets.
+///
+/// Profiles can be set per console and per game. `ConsoleMapper::launch_game` layers them, from
+/// lowest to highest priority:
+/// 1. the console's defaults from `consoles.toml` and the core's defaults from `cores.toml`
+/// 2. the console's profile
+/// 3. the game's profile
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LaunchProfile {
+    /// Core to launch with, overriding the console's default core.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub core: Option<String>,
+    /// Extra arguments passed to RetroArch.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub retroarch_args: Vec<String>,
+    /// Config files passed to RetroArch with `--appendconfig`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub append_configs: Vec<PathBuf>,
+    /// CPU frequency scaling while the game is running.
+    #[serde(default, skip_serializing_if = "CpuSettings::is_empty")]
+    pub cpu: CpuSettings,
+    /// Whether swap should be enabled, overriding the core's `swap`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub swap: Option<bool>,
+}
+
+impl LaunchProfile {
+    /// Layers `other` on top of this profile. Options set in `other` replace the ones set here,
+    /// except for RetroArch arguments and appended configs, which are concatenated so that both
+    /// layers apply.
+    pub fn merge(&self, other: &LaunchProfile) -> LaunchProfile {
+        LaunchProfile {
+            core: other.core.clone().or_else(|| self.core.clone()),
+            retroarch_args: self
+                .retroarch_args
+                .iter()
+                .chain(&other.retroarch_args)
+                .cloned()
+                .collect(),
+            append_configs: self
+                .append_configs
+                .iter()
+                .chain(&other.append_configs)
+                .cloned()
+                .collect(),
+            cpu: CpuSettings {
+                governor: other
+                    .cpu
+                    .governor
+                    .clone()
+                    .or_else(|| self.cpu.governor.clone()),
+                max_clock_mhz: other.cpu.max_clock_mhz.or(self.cpu.max_clock_mhz),
+            },
+            swap: other.swap.or(self.swap),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// RetroArch arguments for this profile, including the appended configs.
+    pub fn retroarch_command_args(&self) -> Vec<String> {
+        let mut args = Vec::new();
+        if !self.append_configs.is_empty() {
+            args.push("--appendconfig".to_string());
+            args.push(
+                self.append_configs
+                    .iter()
+                    .map(|path| path.display().to_string())
+                    .collect::<Vec<_>>()
+                    .join("|"),
+            );
+        }
+        args.extend(self.retroarch_args.iter().cloned());
+        args
+    }
+}
+
+/// What a launch profile applies to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LaunchProfileScope {
+    /// A single game, by path.
+    Game,
+    /// All games of a console, by console name.
+    Console,
+}
+
+impl LaunchProfileScope {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            LaunchProfileScope::Game => "game",
+            LaunchProfileScope::Console => "console",
+        }
+    }
+}
+
+/// CPU frequency scaling settings.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CpuSettings {
+    /// cpufreq governor, e.g. `performance` or `powersave`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub governor: Option<String>,
+    /// Maximum CPU clock.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_clock_mhz: Option<u32>,
+}
+
+impl CpuSettings {
+    pub fn is_empty(&self) -> bool {
+        self.governor.is_none() && self.max_clock_mhz.is_none()
+    }
+
+    /// Applies the settings. The settings from before the first call are saved, so that `restore`
+    /// can undo it once the game exits.
+    pub fn apply(&self) -> Result<()> {
+        if self.is_empty() || !Path::new(CPUFREQ_DIR).is_dir() {
+            return Ok(());
+        }
+
+        if !ALLIUM_CPU_DEFAULTS.exists() {
+            let defaults = Self {
+                governor: read_cpufreq("scaling_governor"),
+                max_clock_mhz: read_cpufreq("scaling_max_freq")
+                    .and_then(|khz| khz.parse::<u32>().ok())
+                    .map(|khz| khz / 1000),
+            };
+            serde_json::to_writer(File::create(ALLIUM_CPU_DEFAULTS.as_path())?, &defaults)?;
+        }
+
+        debug!("applying cpu settings: {:?}", self);
+        self.write()
+    }
+
+    /// Restores the settings from before `apply` was called, if it was.
+    pub fn restore() -> Result<()> {
+        if !ALLIUM_CPU_DEFAULTS.exists() {
+            return Ok(());
+        }
+
+        let defaults: Self = serde_json::from_reader(File::open(ALLIUM_CPU_DEFAULTS.as_path())?)?;
+        debug!("restoring cpu settings: {:?}", defaults);
+        defaults.write()?;
+        fs::remove_file(ALLIUM_CPU_DEFAULTS.as_path())?;
+        Ok(())
+    }
+
+    fn write(&self) -> Result<()> {
+        let dir = Path::new(CPUFREQ_DIR);
+        if let Some(governor) = self.governor.as_deref() {
+            fs::write(dir.join("scaling_governor"), governor)?;
+        }
+        if let Some(mhz) = self.max_clock_mhz {
+            fs::write(dir.join("scaling_max_freq"), (mhz * 1000).to_string())?;
+        }
+        Ok(())
+    }
+}
+
+fn read_cpufreq(file: &str) -> Option<String> {
+    match fs::read_to_string(Path::new(CPUFREQ_DIR).join(file)) {
+        Ok(value) => Some(value.trim().to_string()),
+        Err(e) => {
+            warn!("failed to read cpufreq {}: {}", file, e);
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_merge_launch_profiles() {
+        let console = LaunchProfile {
+            core: Some("gpsp".to_string()),
+            retroarch_args: vec!["--verbose".to_string()],
+            append_configs: vec![PathBuf::from("gba.cfg")],
+            cpu: CpuSettings {
+                governor: Some("performance".to_string()),
+                max_clock_mhz: Some(1500),
+            },
+            swap: Some(true),
+        };
+        let game = LaunchProfile {
+            core: Some("mgba".to_string()),
+            append_configs: vec![PathBuf::from("golden-sun.cfg")],
+            cpu: CpuSettings {
+                governor: None,
+                max_clock_mhz: Some(1200),
+            },
+            ..Default::default()
+        };
+
+        let merged = console.merge(&game);
+        assert_eq!(merged.core.as_deref(), Some("mgba"));
+        assert_eq!(merged.retroarch_args, vec!["--verbose"]);
+        assert_eq!(merged.cpu.governor.as_deref(), Some("performance"));
+        assert_eq!(merged.cpu.max_clock_mhz, Some(1200));
+        assert_eq!(merged.swap, Some(true));
+        assert_eq!(
+            merged.retroarch_command_args(),
+            vec!["--appendconfig", "gba.cfg|golden-sun.cfg", "--verbose"]
+        );
+
+        assert_eq!(LaunchProfile::default().merge(&game), game);
+        assert!(LaunchProfile::default().is_empty());
+    }
+}
@@ -0,0 +1,51 @@
+use std::fs::{self, File};
+use std::path::PathBuf;
+
+use anyhow::Result;
+use log::warn;
+use serde::{Deserialize, Serialize};
+
+use crate::constants::ALLIUM_LAUNCHER_REQUEST;
+
+/// A request for the launcher from another process, e.g. alliumd's HTTP API.
+///
+/// Requests are written to a file, then the launcher is sent SIGUSR1. If the launcher isn't
+/// running, e.g. because a game is, the request is handled when it starts. Only the latest
+/// request is kept.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum LauncherRequest {
+    /// Launch the game at `path`.
+    Launch { path: PathBuf },
+    /// Launch the app in the directory `path`.
+    LaunchApp { path: PathBuf },
+    /// Repopulate the database.
+    Rescan,
+}
+
+impl LauncherRequest {
+    pub fn save(&self) -> Result<()> {
+        if let Some(parent) = ALLIUM_LAUNCHER_REQUEST.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let file = File::create(ALLIUM_LAUNCHER_REQUEST.as_path())?;
+        serde_json::to_writer(file, self)?;
+        Ok(())
+    }
+
+    /// Returns the pending request, if there is one, and removes it.
+    pub fn take() -> Result<Option<Self>> {
+        if !ALLIUM_LAUNCHER_REQUEST.exists() {
+            return Ok(None);
+        }
+        let request = fs::read_to_string(ALLIUM_LAUNCHER_REQUEST.as_path())?;
+        fs::remove_file(ALLIUM_LAUNCHER_REQUEST.as_path())?;
+        match serde_json::from_str(&request) {
+            Ok(request) => Ok(Some(request)),
+            Err(e) => {
+                warn!("failed to read launcher request: {}", e);
+                Ok(None)
+            }
+        }
+    }
+}
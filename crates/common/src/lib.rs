@@ -2,17 +2,31 @@
 #![warn(rust_2018_idioms)]
 
 pub mod battery;
+pub mod battery_log;
 pub mod command;
 pub mod constants;
+pub mod dat;
 pub mod database;
 pub mod display;
+pub mod export;
 pub mod game_info;
+pub mod gamelist;
 pub mod geom;
+pub mod hash;
+pub mod hotkeys;
+pub mod launch_profile;
+pub mod launcher_request;
 pub mod locale;
+pub mod parental;
 pub mod platform;
 pub mod power;
+pub mod profile;
 pub mod resources;
+pub mod retroachievements;
 pub mod retroarch;
+pub mod save_state;
+pub mod search;
 pub mod stylesheet;
+pub mod theme;
 pub mod view;
 pub mod wifi;
@@ -2,6 +2,7 @@ use std::{
     collections::HashMap,
     fmt,
     fs::{self, File},
+    path::Path,
 };
 
 use anyhow::Result;
@@ -58,7 +59,12 @@ pub struct Locale {
 
 impl Locale {
     pub fn new(lang: &str) -> Self {
-        let loader = ArcLoader::builder(ALLIUM_LOCALES_DIR.as_path(), langid!("en-US"))
+        Self::from_dir(ALLIUM_LOCALES_DIR.as_path(), lang)
+    }
+
+    /// Loads the locale from a directory other than the default locales directory.
+    pub fn from_dir(dir: &Path, lang: &str) -> Self {
+        let loader = ArcLoader::builder(dir, langid!("en-US"))
             .customize(|b| b.set_use_isolating(false))
             .build()
             .unwrap();
@@ -0,0 +1,115 @@
+use std::fs::{self, File};
+use std::path::{Path, PathBuf};
+
+use anyhow::Result;
+use log::{debug, warn};
+use serde::{Deserialize, Serialize};
+
+use crate::constants::ALLIUM_PARENTAL_SETTINGS;
+use crate::power::SleepSchedule;
+
+/// Parental controls. In restricted mode, the hidden consoles, directories and games are left out
+/// of the launcher, and Settings and Apps can only be opened with the sleep schedule PIN.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ParentalSettings {
+    /// Whether restricted mode is on.
+    pub enabled: bool,
+    /// Names of the consoles whose games are hidden, as in `Console.name`.
+    pub hidden_consoles: Vec<String>,
+    /// Directories and games that are hidden. Everything inside a hidden directory is hidden too.
+    pub hidden_paths: Vec<PathBuf>,
+}
+
+impl ParentalSettings {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    pub fn load() -> Result<Self> {
+        if ALLIUM_PARENTAL_SETTINGS.exists() {
+            debug!("found state, loading from file");
+            let file = File::open(ALLIUM_PARENTAL_SETTINGS.as_path())?;
+            if let Ok(json) = serde_json::from_reader(file) {
+                return Ok(json);
+            }
+            warn!("failed to read parental settings file, removing");
+            fs::remove_file(ALLIUM_PARENTAL_SETTINGS.as_path())?;
+        }
+        Ok(Self::new())
+    }
+
+    pub fn save(&self) -> Result<()> {
+        let file = File::create(ALLIUM_PARENTAL_SETTINGS.as_path())?;
+        serde_json::to_writer(file, &self)?;
+        Ok(())
+    }
+
+    /// Whether Settings and Apps need the PIN of `schedule` to be opened.
+    pub fn is_locked(&self, schedule: &SleepSchedule) -> bool {
+        self.enabled && schedule.pin_hash.is_some()
+    }
+
+    /// Whether a console is hidden in restricted mode.
+    pub fn is_console_hidden(&self, name: &str) -> bool {
+        self.enabled && self.hidden_consoles.iter().any(|console| console == name)
+    }
+
+    /// Whether a directory or game is hidden in restricted mode, either itself or because it is
+    /// inside a hidden directory.
+    pub fn is_path_hidden(&self, path: &Path) -> bool {
+        self.enabled
+            && self
+                .hidden_paths
+                .iter()
+                .any(|hidden| path.starts_with(hidden))
+    }
+
+    /// Hides a directory or game, or shows it again if it was hidden.
+    pub fn toggle_hidden_path(&mut self, path: &Path) {
+        if let Some(i) = self.hidden_paths.iter().position(|hidden| hidden == path) {
+            self.hidden_paths.remove(i);
+        } else {
+            self.hidden_paths.push(path.to_path_buf());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parental_settings() {
+        let mut settings = ParentalSettings {
+            hidden_consoles: vec!["Game Boy Advance".to_string()],
+            hidden_paths: vec![PathBuf::from("/Roms/SFC/Horror")],
+            ..Default::default()
+        };
+
+        let mut schedule = SleepSchedule::default();
+        schedule.set_pin("1234");
+
+        // Nothing is hidden or locked outside restricted mode
+        assert!(!settings.is_locked(&schedule));
+        assert!(!settings.is_console_hidden("Game Boy Advance"));
+        assert!(!settings.is_path_hidden(Path::new("/Roms/SFC/Horror")));
+
+        settings.enabled = true;
+        assert!(settings.is_locked(&schedule));
+        assert!(!settings.is_locked(&SleepSchedule::default()));
+        assert!(settings.is_console_hidden("Game Boy Advance"));
+        assert!(!settings.is_console_hidden("Game Boy"));
+        assert!(settings.is_path_hidden(Path::new("/Roms/SFC/Horror")));
+        assert!(settings.is_path_hidden(Path::new("/Roms/SFC/Horror/Game.sfc")));
+        assert!(!settings.is_path_hidden(Path::new("/Roms/SFC/Horror Stories.sfc")));
+        assert!(!settings.is_path_hidden(Path::new("/Roms/SFC")));
+
+        settings.toggle_hidden_path(Path::new("/Roms/SFC/Horror"));
+        settings.toggle_hidden_path(Path::new("/Roms/GB/Game.gb"));
+        assert_eq!(
+            settings.hidden_paths,
+            vec![PathBuf::from("/Roms/GB/Game.gb")]
+        );
+    }
+}
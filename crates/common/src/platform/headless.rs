@@ -0,0 +1,366 @@
+//! Platform that renders into memory instead of a screen, for running views in tests.
+
+use std::collections::VecDeque;
+use std::env;
+use std::path::Path;
+use std::time::Duration;
+
+use anyhow::{bail, Result};
+use async_trait::async_trait;
+use embedded_graphics::prelude::*;
+use image::{Rgba, RgbaImage};
+use log::warn;
+
+use crate::battery::Battery;
+use crate::display::color::Color;
+use crate::display::settings::DisplaySettings;
+use crate::display::Display;
+use crate::geom::Rect;
+use crate::locale::Locale;
+use crate::platform::{Key, KeyEvent, Platform};
+use crate::stylesheet::{Stylesheet, StylesheetFont};
+
+pub const SCREEN_WIDTH: u32 = 640;
+pub const SCREEN_HEIGHT: u32 = 480;
+
+/// Set to overwrite golden images instead of comparing against them.
+pub const UPDATE_SNAPSHOTS_ENV: &str = "ALLIUM_UPDATE_SNAPSHOTS";
+
+/// Resources shipped with the repository, used instead of the SD card in tests.
+const STATIC_DIR: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/../../static/.allium");
+
+pub struct HeadlessPlatform {
+    events: VecDeque<(Duration, KeyEvent)>,
+    battery: HeadlessBattery,
+}
+
+impl HeadlessPlatform {
+    /// Queues a key event to be returned by `poll` after `delay`.
+    pub fn push_key_event(&mut self, delay: Duration, event: KeyEvent) {
+        self.events.push_back((delay, event));
+    }
+
+    /// Queues a press and release of `key`.
+    pub fn press(&mut self, key: Key) {
+        self.push_key_event(Duration::ZERO, KeyEvent::Pressed(key));
+        self.push_key_event(Duration::ZERO, KeyEvent::Released(key));
+    }
+
+    /// Returns true if there are queued key events that have not been polled yet.
+    pub fn has_key_events(&self) -> bool {
+        !self.events.is_empty()
+    }
+
+    /// Sets the state reported by batteries created after this call.
+    pub fn set_battery(&mut self, percentage: i32, charging: bool) {
+        self.battery = HeadlessBattery {
+            percentage,
+            charging,
+        };
+    }
+}
+
+#[async_trait(?Send)]
+impl Platform for HeadlessPlatform {
+    type Display = HeadlessDisplay;
+    type Battery = HeadlessBattery;
+    type SuspendContext = ();
+
+    fn new() -> Result<HeadlessPlatform> {
+        Ok(HeadlessPlatform {
+            events: VecDeque::new(),
+            battery: HeadlessBattery::default(),
+        })
+    }
+
+    async fn poll(&mut self) -> KeyEvent {
+        match self.events.pop_front() {
+            Some((delay, event)) => {
+                tokio::time::sleep(delay).await;
+                event
+            }
+            None => std::future::pending().await,
+        }
+    }
+
+    fn display(&mut self) -> Result<Self::Display> {
+        Ok(HeadlessDisplay::new(SCREEN_WIDTH, SCREEN_HEIGHT))
+    }
+
+    fn battery(&self) -> Result<Self::Battery> {
+        Ok(self.battery.clone())
+    }
+
+    fn shutdown(&self) -> Result<()> {
+        Ok(())
+    }
+
+    fn suspend(&self) -> Result<Self::SuspendContext> {
+        Ok(())
+    }
+
+    fn unsuspend(&self, _ctx: Self::SuspendContext) -> Result<()> {
+        Ok(())
+    }
+
+    fn set_volume(&mut self, _volume: i32) -> Result<()> {
+        Ok(())
+    }
+
+    fn get_brightness(&self) -> Result<u8> {
+        Ok(50)
+    }
+
+    fn set_brightness(&mut self, _brightness: u8) -> Result<()> {
+        Ok(())
+    }
+
+    fn set_display_settings(&mut self, _settings: &mut DisplaySettings) -> Result<()> {
+        Ok(())
+    }
+
+    fn device_model() -> String {
+        "Headless".to_string()
+    }
+
+    fn firmware() -> String {
+        "00000000".to_string()
+    }
+
+    fn has_wifi() -> bool {
+        false
+    }
+}
+
+impl Default for HeadlessPlatform {
+    fn default() -> Self {
+        Self::new().unwrap()
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct HeadlessDisplay {
+    buffer: RgbaImage,
+    saved: Vec<RgbaImage>,
+}
+
+impl HeadlessDisplay {
+    pub fn new(width: u32, height: u32) -> Self {
+        Self {
+            buffer: RgbaImage::from_pixel(width, height, Rgba([0, 0, 0, 255])),
+            saved: Vec::new(),
+        }
+    }
+
+    /// The current contents of the display.
+    pub fn image(&self) -> &RgbaImage {
+        &self.buffer
+    }
+
+    pub fn save_png(&self, path: &Path) -> Result<()> {
+        self.buffer.save(path)?;
+        Ok(())
+    }
+
+    /// Compares the display against the golden image at `path`, panicking if they differ.
+    ///
+    /// The golden image is written instead if `ALLIUM_UPDATE_SNAPSHOTS` is set. If it is missing
+    /// or differs, the rendered image is written next to it with an `.actual.png` extension.
+    pub fn assert_snapshot(&self, path: &Path) {
+        if env::var_os(UPDATE_SNAPSHOTS_ENV).is_some() {
+            if let Some(parent) = path.parent() {
+                std::fs::create_dir_all(parent).unwrap();
+            }
+            self.save_png(path).unwrap();
+            return;
+        }
+
+        let actual = path.with_extension("actual.png");
+        if !path.exists() {
+            if let Some(parent) = path.parent() {
+                std::fs::create_dir_all(parent).unwrap();
+            }
+            self.save_png(&actual).unwrap();
+            panic!(
+                "{} does not exist, see {} and run `make update-snapshots` to accept it",
+                path.display(),
+                actual.display(),
+            );
+        }
+
+        let expected = image::open(path)
+            .unwrap_or_else(|e| panic!("failed to open {}: {}", path.display(), e))
+            .to_rgba8();
+        if expected.dimensions() == self.buffer.dimensions()
+            && expected.as_raw() == self.buffer.as_raw()
+        {
+            return;
+        }
+
+        self.save_png(&actual).unwrap();
+        let differing = expected
+            .pixels()
+            .zip(self.buffer.pixels())
+            .filter(|(a, b)| a != b)
+            .count();
+        panic!(
+            "display does not match {} ({} pixels differ), see {}",
+            path.display(),
+            differing,
+            actual.display(),
+        );
+    }
+
+    fn get_pixel(&self, point: Point) -> Option<Color> {
+        if point.x < 0
+            || point.y < 0
+            || point.x as u32 >= self.buffer.width()
+            || point.y as u32 >= self.buffer.height()
+        {
+            return None;
+        }
+        let [r, g, b, _] = self.buffer.get_pixel(point.x as u32, point.y as u32).0;
+        Some(Color::new(r, g, b))
+    }
+}
+
+impl Display for HeadlessDisplay {
+    fn map_pixels<F>(&mut self, mut f: F) -> Result<()>
+    where
+        F: FnMut(Color) -> Color,
+    {
+        for pixel in self.buffer.pixels_mut() {
+            let [r, g, b, _] = pixel.0;
+            let color = f(Color::new(r, g, b));
+            *pixel = Rgba([color.r(), color.g(), color.b(), 255]);
+        }
+        Ok(())
+    }
+
+    fn save(&mut self) -> Result<()> {
+        self.saved.push(self.buffer.clone());
+        Ok(())
+    }
+
+    fn load(&mut self, rect: Rect) -> Result<()> {
+        let Some(saved) = self.saved.last() else {
+            bail!("No saved image");
+        };
+
+        let (width, height) = self.buffer.dimensions();
+        let x0 = rect.x.clamp(0, width as i32) as u32;
+        let y0 = rect.y.clamp(0, height as i32) as u32;
+        let x1 = (rect.x + rect.w as i32).clamp(0, width as i32) as u32;
+        let y1 = (rect.y + rect.h as i32).clamp(0, height as i32) as u32;
+        if x0 != rect.x as u32 || y0 != rect.y as u32 || x1 - x0 != rect.w || y1 - y0 != rect.h {
+            warn!(
+                "Area exceeds display bounds: x: {}, y: {}, w: {}, h: {}",
+                rect.x, rect.y, rect.w, rect.h,
+            );
+        }
+
+        for y in y0..y1 {
+            for x in x0..x1 {
+                self.buffer.put_pixel(x, y, *saved.get_pixel(x, y));
+            }
+        }
+
+        Ok(())
+    }
+
+    fn pop(&mut self) -> bool {
+        self.saved.pop();
+        !self.saved.is_empty()
+    }
+}
+
+impl DrawTarget for HeadlessDisplay {
+    type Color = Color;
+    type Error = anyhow::Error;
+
+    fn draw_iter<I>(&mut self, pixels: I) -> Result<()>
+    where
+        I: IntoIterator<Item = Pixel<Self::Color>>,
+    {
+        for Pixel(point, color) in pixels {
+            let Some(curr) = self.get_pixel(point) else {
+                continue;
+            };
+
+            let a = color.a() as u32;
+            let a_inv = 255 - a;
+
+            let b = (curr.b() as u32 * a_inv + color.b() as u32 * a) / 255;
+            let g = (curr.g() as u32 * a_inv + color.g() as u32 * a) / 255;
+            let r = (curr.r() as u32 * a_inv + color.r() as u32 * a) / 255;
+
+            self.buffer.put_pixel(
+                point.x as u32,
+                point.y as u32,
+                Rgba([r as u8, g as u8, b as u8, 255]),
+            );
+        }
+        Ok(())
+    }
+}
+
+impl OriginDimensions for HeadlessDisplay {
+    fn size(&self) -> Size {
+        Size::new(self.buffer.width(), self.buffer.height())
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct HeadlessBattery {
+    percentage: i32,
+    charging: bool,
+}
+
+impl Default for HeadlessBattery {
+    fn default() -> Self {
+        Self {
+            percentage: 50,
+            charging: false,
+        }
+    }
+}
+
+impl Battery for HeadlessBattery {
+    fn update(&mut self) -> Result<()> {
+        Ok(())
+    }
+
+    fn percentage(&self) -> i32 {
+        self.percentage
+    }
+
+    fn charging(&self) -> bool {
+        self.charging
+    }
+}
+
+/// Default stylesheet with fonts loaded from the repository's static directory.
+pub fn test_stylesheet() -> Stylesheet {
+    let fonts = Path::new(STATIC_DIR).join("fonts");
+    let mut styles = Stylesheet::default();
+    styles.ui_font = StylesheetFont::new(fonts.join("Nunito.ttf"), 36);
+    styles.guide_font = StylesheetFont::new(fonts.join("Nunito.ttf"), 28);
+    styles.cjk_font = StylesheetFont::new(fonts.join("MPLUSRounded1c.ttf"), 32);
+    styles.load_fonts().unwrap();
+    styles
+}
+
+/// en-US locale loaded from the repository's static directory.
+pub fn test_locale() -> Locale {
+    Locale::from_dir(&Path::new(STATIC_DIR).join("locales"), "en-US")
+}
+
+/// Path to a golden image, relative to the calling crate's `tests/snapshots` directory.
+#[macro_export]
+macro_rules! snapshot_path {
+    ($name:expr) => {
+        ::std::path::Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("tests/snapshots")
+            .join(concat!($name, ".png"))
+    };
+}
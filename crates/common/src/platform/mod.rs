@@ -1,5 +1,5 @@
 #[cfg(not(any(feature = "miyoo", feature = "simulator")))]
-mod mock;
+pub mod headless;
 
 #[cfg(feature = "miyoo")]
 mod miyoo;
@@ -23,7 +23,7 @@ pub type DefaultPlatform = miyoo::MiyooPlatform;
 pub type DefaultPlatform = simulator::SimulatorPlatform;
 
 #[cfg(not(any(feature = "miyoo", feature = "simulator")))]
-pub type DefaultPlatform = mock::MockPlatform;
+pub type DefaultPlatform = headless::HeadlessPlatform;
 
 // Platform is not threadsafe because it is ?Send
 #[async_trait(?Send)]
@@ -1,17 +1,26 @@
 use std::fs::{self, File};
 
 use anyhow::Result;
+use chrono::{DateTime, Datelike, Duration, Local, NaiveTime, TimeZone, Weekday};
 use log::{debug, warn};
 use serde::{Deserialize, Serialize};
 use strum::FromRepr;
 
-use crate::constants::ALLIUM_POWER_SETTINGS;
+use crate::constants::{ALLIUM_POWER_SETTINGS, BATTERY_SHUTDOWN_THRESHOLD};
+use crate::database::Session;
+use crate::hash::hash_pin;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct PowerSettings {
     pub power_button_action: PowerButtonAction,
     pub auto_sleep_when_charging: bool,
     pub auto_sleep_duration_minutes: i32,
+    /// Battery level at which a warning is shown. 0 disables the warning.
+    pub low_battery_warning_percentage: i32,
+    /// Battery level at which the running game is saved and the device shuts down.
+    pub critical_battery_percentage: i32,
+    pub schedule: SleepSchedule,
 }
 
 #[derive(Debug, Copy, Clone, Serialize, Deserialize, FromRepr, Default)]
@@ -28,6 +37,9 @@ impl Default for PowerSettings {
             power_button_action: PowerButtonAction::Suspend,
             auto_sleep_when_charging: true,
             auto_sleep_duration_minutes: 5,
+            low_battery_warning_percentage: 15,
+            critical_battery_percentage: BATTERY_SHUTDOWN_THRESHOLD,
+            schedule: SleepSchedule::default(),
         }
     }
 }
@@ -56,3 +68,276 @@ impl PowerSettings {
         Ok(())
     }
 }
+
+/// Hours of a day during which games can be played.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PlayWindow {
+    pub start: NaiveTime,
+    /// Games can't be played from this time until `start`. May be after midnight.
+    pub bedtime: NaiveTime,
+}
+
+impl PlayWindow {
+    pub fn new(start: NaiveTime, bedtime: NaiveTime) -> Self {
+        Self { start, bedtime }
+    }
+
+    /// Whether bedtime is after midnight, so the window runs into the next day.
+    pub fn wraps(&self) -> bool {
+        self.bedtime < self.start
+    }
+
+    /// Time from `time` until the next bedtime.
+    pub fn until_bedtime(&self, time: NaiveTime) -> Duration {
+        let until = self.bedtime - time;
+        if until < Duration::zero() {
+            until + Duration::days(1)
+        } else {
+            until
+        }
+    }
+}
+
+/// Limits on when and how long games can be played, enforced by alliumd.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SleepSchedule {
+    pub enabled: bool,
+    /// Play window from Monday to Friday.
+    pub weekdays: PlayWindow,
+    /// Play window on Saturday and Sunday.
+    pub weekends: PlayWindow,
+    /// Play time allowed per day, counted from the play sessions. 0 is unlimited.
+    pub daily_budget_minutes: i32,
+    /// SHA-256 hash of the PIN that protects the schedule settings, if one is set.
+    pub pin_hash: Option<String>,
+}
+
+impl Default for SleepSchedule {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            weekdays: PlayWindow::new(
+                NaiveTime::from_hms_opt(7, 0, 0).unwrap(),
+                NaiveTime::from_hms_opt(20, 0, 0).unwrap(),
+            ),
+            weekends: PlayWindow::new(
+                NaiveTime::from_hms_opt(8, 0, 0).unwrap(),
+                NaiveTime::from_hms_opt(21, 0, 0).unwrap(),
+            ),
+            daily_budget_minutes: 0,
+            pin_hash: None,
+        }
+    }
+}
+
+/// Whether games can be played under a `SleepSchedule`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScheduleStatus {
+    /// Games can be played for this long, or indefinitely if `None`.
+    Allowed(Option<Duration>),
+    /// Outside of the play window.
+    Bedtime,
+    /// The daily play time has been used up.
+    BudgetSpent,
+}
+
+impl SleepSchedule {
+    /// Play window that `now` falls in, if any. After midnight, this is the previous day's window
+    /// until its bedtime.
+    pub fn window<Tz: TimeZone>(&self, now: &DateTime<Tz>) -> Option<PlayWindow> {
+        let time = now.time();
+        let previous = self.day_window(now.weekday().pred());
+        if previous.wraps() && time < previous.bedtime {
+            return Some(previous);
+        }
+        let window = self.day_window(now.weekday());
+        (window.start <= time && (window.wraps() || time < window.bedtime)).then_some(window)
+    }
+
+    /// Play window that starts on `weekday`.
+    fn day_window(&self, weekday: Weekday) -> PlayWindow {
+        match weekday {
+            Weekday::Sat | Weekday::Sun => self.weekends,
+            _ => self.weekdays,
+        }
+    }
+
+    /// Whether games can be played at `now`, having already played for `played_today`.
+    pub fn status<Tz: TimeZone>(
+        &self,
+        now: &DateTime<Tz>,
+        played_today: Duration,
+    ) -> ScheduleStatus {
+        if !self.enabled {
+            return ScheduleStatus::Allowed(None);
+        }
+
+        let Some(window) = self.window(now) else {
+            return ScheduleStatus::Bedtime;
+        };
+
+        let mut remaining = window.until_bedtime(now.time());
+        if self.daily_budget_minutes > 0 {
+            let budget = Duration::minutes(self.daily_budget_minutes as i64) - played_today;
+            if budget <= Duration::zero() {
+                return ScheduleStatus::BudgetSpent;
+            }
+            remaining = remaining.min(budget);
+        }
+        ScheduleStatus::Allowed(Some(remaining))
+    }
+
+    /// Sets the PIN, or removes it if `pin` is empty.
+    pub fn set_pin(&mut self, pin: &str) {
+        self.pin_hash = (!pin.is_empty()).then(|| hash_pin(pin));
+    }
+
+    /// Whether `pin` unlocks the schedule settings. Always true if no PIN is set.
+    pub fn check_pin(&self, pin: &str) -> bool {
+        self.pin_hash
+            .as_ref()
+            .is_none_or(|pin_hash| *pin_hash == hash_pin(pin))
+    }
+}
+
+/// Play time on the day of `now`, in local time. Sessions that started the day before only count
+/// from midnight.
+pub fn played_today(sessions: &[Session], now: DateTime<Local>) -> Duration {
+    let midnight = now
+        .date_naive()
+        .and_time(NaiveTime::MIN)
+        .and_local_timezone(Local)
+        .earliest()
+        .unwrap_or(now);
+    sessions
+        .iter()
+        .map(|session| {
+            let start = session.start.with_timezone(&Local).max(midnight);
+            let end = session.end.with_timezone(&Local).min(now);
+            (end - start).max(Duration::zero()).min(session.duration)
+        })
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use chrono::Utc;
+
+    use super::*;
+
+    fn time(h: u32, m: u32) -> NaiveTime {
+        NaiveTime::from_hms_opt(h, m, 0).unwrap()
+    }
+
+    #[test]
+    fn test_sleep_schedule() {
+        let mut schedule = SleepSchedule {
+            enabled: true,
+            weekdays: PlayWindow::new(time(7, 0), time(20, 0)),
+            weekends: PlayWindow::new(time(9, 0), time(1, 0)),
+            daily_budget_minutes: 90,
+            pin_hash: None,
+        };
+
+        // Wednesday
+        let now = Utc.with_ymd_and_hms(2024, 5, 15, 19, 0, 0).unwrap();
+        assert_eq!(
+            schedule.status(&now, Duration::zero()),
+            ScheduleStatus::Allowed(Some(Duration::hours(1)))
+        );
+        assert_eq!(
+            schedule.status(&now, Duration::minutes(60)),
+            ScheduleStatus::Allowed(Some(Duration::minutes(30)))
+        );
+        assert_eq!(
+            schedule.status(&now, Duration::minutes(90)),
+            ScheduleStatus::BudgetSpent
+        );
+        let now = Utc.with_ymd_and_hms(2024, 5, 15, 20, 0, 0).unwrap();
+        assert_eq!(
+            schedule.status(&now, Duration::zero()),
+            ScheduleStatus::Bedtime
+        );
+
+        // Saturday, with a bedtime after midnight
+        schedule.daily_budget_minutes = 0;
+        let now = Utc.with_ymd_and_hms(2024, 5, 18, 23, 30, 0).unwrap();
+        assert_eq!(
+            schedule.status(&now, Duration::hours(5)),
+            ScheduleStatus::Allowed(Some(Duration::minutes(90)))
+        );
+        let now = Utc.with_ymd_and_hms(2024, 5, 18, 8, 0, 0).unwrap();
+        assert_eq!(
+            schedule.status(&now, Duration::zero()),
+            ScheduleStatus::Bedtime
+        );
+
+        // Monday at 00:30, before Sunday's bedtime at 01:00
+        let now = Utc.with_ymd_and_hms(2024, 5, 20, 0, 30, 0).unwrap();
+        assert_eq!(
+            schedule.status(&now, Duration::zero()),
+            ScheduleStatus::Allowed(Some(Duration::minutes(30)))
+        );
+        let now = Utc.with_ymd_and_hms(2024, 5, 20, 1, 0, 0).unwrap();
+        assert_eq!(
+            schedule.status(&now, Duration::zero()),
+            ScheduleStatus::Bedtime
+        );
+
+        // Friday at 00:30, after Thursday's window ended at 20:00
+        let now = Utc.with_ymd_and_hms(2024, 5, 17, 0, 30, 0).unwrap();
+        assert_eq!(
+            schedule.status(&now, Duration::zero()),
+            ScheduleStatus::Bedtime
+        );
+        // Saturday at 00:30, before the weekend bedtime but after Friday's window ended at 20:00
+        let now = Utc.with_ymd_and_hms(2024, 5, 18, 0, 30, 0).unwrap();
+        assert_eq!(
+            schedule.status(&now, Duration::zero()),
+            ScheduleStatus::Bedtime
+        );
+
+        schedule.enabled = false;
+        assert_eq!(
+            schedule.status(&now, Duration::zero()),
+            ScheduleStatus::Allowed(None)
+        );
+
+        assert!(schedule.check_pin("1234"));
+        schedule.set_pin("1234");
+        assert!(schedule.check_pin("1234"));
+        assert!(!schedule.check_pin("4321"));
+        assert!(!schedule.check_pin(""));
+        schedule.set_pin("");
+        assert!(schedule.check_pin("4321"));
+    }
+
+    #[test]
+    fn test_played_today() {
+        let at = |d: u32, h: u32, m: u32| {
+            Local
+                .with_ymd_and_hms(2024, 5, d, h, m, 0)
+                .unwrap()
+                .with_timezone(&Utc)
+        };
+        let session = |start: DateTime<Utc>, end: DateTime<Utc>| Session {
+            path: PathBuf::from("Roms/GBA/Game.gba"),
+            core: None,
+            start,
+            end,
+            duration: end - start,
+        };
+
+        let sessions = vec![
+            session(at(14, 20, 0), at(14, 21, 0)),
+            session(at(14, 23, 30), at(15, 0, 30)),
+            session(at(15, 9, 0), at(15, 9, 45)),
+            session(at(15, 12, 0), at(15, 13, 0)),
+        ];
+        let now = Local.with_ymd_and_hms(2024, 5, 15, 12, 30, 0).unwrap();
+        assert_eq!(played_today(&sessions, now), Duration::minutes(105));
+    }
+}
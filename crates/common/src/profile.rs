@@ -0,0 +1,309 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, Result};
+use log::{info, warn};
+
+use crate::constants::{ALLIUM_PROFILE_SWITCH, ALLIUM_SD_ROOT};
+
+/// Name of the profile that has not been named yet, i.e. the one created by the installer.
+pub const DEFAULT_PROFILE_NAME: &str = "Default";
+
+/// File in the current profile that holds its name.
+const PROFILE_NAME_FILE: &str = ".profile";
+
+/// Manages user profiles. Each profile has its own database, RetroArch saves, states and config.
+///
+/// RetroArch and the database expect the active profile at `Saves/CurrentProfile`, and the SD card
+/// filesystem does not support symlinks, so inactive profiles are stored in `Saves/Profiles/<name>`
+/// and switching moves directories around. Switching is done by alliumd while no game is running:
+/// the launcher requests a switch and exits, and alliumd completes it before restarting the
+/// launcher.
+#[derive(Debug, Clone)]
+pub struct Profiles {
+    current: PathBuf,
+    profiles: PathBuf,
+    pending: PathBuf,
+}
+
+impl Default for Profiles {
+    fn default() -> Self {
+        Self::new(&ALLIUM_SD_ROOT.join("Saves"), &ALLIUM_PROFILE_SWITCH)
+    }
+}
+
+impl Profiles {
+    /// `saves` is the directory containing `CurrentProfile`, `pending` is where switch requests
+    /// are written.
+    pub fn new(saves: &Path, pending: &Path) -> Self {
+        Self {
+            current: saves.join("CurrentProfile"),
+            profiles: saves.join("Profiles"),
+            pending: pending.to_path_buf(),
+        }
+    }
+
+    /// Returns the name of the active profile.
+    pub fn current(&self) -> String {
+        fs::read_to_string(self.current.join(PROFILE_NAME_FILE))
+            .ok()
+            .map(|name| name.trim().to_string())
+            .filter(|name| !name.is_empty())
+            .unwrap_or_else(|| DEFAULT_PROFILE_NAME.to_string())
+    }
+
+    /// Returns all profile names, the active profile first.
+    pub fn list(&self) -> Result<Vec<String>> {
+        let mut names = Vec::new();
+        if self.profiles.is_dir() {
+            for entry in fs::read_dir(&self.profiles)? {
+                let entry = entry?;
+                if entry.file_type()?.is_dir() {
+                    names.push(entry.file_name().to_string_lossy().to_string());
+                }
+            }
+        }
+        names.sort_unstable_by_key(|name| name.to_lowercase());
+        names.insert(0, self.current());
+        Ok(names)
+    }
+
+    /// Creates an empty profile. RetroArch config is copied from the active profile, so that core
+    /// overrides carry over.
+    pub fn create(&self, name: &str) -> Result<()> {
+        let name = self.validate_new_name(name, None)?;
+        let path = self.profiles.join(name);
+        info!("creating profile {}", name);
+
+        fs::create_dir_all(path.join("saves"))?;
+        fs::create_dir_all(path.join("states"))?;
+        let config = self.current.join("config");
+        if config.is_dir() {
+            copy_dir(&config, &path.join("config"))?;
+        }
+        Ok(())
+    }
+
+    pub fn rename(&self, name: &str, new_name: &str) -> Result<()> {
+        let new_name = self.validate_new_name(new_name, Some(name))?;
+        info!("renaming profile {} to {}", name, new_name);
+
+        if name == self.current() {
+            fs::write(self.current.join(PROFILE_NAME_FILE), new_name)?;
+        } else {
+            let path = self.path(name)?;
+            fs::rename(path, self.profiles.join(new_name))?;
+        }
+        Ok(())
+    }
+
+    /// Deletes a profile, including its saves. The active profile cannot be deleted.
+    pub fn delete(&self, name: &str) -> Result<()> {
+        if name == self.current() {
+            bail!("cannot delete the active profile");
+        }
+        info!("deleting profile {}", name);
+        fs::remove_dir_all(self.path(name)?)?;
+        Ok(())
+    }
+
+    /// Requests a switch to another profile. The switch happens once the launcher has exited, see
+    /// [`Profiles::complete_switch`].
+    pub fn request_switch(&self, name: &str) -> Result<()> {
+        self.path(name)?;
+        if let Some(parent) = self.pending.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(&self.pending, name)?;
+        Ok(())
+    }
+
+    /// Completes a requested switch, if there is one. Must only be called while nothing has the
+    /// database or saves open. Returns whether the profile was switched.
+    ///
+    /// This is safe to call again if a previous switch was interrupted: the request is only removed
+    /// once the target profile is in place.
+    pub fn complete_switch(&self) -> Result<bool> {
+        let Ok(target) = fs::read_to_string(&self.pending) else {
+            return Ok(false);
+        };
+        let target = target.trim().to_string();
+
+        let source = self.profiles.join(&target);
+        if self.current.exists() {
+            let current = self.current();
+            if current == target {
+                fs::remove_file(&self.pending)?;
+                return Ok(false);
+            }
+            if !source.is_dir() {
+                warn!("profile {} does not exist, not switching", target);
+                fs::remove_file(&self.pending)?;
+                return Ok(false);
+            }
+            let dest = self.profiles.join(&current);
+            if dest.exists() {
+                bail!("profile {} already exists", current);
+            }
+
+            info!("switching profile from {} to {}", current, target);
+            fs::create_dir_all(&self.profiles)?;
+            // Write the name now, the default profile may not have one yet
+            fs::write(self.current.join(PROFILE_NAME_FILE), &current)?;
+            fs::rename(&self.current, dest)?;
+        } else if !source.is_dir() {
+            bail!("profile {} does not exist", target);
+        }
+
+        // Write the name first, so that a resumed switch sees that the target is already active
+        fs::write(source.join(PROFILE_NAME_FILE), &target)?;
+        fs::rename(source, &self.current)?;
+        fs::remove_file(&self.pending)?;
+
+        Ok(true)
+    }
+
+    fn path(&self, name: &str) -> Result<PathBuf> {
+        let path = self.profiles.join(name);
+        if !is_valid_name(name) || !path.is_dir() {
+            bail!("profile {} does not exist", name);
+        }
+        Ok(path)
+    }
+
+    /// Checks that a name is valid and not taken by another profile than `renaming`.
+    fn validate_new_name<'a>(&self, name: &'a str, renaming: Option<&str>) -> Result<&'a str> {
+        let name = name.trim();
+        if !is_valid_name(name) {
+            bail!("invalid profile name: {}", name);
+        }
+        if self
+            .list()?
+            .iter()
+            .any(|profile| Some(profile.as_str()) != renaming && profile.eq_ignore_ascii_case(name))
+        {
+            bail!("profile {} already exists", name);
+        }
+        Ok(name)
+    }
+}
+
+/// Profile names are used as directory names on a FAT32 SD card.
+fn is_valid_name(name: &str) -> bool {
+    !name.is_empty()
+        && name != "."
+        && name != ".."
+        && !name.starts_with('.')
+        && !name.chars().any(|c| {
+            c.is_control() || matches!(c, '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|')
+        })
+}
+
+fn copy_dir(src: &Path, dest: &Path) -> Result<()> {
+    fs::create_dir_all(dest)?;
+    for entry in fs::read_dir(src)? {
+        let entry = entry?;
+        let path = entry.path();
+        if entry.file_type()?.is_dir() {
+            copy_dir(&path, &dest.join(entry.file_name()))?;
+        } else {
+            fs::copy(&path, dest.join(entry.file_name()))?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_profiles() -> Result<()> {
+        let dir = std::env::temp_dir().join("allium-test-profiles");
+        let _ = fs::remove_dir_all(&dir);
+        let saves = dir.join("Saves");
+        fs::create_dir_all(saves.join("CurrentProfile/config"))?;
+        fs::write(saves.join("CurrentProfile/allium.db"), "default")?;
+        fs::write(saves.join("CurrentProfile/config/retroarch.cfg"), "cfg")?;
+
+        let profiles = Profiles::new(&saves, &dir.join("state/profile-switch"));
+        assert_eq!(profiles.list()?, vec![DEFAULT_PROFILE_NAME]);
+
+        profiles.create("Alice")?;
+        profiles.create("bob")?;
+        assert!(profiles.create("alice").is_err());
+        assert!(profiles.create("../x").is_err());
+        assert!(profiles.create(DEFAULT_PROFILE_NAME).is_err());
+        assert_eq!(
+            fs::read_to_string(saves.join("Profiles/Alice/config/retroarch.cfg"))?,
+            "cfg"
+        );
+
+        profiles.rename("bob", "Bob")?;
+        assert_eq!(profiles.list()?, vec![DEFAULT_PROFILE_NAME, "Alice", "Bob"]);
+
+        assert!(!profiles.complete_switch()?);
+        profiles.request_switch("Alice")?;
+        assert!(profiles.complete_switch()?);
+        assert_eq!(profiles.current(), "Alice");
+        assert_eq!(profiles.list()?, vec!["Alice", "Bob", DEFAULT_PROFILE_NAME]);
+        assert!(!saves.join("CurrentProfile/allium.db").exists());
+        assert_eq!(
+            fs::read_to_string(saves.join("Profiles/Default/allium.db"))?,
+            "default"
+        );
+
+        assert!(profiles.delete("Alice").is_err());
+        profiles.delete("Bob")?;
+        profiles.rename("Alice", "Carol")?;
+
+        // Resume an interrupted switch
+        profiles.request_switch(DEFAULT_PROFILE_NAME)?;
+        fs::rename(
+            saves.join("CurrentProfile"),
+            saves.join("Profiles").join("Carol"),
+        )?;
+        assert!(profiles.complete_switch()?);
+        assert_eq!(profiles.current(), DEFAULT_PROFILE_NAME);
+        assert_eq!(profiles.list()?, vec![DEFAULT_PROFILE_NAME, "Carol"]);
+
+        fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_interrupted_switch() -> Result<()> {
+        let dir = std::env::temp_dir().join("allium-test-interrupted-switch");
+        let _ = fs::remove_dir_all(&dir);
+        let saves = dir.join("Saves");
+        let pending = dir.join("state/profile-switch");
+        fs::create_dir_all(saves.join("CurrentProfile"))?;
+        fs::write(saves.join("CurrentProfile/allium.db"), "default")?;
+
+        let profiles = Profiles::new(&saves, &pending);
+        profiles.create("Alice")?;
+        profiles.request_switch("Alice")?;
+
+        // Fail after the active profile was moved away, but before Alice was moved in
+        fs::create_dir_all(saves.join("Profiles/Alice").join(PROFILE_NAME_FILE))?;
+        assert!(profiles.complete_switch().is_err());
+        assert!(!saves.join("CurrentProfile").exists());
+        assert!(saves.join("Profiles/Default/allium.db").exists());
+        assert!(pending.exists());
+
+        fs::remove_dir(saves.join("Profiles/Alice").join(PROFILE_NAME_FILE))?;
+        assert!(profiles.complete_switch()?);
+        assert_eq!(profiles.current(), "Alice");
+        assert_eq!(profiles.list()?, vec!["Alice", DEFAULT_PROFILE_NAME]);
+        assert!(!pending.exists());
+
+        // Resuming after both moves only removes the request
+        fs::write(&pending, "Alice")?;
+        assert!(!profiles.complete_switch()?);
+        assert_eq!(profiles.current(), "Alice");
+        assert!(!pending.exists());
+
+        fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,553 @@
+use std::collections::HashSet;
+use std::ffi::OsStr;
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::Path;
+use std::time::Duration;
+
+use anyhow::{anyhow, bail, Result};
+use log::{debug, warn};
+use md5::{Digest, Md5};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+
+use crate::constants::{
+    ALLIUM_RETROACHIEVEMENTS_SETTINGS, ALLIUM_RETROARCH_CHEEVOS_CONFIG,
+    RETROACHIEVEMENTS_SERVER_URL,
+};
+use crate::hash::{self, hex};
+
+/// How long to wait for the RetroAchievements server before giving up.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Achievements with these flags are part of the official set. Unofficial achievements are not
+/// listed, as RetroArch doesn't award them either.
+const OFFICIAL_FLAGS: u32 = 3;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RetroAchievementsSettings {
+    pub enabled: bool,
+    pub username: String,
+    /// Login token. The password is only used to request a token, and is never stored.
+    pub token: String,
+    pub hardcore: bool,
+    /// Server to send requests to. Point this at a local server to test without network access.
+    #[serde(default = "RetroAchievementsSettings::default_server_url")]
+    pub server_url: String,
+}
+
+impl RetroAchievementsSettings {
+    pub fn new() -> Self {
+        Self {
+            enabled: false,
+            username: String::new(),
+            token: String::new(),
+            hardcore: false,
+            server_url: Self::default_server_url(),
+        }
+    }
+
+    pub fn load() -> Result<Self> {
+        if ALLIUM_RETROACHIEVEMENTS_SETTINGS.exists() {
+            debug!("found state, loading from file");
+            if let Ok(json) = fs::read_to_string(ALLIUM_RETROACHIEVEMENTS_SETTINGS.as_path()) {
+                if let Ok(json) = serde_json::from_str(&json) {
+                    return Ok(json);
+                }
+            }
+            warn!("failed to read state file, removing");
+            fs::remove_file(ALLIUM_RETROACHIEVEMENTS_SETTINGS.as_path())?;
+        }
+        Ok(Self::new())
+    }
+
+    pub fn save(&self) -> Result<()> {
+        let json = serde_json::to_string(&self).unwrap();
+        File::create(ALLIUM_RETROACHIEVEMENTS_SETTINGS.as_path())?.write_all(json.as_bytes())?;
+        if let Err(e) = self.patch_ra_config() {
+            warn!("failed to patch RA config: {}", e);
+        }
+        Ok(())
+    }
+
+    /// Whether achievements can be fetched, i.e. they are enabled and the user has logged in.
+    pub fn is_logged_in(&self) -> bool {
+        self.enabled && !self.username.is_empty() && !self.token.is_empty()
+    }
+
+    pub fn client(&self) -> Client {
+        Client::new(&self.server_url)
+    }
+
+    /// Requests a login token for the password, and stores it in place of the password.
+    pub fn login(&mut self, username: String, password: &str) -> Result<()> {
+        self.token = self.client().login(&username, password)?;
+        self.username = username;
+        Ok(())
+    }
+
+    pub fn logout(&mut self) {
+        self.token.clear();
+    }
+
+    /// Fetches the achievements of a ROM, and whether the user has unlocked them. Returns None if
+    /// the ROM is not recognised by RetroAchievements.
+    pub fn game_achievements(&self, rom: &Path) -> Result<Option<GameAchievements>> {
+        if !self.is_logged_in() {
+            bail!("not logged in to RetroAchievements");
+        }
+        let md5 = rom_md5(rom)?;
+        self.client()
+            .game_achievements(&self.username, &self.token, &md5, self.hardcore)
+    }
+
+    /// Writes the `cheevos_*` settings to the config that the launcher appends to RetroArch's.
+    fn patch_ra_config(&self) -> Result<()> {
+        self.write_ra_config(&ALLIUM_RETROARCH_CHEEVOS_CONFIG)
+    }
+
+    fn write_ra_config(&self, path: &Path) -> Result<()> {
+        let mut file = File::create(path)?;
+        write!(
+            file,
+            r#"cheevos_enable = "{enabled}"
+cheevos_username = "{username}"
+cheevos_password = ""
+cheevos_token = "{token}"
+cheevos_hardcore_mode_enable = "{hardcore}"
+"#,
+            enabled = self.is_logged_in(),
+            username = self.username.replace('"', ""),
+            token = self.token.replace('"', ""),
+            hardcore = self.hardcore,
+        )?;
+        Ok(())
+    }
+
+    fn default_server_url() -> String {
+        RETROACHIEVEMENTS_SERVER_URL.to_string()
+    }
+}
+
+impl Default for RetroAchievementsSettings {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Achievement {
+    pub id: u32,
+    pub title: String,
+    pub description: String,
+    pub points: u32,
+    pub unlocked: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GameAchievements {
+    pub game_id: u32,
+    pub title: String,
+    pub achievements: Vec<Achievement>,
+}
+
+impl GameAchievements {
+    pub fn unlocked(&self) -> usize {
+        self.achievements.iter().filter(|a| a.unlocked).count()
+    }
+
+    pub fn points(&self) -> u32 {
+        self.achievements.iter().map(|a| a.points).sum()
+    }
+
+    pub fn unlocked_points(&self) -> u32 {
+        self.achievements
+            .iter()
+            .filter(|a| a.unlocked)
+            .map(|a| a.points)
+            .sum()
+    }
+}
+
+/// Client for the RetroAchievements `dorequest.php` API, which is the API that emulators use.
+#[derive(Debug, Clone)]
+pub struct Client {
+    server_url: String,
+    agent: ureq::Agent,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct Status {
+    success: bool,
+    error: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct LoginResponse {
+    token: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct GameIdResponse {
+    #[serde(rename = "GameID")]
+    game_id: u32,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct PatchResponse {
+    patch_data: PatchData,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct PatchData {
+    #[serde(rename = "ID")]
+    id: u32,
+    title: String,
+    #[serde(default)]
+    achievements: Vec<PatchAchievement>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct PatchAchievement {
+    #[serde(rename = "ID")]
+    id: u32,
+    title: String,
+    description: String,
+    points: u32,
+    flags: u32,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct UnlocksResponse {
+    user_unlocks: Vec<u32>,
+}
+
+impl Client {
+    pub fn new(server_url: &str) -> Self {
+        Self {
+            server_url: server_url.trim_end_matches('/').to_string(),
+            agent: ureq::AgentBuilder::new()
+                .timeout(REQUEST_TIMEOUT)
+                .user_agent(concat!("Allium/", env!("CARGO_PKG_VERSION")))
+                .build(),
+        }
+    }
+
+    /// Logs in with a password, and returns the login token.
+    pub fn login(&self, username: &str, password: &str) -> Result<String> {
+        let response: LoginResponse =
+            self.request(&[("r", "login"), ("u", username), ("p", password)])?;
+        Ok(response.token)
+    }
+
+    /// Looks up the game ID of a ROM by its RetroAchievements hash. Returns None if the ROM is not
+    /// recognised.
+    pub fn game_id(&self, md5: &str) -> Result<Option<u32>> {
+        let response: GameIdResponse = self.request(&[("r", "gameid"), ("m", md5)])?;
+        Ok(Some(response.game_id).filter(|&id| id != 0))
+    }
+
+    /// Fetches the official achievements of a game, and whether the user has unlocked them.
+    pub fn achievements(
+        &self,
+        username: &str,
+        token: &str,
+        game_id: u32,
+        hardcore: bool,
+    ) -> Result<GameAchievements> {
+        let game_id_str = game_id.to_string();
+        let patch: PatchResponse = self.request(&[
+            ("r", "patch"),
+            ("u", username),
+            ("t", token),
+            ("g", &game_id_str),
+        ])?;
+        let unlocks: UnlocksResponse = self.request(&[
+            ("r", "unlocks"),
+            ("u", username),
+            ("t", token),
+            ("g", &game_id_str),
+            ("h", if hardcore { "1" } else { "0" }),
+        ])?;
+        let unlocks: HashSet<u32> = unlocks.user_unlocks.into_iter().collect();
+
+        Ok(GameAchievements {
+            game_id: patch.patch_data.id,
+            title: patch.patch_data.title,
+            achievements: patch
+                .patch_data
+                .achievements
+                .into_iter()
+                .filter(|a| a.flags == OFFICIAL_FLAGS)
+                .map(|a| Achievement {
+                    unlocked: unlocks.contains(&a.id),
+                    id: a.id,
+                    title: a.title,
+                    description: a.description,
+                    points: a.points,
+                })
+                .collect(),
+        })
+    }
+
+    /// Fetches the achievements of a ROM by its hash. Returns None if the ROM is not recognised.
+    pub fn game_achievements(
+        &self,
+        username: &str,
+        token: &str,
+        md5: &str,
+        hardcore: bool,
+    ) -> Result<Option<GameAchievements>> {
+        match self.game_id(md5)? {
+            Some(game_id) => self
+                .achievements(username, token, game_id, hardcore)
+                .map(Some),
+            None => Ok(None),
+        }
+    }
+
+    fn request<T: DeserializeOwned>(&self, params: &[(&str, &str)]) -> Result<T> {
+        let url = format!("{}/dorequest.php", self.server_url);
+        // Failed requests still have a JSON body explaining why, e.g. 401 for a wrong password
+        let response = match self.agent.post(&url).send_form(params) {
+            Ok(response) | Err(ureq::Error::Status(_, response)) => response,
+            Err(e) => return Err(e.into()),
+        };
+        let json: serde_json::Value = response.into_json()?;
+
+        let status: Status = serde_json::from_value(json.clone())?;
+        if !status.success {
+            return Err(anyhow!(status
+                .error
+                .unwrap_or_else(|| "request failed".to_string())));
+        }
+        Ok(serde_json::from_value(json)?)
+    }
+}
+
+/// Hashes a ROM the way RetroAchievements identifies it. This is the MD5 of the ROM, without the
+/// headers that RetroAchievements ignores: iNES and FDS headers, Atari Lynx and 7800 headers, and the
+/// copier header of SNES ROMs. Zip archives are hashed by their largest file. Disc images are hashed
+/// differently by RetroAchievements, and are not supported.
+pub fn rom_md5(path: &Path) -> Result<String> {
+    let Some((name, data)) = hash::read_rom(path)? else {
+        bail!("ROM is too large to hash");
+    };
+    let ext = Path::new(&name)
+        .extension()
+        .and_then(OsStr::to_str)
+        .unwrap_or_default()
+        .to_lowercase();
+    let data = if data.starts_with(b"NES\x1a") || data.starts_with(b"FDS\x1a") {
+        &data[16..]
+    } else if data.starts_with(b"LYNX\0") {
+        &data[64..]
+    } else if data.get(1..10) == Some(b"ATARI7800") {
+        &data[128..]
+    } else if matches!(ext.as_str(), "sfc" | "smc") && data.len() % 1024 == 512 {
+        &data[512..]
+    } else {
+        &data[..]
+    };
+    Ok(hex(&Md5::digest(data)))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{BufRead, BufReader, Read};
+    use std::net::TcpListener;
+    use std::thread;
+
+    use super::*;
+
+    /// Serves canned responses to `dorequest.php`, like the RetroAchievements server would.
+    fn mock_server() -> Result<String> {
+        let listener = TcpListener::bind("127.0.0.1:0")?;
+        let url = format!("http://{}", listener.local_addr()?);
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                let Ok(mut stream) = stream else {
+                    continue;
+                };
+
+                let mut reader = BufReader::new(&mut stream);
+                let mut content_length = 0;
+                loop {
+                    let mut line = String::new();
+                    if reader.read_line(&mut line).unwrap_or(0) == 0 || line == "\r\n" {
+                        break;
+                    }
+                    if let Some((name, value)) = line.split_once(':') {
+                        if name.eq_ignore_ascii_case("content-length") {
+                            content_length = value.trim().parse().unwrap_or(0);
+                        }
+                    }
+                }
+                let mut body = vec![0; content_length];
+                if reader.read_exact(&mut body).is_err() {
+                    continue;
+                }
+                let body = String::from_utf8_lossy(&body);
+                let param = |key: &str| {
+                    body.split('&')
+                        .filter_map(|pair| pair.split_once('='))
+                        .find(|(k, _)| *k == key)
+                        .map(|(_, v)| v.to_string())
+                        .unwrap_or_default()
+                };
+
+                let authorized = param("u") == "alice" && param("t") == "token";
+                let (status, json) = match param("r").as_str() {
+                    "login" if param("u") == "alice" && param("p") == "hunter2" => {
+                        (200, r#"{"Success":true,"User":"alice","Token":"token"}"#)
+                    }
+                    "login" => (
+                        401,
+                        r#"{"Success":false,"Error":"Invalid user/password combination."}"#,
+                    ),
+                    "gameid" if param("m") == "900150983cd24fb0d6963f7d28e17f72" => {
+                        (200, r#"{"Success":true,"GameID":42}"#)
+                    }
+                    "gameid" => (200, r#"{"Success":true,"GameID":0}"#),
+                    "patch" if authorized => (
+                        200,
+                        r#"{"Success":true,"PatchData":{"ID":42,"Title":"Golden Sun","Achievements":[
+                            {"ID":1,"Title":"Venus","Description":"Find Venus","Points":5,"Flags":3},
+                            {"ID":2,"Title":"Mars","Description":"Find Mars","Points":10,"Flags":3},
+                            {"ID":3,"Title":"Jupiter","Description":"Unofficial","Points":25,"Flags":5}
+                        ]}}"#,
+                    ),
+                    "unlocks" if authorized && param("h") == "1" => (
+                        200,
+                        r#"{"Success":true,"UserUnlocks":[],"HardcoreMode":true}"#,
+                    ),
+                    "unlocks" if authorized => (
+                        200,
+                        r#"{"Success":true,"UserUnlocks":[2,3],"HardcoreMode":false}"#,
+                    ),
+                    _ => (
+                        401,
+                        r#"{"Success":false,"Error":"Invalid user/token combination."}"#,
+                    ),
+                };
+                write!(
+                    stream,
+                    "HTTP/1.1 {} OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    status,
+                    json.len(),
+                    json
+                )
+                .ok();
+            }
+        });
+        Ok(url)
+    }
+
+    #[test]
+    fn test_client() -> Result<()> {
+        let client = Client::new(&mock_server()?);
+
+        assert_eq!(client.login("alice", "hunter2")?, "token");
+        assert_eq!(
+            client.login("alice", "wrong").unwrap_err().to_string(),
+            "Invalid user/password combination."
+        );
+
+        assert_eq!(client.game_id("d41d8cd98f00b204e9800998ecf8427e")?, None);
+        assert!(client
+            .game_achievements("alice", "token", "d41d8cd98f00b204e9800998ecf8427e", false)?
+            .is_none());
+
+        let game = client
+            .game_achievements("alice", "token", "900150983cd24fb0d6963f7d28e17f72", false)?
+            .unwrap();
+        assert_eq!(game.game_id, 42);
+        assert_eq!(game.title, "Golden Sun");
+        assert_eq!(
+            game.achievements
+                .iter()
+                .map(|a| (a.id, a.unlocked))
+                .collect::<Vec<_>>(),
+            vec![(1, false), (2, true)]
+        );
+        assert_eq!(game.unlocked(), 1);
+        assert_eq!(game.points(), 15);
+        assert_eq!(game.unlocked_points(), 10);
+
+        let game = client.achievements("alice", "token", 42, true)?;
+        assert_eq!(game.unlocked(), 0);
+
+        assert!(client.achievements("alice", "expired", 42, false).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_ra_config() -> Result<()> {
+        let dir = std::env::temp_dir().join("allium-test-retroachievements");
+        fs::create_dir_all(&dir)?;
+
+        let mut settings = RetroAchievementsSettings {
+            enabled: true,
+            hardcore: true,
+            server_url: mock_server()?,
+            ..Default::default()
+        };
+        assert!(settings.login("alice".to_string(), "wrong").is_err());
+        assert!(!settings.is_logged_in());
+        settings.login("alice".to_string(), "hunter2")?;
+        assert!(settings.is_logged_in());
+
+        let path = dir.join("cheevos.cfg");
+        settings.write_ra_config(&path)?;
+        assert_eq!(
+            fs::read_to_string(&path)?,
+            r#"cheevos_enable = "true"
+cheevos_username = "alice"
+cheevos_password = ""
+cheevos_token = "token"
+cheevos_hardcore_mode_enable = "true"
+"#
+        );
+
+        settings.logout();
+        settings.write_ra_config(&path)?;
+        assert!(fs::read_to_string(&path)?.starts_with("cheevos_enable = \"false\""));
+
+        let rom = dir.join("abc.gba");
+        fs::write(&rom, "abc")?;
+        assert_eq!(rom_md5(&rom)?, "900150983cd24fb0d6963f7d28e17f72");
+        let rom = dir.join("abc.nes");
+        fs::write(&rom, b"NES\x1a\x01\x01\0\0\0\0\0\0\0\0\0\0abc")?;
+        assert_eq!(rom_md5(&rom)?, "900150983cd24fb0d6963f7d28e17f72");
+        let rom = dir.join("abc.sfc");
+        let mut data = vec![0; 512];
+        data.extend(vec![b'a'; 1024]);
+        fs::write(&rom, &data)?;
+        assert_eq!(rom_md5(&rom)?, hex(&Md5::digest(vec![b'a'; 1024])));
+        let rom = dir.join("abc.lnx");
+        let mut data = b"LYNX\0".to_vec();
+        data.resize(64, 0);
+        data.extend(b"abc");
+        fs::write(&rom, &data)?;
+        assert_eq!(rom_md5(&rom)?, "900150983cd24fb0d6963f7d28e17f72");
+
+        // Headers are stripped from zipped ROMs too
+        let rom = dir.join("abc.zip");
+        let mut zip = zip::ZipWriter::new(File::create(&rom)?);
+        zip.start_file("abc.nes", Default::default())?;
+        zip.write_all(b"NES\x1a\x01\x01\0\0\0\0\0\0\0\0\0\0abc")?;
+        zip.finish()?;
+        assert_eq!(rom_md5(&rom)?, "900150983cd24fb0d6963f7d28e17f72");
+
+        fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,574 @@
+use std::fs::{self, File};
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, Result};
+use base32::encode;
+use chrono::{DateTime, Duration, Local, NaiveDateTime, TimeZone, Utc};
+use log::{debug, warn};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::constants::{
+    ALLIUM_LOW_BATTERY_SAVE, ALLIUM_SAVE_STATES_DIR, ALLIUM_SAVE_STATE_SETTINGS,
+    ALLIUM_SCREENSHOTS_DIR,
+};
+
+/// Slot number of the state RetroArch saves on quit and loads on launch.
+pub const AUTO_SLOT: i8 = -1;
+
+/// Slot that alliumd saves the running game to before shutting down on a critical battery.
+pub const LOW_BATTERY_SLOT: i8 = 99;
+
+/// Directory next to the state files that holds backups of overwritten states.
+const BACKUPS_DIR: &str = "backups";
+
+/// Backups are named after the state file, suffixed with the time the backed up state was saved.
+const BACKUP_TIME_FORMAT: &str = "%Y%m%d-%H%M%S";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SaveStateSettings {
+    /// Number of backups kept per slot. 0 disables backups.
+    pub backups_per_slot: i32,
+    /// Backups older than this many days are removed. 0 keeps them until they are rotated out.
+    pub backup_max_age_days: i32,
+}
+
+impl Default for SaveStateSettings {
+    fn default() -> Self {
+        Self {
+            backups_per_slot: 3,
+            backup_max_age_days: 0,
+        }
+    }
+}
+
+impl SaveStateSettings {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    pub fn load() -> Result<Self> {
+        if ALLIUM_SAVE_STATE_SETTINGS.exists() {
+            debug!("found state, loading from file");
+            let file = File::open(ALLIUM_SAVE_STATE_SETTINGS.as_path())?;
+            if let Ok(json) = serde_json::from_reader(file) {
+                return Ok(json);
+            }
+            warn!("failed to read save state settings file, removing");
+            fs::remove_file(ALLIUM_SAVE_STATE_SETTINGS.as_path())?;
+        }
+        Ok(Self::new())
+    }
+
+    pub fn save(&self) -> Result<()> {
+        let file = File::create(ALLIUM_SAVE_STATE_SETTINGS.as_path())?;
+        serde_json::to_writer(file, &self)?;
+        Ok(())
+    }
+}
+
+/// A state saved to `LOW_BATTERY_SLOT` before shutting down on a critical battery, which is offered
+/// to be restored when the game is resumed.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LowBatterySave {
+    /// Path to the game.
+    pub path: PathBuf,
+    pub core: String,
+    pub time: DateTime<Utc>,
+}
+
+impl LowBatterySave {
+    pub fn new(path: PathBuf, core: String) -> Self {
+        Self {
+            path,
+            core,
+            time: Utc::now(),
+        }
+    }
+
+    pub fn load() -> Result<Option<Self>> {
+        if !ALLIUM_LOW_BATTERY_SAVE.exists() {
+            return Ok(None);
+        }
+        let file = File::open(ALLIUM_LOW_BATTERY_SAVE.as_path())?;
+        if let Ok(json) = serde_json::from_reader(file) {
+            return Ok(Some(json));
+        }
+        warn!("failed to read low battery save file, removing");
+        Self::delete()?;
+        Ok(None)
+    }
+
+    pub fn save(&self) -> Result<()> {
+        let file = File::create(ALLIUM_LOW_BATTERY_SAVE.as_path())?;
+        serde_json::to_writer(file, &self)?;
+        Ok(())
+    }
+
+    pub fn delete() -> Result<()> {
+        if ALLIUM_LOW_BATTERY_SAVE.exists() {
+            fs::remove_file(ALLIUM_LOW_BATTERY_SAVE.as_path())?;
+        }
+        Ok(())
+    }
+}
+
+/// Backs up the states of a game in a slot. Must be called before RetroArch overwrites the slot.
+pub fn backup(game: &Path, core: &str, slot: i8) -> Result<()> {
+    let settings = SaveStateSettings::load()?;
+    for state in SaveState::list(game, core)? {
+        if state.slot == slot {
+            state.backup(&settings)?;
+        }
+    }
+    Ok(())
+}
+
+/// Path to the screenshot taken by allium-menu when saving a state. `path` is the canonicalized
+/// path to the game.
+pub fn screenshot_path(path: &str, core: &str, slot: i8) -> PathBuf {
+    let mut hasher = Sha256::new();
+    hasher.update(path);
+    hasher.update(core);
+    hasher.update(slot.to_le_bytes());
+    let hash = hasher.finalize();
+    let base32 = encode(base32::Alphabet::Crockford, &hash);
+    ALLIUM_SCREENSHOTS_DIR.join(format!("{}.png", base32))
+}
+
+/// Previously, the screenshot hash did not include the core name.
+pub fn legacy_screenshot_path(path: &str, slot: i8) -> PathBuf {
+    let mut hasher = Sha256::new();
+    hasher.update(path);
+    hasher.update(slot.to_le_bytes());
+    let hash = hasher.finalize();
+    let base32 = encode(base32::Alphabet::Crockford, &hash);
+    ALLIUM_SCREENSHOTS_DIR.join(format!("{}.png", base32))
+}
+
+/// Returns the screenshot for a save state slot, if one was taken.
+pub fn find_screenshot(path: &str, core: &str, slot: i8) -> Option<PathBuf> {
+    let screenshot = screenshot_path(path, core, slot);
+    if screenshot.exists() {
+        return Some(screenshot);
+    }
+    let screenshot = legacy_screenshot_path(path, slot);
+    screenshot.exists().then_some(screenshot)
+}
+
+/// A RetroArch save state of a game.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SaveState {
+    /// Path to the state file.
+    pub path: PathBuf,
+    /// State slot, or `AUTO_SLOT`.
+    pub slot: i8,
+    pub modified: DateTime<Local>,
+    /// Canonicalized path to the game, used to find screenshots.
+    game: String,
+    core: String,
+}
+
+impl SaveState {
+    /// Lists the save states of a game, the auto state first, then by slot.
+    pub fn list(game: &Path, core: &str) -> Result<Vec<SaveState>> {
+        Self::list_in(&ALLIUM_SAVE_STATES_DIR, game, core)
+    }
+
+    /// Lists the save states of a game in a RetroArch states directory. States are sorted into a
+    /// directory per core, named after the libretro core's library name rather than Allium's core
+    /// name, so all of them are searched.
+    pub fn list_in(dir: &Path, game: &Path, core: &str) -> Result<Vec<SaveState>> {
+        let mut states = Vec::new();
+        let Some(stem) = game.file_stem().and_then(|s| s.to_str()) else {
+            return Ok(states);
+        };
+        if !dir.is_dir() {
+            return Ok(states);
+        }
+        let prefix = format!("{stem}.state");
+        let canonical = game
+            .canonicalize()
+            .unwrap_or_else(|_| game.to_path_buf())
+            .to_string_lossy()
+            .to_string();
+
+        let mut dirs = vec![dir.to_path_buf()];
+        for entry in fs::read_dir(dir)? {
+            let entry = entry?;
+            if entry.file_type()?.is_dir() {
+                dirs.push(entry.path());
+            }
+        }
+
+        for dir in dirs {
+            for entry in fs::read_dir(&dir)? {
+                let entry = entry?;
+                let file_name = entry.file_name();
+                let Some(slot) = file_name
+                    .to_str()
+                    .and_then(|name| name.strip_prefix(&prefix))
+                    .and_then(parse_slot)
+                else {
+                    continue;
+                };
+                let metadata = entry.metadata()?;
+                if !metadata.is_file() {
+                    continue;
+                }
+                states.push(SaveState {
+                    path: entry.path(),
+                    slot,
+                    modified: metadata.modified()?.into(),
+                    game: canonical.clone(),
+                    core: core.to_string(),
+                });
+            }
+        }
+
+        states.sort_by_key(|state| (state.slot, std::cmp::Reverse(state.modified)));
+        Ok(states)
+    }
+
+    /// Returns the screenshot taken by Allium when the state was saved, falling back to the
+    /// thumbnail saved by RetroArch.
+    pub fn screenshot(&self) -> Option<PathBuf> {
+        find_screenshot(&self.game, &self.core, self.slot).or_else(|| {
+            let thumbnail = thumbnail_path(&self.path);
+            thumbnail.exists().then_some(thumbnail)
+        })
+    }
+
+    /// Deletes the state and its screenshots.
+    pub fn delete(&self) -> Result<()> {
+        fs::remove_file(&self.path)?;
+        let _ = fs::remove_file(thumbnail_path(&self.path));
+        let _ = fs::remove_file(screenshot_path(&self.game, &self.core, self.slot));
+        let _ = fs::remove_file(legacy_screenshot_path(&self.game, self.slot));
+        Ok(())
+    }
+
+    /// Copies the state and its screenshot to the backups directory, then removes backups of this
+    /// state that exceed the retention limits.
+    pub fn backup(&self, settings: &SaveStateSettings) -> Result<()> {
+        if settings.backups_per_slot <= 0 {
+            return Ok(());
+        }
+
+        let dir = backups_dir(&self.path);
+        fs::create_dir_all(&dir)?;
+        let path = dir.join(format!(
+            "{}.{}",
+            self.file_name(),
+            self.modified.format(BACKUP_TIME_FORMAT)
+        ));
+        debug!("backing up save state to {}", path.display());
+        fs::copy(&self.path, &path)?;
+        if let Some(screenshot) = self.screenshot() {
+            fs::copy(screenshot, thumbnail_path(&path))?;
+        }
+
+        self.prune_backups(settings)
+    }
+
+    /// Lists the backups of this state, newest first.
+    pub fn backups(&self) -> Result<Vec<Backup>> {
+        let mut backups = Vec::new();
+        let dir = backups_dir(&self.path);
+        if !dir.is_dir() {
+            return Ok(backups);
+        }
+        let prefix = format!("{}.", self.file_name());
+
+        for entry in fs::read_dir(&dir)? {
+            let entry = entry?;
+            let file_name = entry.file_name();
+            let Some(modified) = file_name
+                .to_str()
+                .and_then(|name| name.strip_prefix(&prefix))
+                .and_then(|time| NaiveDateTime::parse_from_str(time, BACKUP_TIME_FORMAT).ok())
+                .and_then(|time| Local.from_local_datetime(&time).earliest())
+            else {
+                continue;
+            };
+            backups.push(Backup {
+                path: entry.path(),
+                modified,
+            });
+        }
+
+        backups.sort_by_key(|backup| std::cmp::Reverse(backup.modified));
+        Ok(backups)
+    }
+
+    /// Replaces the state with a backup. The current state is backed up first, so restoring can
+    /// be undone.
+    pub fn restore(&self, backup: &Backup, settings: &SaveStateSettings) -> Result<()> {
+        // Read the backup first, backing up the current state may rotate it out
+        let data = fs::read(&backup.path)?;
+        let screenshot = backup.screenshot().map(fs::read).transpose()?;
+        self.backup(settings)?;
+
+        fs::write(&self.path, data)?;
+
+        // The screenshots of the current state are stale now. Allium's screenshot is only
+        // replaced if there was one, otherwise the thumbnail is used.
+        let allium_screenshot = find_screenshot(&self.game, &self.core, self.slot);
+        let _ = fs::remove_file(legacy_screenshot_path(&self.game, self.slot));
+        match screenshot {
+            Some(screenshot) => {
+                if allium_screenshot.is_some() {
+                    fs::write(
+                        screenshot_path(&self.game, &self.core, self.slot),
+                        &screenshot,
+                    )?;
+                }
+                fs::write(thumbnail_path(&self.path), screenshot)?;
+            }
+            None => {
+                let _ = fs::remove_file(screenshot_path(&self.game, &self.core, self.slot));
+                let _ = fs::remove_file(thumbnail_path(&self.path));
+            }
+        }
+        Ok(())
+    }
+
+    fn prune_backups(&self, settings: &SaveStateSettings) -> Result<()> {
+        let cutoff = (settings.backup_max_age_days > 0)
+            .then(|| Local::now() - Duration::days(settings.backup_max_age_days.into()));
+        for (i, backup) in self.backups()?.iter().enumerate() {
+            if i >= settings.backups_per_slot as usize
+                || cutoff.is_some_and(|cutoff| backup.modified < cutoff)
+            {
+                backup.delete()?;
+            }
+        }
+        Ok(())
+    }
+
+    fn file_name(&self) -> String {
+        self.path.file_name().unwrap().to_string_lossy().to_string()
+    }
+
+    /// Moves the state and its screenshots to another game, keeping the slot. Used when discs are
+    /// grouped into a playlist, as RetroArch names states after the playlist instead.
+    pub fn move_to(&self, game: &Path) -> Result<SaveState> {
+        let Some(stem) = game.file_stem().and_then(|s| s.to_str()) else {
+            bail!("invalid game path: {}", game.display());
+        };
+        let path = self.path.with_file_name(state_file_name(stem, self.slot));
+        let canonical = game
+            .canonicalize()
+            .unwrap_or_else(|_| game.to_path_buf())
+            .to_string_lossy()
+            .to_string();
+
+        fs::rename(&self.path, &path)?;
+        let thumbnail = thumbnail_path(&self.path);
+        if thumbnail.exists() {
+            fs::rename(&thumbnail, thumbnail_path(&path))?;
+        }
+        if let Some(screenshot) = find_screenshot(&self.game, &self.core, self.slot) {
+            fs::rename(
+                screenshot,
+                screenshot_path(&canonical, &self.core, self.slot),
+            )?;
+        }
+
+        Ok(SaveState {
+            modified: fs::metadata(&path)?.modified()?.into(),
+            path,
+            slot: self.slot,
+            game: canonical,
+            core: self.core.clone(),
+        })
+    }
+
+    /// Copies the state and its screenshots to another slot, overwriting it.
+    pub fn copy_to(&self, slot: i8) -> Result<SaveState> {
+        let file_name = self.file_name();
+        let stem = &file_name[..file_name.rfind(".state").unwrap()];
+        let path = self.path.with_file_name(state_file_name(stem, slot));
+
+        fs::copy(&self.path, &path)?;
+        let thumbnail = thumbnail_path(&self.path);
+        if thumbnail.exists() {
+            fs::copy(&thumbnail, thumbnail_path(&path))?;
+        } else {
+            let _ = fs::remove_file(thumbnail_path(&path));
+        }
+        let screenshot = screenshot_path(&self.game, &self.core, slot);
+        match find_screenshot(&self.game, &self.core, self.slot) {
+            Some(src) => {
+                fs::create_dir_all(ALLIUM_SCREENSHOTS_DIR.as_path())?;
+                fs::copy(src, screenshot)?;
+            }
+            None => {
+                let _ = fs::remove_file(screenshot);
+            }
+        }
+
+        Ok(SaveState {
+            modified: fs::metadata(&path)?.modified()?.into(),
+            path,
+            slot,
+            game: self.game.clone(),
+            core: self.core.clone(),
+        })
+    }
+}
+
+/// A backup of an overwritten save state.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Backup {
+    pub path: PathBuf,
+    /// When the backed up state was saved.
+    pub modified: DateTime<Local>,
+}
+
+impl Backup {
+    pub fn screenshot(&self) -> Option<PathBuf> {
+        let screenshot = thumbnail_path(&self.path);
+        screenshot.exists().then_some(screenshot)
+    }
+
+    pub fn delete(&self) -> Result<()> {
+        fs::remove_file(&self.path)?;
+        let _ = fs::remove_file(thumbnail_path(&self.path));
+        Ok(())
+    }
+}
+
+/// Parses the suffix after ".state" in a RetroArch state file name.
+fn parse_slot(suffix: &str) -> Option<i8> {
+    match suffix {
+        "" => Some(0),
+        ".auto" => Some(AUTO_SLOT),
+        n if n.bytes().all(|b| b.is_ascii_digit()) => n.parse().ok(),
+        _ => None,
+    }
+}
+
+fn state_file_name(stem: &str, slot: i8) -> String {
+    match slot {
+        AUTO_SLOT => format!("{stem}.state.auto"),
+        0 => format!("{stem}.state"),
+        n => format!("{stem}.state{n}"),
+    }
+}
+
+fn backups_dir(path: &Path) -> PathBuf {
+    path.parent().unwrap_or(path).join(BACKUPS_DIR)
+}
+
+/// RetroArch saves a thumbnail next to each state.
+fn thumbnail_path(path: &Path) -> PathBuf {
+    let mut path = path.as_os_str().to_owned();
+    path.push(".png");
+    PathBuf::from(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_save_states() -> Result<()> {
+        let dir = std::env::temp_dir().join("allium-test-save-states");
+        let _ = fs::remove_dir_all(&dir);
+        let states = dir.join("states");
+        fs::create_dir_all(states.join("mGBA"))?;
+        fs::create_dir_all(states.join("gpSP"))?;
+
+        let game = dir.join("Pokemon Emerald.gba");
+        fs::write(&game, "")?;
+        fs::write(states.join("mGBA/Pokemon Emerald.state"), "0")?;
+        fs::write(states.join("mGBA/Pokemon Emerald.state.png"), "")?;
+        fs::write(states.join("mGBA/Pokemon Emerald.state3"), "3")?;
+        fs::write(states.join("gpSP/Pokemon Emerald.state.auto"), "auto")?;
+        fs::write(states.join("mGBA/Pokemon Emerald.srm"), "")?;
+        fs::write(states.join("mGBA/Pokemon Ruby.state"), "")?;
+
+        let list = SaveState::list_in(&states, &game, "mgba")?;
+        assert_eq!(
+            list.iter().map(|s| s.slot).collect::<Vec<_>>(),
+            vec![AUTO_SLOT, 0, 3]
+        );
+        assert_eq!(
+            list[1].screenshot(),
+            Some(states.join("mGBA/Pokemon Emerald.state.png"))
+        );
+        assert_eq!(list[2].screenshot(), None);
+
+        let copy = list[1].copy_to(5)?;
+        assert_eq!(copy.path, states.join("mGBA/Pokemon Emerald.state5"));
+        assert_eq!(fs::read_to_string(&copy.path)?, "0");
+        assert!(states.join("mGBA/Pokemon Emerald.state5.png").exists());
+
+        list[1].delete()?;
+        assert!(!states.join("mGBA/Pokemon Emerald.state").exists());
+        assert!(!states.join("mGBA/Pokemon Emerald.state.png").exists());
+        assert_eq!(
+            SaveState::list_in(&states, &game, "mgba")?
+                .iter()
+                .map(|s| s.slot)
+                .collect::<Vec<_>>(),
+            vec![AUTO_SLOT, 3, 5]
+        );
+
+        fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_save_state_backups() -> Result<()> {
+        let dir = std::env::temp_dir().join("allium-test-save-state-backups");
+        let _ = fs::remove_dir_all(&dir);
+        let states = dir.join("states");
+        fs::create_dir_all(states.join("mGBA"))?;
+
+        let game = dir.join("Pokemon Emerald.gba");
+        fs::write(&game, "")?;
+        let path = states.join("mGBA/Pokemon Emerald.state");
+        let settings = SaveStateSettings {
+            backups_per_slot: 2,
+            backup_max_age_days: 0,
+        };
+
+        let now = Local::now();
+        for (i, data) in ["a", "b", "c"].iter().enumerate() {
+            fs::write(&path, data)?;
+            fs::write(states.join("mGBA/Pokemon Emerald.state.png"), data)?;
+            let state = SaveState::list_in(&states, &game, "mgba")?.remove(0);
+            let state = SaveState {
+                modified: now - Duration::minutes(10 - i as i64),
+                ..state
+            };
+            state.backup(&settings)?;
+        }
+        fs::write(&path, "d")?;
+
+        let state = SaveState::list_in(&states, &game, "mgba")?.remove(0);
+        let backups = state.backups()?;
+        assert_eq!(backups.len(), 2);
+        assert_eq!(fs::read_to_string(&backups[0].path)?, "c");
+        assert_eq!(fs::read_to_string(&backups[1].path)?, "b");
+        assert!(backups[0].screenshot().is_some());
+        // Backups are not listed as states
+        assert_eq!(SaveState::list_in(&states, &game, "mgba")?.len(), 1);
+
+        state.restore(&backups[1], &settings)?;
+        assert_eq!(fs::read_to_string(&path)?, "b");
+        let backups = state.backups()?;
+        assert_eq!(backups.len(), 2);
+        assert_eq!(fs::read_to_string(&backups[0].path)?, "d");
+        assert_eq!(fs::read_to_string(&backups[1].path)?, "c");
+        assert_eq!(
+            fs::read_to_string(states.join("mGBA/Pokemon Emerald.state.png"))?,
+            "b"
+        );
+
+        fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,449 @@
+use rusqlite::types::Value;
+
+/// A parsed search query.
+///
+/// Queries are made of whitespace-separated terms, which must all match. A term is either free
+/// text, a "quoted phrase", or a `field:value` filter. Any term can be negated with a leading `-`.
+///
+/// Supported filters:
+/// - `dev:capcom`, `pub:nintendo`: developer or publisher, prefix matched
+/// - `genre:rpg`: genre containing the value
+/// - `console:gba`: ROM folder name, or the tag in a folder such as "Game Boy Advance (GBA)"
+/// - `year:1994`, `year:1994..1998`, `year:>=2000`: release year
+/// - `played:>2h`, `played:30m..1h30m`: play time, a bare number is in hours
+/// - `rating:>=8`: rating out of 10
+///
+/// Values containing spaces can be quoted, e.g. `dev:"square enix"`. Terms that are not valid
+/// filters are searched as text.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Query {
+    pub clauses: Vec<Clause>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Clause {
+    pub negated: bool,
+    pub filter: Filter,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Filter {
+    /// Prefix match on the name, developer or publisher.
+    Text(String),
+    /// Exact phrase match on the name, developer or publisher.
+    Phrase(String),
+    Developer(String),
+    Publisher(String),
+    Genre(String),
+    Console(String),
+    Year(Comparison),
+    /// Play time in seconds.
+    Played(Comparison),
+    Rating(Comparison),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Comparison {
+    Eq(i64),
+    Gt(i64),
+    Ge(i64),
+    Lt(i64),
+    Le(i64),
+    /// Inclusive range, either end may be open.
+    Between(Option<i64>, Option<i64>),
+}
+
+impl Query {
+    /// Parses a search query. This never fails, invalid filters are treated as text.
+    pub fn parse(s: &str) -> Self {
+        let clauses = tokenize(s)
+            .into_iter()
+            .filter_map(|token| {
+                let filter = Filter::parse(&token)?;
+                Some(Clause {
+                    negated: token.negated,
+                    filter,
+                })
+            })
+            .collect();
+        Self { clauses }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.clauses.is_empty()
+    }
+
+    /// Compiles the query into a condition on the games table, and its parameters. Free text and
+    /// developer/publisher filters become a single FTS5 MATCH, the other filters become SQL
+    /// predicates. Returns None if nothing in the query can be searched, e.g. if it is empty or only
+    /// punctuation.
+    pub fn to_sql(&self) -> Option<(String, Vec<Value>)> {
+        if self.is_empty() {
+            return None;
+        }
+
+        let mut conditions = Vec::new();
+        let mut params = Vec::new();
+
+        let matches: Vec<String> = self
+            .clauses
+            .iter()
+            .filter(|clause| !clause.negated)
+            .filter_map(|clause| clause.filter.fts())
+            .collect();
+        if !matches.is_empty() {
+            conditions.push(
+                "games.id IN (SELECT rowid FROM games_fts WHERE games_fts MATCH ?)".to_string(),
+            );
+            params.push(Value::Text(matches.join(" AND ")));
+        }
+
+        for clause in &self.clauses {
+            if let Some(fts) = clause.filter.fts() {
+                if clause.negated {
+                    conditions.push(
+                        "games.id NOT IN (SELECT rowid FROM games_fts WHERE games_fts MATCH ?)"
+                            .to_string(),
+                    );
+                    params.push(Value::Text(fts));
+                }
+            } else if let Some((condition, values)) = clause.filter.predicate() {
+                if clause.negated {
+                    // Games without the field, e.g. unrated games, are kept when negated
+                    conditions.push(format!("NOT coalesce({condition}, 0)"));
+                } else {
+                    conditions.push(condition);
+                }
+                params.extend(values);
+            }
+        }
+
+        if conditions.is_empty() {
+            return None;
+        }
+        Some((conditions.join(" AND "), params))
+    }
+}
+
+impl Filter {
+    fn parse(token: &Token) -> Option<Self> {
+        if token.value.is_empty() {
+            return None;
+        }
+        if token.phrase {
+            return Some(Filter::Phrase(token.value.clone()));
+        }
+
+        let text = || Some(Filter::Text(token.value.clone()));
+        let Some((field, value)) = token.value.split_once(':') else {
+            return text();
+        };
+        if value.is_empty() {
+            return text();
+        }
+
+        let value = value.to_string();
+        let filter = match field.to_lowercase().as_str() {
+            "dev" | "developer" => Filter::Developer(value),
+            "pub" | "publisher" => Filter::Publisher(value),
+            "genre" => Filter::Genre(value),
+            "console" | "system" => Filter::Console(value),
+            "year" => match Comparison::parse(&value, |s| s.parse().ok()) {
+                Some(cmp) => Filter::Year(cmp),
+                None => return text(),
+            },
+            "played" => match Comparison::parse(&value, parse_duration) {
+                // A bare duration means at least that long
+                Some(Comparison::Eq(secs)) => Filter::Played(Comparison::Ge(secs)),
+                Some(cmp) => Filter::Played(cmp),
+                None => return text(),
+            },
+            "rating" => match Comparison::parse(&value, |s| s.parse().ok()) {
+                Some(cmp) => Filter::Rating(cmp),
+                None => return text(),
+            },
+            _ => return text(),
+        };
+        Some(filter)
+    }
+
+    /// Returns the FTS5 query for filters matched against the full text index.
+    fn fts(&self) -> Option<String> {
+        match self {
+            Filter::Text(text) if text.chars().any(char::is_alphanumeric) => Some(format!(
+                "{{name developer publisher}} : {} *",
+                fts_string(text)
+            )),
+            Filter::Phrase(phrase) if phrase.chars().any(char::is_alphanumeric) => Some(format!(
+                "{{name developer publisher}} : {}",
+                fts_string(phrase)
+            )),
+            Filter::Developer(dev) if dev.chars().any(char::is_alphanumeric) => {
+                Some(format!("developer : {} *", fts_string(dev)))
+            }
+            Filter::Publisher(publisher) if publisher.chars().any(char::is_alphanumeric) => {
+                Some(format!("publisher : {} *", fts_string(publisher)))
+            }
+            _ => None,
+        }
+    }
+
+    /// Returns the SQL predicate for filters on other columns.
+    fn predicate(&self) -> Option<(String, Vec<Value>)> {
+        match self {
+            Filter::Genre(genre) => Some((
+                "EXISTS (SELECT 1 FROM json_each(games.genres) WHERE json_each.value LIKE ? ESCAPE '\\')"
+                    .to_string(),
+                vec![Value::Text(format!("%{}%", escape_like(genre)))],
+            )),
+            Filter::Console(console) => {
+                let console = escape_like(console);
+                Some((
+                    "(games.path LIKE ? ESCAPE '\\' OR games.path LIKE ? ESCAPE '\\' OR games.path LIKE ? ESCAPE '\\')"
+                        .to_string(),
+                    vec![
+                        Value::Text(format!("%/{console}/%")),
+                        Value::Text(format!("%/{console} (%)/%")),
+                        Value::Text(format!("%({console})/%")),
+                    ],
+                ))
+            }
+            Filter::Year(cmp) => Some(cmp.to_sql(
+                "CAST(substr(games.release_date, 1, 4) AS INTEGER)",
+            )),
+            Filter::Played(cmp) => Some(cmp.to_sql("games.play_time")),
+            Filter::Rating(cmp) => Some(cmp.to_sql("games.rating")),
+            Filter::Text(_) | Filter::Phrase(_) | Filter::Developer(_) | Filter::Publisher(_) => {
+                None
+            }
+        }
+    }
+}
+
+impl Comparison {
+    /// Parses `N`, `>N`, `>=N`, `<N`, `<=N`, `=N`, `A..B`, `A..` or `..B`.
+    fn parse(s: &str, value: impl Fn(&str) -> Option<i64>) -> Option<Self> {
+        if let Some((start, end)) = s.split_once("..") {
+            let start = if start.is_empty() {
+                None
+            } else {
+                Some(value(start)?)
+            };
+            let end = if end.is_empty() {
+                None
+            } else {
+                Some(value(end)?)
+            };
+            if start.is_none() && end.is_none() {
+                return None;
+            }
+            return Some(Comparison::Between(start, end));
+        }
+
+        if let Some(s) = s.strip_prefix(">=") {
+            Some(Comparison::Ge(value(s)?))
+        } else if let Some(s) = s.strip_prefix("<=") {
+            Some(Comparison::Le(value(s)?))
+        } else if let Some(s) = s.strip_prefix('>') {
+            Some(Comparison::Gt(value(s)?))
+        } else if let Some(s) = s.strip_prefix('<') {
+            Some(Comparison::Lt(value(s)?))
+        } else {
+            Some(Comparison::Eq(value(s.strip_prefix('=').unwrap_or(s))?))
+        }
+    }
+
+    fn to_sql(self, column: &str) -> (String, Vec<Value>) {
+        match self {
+            Comparison::Eq(n) => (format!("{column} = ?"), vec![Value::Integer(n)]),
+            Comparison::Gt(n) => (format!("{column} > ?"), vec![Value::Integer(n)]),
+            Comparison::Ge(n) => (format!("{column} >= ?"), vec![Value::Integer(n)]),
+            Comparison::Lt(n) => (format!("{column} < ?"), vec![Value::Integer(n)]),
+            Comparison::Le(n) => (format!("{column} <= ?"), vec![Value::Integer(n)]),
+            Comparison::Between(Some(start), Some(end)) => (
+                format!("{column} BETWEEN ? AND ?"),
+                vec![Value::Integer(start), Value::Integer(end)],
+            ),
+            Comparison::Between(Some(start), None) => {
+                (format!("{column} >= ?"), vec![Value::Integer(start)])
+            }
+            Comparison::Between(None, Some(end)) => {
+                (format!("{column} <= ?"), vec![Value::Integer(end)])
+            }
+            Comparison::Between(None, None) => ("1".to_string(), Vec::new()),
+        }
+    }
+}
+
+/// Parses a duration such as `2h`, `30m`, `1h30m` or `45s` into seconds. A bare number is in
+/// hours.
+fn parse_duration(s: &str) -> Option<i64> {
+    if let Ok(hours) = s.parse::<i64>() {
+        return Some(hours * 3600);
+    }
+
+    let mut secs = 0;
+    let mut n = String::new();
+    for c in s.chars() {
+        if c.is_ascii_digit() {
+            n.push(c);
+            continue;
+        }
+        let unit = match c.to_ascii_lowercase() {
+            'd' => 86400,
+            'h' => 3600,
+            'm' => 60,
+            's' => 1,
+            _ => return None,
+        };
+        secs += n.parse::<i64>().ok()? * unit;
+        n.clear();
+    }
+    if !n.is_empty() {
+        return None;
+    }
+    Some(secs)
+}
+
+/// Quotes a string for FTS5, which escapes double quotes by doubling them.
+fn fts_string(s: &str) -> String {
+    format!("\"{}\"", s.replace('"', "\"\""))
+}
+
+fn escape_like(s: &str) -> String {
+    s.replace('\\', "\\\\")
+        .replace('%', "\\%")
+        .replace('_', "\\_")
+}
+
+#[derive(Debug, Default)]
+struct Token {
+    negated: bool,
+    /// Whether the whole token was quoted.
+    phrase: bool,
+    value: String,
+}
+
+/// Splits a query on whitespace outside of quotes. Quotes are removed from the tokens.
+fn tokenize(s: &str) -> Vec<Token> {
+    let mut tokens = Vec::new();
+    let mut chars = s.chars().peekable();
+
+    loop {
+        while chars.next_if(|c| c.is_whitespace()).is_some() {}
+        let Some(&c) = chars.peek() else {
+            break;
+        };
+
+        let mut token = Token::default();
+        if c == '-' {
+            chars.next();
+            token.negated = true;
+        }
+        token.phrase = chars.peek() == Some(&'"');
+
+        let mut quoted = false;
+        while let Some(c) = chars.next_if(|&c| quoted || !c.is_whitespace()) {
+            if c == '"' {
+                quoted = !quoted;
+            } else {
+                token.value.push(c);
+            }
+        }
+
+        if token.phrase {
+            token.value = token.value.trim().to_string();
+        }
+        tokens.push(token);
+    }
+
+    tokens
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn clause(negated: bool, filter: Filter) -> Clause {
+        Clause { negated, filter }
+    }
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(Query::parse("  "), Query::default());
+
+        assert_eq!(
+            Query::parse("mario -\"super mario\" dev:\"square enix\" genre:rpg -console:gba"),
+            Query {
+                clauses: vec![
+                    clause(false, Filter::Text("mario".to_string())),
+                    clause(true, Filter::Phrase("super mario".to_string())),
+                    clause(false, Filter::Developer("square enix".to_string())),
+                    clause(false, Filter::Genre("rpg".to_string())),
+                    clause(true, Filter::Console("gba".to_string())),
+                ]
+            }
+        );
+
+        assert_eq!(
+            Query::parse("year:1994..1998 year:2000.. played:>2h played:1h30m rating:>=8"),
+            Query {
+                clauses: vec![
+                    clause(
+                        false,
+                        Filter::Year(Comparison::Between(Some(1994), Some(1998)))
+                    ),
+                    clause(false, Filter::Year(Comparison::Between(Some(2000), None))),
+                    clause(false, Filter::Played(Comparison::Gt(7200))),
+                    clause(false, Filter::Played(Comparison::Ge(5400))),
+                    clause(false, Filter::Rating(Comparison::Ge(8))),
+                ]
+            }
+        );
+
+        // Invalid and unknown filters are searched as text
+        assert_eq!(
+            Query::parse("year:soon re:zero rating:"),
+            Query {
+                clauses: vec![
+                    clause(false, Filter::Text("year:soon".to_string())),
+                    clause(false, Filter::Text("re:zero".to_string())),
+                    clause(false, Filter::Text("rating:".to_string())),
+                ]
+            }
+        );
+    }
+
+    #[test]
+    fn test_parse_duration() {
+        assert_eq!(parse_duration("2"), Some(7200));
+        assert_eq!(parse_duration("2h"), Some(7200));
+        assert_eq!(parse_duration("30m"), Some(1800));
+        assert_eq!(parse_duration("1h30m"), Some(5400));
+        assert_eq!(parse_duration("45s"), Some(45));
+        assert_eq!(parse_duration("h"), None);
+        assert_eq!(parse_duration("2x"), None);
+        assert_eq!(parse_duration("1h30"), None);
+    }
+
+    #[test]
+    fn test_to_sql() {
+        assert_eq!(Query::parse("").to_sql(), None);
+
+        let (sql, params) = Query::parse("zelda -\"a link\" rating:>=8")
+            .to_sql()
+            .unwrap();
+        assert_eq!(
+            sql,
+            "games.id IN (SELECT rowid FROM games_fts WHERE games_fts MATCH ?) AND games.id NOT IN (SELECT rowid FROM games_fts WHERE games_fts MATCH ?) AND games.rating >= ?"
+        );
+        assert_eq!(
+            params,
+            vec![
+                Value::Text("{name developer publisher} : \"zelda\" *".to_string()),
+                Value::Text("{name developer publisher} : \"a link\"".to_string()),
+                Value::Integer(8),
+            ]
+        );
+    }
+}
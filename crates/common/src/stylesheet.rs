@@ -11,8 +11,12 @@ use serde::{Deserialize, Serialize};
 use crate::{
     constants::{ALLIUM_FONTS_DIR, ALLIUM_STYLESHEET},
     display::color::Color,
+    platform::Key,
 };
 
+/// Buttons that can be drawn with a custom icon, named `<key>.png` in `Stylesheet::button_icons`.
+pub const BUTTON_ICON_KEYS: [&str; 4] = ["a", "b", "x", "y"];
+
 #[derive(Debug, Clone, Copy, Serialize, Deserialize)]
 pub enum StylesheetColor {
     Foreground,
@@ -126,6 +130,9 @@ impl StylesheetFont {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Stylesheet {
     pub wallpaper: Option<PathBuf>,
+    /// Directory of button icons that replace the drawn A/B/X/Y buttons.
+    #[serde(default)]
+    pub button_icons: Option<PathBuf>,
     pub enable_box_art: bool,
     pub show_battery_level: bool,
     #[serde(default = "Stylesheet::default_foreground_color")]
@@ -236,15 +243,32 @@ impl Stylesheet {
         Ok(())
     }
 
+    /// Saves the stylesheet. It is written to a temporary file first, so that a failed write never
+    /// leaves a partially written stylesheet behind.
     pub fn save(&self) -> Result<()> {
         let json = serde_json::to_string(&self).unwrap();
-        File::create(ALLIUM_STYLESHEET.as_path())?.write_all(json.as_bytes())?;
+        let tmp = ALLIUM_STYLESHEET.with_extension("json.tmp");
+        File::create(&tmp)?.write_all(json.as_bytes())?;
+        fs::rename(&tmp, ALLIUM_STYLESHEET.as_path())?;
         if let Err(e) = self.patch_ra_config() {
             warn!("failed to patch RA config: {}", e);
         }
         Ok(())
     }
 
+    /// Returns the custom icon for a button, if the stylesheet has one.
+    pub fn button_icon(&self, key: Key) -> Option<PathBuf> {
+        let name = match key {
+            Key::A => "a",
+            Key::B => "b",
+            Key::X => "x",
+            Key::Y => "y",
+            _ => return None,
+        };
+        let path = self.button_icons.as_ref()?.join(format!("{}.png", name));
+        path.is_file().then_some(path)
+    }
+
     pub fn toggle_dark_mode(&mut self) {
         mem::swap(&mut self.foreground_color, &mut self.alt_foreground_color);
         mem::swap(&mut self.background_color, &mut self.alt_background_color);
@@ -421,6 +445,7 @@ impl Default for Stylesheet {
     fn default() -> Self {
         Self {
             wallpaper: None,
+            button_icons: None,
             enable_box_art: true,
             show_battery_level: false,
             foreground_color: Self::default_foreground_color(),
@@ -0,0 +1,400 @@
+use std::fs::{self, File};
+use std::path::{Path, PathBuf};
+
+use anyhow::{anyhow, bail, Result};
+use image::{imageops::FilterType, Rgba, RgbaImage};
+use log::{info, warn};
+
+use crate::constants::{ALLIUM_SD_ROOT, ALLIUM_THEMES_DIR};
+use crate::display::color::Color;
+use crate::stylesheet::{Stylesheet, BUTTON_ICON_KEYS};
+
+/// Stylesheet of a theme package.
+pub const THEME_FILE: &str = "theme.json";
+
+/// Image shown for a theme in the theme browser.
+pub const PREVIEW_FILE: &str = "preview.png";
+
+const PREVIEW_WIDTH: u32 = 320;
+const PREVIEW_HEIGHT: u32 = 240;
+
+/// Manages theme packages.
+///
+/// A theme package is a directory in `.allium/themes` containing:
+/// - `theme.json`: the stylesheet, with font, wallpaper and button icon paths relative to the
+///   package
+/// - `fonts/`: the fonts used by the stylesheet
+/// - `wallpaper.png`: optional wallpaper
+/// - `buttons/`: optional A/B/X/Y button icons, named `a.png`, `b.png`, `x.png` and `y.png`
+/// - `preview.png`: optional preview image
+///
+/// Themes can also be copied to `.allium/themes` as zip archives of such a directory, which are
+/// extracted the next time themes are listed.
+#[derive(Debug, Clone)]
+pub struct Themes {
+    dir: PathBuf,
+}
+
+impl Default for Themes {
+    fn default() -> Self {
+        Self::new(&ALLIUM_THEMES_DIR)
+    }
+}
+
+impl Themes {
+    pub fn new(dir: &Path) -> Self {
+        Self {
+            dir: dir.to_path_buf(),
+        }
+    }
+
+    /// Returns all installed themes, sorted by name. Zip archives are installed first.
+    pub fn list(&self) -> Result<Vec<ThemePackage>> {
+        if !self.dir.is_dir() {
+            return Ok(Vec::new());
+        }
+
+        for entry in fs::read_dir(&self.dir)? {
+            let path = entry?.path();
+            if path
+                .extension()
+                .is_some_and(|ext| ext.eq_ignore_ascii_case("zip"))
+            {
+                if let Err(e) = self.install(&path) {
+                    warn!("failed to install theme {}: {}", path.display(), e);
+                }
+            }
+        }
+
+        let mut themes = Vec::new();
+        for entry in fs::read_dir(&self.dir)? {
+            let path = entry?.path();
+            if path.join(THEME_FILE).is_file() {
+                themes.push(ThemePackage::new(path));
+            }
+        }
+        themes.sort_unstable_by_key(|theme| theme.name.to_lowercase());
+        Ok(themes)
+    }
+
+    /// Extracts a theme archive into a directory named after it, then removes the archive. The
+    /// archive is extracted to a temporary directory first, so that a partially extracted theme is
+    /// never listed.
+    pub fn install(&self, archive: &Path) -> Result<ThemePackage> {
+        let name = archive
+            .file_stem()
+            .and_then(std::ffi::OsStr::to_str)
+            .ok_or_else(|| anyhow!("invalid theme archive name"))?;
+        let name = self.validate_new_name(name)?;
+        info!("installing theme {}", name);
+
+        let tmp = self.dir.join(format!(".{}.tmp", name));
+        if tmp.exists() {
+            fs::remove_dir_all(&tmp)?;
+        }
+        zip::ZipArchive::new(File::open(archive)?)?.extract(&tmp)?;
+
+        // Archives of a theme directory contain the directory itself
+        let root = if tmp.join(THEME_FILE).is_file() {
+            tmp.clone()
+        } else {
+            let dirs = fs::read_dir(&tmp)?
+                .map(|entry| entry.map(|entry| entry.path()))
+                .collect::<std::io::Result<Vec<_>>>()?;
+            match dirs.as_slice() {
+                [dir] if dir.join(THEME_FILE).is_file() => dir.clone(),
+                _ => {
+                    fs::remove_dir_all(&tmp)?;
+                    bail!("archive does not contain {}", THEME_FILE);
+                }
+            }
+        };
+
+        let path = self.dir.join(name);
+        fs::rename(&root, &path)?;
+        if tmp.exists() {
+            fs::remove_dir_all(&tmp)?;
+        }
+        fs::remove_file(archive)?;
+        Ok(ThemePackage::new(path))
+    }
+
+    /// Saves a stylesheet as a new theme, copying its fonts, wallpaper and button icons into the
+    /// package and rendering a preview.
+    pub fn save(&self, name: &str, styles: &Stylesheet) -> Result<ThemePackage> {
+        let name = self.validate_new_name(name)?;
+        info!("saving theme {}", name);
+
+        let tmp = self.dir.join(format!(".{}.tmp", name));
+        if tmp.exists() {
+            fs::remove_dir_all(&tmp)?;
+        }
+        fs::create_dir_all(tmp.join("fonts"))?;
+
+        let mut theme = styles.clone();
+        for font in [&mut theme.ui_font, &mut theme.guide_font] {
+            let file_name = font
+                .path
+                .file_name()
+                .ok_or_else(|| anyhow!("invalid font path: {}", font.path.display()))?;
+            let relative = Path::new("fonts").join(file_name);
+            if !tmp.join(&relative).exists() {
+                fs::copy(&font.path, tmp.join(&relative))?;
+            }
+            font.path = relative;
+        }
+
+        let wallpaper = styles
+            .wallpaper
+            .as_deref()
+            .map(|wallpaper| ALLIUM_SD_ROOT.join(wallpaper))
+            .filter(|wallpaper| wallpaper.is_file());
+        theme.wallpaper = match wallpaper.as_deref() {
+            Some(wallpaper) => {
+                let relative = Path::new("wallpaper").with_extension(
+                    wallpaper
+                        .extension()
+                        .unwrap_or_else(|| std::ffi::OsStr::new("png")),
+                );
+                fs::copy(wallpaper, tmp.join(&relative))?;
+                Some(relative)
+            }
+            None => None,
+        };
+
+        theme.button_icons = match styles.button_icons.as_deref() {
+            Some(icons) => {
+                fs::create_dir_all(tmp.join("buttons"))?;
+                for key in BUTTON_ICON_KEYS {
+                    let icon = icons.join(format!("{}.png", key));
+                    if icon.is_file() {
+                        fs::copy(&icon, tmp.join("buttons").join(format!("{}.png", key)))?;
+                    }
+                }
+                Some(PathBuf::from("buttons"))
+            }
+            None => None,
+        };
+
+        serde_json::to_writer_pretty(File::create(tmp.join(THEME_FILE))?, &theme)?;
+        render_preview(styles, wallpaper.as_deref()).save(tmp.join(PREVIEW_FILE))?;
+
+        let path = self.dir.join(name);
+        fs::rename(&tmp, &path)?;
+        Ok(ThemePackage::new(path))
+    }
+
+    fn validate_new_name<'a>(&self, name: &'a str) -> Result<&'a str> {
+        let name = name.trim();
+        if name.is_empty()
+            || name.starts_with('.')
+            || name.contains(['/', '\\', ':'])
+            || name.len() > 64
+        {
+            bail!("invalid theme name: {:?}", name);
+        }
+        if self.dir.join(name).exists() {
+            bail!("theme already exists: {}", name);
+        }
+        Ok(name)
+    }
+}
+
+/// An installed theme package.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ThemePackage {
+    pub name: String,
+    pub path: PathBuf,
+}
+
+impl ThemePackage {
+    fn new(path: PathBuf) -> Self {
+        Self {
+            name: path
+                .file_name()
+                .unwrap_or_default()
+                .to_string_lossy()
+                .to_string(),
+            path,
+        }
+    }
+
+    /// Path to the preview image, if the theme has one.
+    pub fn preview(&self) -> Option<PathBuf> {
+        Some(self.path.join(PREVIEW_FILE)).filter(|path| path.is_file())
+    }
+
+    /// Loads the theme's stylesheet, with paths resolved and the UI and guide fonts loaded. Fails if
+    /// any file the theme refers to is missing, so that a broken theme is never partially applied.
+    pub fn stylesheet(&self) -> Result<Stylesheet> {
+        let mut styles: Stylesheet =
+            serde_json::from_reader(File::open(self.path.join(THEME_FILE))?)?;
+
+        for font in [&mut styles.ui_font, &mut styles.guide_font] {
+            font.path = self.path.join(&font.path);
+            font.load()?;
+        }
+        if let Some(wallpaper) = styles.wallpaper.as_mut() {
+            *wallpaper = self.path.join(&wallpaper);
+            if !wallpaper.is_file() {
+                bail!("missing wallpaper: {}", wallpaper.display());
+            }
+        }
+        if let Some(icons) = styles.button_icons.as_mut() {
+            *icons = self.path.join(&icons);
+            if !icons.is_dir() {
+                bail!("missing button icons: {}", icons.display());
+            }
+        }
+
+        Ok(styles)
+    }
+}
+
+/// Renders a simplified launcher screen in the stylesheet's colours: a tab bar, a list with the
+/// first entry highlighted, and the face buttons.
+fn render_preview(styles: &Stylesheet, wallpaper: Option<&Path>) -> RgbaImage {
+    let mut image = wallpaper
+        .and_then(|wallpaper| image::open(wallpaper).ok())
+        .map(|wallpaper| {
+            wallpaper
+                .resize_to_fill(PREVIEW_WIDTH, PREVIEW_HEIGHT, FilterType::Triangle)
+                .into_rgba8()
+        })
+        .unwrap_or_else(|| {
+            RgbaImage::from_pixel(PREVIEW_WIDTH, PREVIEW_HEIGHT, rgba(styles.background_color))
+        });
+
+    let tabs = [
+        styles.tab_color,
+        styles.tab_selected_color,
+        styles.tab_color,
+    ];
+    for (i, color) in tabs.into_iter().enumerate() {
+        fill_rounded_rect(&mut image, 8 + i as u32 * 56, 8, 48, 10, 5, color);
+    }
+
+    fill_rounded_rect(&mut image, 8, 32, 180, 24, 12, styles.highlight_color);
+    fill_rounded_rect(&mut image, 16, 39, 100, 10, 5, styles.foreground_color);
+    for i in 0..4 {
+        let color = if i == 3 {
+            styles.disabled_color
+        } else {
+            styles.foreground_color
+        };
+        fill_rounded_rect(
+            &mut image,
+            16,
+            71 + i * 24,
+            80 + (i * 37) % 60,
+            10,
+            5,
+            color,
+        );
+    }
+
+    let buttons = [
+        styles.button_y_color,
+        styles.button_x_color,
+        styles.button_b_color,
+        styles.button_a_color,
+    ];
+    for (i, color) in buttons.into_iter().enumerate() {
+        fill_rounded_rect(
+            &mut image,
+            PREVIEW_WIDTH - 32 - i as u32 * 28,
+            PREVIEW_HEIGHT - 32,
+            24,
+            24,
+            12,
+            color,
+        );
+    }
+
+    image
+}
+
+fn fill_rounded_rect(
+    image: &mut RgbaImage,
+    x: u32,
+    y: u32,
+    w: u32,
+    h: u32,
+    radius: u32,
+    color: Color,
+) {
+    let radius = radius as f32;
+    for py in y..(y + h).min(image.height()) {
+        for px in x..(x + w).min(image.width()) {
+            // Distance from the nearest corner centre, if the pixel is in a corner
+            let cx = (px as f32 + 0.5).clamp(x as f32 + radius, (x + w) as f32 - radius);
+            let cy = (py as f32 + 0.5).clamp(y as f32 + radius, (y + h) as f32 - radius);
+            let dx = px as f32 + 0.5 - cx;
+            let dy = py as f32 + 0.5 - cy;
+            if dx * dx + dy * dy <= radius * radius {
+                image.put_pixel(px, py, rgba(color));
+            }
+        }
+    }
+}
+
+fn rgba(color: Color) -> Rgba<u8> {
+    Rgba([color.r(), color.g(), color.b(), 255])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::stylesheet::StylesheetFont;
+
+    const FONTS_DIR: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/../../static/.allium/fonts");
+
+    #[test]
+    fn test_save_and_install_theme() -> Result<()> {
+        let dir = std::env::temp_dir().join("allium-test-themes");
+        if dir.exists() {
+            fs::remove_dir_all(&dir)?;
+        }
+        fs::create_dir_all(&dir)?;
+        let themes = Themes::new(&dir);
+
+        let mut styles = Stylesheet::default();
+        styles.ui_font = StylesheetFont::new(Path::new(FONTS_DIR).join("Nunito.ttf"), 36);
+        styles.guide_font = StylesheetFont::new(Path::new(FONTS_DIR).join("Nunito.ttf"), 28);
+        styles.highlight_color = Color::new(255, 0, 128);
+
+        let theme = themes.save("Pink", &styles)?;
+        assert!(theme.path.join("fonts/Nunito.ttf").is_file());
+        assert!(theme.preview().is_some());
+        assert!(themes.save("Pink", &styles).is_err());
+        assert!(themes.save("../Pink", &styles).is_err());
+
+        let applied = theme.stylesheet()?;
+        assert_eq!(applied.highlight_color, Color::new(255, 0, 128));
+        assert_eq!(applied.ui_font.path, theme.path.join("fonts/Nunito.ttf"));
+        assert!(applied.ui_font.font.is_some());
+
+        // Zip the theme as a directory, the way themes are usually distributed
+        let archive = dir.join("Pink Copy.zip");
+        {
+            let mut zip = zip::ZipWriter::new(File::create(&archive)?);
+            for file in ["theme.json", "fonts/Nunito.ttf"] {
+                zip.start_file(
+                    format!("Pink/{}", file),
+                    zip::write::FileOptions::default()
+                        .compression_method(zip::CompressionMethod::Stored),
+                )?;
+                std::io::copy(&mut File::open(theme.path.join(file))?, &mut zip)?;
+            }
+            zip.finish()?;
+        }
+
+        let names: Vec<_> = themes.list()?.into_iter().map(|t| t.name).collect();
+        assert_eq!(names, vec!["Pink", "Pink Copy"]);
+        assert!(!archive.exists());
+        assert!(dir.join("Pink Copy").join(THEME_FILE).is_file());
+
+        Ok(())
+    }
+}
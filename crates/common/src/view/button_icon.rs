@@ -1,16 +1,20 @@
 use std::collections::VecDeque;
+use std::path::Path;
 
 use anyhow::Result;
 use async_trait::async_trait;
+use embedded_graphics::image::ImageRaw;
 use embedded_graphics::prelude::{Dimensions, Size};
 use embedded_graphics::primitives::{
     Circle, CornerRadii, CornerRadiiBuilder, Primitive, PrimitiveStyle, Rectangle, RoundedRectangle,
 };
 use embedded_graphics::text::{Text, TextStyleBuilder};
 use embedded_graphics::Drawable;
+use log::warn;
 use serde::{Deserialize, Serialize};
 use tokio::sync::mpsc::Sender;
 
+use crate::display::color::Color;
 use crate::display::font::FontTextStyleBuilder;
 use crate::geom::{Alignment, Point, Rect};
 use crate::platform::{DefaultPlatform, Key, KeyEvent, Platform};
@@ -83,6 +87,16 @@ impl View for ButtonIcon {
             }
         };
 
+        if let Some(icon) = styles.button_icon(self.button) {
+            match draw_icon(display, &icon, point, diameter) {
+                Ok(()) => {
+                    self.dirty = false;
+                    return Ok(true);
+                }
+                Err(e) => warn!("failed to draw button icon {}: {}", icon.display(), e),
+            }
+        }
+
         let text_style = FontTextStyleBuilder::new(styles.ui_font.font())
             .font_fallback(styles.cjk_font.font())
             .font_size(diameter * 3 / 4)
@@ -329,3 +343,18 @@ impl View for ButtonIcon {
         self.dirty = true;
     }
 }
+
+/// Draws a button icon from a theme, scaled to the button's diameter.
+fn draw_icon(
+    display: &mut <DefaultPlatform as Platform>::Display,
+    path: &Path,
+    point: embedded_graphics::prelude::Point,
+    diameter: u32,
+) -> Result<()> {
+    let image = ::image::open(path)?
+        .resize_exact(diameter, diameter, ::image::imageops::FilterType::Lanczos3)
+        .into_rgba8();
+    let image: ImageRaw<'_, Color> = ImageRaw::new(&image, diameter);
+    embedded_graphics::image::Image::new(&image, point).draw(display)?;
+    Ok(())
+}
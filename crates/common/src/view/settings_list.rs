@@ -395,3 +395,64 @@ impl View for SettingsList {
         self.dirty = true;
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use embedded_graphics::prelude::DrawTarget;
+
+    use crate::platform::headless::{test_stylesheet, HeadlessPlatform};
+    use crate::snapshot_path;
+    use crate::view::{Percentage, Toggle};
+
+    fn settings_list() -> SettingsList {
+        SettingsList::new(
+            Rect::new(0, 0, 640, 480),
+            vec![
+                "Wi-Fi".to_string(),
+                "Brightness".to_string(),
+                "Language".to_string(),
+            ],
+            vec![
+                Box::new(Toggle::new(Point::zero(), true, Alignment::Right)),
+                Box::new(Percentage::new(Point::zero(), 70, 0, 100, Alignment::Right)),
+                Box::new(Label::new(
+                    Point::zero(),
+                    "English".to_string(),
+                    Alignment::Right,
+                    None,
+                )),
+            ],
+            60,
+        )
+    }
+
+    #[tokio::test]
+    async fn test_settings_list_snapshot() {
+        let styles = test_stylesheet();
+        let mut platform = HeadlessPlatform::new().unwrap();
+        let mut display = platform.display().unwrap();
+        display.clear(styles.background_color).unwrap();
+        display.save().unwrap();
+
+        let mut list = settings_list();
+        list.draw(&mut display, &styles).unwrap();
+        display.assert_snapshot(&snapshot_path!("settings_list"));
+
+        platform.press(Key::Down);
+        platform.press(Key::A);
+        platform.press(Key::Right);
+        let (tx, _rx) = tokio::sync::mpsc::channel(1);
+        while platform.has_key_events() {
+            let event = platform.poll().await;
+            list.handle_key_event(event, tx.clone(), &mut VecDeque::new())
+                .await
+                .unwrap();
+        }
+        while list.should_draw() {
+            list.draw(&mut display, &styles).unwrap();
+        }
+        display.assert_snapshot(&snapshot_path!("settings_list_editing"));
+    }
+}
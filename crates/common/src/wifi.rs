@@ -5,6 +5,7 @@ use tokio::process::Command;
 
 use anyhow::Result;
 use log::{debug, warn};
+use rand::distributions::{Alphanumeric, DistString};
 use serde::{Deserialize, Serialize};
 
 use crate::constants::ALLIUM_WIFI_SETTINGS;
@@ -18,6 +19,12 @@ pub struct WiFiSettings {
     pub web_file_browser: bool,
     pub telnet: bool,
     pub ftp: bool,
+    /// Whether alliumd serves the HTTP API.
+    #[serde(default)]
+    pub api: bool,
+    /// Token that HTTP API requests must send as `Authorization: Bearer <token>`.
+    #[serde(default)]
+    pub api_token: String,
 }
 
 impl WiFiSettings {
@@ -30,6 +37,8 @@ impl WiFiSettings {
             web_file_browser: false,
             telnet: false,
             ftp: false,
+            api: false,
+            api_token: String::new(),
         }
     }
 
@@ -200,6 +209,20 @@ network={{
         }
         Ok(())
     }
+
+    /// alliumd starts or stops the HTTP API server when it notices the saved setting changed. A
+    /// new token is generated each time the API is enabled, so that old ones stop working.
+    pub fn toggle_api(&mut self, enabled: bool) -> Result<()> {
+        self.api = enabled;
+        if enabled {
+            self.reset_api_token();
+        }
+        Ok(())
+    }
+
+    pub fn reset_api_token(&mut self) {
+        self.api_token = Alphanumeric.sample_string(&mut rand::thread_rng(), 12);
+    }
 }
 
 impl Default for WiFiSettings {
@@ -1,9 +1,12 @@
 #![warn(clippy::all, rust_2018_idioms)]
 
+use std::time::Duration;
+
 use anyhow::Result;
 use clap::Parser;
 use common::{
     display::{color::Color, font::FontTextStyleBuilder, Display},
+    geom::Rect,
     platform::{DefaultPlatform, Platform},
     stylesheet::Stylesheet,
 };
@@ -23,24 +26,36 @@ struct Cli {
     /// Whether to draw a box behind the text
     #[arg(short, long)]
     bg: bool,
+
+    /// Whether to draw the text at the top of the screen instead of the middle
+    #[arg(short, long)]
+    top: bool,
+
+    /// Restore the screen after this many seconds, instead of leaving the text on it
+    #[arg(short, long)]
+    restore_after: Option<u64>,
 }
 
 #[tokio::main]
 async fn main() -> Result<()> {
     let cli = Cli::parse();
 
-    if let Err(e) = say(&cli.text, cli.bg) {
+    if let Err(e) = say(&cli) {
         eprintln!("Error: {}", e);
     }
 
     Ok(())
 }
 
-fn say(text: &str, bg: bool) -> Result<()> {
+fn say(cli: &Cli) -> Result<()> {
     let mut platform = DefaultPlatform::new()?;
     let mut display = platform.display()?;
     let styles = Stylesheet::load()?;
 
+    if cli.restore_after.is_some() {
+        display.save()?;
+    }
+
     let text_style = FontTextStyleBuilder::<Color>::new(styles.ui_font.font())
         .text_color(styles.foreground_color)
         .font_fallback(styles.cjk_font.font())
@@ -49,16 +64,21 @@ fn say(text: &str, bg: bool) -> Result<()> {
 
     let w = display.size().width;
     let h = display.size().height;
-    let height = text.lines().count() as u32 * styles.ui_font.size;
+    let height = cli.text.lines().count() as u32 * styles.ui_font.size;
+    let y = if cli.top {
+        styles.ui_font.size as i32 + 16
+    } else {
+        (h - height) as i32 / 2
+    };
 
     let text = Text::with_alignment(
-        text,
-        Point::new(w as i32 / 2, (h - height) as i32 / 2),
+        &cli.text,
+        Point::new(w as i32 / 2, y),
         text_style,
         Alignment::Center,
     );
 
-    if bg {
+    if cli.bg {
         let mut rect = text.bounding_box();
         rect.top_left.x -= 12;
         rect.top_left.y -= 8;
@@ -75,5 +95,11 @@ fn say(text: &str, bg: bool) -> Result<()> {
     text.draw(&mut display)?;
     display.flush()?;
 
+    if let Some(seconds) = cli.restore_after {
+        std::thread::sleep(Duration::from_secs(seconds));
+        display.load(Rect::new(0, 0, w, h))?;
+        display.flush()?;
+    }
+
     Ok(())
 }
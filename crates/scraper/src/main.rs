@@ -0,0 +1,89 @@
+#![deny(clippy::all)]
+#![warn(rust_2018_idioms)]
+
+mod metadata;
+mod scraper;
+
+use std::path::PathBuf;
+
+use anyhow::{bail, Result};
+use clap::Parser;
+use log::error;
+use simple_logger::SimpleLogger;
+
+use crate::metadata::MetadataDb;
+use crate::scraper::Scraper;
+
+/// Scrapes metadata and box art from local database dumps into gamelist.xml, without a network
+/// connection.
+#[derive(Parser, Debug)]
+#[command(author, version, about, long_about = None)]
+struct Cli {
+    /// ROM directories to scrape, e.g. /mnt/SDCARD/Roms/GBA
+    #[arg(required = true)]
+    roms: Vec<PathBuf>,
+
+    /// Database dump to match against: a clrmamepro DAT (.dat), e.g. from libretro-database,
+    /// or a gamelist.xml export (.xml), e.g. from ScreenScraper. Can be repeated.
+    #[arg(short, long, required = true)]
+    database: Vec<PathBuf>,
+
+    /// Directory of images named after the game or ROM, e.g. libretro-thumbnails'
+    /// Named_Boxarts. Can be repeated.
+    #[arg(short, long)]
+    art: Vec<PathBuf>,
+
+    /// Overwrite existing metadata and images
+    #[arg(short, long)]
+    force: bool,
+
+    /// List ROMs that could not be matched
+    #[arg(short, long)]
+    verbose: bool,
+}
+
+fn main() -> Result<()> {
+    SimpleLogger::new()
+        .with_level(log::LevelFilter::Warn)
+        .init()
+        .unwrap();
+
+    let cli = Cli::parse();
+
+    let mut db = MetadataDb::default();
+    for path in &cli.database {
+        if let Err(e) = db.load(path) {
+            error!("failed to load {}: {}", path.display(), e);
+        }
+    }
+    if db.is_empty() {
+        bail!("no games found in the given databases");
+    }
+    println!("Loaded {} games", db.len());
+
+    let scraper = Scraper::new(&db, &cli.art, cli.force);
+    for dir in &cli.roms {
+        let report = match scraper.scrape(dir) {
+            Ok(report) => report,
+            Err(e) => {
+                error!("failed to scrape {}: {}", dir.display(), e);
+                continue;
+            }
+        };
+
+        println!(
+            "{}: scraped {}/{} games, copied {} images",
+            dir.display(),
+            report.scraped,
+            report.scraped + report.unmatched.len(),
+            report.images,
+        );
+        if cli.verbose {
+            for rom in &report.unmatched {
+                println!("  no match: {}", rom.display());
+            }
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,232 @@
+use std::collections::HashMap;
+use std::ffi::OsStr;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, Result};
+use chrono::NaiveDateTime;
+use common::dat::{self, ClrMameProGame};
+use common::gamelist::GameList;
+use log::debug;
+
+/// Metadata of a single game from a database dump.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Metadata {
+    pub name: Option<String>,
+    /// Rating out of 10, like `common::gamelist::Game::rating`.
+    pub rating: Option<u8>,
+    pub release_date: Option<NaiveDateTime>,
+    pub developer: Option<String>,
+    pub publisher: Option<String>,
+    pub genres: Vec<String>,
+    /// Art provided by the dump itself.
+    pub image: Option<PathBuf>,
+    pub roms: Vec<Rom>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Rom {
+    pub name: Option<String>,
+    pub crc: Option<u32>,
+}
+
+impl From<ClrMameProGame> for Metadata {
+    fn from(game: ClrMameProGame) -> Self {
+        Self {
+            name: game.name,
+            rating: game.rating,
+            release_date: game.release_date,
+            developer: game.developer,
+            publisher: game.publisher,
+            genres: game.genres,
+            image: None,
+            roms: game
+                .roms
+                .into_iter()
+                .map(|rom| Rom {
+                    name: rom.name,
+                    crc: rom.crc,
+                })
+                .collect(),
+        }
+    }
+}
+
+impl Metadata {
+    /// Fills in fields that are missing from `self` with those from `other`.
+    fn merge(&mut self, other: Metadata) {
+        self.name = self.name.take().or(other.name);
+        self.rating = self.rating.or(other.rating);
+        self.release_date = self.release_date.or(other.release_date);
+        self.developer = self.developer.take().or(other.developer);
+        self.publisher = self.publisher.take().or(other.publisher);
+        if self.genres.is_empty() {
+            self.genres = other.genres;
+        }
+        self.image = self.image.take().or(other.image);
+        for rom in other.roms {
+            if !self.roms.contains(&rom) {
+                self.roms.push(rom);
+            }
+        }
+    }
+}
+
+/// Metadata loaded from one or more database dumps, indexed by CRC32 and file name.
+///
+/// libretro-database splits metadata across several DATs (e.g. `metadat/developer`,
+/// `metadat/genre`), so games with the same CRC32 are merged together.
+#[derive(Debug, Default)]
+pub struct MetadataDb {
+    games: Vec<Metadata>,
+    by_crc: HashMap<u32, usize>,
+    by_name: HashMap<String, usize>,
+}
+
+impl MetadataDb {
+    /// Loads a database dump. Supported formats are clrmamepro DATs (`.dat`) and
+    /// EmulationStation gamelist.xml exports, as produced by ScreenScraper and Skraper (`.xml`).
+    pub fn load(&mut self, path: &Path) -> Result<()> {
+        let s = fs::read_to_string(path)?;
+        let games = match path.extension().and_then(OsStr::to_str) {
+            Some("dat") => dat::parse_clrmamepro(&s)?
+                .into_iter()
+                .map(Metadata::from)
+                .collect(),
+            Some("xml") => from_gamelist(path, GameList::from_xml(&s)?),
+            _ => bail!("unsupported database format: {}", path.display()),
+        };
+        debug!("loaded {} games from {}", games.len(), path.display());
+
+        for game in games {
+            self.insert(game);
+        }
+
+        Ok(())
+    }
+
+    fn insert(&mut self, game: Metadata) {
+        let existing = game.roms.iter().find_map(|rom| {
+            rom.crc
+                .and_then(|crc| self.by_crc.get(&crc))
+                .or_else(|| rom.name.as_deref().and_then(|n| self.by_name.get(&key(n))))
+                .copied()
+        });
+
+        let i = match existing {
+            Some(i) => {
+                self.games[i].merge(game);
+                i
+            }
+            None => {
+                self.games.push(game);
+                self.games.len() - 1
+            }
+        };
+
+        let game = &self.games[i];
+        for rom in &game.roms {
+            if let Some(crc) = rom.crc {
+                self.by_crc.entry(crc).or_insert(i);
+            }
+            if let Some(name) = &rom.name {
+                self.by_name.entry(key(name)).or_insert(i);
+            }
+        }
+        if let Some(name) = &game.name {
+            self.by_name.entry(name_key(name)).or_insert(i);
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.games.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.games.is_empty()
+    }
+
+    /// Finds a game by CRC32, falling back to its file name.
+    pub fn find(&self, crc: Option<u32>, file_name: &str) -> Option<&Metadata> {
+        crc.and_then(|crc| self.by_crc.get(&crc))
+            .or_else(|| self.by_name.get(&key(file_name)))
+            .map(|&i| &self.games[i])
+    }
+}
+
+/// Converts a gamelist.xml export into metadata. Paths are relative to the gamelist.
+fn from_gamelist(path: &Path, gamelist: GameList) -> Vec<Metadata> {
+    let dir = path.parent().unwrap_or(Path::new(""));
+    gamelist
+        .games
+        .into_iter()
+        .map(|game| Metadata {
+            name: Some(game.name),
+            rating: game.rating,
+            release_date: game.release_date,
+            developer: game.developer,
+            publisher: game.publisher,
+            genres: game.genres,
+            image: game.image.or(game.thumbnail).map(|image| dir.join(image)),
+            roms: vec![Rom {
+                name: game
+                    .path
+                    .file_name()
+                    .and_then(OsStr::to_str)
+                    .map(str::to_string),
+                crc: None,
+            }],
+        })
+        .collect()
+}
+
+/// Key used to match file names: the lowercased file name without extension.
+fn key(file_name: &str) -> String {
+    name_key(
+        Path::new(file_name)
+            .file_stem()
+            .and_then(OsStr::to_str)
+            .unwrap_or(file_name),
+    )
+}
+
+/// Key used to match game names, which may contain dots (e.g. "Mr. Driller").
+fn name_key(name: &str) -> String {
+    name.trim().to_lowercase()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_merge_by_crc() {
+        let mut db = MetadataDb::default();
+        db.insert(Metadata {
+            name: Some("Advance Wars (USA)".to_string()),
+            roms: vec![Rom {
+                name: Some("Advance Wars (USA).gba".to_string()),
+                crc: Some(0x5AD0E571),
+            }],
+            ..Default::default()
+        });
+        db.insert(Metadata {
+            developer: Some("Intelligent Systems".to_string()),
+            roms: vec![Rom {
+                name: None,
+                crc: Some(0x5AD0E571),
+            }],
+            ..Default::default()
+        });
+        assert_eq!(db.len(), 1);
+
+        let game = db.find(Some(0x5AD0E571), "aw.gba").unwrap();
+        assert_eq!(game.name.as_deref(), Some("Advance Wars (USA)"));
+        assert_eq!(game.developer.as_deref(), Some("Intelligent Systems"));
+
+        let game = db.find(None, "advance wars (usa).zip").unwrap();
+        assert_eq!(game.name.as_deref(), Some("Advance Wars (USA)"));
+
+        assert_eq!(db.find(Some(0), "Golden Sun.gba"), None);
+    }
+}
@@ -0,0 +1,356 @@
+use std::ffi::OsStr;
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+
+use anyhow::Result;
+use common::gamelist::{Game, GameList};
+use common::hash;
+use log::{debug, warn};
+
+use crate::metadata::{Metadata, MetadataDb};
+
+/// Files that are never ROMs, matching what the launcher excludes.
+const EXCLUDE_FILE_NAMES: [&str; 5] = [
+    "Imgs",
+    "Guides",
+    "gamelist.xml",
+    "miyoogamelist.xml",
+    "neogeo.zip",
+];
+const EXCLUDE_EXTENSIONS: [&str; 8] = ["db", "sbi", "nfo", "txt", "xml", "png", "jpg", "jpeg"];
+const IMAGE_EXTENSIONS: [&str; 2] = ["png", "jpg"];
+
+#[derive(Debug, Default)]
+pub struct Report {
+    /// Number of ROMs that were matched against the database.
+    pub scraped: usize,
+    /// Number of images copied into `Imgs/`.
+    pub images: usize,
+    /// ROMs that could not be matched, relative to the scraped directory.
+    pub unmatched: Vec<PathBuf>,
+}
+
+#[derive(Debug)]
+pub struct Scraper<'a> {
+    db: &'a MetadataDb,
+    art: &'a [PathBuf],
+    force: bool,
+}
+
+impl<'a> Scraper<'a> {
+    /// Creates a scraper. `art` are directories containing images named after the game or ROM,
+    /// such as libretro-thumbnails' `Named_Boxarts`. If `force` is set, existing metadata and
+    /// images are overwritten.
+    pub fn new(db: &'a MetadataDb, art: &'a [PathBuf], force: bool) -> Self {
+        Self { db, art, force }
+    }
+
+    /// Scrapes every ROM in `dir` and its subdirectories, and writes the results to
+    /// `dir/gamelist.xml`. Entries already in the gamelist are kept.
+    pub fn scrape(&self, dir: &Path) -> Result<Report> {
+        let gamelist_path = dir.join("gamelist.xml");
+        let mut gamelist = if gamelist_path.exists() {
+            GameList::from_xml(&fs::read_to_string(&gamelist_path)?)?
+        } else {
+            GameList {
+                games: Vec::new(),
+                folders: Vec::new(),
+            }
+        };
+
+        let mut report = Report::default();
+        for rom in roms(dir, Path::new(""))? {
+            let file_name = rom.file_name().and_then(OsStr::to_str).unwrap_or_default();
+            let crc = match hash::hash_rom(&dir.join(&rom)) {
+                Ok(hash) => hash.map(|hash| hash.crc32),
+                Err(e) => {
+                    warn!("failed to compute CRC32 of {}: {}", rom.display(), e);
+                    None
+                }
+            };
+
+            let Some(metadata) = self.db.find(crc, file_name) else {
+                debug!("no match for {} ({:08X?})", rom.display(), crc);
+                report.unmatched.push(rom);
+                continue;
+            };
+
+            let image = match self.copy_art(dir, &rom, metadata) {
+                Ok(Some((image, copied))) => {
+                    if copied {
+                        report.images += 1;
+                    }
+                    Some(image)
+                }
+                Ok(None) => None,
+                Err(e) => {
+                    warn!("failed to copy art for {}: {}", rom.display(), e);
+                    None
+                }
+            };
+
+            self.update(&mut gamelist, &rom, metadata, image);
+            report.scraped += 1;
+        }
+
+        if report.scraped > 0 {
+            fs::write(&gamelist_path, gamelist.to_xml()?)?;
+        }
+
+        Ok(report)
+    }
+
+    /// Updates or inserts the gamelist entry for `rom`.
+    fn update(
+        &self,
+        gamelist: &mut GameList,
+        rom: &Path,
+        metadata: &Metadata,
+        image: Option<PathBuf>,
+    ) {
+        let path = Path::new(".").join(rom);
+        let game = match gamelist
+            .games
+            .iter()
+            .position(|game| normalize(&game.path) == rom)
+        {
+            Some(i) => &mut gamelist.games[i],
+            None => {
+                gamelist.games.push(Game {
+                    path,
+                    name: String::new(),
+                    image: None,
+                    thumbnail: None,
+                    rating: None,
+                    release_date: None,
+                    developer: None,
+                    publisher: None,
+                    genres: Vec::new(),
+                });
+                gamelist.games.last_mut().unwrap()
+            }
+        };
+
+        if self.force || game.name.is_empty() {
+            game.name = metadata.name.clone().unwrap_or_else(|| {
+                rom.file_stem()
+                    .and_then(OsStr::to_str)
+                    .unwrap_or_default()
+                    .to_string()
+            });
+        }
+        if self.force || game.image.is_none() {
+            game.image = image.or(game.image.take());
+        }
+        if self.force || game.rating.is_none() {
+            game.rating = metadata.rating.or(game.rating);
+        }
+        if self.force || game.release_date.is_none() {
+            game.release_date = metadata.release_date.or(game.release_date);
+        }
+        if self.force || game.developer.is_none() {
+            game.developer = metadata.developer.clone().or(game.developer.take());
+        }
+        if self.force || game.publisher.is_none() {
+            game.publisher = metadata.publisher.clone().or(game.publisher.take());
+        }
+        if (self.force || game.genres.is_empty()) && !metadata.genres.is_empty() {
+            game.genres = metadata.genres.clone();
+        }
+    }
+
+    /// Copies art for `rom` into the `Imgs` directory next to it. Returns the image path relative
+    /// to `dir`, and whether it was copied.
+    fn copy_art(
+        &self,
+        dir: &Path,
+        rom: &Path,
+        metadata: &Metadata,
+    ) -> Result<Option<(PathBuf, bool)>> {
+        let stem = rom.file_stem().and_then(OsStr::to_str).unwrap_or_default();
+        let imgs = rom.parent().unwrap_or(Path::new("")).join("Imgs");
+
+        let existing = IMAGE_EXTENSIONS
+            .iter()
+            .map(|ext| imgs.join(format!("{}.{}", stem, ext)))
+            .find(|image| dir.join(image).exists());
+        if let (Some(image), false) = (&existing, self.force) {
+            return Ok(Some((Path::new(".").join(image), false)));
+        }
+
+        let mut names = vec![stem.to_string()];
+        if let Some(name) = &metadata.name {
+            names.insert(0, thumbnail_name(name));
+        }
+        let source = metadata
+            .image
+            .iter()
+            .cloned()
+            .chain(self.art.iter().flat_map(|art| {
+                names.iter().flat_map(move |name| {
+                    IMAGE_EXTENSIONS
+                        .iter()
+                        .map(move |ext| art.join(format!("{}.{}", name, ext)))
+                })
+            }))
+            .find(|path| path.exists());
+        let Some(source) = source else {
+            return Ok(existing.map(|image| (Path::new(".").join(image), false)));
+        };
+
+        let extension = source.extension().and_then(OsStr::to_str).unwrap_or("png");
+        let image = imgs.join(format!("{}.{}", stem, extension));
+        fs::create_dir_all(dir.join(&imgs))?;
+        fs::copy(&source, dir.join(&image))?;
+
+        Ok(Some((Path::new(".").join(image), true)))
+    }
+}
+
+/// Lists ROMs in `dir.join(relative)`, recursing into subdirectories. Paths are relative to `dir`.
+fn roms(dir: &Path, relative: &Path) -> Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    for entry in fs::read_dir(dir.join(relative))? {
+        let path = entry?.path();
+        let Some(file_name) = path.file_name().and_then(OsStr::to_str) else {
+            continue;
+        };
+        if file_name.starts_with('.')
+            || file_name.starts_with('_')
+            || EXCLUDE_FILE_NAMES.contains(&file_name)
+        {
+            continue;
+        }
+
+        let extension = path
+            .extension()
+            .and_then(OsStr::to_str)
+            .unwrap_or_default()
+            .to_lowercase();
+        if path.is_dir() {
+            // Directories with extensions are games or apps (e.g. .pak), not folders
+            if extension.is_empty() {
+                files.extend(roms(dir, &relative.join(file_name))?);
+            }
+        } else if !EXCLUDE_EXTENSIONS.contains(&extension.as_str()) {
+            files.push(relative.join(file_name));
+        }
+    }
+    files.sort_unstable();
+    Ok(files)
+}
+
+/// Strips a leading `./` so that paths from an existing gamelist can be compared.
+fn normalize(path: &Path) -> PathBuf {
+    path.components()
+        .filter(|c| !matches!(c, Component::CurDir))
+        .collect()
+}
+
+/// libretro-thumbnails replaces these characters in file names with underscores.
+fn thumbnail_name(name: &str) -> String {
+    name.chars()
+        .map(|c| match c {
+            '&' | '*' | '/' | ':' | '`' | '<' | '>' | '?' | '\\' | '|' | '"' => '_',
+            c => c,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDate;
+
+    use super::*;
+
+    #[test]
+    fn test_scrape() -> Result<()> {
+        let dir = std::env::temp_dir().join("allium-test-scrape");
+        let _ = fs::remove_dir_all(&dir);
+        let roms = dir.join("GBA");
+        let art = dir.join("Named_Boxarts");
+        fs::create_dir_all(roms.join("Hacks"))?;
+        fs::create_dir_all(&art)?;
+
+        fs::write(roms.join("aw.gba"), b"advance wars")?;
+        fs::write(
+            roms.join("Hacks/Golden Sun (USA, Europe).gba"),
+            b"golden sun",
+        )?;
+        fs::write(roms.join("Unknown.gba"), b"unknown")?;
+        fs::write(art.join("Advance Wars _USA_.png"), b"png")?;
+
+        let crc = hash::hash_rom(&roms.join("aw.gba"))?.unwrap().crc32;
+
+        let mut db = MetadataDb::default();
+        let dat = format!(
+            r#"
+game (
+	name "Advance Wars _USA_"
+	developer "Intelligent Systems"
+	genre "Strategy, Tactics"
+	releaseyear "2001"
+	edge_rating "8"
+	rom ( name "Advance Wars (USA).gba" crc {:08X} )
+)
+game (
+	name "Golden Sun (USA, Europe)"
+	rom ( name "Golden Sun (USA, Europe).gba" crc 3D0C49B1 )
+)
+"#,
+            crc
+        );
+        fs::write(dir.join("test.dat"), dat)?;
+        db.load(&dir.join("test.dat"))?;
+
+        let art_dirs = vec![art];
+        let report = Scraper::new(&db, &art_dirs, false).scrape(&roms)?;
+        assert_eq!(report.scraped, 2);
+        assert_eq!(report.images, 1);
+        assert_eq!(report.unmatched, vec![PathBuf::from("Unknown.gba")]);
+        assert!(roms.join("Imgs/aw.png").exists());
+
+        let gamelist = GameList::from_xml(&fs::read_to_string(roms.join("gamelist.xml"))?)?;
+        assert_eq!(gamelist.games.len(), 2);
+        assert_eq!(
+            gamelist.games[0].path,
+            PathBuf::from("./Hacks/Golden Sun (USA, Europe).gba")
+        );
+        assert_eq!(gamelist.games[0].name, "Golden Sun (USA, Europe)");
+        assert_eq!(gamelist.games[1].path, PathBuf::from("./aw.gba"));
+        assert_eq!(gamelist.games[1].name, "Advance Wars _USA_");
+        assert_eq!(
+            gamelist.games[1].image,
+            Some(PathBuf::from("./Imgs/aw.png"))
+        );
+        assert_eq!(gamelist.games[1].rating, Some(8));
+        assert_eq!(
+            gamelist.games[1].release_date,
+            NaiveDate::from_ymd_opt(2001, 1, 1)
+                .unwrap()
+                .and_hms_opt(0, 0, 0)
+        );
+        assert_eq!(
+            gamelist.games[1].developer.as_deref(),
+            Some("Intelligent Systems")
+        );
+        assert_eq!(gamelist.games[1].genres, vec!["Strategy", "Tactics"]);
+
+        // Existing entries are kept unless forced
+        let mut db = MetadataDb::default();
+        db.load(&dir.join("test.dat"))?;
+        let report = Scraper::new(&db, &[], false).scrape(&roms)?;
+        assert_eq!(report.images, 0);
+        let rescraped = GameList::from_xml(&fs::read_to_string(roms.join("gamelist.xml"))?)?;
+        assert_eq!(rescraped, gamelist);
+
+        fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_normalize() {
+        assert_eq!(normalize(Path::new("./a/b.gba")), PathBuf::from("a/b.gba"));
+        assert_eq!(normalize(Path::new("a/b.gba")), PathBuf::from("a/b.gba"));
+    }
+}
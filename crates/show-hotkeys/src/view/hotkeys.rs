@@ -4,21 +4,24 @@ use anyhow::Result;
 use async_trait::async_trait;
 use common::command::Command;
 use common::geom::{Alignment, Point, Rect};
+use common::hotkeys::HotkeySettings;
 use common::locale::Locale;
 use common::platform::{DefaultPlatform, Key, KeyEvent, Platform};
 use common::resources::Resources;
 use common::stylesheet::Stylesheet;
 use common::view::{Label, View};
+use log::warn;
 use tokio::sync::mpsc::Sender;
 
 use crate::view::button_chord::ButtonChordHint;
 
+/// Space left at the top and bottom of the screen.
+const MARGIN: i32 = 40;
+
 pub struct Hotkeys {
     rect: Rect,
-    global_label: Label<String>,
-    global_hotkeys: Vec<ButtonChordHint<String>>,
-    ingame_label: Label<String>,
-    ingame_hotkeys: Vec<ButtonChordHint<String>>,
+    labels: Vec<Label<String>>,
+    hotkeys: Vec<ButtonChordHint<String>>,
     dirty: bool,
 }
 
@@ -27,58 +30,67 @@ impl Hotkeys {
         let locale = res.get::<Locale>();
         let styles = res.get::<Stylesheet>();
 
-        let x = 100;
-        let mut y = 40;
+        let mut layout = Layout::new(rect, styles.ui_font.size as i32 + 8);
+        let mut labels = Vec::new();
+        let mut hotkeys = Vec::new();
 
-        let global_label = Label::new(
-            Point::new(x, y),
-            locale.t("hotkeys-global"),
-            Alignment::Left,
-            None,
-        );
-        y += styles.ui_font.size as i32 + 8;
-
-        let mut global_hotkeys = Vec::with_capacity(5);
-        let global_hotkeys_data = [
-            (Key::Power, locale.t("hotkeys-screenshot")),
-            (Key::Up, locale.t("hotkeys-brightness-up")),
-            (Key::Down, locale.t("hotkeys-brightness-down")),
-            (Key::Right, locale.t("hotkeys-volume-up")),
-            (Key::Left, locale.t("hotkeys-volume-down")),
-        ];
-        for (key, label) in global_hotkeys_data {
-            global_hotkeys.push(ButtonChordHint::new(
-                Point::new(x, y),
-                vec![Key::Menu, key],
-                label,
+        if let Some(point) = layout.next() {
+            labels.push(Label::new(
+                point,
+                locale.t("hotkeys-global"),
                 Alignment::Left,
+                None,
             ));
-            y += styles.ui_font.size as i32 + 8;
         }
 
-        y += 16;
-
-        let ingame_label = Label::new(
-            Point::new(x, y),
-            locale.t("hotkeys-ingame"),
-            Alignment::Left,
-            None,
-        );
-        y += styles.ui_font.size as i32 + 8;
-
-        let mut ingame_hotkeys = Vec::with_capacity(2);
-        let ingame_hotkeys_data = [
+        // The same settings that alliumd handles the hotkeys with
+        let settings = match HotkeySettings::load() {
+            Ok(settings) => settings,
+            Err(e) => {
+                warn!("failed to load hotkeys: {}", e);
+                HotkeySettings::default()
+            }
+        };
+        let ingame_hotkeys = [
             (Key::Start, locale.t("hotkeys-toggle-aspect-ratio")),
             (Key::X, locale.t("hotkeys-toggle-fps")),
         ];
-        for (key, label) in ingame_hotkeys_data {
-            ingame_hotkeys.push(ButtonChordHint::new(
-                Point::new(x, y),
+        for hotkey in settings.enabled() {
+            let Some(point) = layout.next() else {
+                break;
+            };
+            hotkeys.push(ButtonChordHint::new(
+                point,
+                hotkey.chord(),
+                hotkey.action.label(&locale),
+                Alignment::Left,
+            ));
+        }
+
+        layout.skip(16);
+        if let Some(point) = layout.next() {
+            labels.push(Label::new(
+                point,
+                locale.t("hotkeys-ingame"),
+                Alignment::Left,
+                None,
+            ));
+        }
+
+        for (key, label) in ingame_hotkeys {
+            let Some(point) = layout.next() else {
+                break;
+            };
+            hotkeys.push(ButtonChordHint::new(
+                point,
                 vec![Key::Menu, key],
                 label,
                 Alignment::Left,
             ));
-            y += styles.ui_font.size as i32 + 8;
+        }
+
+        if layout.overflowed {
+            warn!("too many hotkeys to show, some were left out");
         }
 
         drop(locale);
@@ -86,15 +98,65 @@ impl Hotkeys {
 
         Self {
             rect,
-            global_label,
-            global_hotkeys,
-            ingame_label,
-            ingame_hotkeys,
+            labels,
+            hotkeys,
             dirty: true,
         }
     }
 }
 
+/// Places rows from top to bottom, continuing in a second column once the first one is full.
+/// Rows that fit in neither column are left out.
+struct Layout {
+    rect: Rect,
+    line_height: i32,
+    column: i32,
+    y: i32,
+    /// Whether a row did not fit.
+    overflowed: bool,
+}
+
+impl Layout {
+    /// Number of columns, the first one starting at `FIRST_COLUMN_X`.
+    const COLUMNS: i32 = 2;
+    const FIRST_COLUMN_X: i32 = 100;
+
+    fn new(rect: Rect, line_height: i32) -> Self {
+        Self {
+            rect,
+            line_height,
+            column: 0,
+            y: rect.y + MARGIN,
+            overflowed: false,
+        }
+    }
+
+    fn next(&mut self) -> Option<Point> {
+        if self.y + self.line_height > self.rect.y + self.rect.h as i32 - MARGIN {
+            self.column += 1;
+            self.y = self.rect.y + MARGIN;
+        }
+        if self.column >= Self::COLUMNS {
+            self.overflowed = true;
+            return None;
+        }
+        let column_width = (self.rect.w as i32 - Self::FIRST_COLUMN_X) / Self::COLUMNS;
+        let point = Point::new(
+            self.rect.x + Self::FIRST_COLUMN_X + self.column * column_width,
+            self.y,
+        );
+        self.y += self.line_height;
+        Some(point)
+    }
+
+    /// Adds space between sections, unless a new column was just started.
+    fn skip(&mut self, height: i32) {
+        if self.y > self.rect.y + MARGIN {
+            self.y += height;
+        }
+    }
+}
+
 #[async_trait(?Send)]
 impl View for Hotkeys {
     fn draw(
@@ -104,15 +166,10 @@ impl View for Hotkeys {
     ) -> Result<bool> {
         let mut drawn = false;
 
-        drawn |= self.global_label.draw(display, styles)?;
-        for hotkey in self.global_hotkeys.iter_mut() {
-            if hotkey.should_draw() {
-                drawn |= hotkey.draw(display, styles)?;
-            }
+        for label in self.labels.iter_mut() {
+            drawn |= label.draw(display, styles)?;
         }
-
-        drawn |= self.ingame_label.draw(display, styles)?;
-        for hotkey in self.ingame_hotkeys.iter_mut() {
+        for hotkey in self.hotkeys.iter_mut() {
             if hotkey.should_draw() {
                 drawn |= hotkey.draw(display, styles)?;
             }
@@ -1,7 +1,10 @@
 #!/bin/sh
 DIR=/mnt/SDCARD/RetroArch
+CORE="$1"
+ROM="$2"
+shift 2
 if [ -f "$DIR/.retroarch/retroarch.cfg" ]; then
     cp "$DIR/.retroarch/retroarch.cfg" "/tmp/retroarch.cfg"
     sed -i 's/savestate_auto_load = "true"/savestate_auto_load = "false"/g' "/tmp/retroarch.cfg"
 fi
-HOME=/mnt/SDCARD/RetroArch LD_PRELOAD=libpadsp.so exec "$DIR/retroarch" -v -L "$DIR/.retroarch/cores/$1_libretro.so" "$2" -c /tmp/retroarch.cfg
\ No newline at end of file
+HOME=/mnt/SDCARD/RetroArch LD_PRELOAD=libpadsp.so exec "$DIR/retroarch" -v -L "$DIR/.retroarch/cores/${CORE}_libretro.so" "$ROM" -c /tmp/retroarch.cfg "$@"
\ No newline at end of file
@@ -1,3 +1,21 @@
 activity-tracker-title = Activity Tracker
 
-activity-tracker-play-time = { $hours_decimal } hours
\ No newline at end of file
+activity-tracker-play-time = { $hours_decimal } hours
+activity-tracker-games = Games
+activity-tracker-days = Last 30 Days
+activity-tracker-weeks = Weekly
+activity-tracker-consoles = Consoles
+activity-tracker-sessions = Sessions
+activity-tracker-no-sessions = No play sessions recorded yet
+activity-tracker-last-30-days = { $play_time } in the last 30 days. Streak: { $current } days (best: { $longest })
+activity-tracker-weekly-average = { $play_time } per week on average
+activity-tracker-total = { $play_time } in total
+activity-tracker-data = Data
+activity-tracker-data-hint = Exports to import on another device go in { $path }
+activity-tracker-export-json = Export (JSON)
+activity-tracker-export-csv = Export (CSV)
+activity-tracker-import = Import
+activity-tracker-exported = Exported to { $path }
+activity-tracker-imported = Imported: { $merged } merged, { $added } added, { $skipped } skipped
+activity-tracker-nothing-to-import = Nothing to import
+activity-tracker-data-failed = Failed, see the log for details
\ No newline at end of file
@@ -1,5 +1,6 @@
 # Launcher
 tab-recents = Recents
+tab-favorites = Favorites
 tab-games = Games
 tab-apps = Apps
 tab-settings = Settings
@@ -10,18 +11,76 @@ sort-most-played = Sort: Playtime
 sort-rating = Sort: Rating
 sort-release-date = Sort: Release Date
 sort-random = Sort: Random
+sort-recently-added = Sort: Added
 sort-search = Search
+search-help-hint = Search Help
+search-help =
+    Search terms must all match. Prefix a term with - to exclude it.
+    "super mario"    exact phrase
+    dev:capcom    developer (pub: for publisher)
+    genre:rpg    genre
+    console:gba    console folder
+    year:1994..1998    release year (also year:>=2000)
+    played:>2h    play time (h, m, s)
+    rating:>=8    rating out of 10
+
+browse-by-genre = By Genre
+browse-by-developer = By Developer
+browse-by-publisher = By Publisher
+browse-by-decade = By Decade
 
 populating-database = Populating database...
     This may take several minutes.
     Go grab a coffee!
 populating-games = Populating games... ({ $directory })
+indexing-progress = Indexing games... ({ $indexed }/{ $total })
+indexing-hint = A: Hide    B: Cancel
+indexing-status = Indexing { $indexed }/{ $total }
+indexing-cancelled = Indexing cancelled
 
 menu-launch = Launch
 menu-launch-with-core = Launch with { $core }
 menu-reset = Reset
+menu-save-states = Save States
+menu-launch-options = Launch Options
+menu-add-to-favorites = Add to Favorites
+menu-remove-from-favorites = Remove from Favorites
+menu-add-to-collection = Add to { $collection }
+menu-remove-from-collection = Remove from { $collection }
+menu-new-collection = New Collection
 menu-remove-from-recents = Remove from Recents
 menu-repopulate-database = Repopulate Database
+menu-repair-library = Repair Library
+menu-hide = Hide in Restricted Mode
+menu-unhide = Show in Restricted Mode
+
+repair-library-relinked = Re-linked { $games } games and { $guides } guides
+repair-library-missing = { $missing } games could not be found
+repair-library-nothing = Nothing to repair
+repair-library-failed = Failed to repair library
+
+save-states-load = Load
+save-states-copy = Copy
+save-states-delete = Delete
+save-states-empty = No save states
+save-states-confirm-delete = Press again to delete
+save-states-copy-to = Copy to { $slot }
+save-states-backups = Backups
+save-states-no-backups = No backups
+save-states-restore = Restore
+save-states-slot-low-battery = Low Battery
+
+launch-options-scope = Applies To
+launch-options-scope-game = This Game
+launch-options-core = Core
+launch-options-retroarch-args = RetroArch Arguments
+launch-options-append-config = Append Config
+launch-options-cpu-governor = CPU Governor
+launch-options-cpu-clock = CPU Clock
+launch-options-swap = Swap
+launch-options-default = Default
+launch-options-on = On
+launch-options-off = Off
 
 settings-wifi = Wi-Fi
 settings-wifi-wifi-enabled = Wi-Fi Enabled
@@ -32,8 +91,20 @@ settings-wifi-ntp-enabled = NTP Enabled
 settings-wifi-web-file-explorer = Web File Explorer
 settings-wifi-telnet-enabled = Telnet Enabled
 settings-wifi-ftp-enabled = FTP Enabled
+settings-wifi-api-enabled = HTTP API Enabled
+settings-wifi-api-token = HTTP API Token
 settings-wifi-connecting= Connecting...
 
+settings-retroachievements = RetroAchievements
+settings-retroachievements-enabled = Achievements Enabled
+settings-retroachievements-username = Username
+settings-retroachievements-password = Password
+settings-retroachievements-hardcore = Hardcore Mode
+settings-retroachievements-status = Status
+settings-retroachievements-logged-in = Logged In
+settings-retroachievements-logged-out = Not Logged In
+settings-retroachievements-login-failed = Login Failed
+
 settings-clock = Date & Time
 settings-clock-datetime = Date & Time
 settings-clock-timezone = Timezone
@@ -68,10 +139,33 @@ settings-theme-button-a-color = Button A Color
 settings-theme-button-b-color = Button B Color
 settings-theme-button-x-color = Button X Color
 settings-theme-button-y-color = Button Y Color
+settings-theme-browse = Themes
+settings-theme-save = Save Theme
+settings-theme-apply = Apply
+settings-theme-no-themes = No themes installed
+settings-theme-saved = Theme saved
+settings-theme-invalid-name = Invalid theme name
+settings-theme-apply-failed = Failed to load theme
 
 settings-language = Language
 settings-language-language = Language
 
+settings-profiles = Profiles
+settings-profiles-active = Active
+settings-profiles-new-profile = New Profile
+settings-profiles-switch = Switch
+settings-profiles-rename = Rename
+settings-profiles-delete = Delete
+settings-profiles-confirm-delete = Press again to delete
+settings-profiles-cannot-delete-active = The active profile cannot be deleted
+settings-profiles-invalid-name = Invalid or duplicate profile name
+
+settings-save-states = Save States
+settings-save-states-backups-per-slot = Backups per Slot
+settings-save-states-backups-disabled = Disabled
+settings-save-states-backup-max-age-days = Keep Backups (Days)
+settings-save-states-backup-max-age-forever = Forever
+
 settings-power = Power
 settings-power-power-button-action = Power Button Action
 settings-power-power-button-action-suspend = Suspend
@@ -80,9 +174,53 @@ settings-power-power-button-action-nothing = Nothing
 settings-power-auto-sleep-when-charging = Auto Sleep When Charging
 settings-power-auto-sleep-duration-minutes = Auto Sleep Duration (Minutes)
 settings-power-auto-sleep-duration-disabled = Disabled
+settings-power-low-battery-warning = Low Battery Warning
+settings-power-low-battery-warning-disabled = Disabled
+settings-power-critical-battery = Save & Power Off At
+settings-schedule = Sleep Schedule
+settings-schedule-enter-pin = Enter PIN
+settings-schedule-wrong-pin = Incorrect PIN
+settings-schedule-enabled = Enabled
+settings-schedule-weekdays-start = Weekdays From
+settings-schedule-weekdays-bedtime = Weekdays Bedtime
+settings-schedule-weekends-start = Weekends From
+settings-schedule-weekends-bedtime = Weekends Bedtime
+settings-schedule-daily-play-time = Daily Play Time
+settings-schedule-unlimited = Unlimited
+settings-schedule-play-time-value = { $hours }h { $minutes }m
+settings-schedule-pin = PIN
+settings-parental = Parental Controls
+settings-parental-enabled = Restricted Mode
+settings-parental-hidden-consoles = Hidden Consoles
+settings-parental-hidden-paths = Hidden Folders & Games
+settings-battery = Battery
+settings-battery-level = Battery Level
+settings-battery-remaining = Time Remaining
+settings-battery-remaining-value = ~{ $hours }h { $minutes }m
+settings-battery-charging = Charging
+settings-battery-unknown = Unknown
+settings-battery-history = Last 24 Hours
+settings-battery-game-usage = Power Use by Game
+settings-battery-rate = { $rate }%/h
 
 settings-files = Files
 
+settings-hotkeys = Hotkeys
+settings-hotkeys-none = None
+
+settings-diagnostics = Diagnostics
+settings-diagnostics-details = Details
+settings-diagnostics-no-issues = No issues found
+settings-diagnostics-invalid-config = Invalid config
+settings-diagnostics-unknown-core = Unknown core
+settings-diagnostics-missing-core = Missing core
+settings-diagnostics-missing-script = Missing launch script
+settings-diagnostics-overlapping-pattern = Overlapping pattern
+settings-diagnostics-overlapping-extension = Overlapping extension
+settings-diagnostics-overlapping-file-name = Overlapping file name
+settings-diagnostics-no-console = No console
+settings-diagnostics-no-games = No games
+
 settings-about = About
 settings-about-allium-version = Allium Version
 settings-about-model-name = Model Name
@@ -97,12 +235,22 @@ ingame-menu-continue = Continue
 ingame-menu-save = Save
 ingame-menu-load = Load
 ingame-menu-reset = Reset
+menu-add-to-favorites = Add to Favorites
+menu-remove-from-favorites = Remove from Favorites
 ingame-menu-settings = Settings
 ingame-menu-guide = Guide
 ingame-menu-quit = Quit
+ingame-menu-favorite = Favorite
 ingame-menu-slot = Slot { $slot }
 ingame-menu-slot-auto = Auto
 ingame-menu-disk = Disk { $disk }
+ingame-menu-achievements = Achievements
+ingame-menu-achievements-loading = Loading achievements...
+ingame-menu-achievements-none = This game has no achievements.
+ingame-menu-achievements-failed = Failed to load achievements.
+ingame-menu-achievements-summary = { $unlocked } of { $total } unlocked, { $points } of { $total_points } points
+ingame-menu-achievements-unlocked = Unlocked · { $points } pts
+ingame-menu-achievements-locked = { $points } pts
 
 guide-button-search = Search
 guide-button-next = Next
@@ -115,6 +263,11 @@ hotkeys-volume-down = Volume -
 hotkeys-volume-up = Volume +
 hotkeys-brightness-down = Brightness -
 hotkeys-brightness-up = Brightness +
+hotkeys-quick-save = Quick Save
+hotkeys-quick-load = Quick Load
+hotkeys-fast-forward = Fast Forward
+hotkeys-launch-app = Launch { $app }
+hotkeys-suspend = Suspend
 
 hotkeys-ingame = Ingame Hotkeys:
 hotkeys-toggle-aspect-ratio = Toggle Aspect Ratio
@@ -131,3 +284,18 @@ keyboard-button-shift = Shift
 
 powering-off = Powering off...
 charging = Charging...
+battery-low = Battery low ({ $percentage }%)
+battery-critical = Battery critical, saving and powering off...
+battery-restore-low-battery-save =
+    The battery ran out while playing { $game }.
+    Press A to load the state saved before powering off,
+    or B to resume as usual.
+schedule-ending = Play time ends in { $minutes } minutes
+schedule-bedtime = It's bedtime. Saving and going to sleep...
+schedule-budget-spent = Play time is used up for today. Saving and going to sleep...
+schedule-blocked-bedtime = It's bedtime, games can't be played now
+schedule-blocked-budget-spent = Play time is used up for today
+parental-blocked = This game is hidden in restricted mode
+parental-wrong-pin = Incorrect PIN
+parental-pin-required = Set a PIN in Sleep Schedule before turning on restricted mode
+parental-apps-locked = Apps are locked in restricted mode